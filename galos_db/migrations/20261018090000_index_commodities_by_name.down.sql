@@ -0,0 +1 @@
+DROP INDEX commodities_name;
@@ -0,0 +1,5 @@
+-- Asking where one commodity is cheapest, or where it sells for the most,
+-- starts from its name. The primary key leads with the market, so without
+-- this the question reads every price on record to find the few hundred
+-- rows for the one commodity it is about.
+CREATE INDEX commodities_name ON commodities (name);
@@ -0,0 +1,40 @@
+-- The ships a commander flies, as the journal's `Loadout` describes them.
+--
+-- Keyed by commander and the game's own id for the ship, which is one per hull
+-- bought and is kept through renaming and refitting. A loadout written later
+-- replaces what was known before, refits included.
+--
+-- What a route needs is kept whole: the drive's size, class and rated mass as
+-- engineered, any Guardian booster's light years, and the masses it jumps
+-- with. The range the game itself last reported is kept beside them, for
+-- comparison rather than for routing.
+CREATE TABLE ships (
+    commander           varchar           NOT NULL,
+    ship_id             bigint            NOT NULL,
+    ship                varchar           NOT NULL,
+    name                varchar,
+    ident               varchar,
+
+    fsd_size            smallint,
+    fsd_class           char(1),
+    optimal_mass        double precision,
+    max_fuel_per_jump   double precision,
+    booster             double precision  NOT NULL DEFAULT 0,
+
+    unladen_mass        double precision  NOT NULL,
+    fuel_capacity       double precision  NOT NULL,
+    fuel_reserve        double precision  NOT NULL,
+    cargo_capacity      integer           NOT NULL,
+    max_jump_range      double precision  NOT NULL,
+
+    updated_at          timestamp         NOT NULL,
+
+    PRIMARY KEY (commander, ship_id),
+    CONSTRAINT ships_fsd_whole CHECK (
+        num_nonnulls(fsd_size, fsd_class, optimal_mass, max_fuel_per_jump)
+            IN (0, 4)
+    )
+);
+
+-- A ship is asked for by what its commander calls it.
+CREATE INDEX ships_name_idx ON ships (lower(name));
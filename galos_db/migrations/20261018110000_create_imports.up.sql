@@ -0,0 +1,18 @@
+-- How far each file given to `galos-sync` has been imported.
+--
+-- A file is known by its path, its size, and a hash of its first mebibyte,
+-- which together tell a file left alone from one written over and from one
+-- only added to. A dump is replaced whole rather than added to, and a new one
+-- can be the size of the last, and start as it did, so `modified`, when it
+-- was last written, tells those apart. `processed` is how far in the last run
+-- got, in whatever the importer counts in: bytes for a journal, records for a
+-- dump.
+CREATE TABLE imports (
+    path        varchar    PRIMARY KEY,
+    size        bigint     NOT NULL,
+    hash        varchar    NOT NULL,
+    modified    timestamp  NOT NULL,
+    processed   bigint     NOT NULL DEFAULT 0,
+    complete    boolean    NOT NULL DEFAULT false,
+    updated_at  timestamp  NOT NULL
+);
@@ -0,0 +1,4 @@
+DROP TABLE staging_body_materials;
+DROP TABLE staging_bodies;
+DROP TABLE staging_stars;
+DROP TABLE staging_systems;
@@ -0,0 +1,29 @@
+-- Where a bulk import copies rows to before they are merged into the tables
+-- they belong in. See `galos_db::bulk`.
+--
+-- `COPY` takes a batch in one round trip where an upsert a row takes one each,
+-- but it can only append, and a single row it cannot take fails the lot. So a
+-- batch lands here first, with the columns of the real table and none of its
+-- constraints, which `CREATE TABLE AS` leaves behind. What would break one is
+-- counted as rejected and deleted before the rest is merged, newer
+-- `updated_at` winning as it does for an upsert.
+--
+-- Unlogged, since nothing here outlives the transaction that copied it in and
+-- a crash losing it loses nothing. Kept rather than created per import so that
+-- a load is just a `TRUNCATE` to begin, which takes the tables for the length
+-- of its transaction and so runs one import's load at a time.
+--
+-- A column added to a real table has to be added here too.
+CREATE UNLOGGED TABLE staging_systems AS
+    SELECT * FROM systems WITH NO DATA;
+
+CREATE UNLOGGED TABLE staging_stars AS
+    SELECT * FROM stars WITH NO DATA;
+
+CREATE UNLOGGED TABLE staging_bodies AS
+    SELECT * FROM bodies WITH NO DATA;
+
+-- Materials are matched back to the body they were scanned with by when, so
+-- an older scan's materials never land on a newer scan's body.
+CREATE UNLOGGED TABLE staging_body_materials AS
+    SELECT *, NULL::timestamp AS updated_at FROM body_materials WITH NO DATA;
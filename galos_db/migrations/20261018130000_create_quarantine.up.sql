@@ -0,0 +1,20 @@
+-- EDDN messages held back from ingest, each with why.
+--
+-- Anyone can upload anything to EDDN. A message placing a system outside the
+-- galaxy, or somewhere other than where it is known to be, dated well away
+-- from when it arrived, or sent by software known to send bad data, is kept
+-- here rather than written, to be looked over and released or purged.
+--
+-- The envelope is kept whole, as it arrived, so a message released is
+-- ingested exactly as it would have been.
+CREATE TABLE quarantine (
+    id        bigserial  PRIMARY KEY,
+    received  timestamp  NOT NULL,
+    schema    varchar    NOT NULL,
+    uploader  varchar    NOT NULL,
+    software  varchar    NOT NULL,
+    reason    varchar    NOT NULL,
+    envelope  jsonb      NOT NULL
+);
+
+CREATE INDEX quarantine_received_idx ON quarantine (received);
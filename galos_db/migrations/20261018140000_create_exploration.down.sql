@@ -0,0 +1,2 @@
+DROP TABLE body_signals;
+DROP TABLE system_scans;
@@ -0,0 +1,31 @@
+-- What exploring a system has said about how much is in it.
+--
+-- `FSSDiscoveryScan`, the honk, and `NavBeaconScan` count its bodies, and
+-- `FSSAllBodiesFound` says every one of them has been found. Set against the
+-- stars and bodies on record, that is how much of the system is still to be
+-- scanned. Neither needs the system on record first, any more than a market
+-- does, and each count only ever fills in: a system has the bodies it has.
+CREATE TABLE system_scans (
+    system_address    bigint     PRIMARY KEY,
+    body_count        integer,
+    non_body_count    integer,
+    all_bodies_found  boolean    NOT NULL DEFAULT false,
+    nav_beacon        boolean    NOT NULL DEFAULT false,
+    updated_at        timestamp  NOT NULL,
+    updated_by        varchar    NOT NULL
+);
+
+-- The signals found on a body, by kind, as `FSSBodySignals` counts them from
+-- orbit and `SAASignalsFound` once it is mapped. `Biological`, `Geological`
+-- and the like, or a commodity for a ring's hotspots. A newer count replaces
+-- the body's whole set.
+CREATE TABLE body_signals (
+    system_address  bigint     NOT NULL,
+    body_id         smallint   NOT NULL,
+    kind            varchar    NOT NULL,
+    count           integer    NOT NULL,
+    updated_at      timestamp  NOT NULL,
+    updated_by      varchar    NOT NULL,
+
+    PRIMARY KEY (system_address, body_id, kind)
+);
@@ -0,0 +1,2 @@
+DROP TABLE shipyard;
+DROP TABLE outfitting;
@@ -0,0 +1,35 @@
+-- The modules a station's outfitting sells and the ships its shipyard does,
+-- as EDDN's `outfitting/2` and `shipyard/2` list them. Like commodities they
+-- hang off the market, which is what both messages are keyed by, and like a
+-- market's prices a newer list replaces the station's whole set.
+--
+-- A module is kept under the game's own name, lowercased, along with what
+-- that name says of it: `int_hyperdrive_size5_class5` is the item
+-- `int_hyperdrive` at class 5, rated A. Hardpoints give a size for a class
+-- and no rating, and the rest, armour and the like, give neither.
+CREATE TABLE outfitting (
+    market_id  bigint     REFERENCES markets NOT NULL,
+    module     varchar    NOT NULL,
+    item       varchar    NOT NULL,
+    class      smallint,
+    rating     char(1),
+    listed_at  timestamp  NOT NULL,
+
+    PRIMARY KEY (market_id, module)
+);
+
+-- Asked after by item far more than by the whole name.
+CREATE INDEX outfitting_item ON outfitting (item);
+
+CREATE TABLE shipyard (
+    market_id  bigint     REFERENCES markets NOT NULL,
+    ship       varchar    NOT NULL,
+    listed_at  timestamp  NOT NULL,
+
+    PRIMARY KEY (market_id, ship)
+);
+
+-- A ship is asked for as `Krait Mk II` as often as `krait_mkii`, so it is
+-- found by its letters and digits alone.
+CREATE INDEX shipyard_ship
+    ON shipyard (regexp_replace(ship, '[^a-z0-9]', '', 'g'));
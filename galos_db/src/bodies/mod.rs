@@ -3,11 +3,12 @@ use chrono::{DateTime, Utc};
 use elite_journal::body::{
     AtmosphereType, BodyType, Composition, Discovery, Material, Orbit, Spin,
 };
+use serde::Serialize;
 use std::collections::BTreeMap as Map;
 
 /// Clone because the map carries one into a component and into whatever
 /// panel is describing it, and a body outlives the query it came back in.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct Body {
     pub system_address: i64,
     pub id: i16,
@@ -60,7 +61,7 @@ mod fetch;
 /// unfamiliar one would otherwise drop an ancestor out of the middle of a
 /// chain and shift everything above it down. It is [`None`] for a body stored
 /// before the kinds were kept, which recorded the nearest ancestor's id alone.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
 pub struct Parent {
     pub ty: Option<String>,
     pub id: i16,
@@ -105,7 +106,7 @@ impl Parent {
 /// Apart from [`elite_journal::body::Surface`] only in that the composition is
 /// optional: a body stored before the fractions were kept has a surface and no
 /// reading of what it is made of.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct Surface {
     pub atmosphere_type: AtmosphereType,
     pub pressure: f32,
@@ -0,0 +1,426 @@
+use super::{Batch, Report, BODIES, MATERIALS, STARS, SYSTEMS};
+use crate::{Database, Error};
+use sqlx::{PgConnection, Postgres, Transaction};
+
+impl Batch {
+    /// Copy everything staged in and merge it, leaving the batch empty
+    ///
+    /// All of it goes in one transaction, so an import stopped partway has
+    /// either loaded a batch or not, and can checkpoint after each.
+    pub async fn load(&mut self, db: &Database) -> Result<Report, Error> {
+        let mut report =
+            Report { rejected: self.rejected, ..Report::default() };
+        let mut tx = db.pool.begin().await?;
+
+        // Taking the staging tables for the whole transaction, so two imports
+        // loading at once take turns rather than merging each other's rows.
+        truncate(&mut tx).await?;
+        copy(&mut tx, "staging_systems", SYSTEMS, &self.systems).await?;
+        copy(&mut tx, "staging_stars", STARS, &self.stars).await?;
+        copy(&mut tx, "staging_bodies", BODIES, &self.bodies).await?;
+        copy(&mut tx, "staging_body_materials", MATERIALS, &self.materials)
+            .await?;
+
+        // Systems first, since stars and bodies refer to them.
+        report += merge_systems(&mut tx).await?;
+        report += merge_stars(&mut tx).await?;
+        report += merge_bodies(&mut tx).await?;
+
+        truncate(&mut tx).await?;
+        tx.commit().await?;
+        *self = Batch::default();
+        Ok(report)
+    }
+}
+
+async fn truncate(tx: &mut Transaction<'_, Postgres>) -> Result<(), Error> {
+    sqlx::query(
+        "TRUNCATE staging_systems, staging_stars, staging_bodies, \
+         staging_body_materials",
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+async fn copy(
+    conn: &mut PgConnection,
+    table: &str,
+    columns: &str,
+    rows: &str,
+) -> Result<(), Error> {
+    if rows.is_empty() {
+        return Ok(());
+    }
+    let statement = format!("COPY {} ({}) FROM STDIN", table, columns);
+    let mut copy = conn.copy_in_raw(&statement).await?;
+    copy.send(rows.as_bytes()).await?;
+    copy.finish().await?;
+    Ok(())
+}
+
+/// What is left staged once the rejected are gone, less what was merged
+fn stale(staged: i64, inserted: i64, updated: i64) -> u64 {
+    (staged - inserted - updated).max(0) as u64
+}
+
+async fn merge_systems(
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<Report, Error> {
+    // A position is unique, and a system claiming another's would fail the
+    // whole merge rather than itself.
+    let rejected = sqlx::query!(
+        "
+        DELETE FROM staging_systems s
+         WHERE EXISTS (
+                SELECT 1 FROM systems t
+                 WHERE t.position = s.position AND t.address <> s.address)
+            OR EXISTS (
+                SELECT 1 FROM staging_systems o
+                 WHERE o.position = s.position AND o.address <> s.address)
+        "
+    )
+    .execute(&mut **tx)
+    .await?
+    .rows_affected();
+
+    // Only the newest of a system staged twice is merged, two rows
+    // conflicting within one statement being an error rather than something
+    // `ON CONFLICT` settles.
+    let row = sqlx::query!(
+        r#"
+        WITH staged AS (
+            SELECT DISTINCT ON (address) *
+              FROM staging_systems
+             ORDER BY address, updated_at DESC
+        ), merged AS (
+            INSERT INTO systems
+                (address,
+                 name,
+                 primary_star_class,
+                 position,
+                 population,
+                 security,
+                 government,
+                 allegiance,
+                 primary_economy,
+                 secondary_economy,
+                 updated_at,
+                 updated_by)
+            SELECT address, name, primary_star_class, position, population,
+                   security, government, allegiance, primary_economy,
+                   secondary_economy, updated_at, updated_by
+              FROM staged
+            ON CONFLICT (address)
+            DO UPDATE SET
+                primary_star_class = COALESCE(EXCLUDED.primary_star_class,
+                                              systems.primary_star_class),
+                position = COALESCE(EXCLUDED.position, systems.position),
+                population = COALESCE(EXCLUDED.population, systems.population),
+                security = COALESCE(EXCLUDED.security, systems.security),
+                government = COALESCE(EXCLUDED.government, systems.government),
+                allegiance = COALESCE(EXCLUDED.allegiance, systems.allegiance),
+                primary_economy = COALESCE(EXCLUDED.primary_economy,
+                                           systems.primary_economy),
+                secondary_economy = COALESCE(EXCLUDED.secondary_economy,
+                                             systems.secondary_economy),
+                updated_at = EXCLUDED.updated_at,
+                updated_by = EXCLUDED.updated_by
+            WHERE systems.updated_at < EXCLUDED.updated_at
+            RETURNING xmax = 0 AS inserted
+        )
+        SELECT
+            (SELECT COUNT(*) FROM staging_systems) AS "staged!",
+            COUNT(*) FILTER (WHERE inserted) AS "inserted!",
+            COUNT(*) FILTER (WHERE NOT inserted) AS "updated!"
+          FROM merged
+        "#
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+
+    // Markets waiting on a system by name, as `System::create` adopts them
+    // one at a time.
+    sqlx::query!(
+        "
+        INSERT INTO stations (system_address, name, updated_at, updated_by)
+        SELECT DISTINCT s.address, m.station_name, s.updated_at, s.updated_by
+          FROM markets m
+          JOIN staging_systems s ON m.system_name = s.name
+         WHERE m.system_address IS NULL
+        ON CONFLICT (system_address, name) DO NOTHING
+        "
+    )
+    .execute(&mut **tx)
+    .await?;
+    sqlx::query!(
+        "
+        UPDATE markets m SET system_address = s.address
+          FROM staging_systems s
+         WHERE m.system_address IS NULL AND m.system_name = s.name
+        "
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(Report {
+        inserted: row.inserted as u64,
+        updated: row.updated as u64,
+        stale: stale(row.staged, row.inserted, row.updated),
+        rejected,
+    })
+}
+
+async fn merge_stars(
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<Report, Error> {
+    // What the columns of `stars` would not take, and a star of a system not
+    // on record, or taking the name of another star in it.
+    let rejected = sqlx::query!(
+        "
+        DELETE FROM staging_stars s
+         WHERE name IS NULL OR absolute_magnitude IS NULL OR age_my IS NULL
+            OR distance_from_arrival_ls IS NULL OR luminosity IS NULL
+            OR star_class IS NULL OR stellar_mass IS NULL OR subclass IS NULL
+            OR axial_tilt IS NULL OR radius IS NULL OR rotation_period IS NULL
+            OR temperature IS NULL OR was_mapped IS NULL
+            OR was_discovered IS NULL OR updated_at IS NULL
+            OR num_nonnulls(semi_major_axis, eccentricity, orbital_inclination,
+                            periapsis, orbital_period, ascending_node,
+                            mean_anomaly) NOT IN (0, 7)
+            OR NOT EXISTS (
+                SELECT 1 FROM systems WHERE address = s.system_address)
+            OR EXISTS (
+                SELECT 1 FROM stars t
+                 WHERE t.system_address = s.system_address
+                   AND t.name = s.name AND t.id <> s.id)
+        "
+    )
+    .execute(&mut **tx)
+    .await?
+    .rows_affected();
+
+    let row = sqlx::query!(
+        r#"
+        WITH staged AS (
+            SELECT DISTINCT ON (system_address, id) *
+              FROM staging_stars
+             ORDER BY system_address, id, updated_at DESC
+        ), merged AS (
+            INSERT INTO stars (
+                system_address, id, name, parent_id, parent_ids, parent_types,
+                updated_at, updated_by, absolute_magnitude, age_my,
+                distance_from_arrival_ls, luminosity, star_class,
+                stellar_mass, subclass, ascending_node, axial_tilt,
+                eccentricity, mean_anomaly, orbital_inclination,
+                orbital_period, periapsis, radius, rotation_period,
+                semi_major_axis, temperature, was_mapped, was_discovered)
+            SELECT
+                system_address, id, name, parent_id, parent_ids, parent_types,
+                updated_at, updated_by, absolute_magnitude, age_my,
+                distance_from_arrival_ls, luminosity, star_class,
+                stellar_mass, subclass, ascending_node, axial_tilt,
+                eccentricity, mean_anomaly, orbital_inclination,
+                orbital_period, periapsis, radius, rotation_period,
+                semi_major_axis, temperature, was_mapped, was_discovered
+              FROM staged
+            ON CONFLICT (system_address, id)
+            DO UPDATE SET
+                name = EXCLUDED.name,
+                parent_id = EXCLUDED.parent_id,
+                parent_ids = EXCLUDED.parent_ids,
+                parent_types = EXCLUDED.parent_types,
+                updated_at = EXCLUDED.updated_at,
+                updated_by = EXCLUDED.updated_by,
+
+                absolute_magnitude = EXCLUDED.absolute_magnitude,
+                age_my = EXCLUDED.age_my,
+                distance_from_arrival_ls = EXCLUDED.distance_from_arrival_ls,
+                luminosity = EXCLUDED.luminosity,
+                star_class = EXCLUDED.star_class,
+                stellar_mass = EXCLUDED.stellar_mass,
+                subclass = EXCLUDED.subclass,
+
+                ascending_node = EXCLUDED.ascending_node,
+                axial_tilt = EXCLUDED.axial_tilt,
+                eccentricity = EXCLUDED.eccentricity,
+                mean_anomaly = EXCLUDED.mean_anomaly,
+                orbital_inclination = EXCLUDED.orbital_inclination,
+                orbital_period = EXCLUDED.orbital_period,
+                periapsis = EXCLUDED.periapsis,
+                radius = EXCLUDED.radius,
+                rotation_period = EXCLUDED.rotation_period,
+                semi_major_axis = EXCLUDED.semi_major_axis,
+                temperature = EXCLUDED.temperature,
+
+                was_mapped = EXCLUDED.was_mapped,
+                was_discovered = EXCLUDED.was_discovered
+            WHERE stars.updated_at < EXCLUDED.updated_at
+            RETURNING xmax = 0 AS inserted
+        )
+        SELECT
+            (SELECT COUNT(*) FROM staging_stars) AS "staged!",
+            COUNT(*) FILTER (WHERE inserted) AS "inserted!",
+            COUNT(*) FILTER (WHERE NOT inserted) AS "updated!"
+          FROM merged
+        "#
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+
+    Ok(Report {
+        inserted: row.inserted as u64,
+        updated: row.updated as u64,
+        stale: stale(row.staged, row.inserted, row.updated),
+        rejected,
+    })
+}
+
+async fn merge_bodies(
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<Report, Error> {
+    let rejected = sqlx::query!(
+        "
+        DELETE FROM staging_bodies s
+         WHERE name IS NULL OR planet_class IS NULL OR tidal_lock IS NULL
+            OR landable IS NULL OR mass IS NULL OR radius IS NULL
+            OR gravity IS NULL OR temperature IS NULL
+            OR semi_major_axis IS NULL OR eccentricity IS NULL
+            OR orbital_inclination IS NULL OR periapsis IS NULL
+            OR orbital_period IS NULL OR rotation_period IS NULL
+            OR axial_tilt IS NULL OR ascending_node IS NULL
+            OR mean_anomaly IS NULL OR was_mapped IS NULL
+            OR was_discovered IS NULL OR updated_at IS NULL
+            OR num_nonnulls(composition_ice, composition_rock,
+                            composition_metal) NOT IN (0, 3)
+            OR NOT EXISTS (
+                SELECT 1 FROM systems WHERE address = s.system_address)
+            OR EXISTS (
+                SELECT 1 FROM bodies b
+                 WHERE b.system_address = s.system_address
+                   AND b.name = s.name AND b.id <> s.id)
+        "
+    )
+    .execute(&mut **tx)
+    .await?
+    .rows_affected();
+
+    // A scan states the whole of what a body is made of, so a body about to
+    // be replaced loses what the older scan said.
+    sqlx::query!(
+        "
+        DELETE FROM body_materials m
+         USING staging_bodies s, bodies b
+         WHERE b.system_address = s.system_address AND b.id = s.id
+           AND b.updated_at < s.updated_at
+           AND m.system_address = b.system_address AND m.body_id = b.id
+        "
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    let row = sqlx::query!(
+        r#"
+        WITH staged AS (
+            SELECT DISTINCT ON (system_address, id) *
+              FROM staging_bodies
+             ORDER BY system_address, id, updated_at DESC
+        ), merged AS (
+            INSERT INTO bodies (
+                system_address, name, id, parent_id, parent_ids,
+                parent_types, updated_at, updated_by, body_type,
+                distance_from_arrival, planet_class, tidal_lock, landable,
+                terraform_state, atmosphere, atmosphere_type, volcanism, mass,
+                radius, gravity, temperature, surface_pressure,
+                composition_ice, composition_rock, composition_metal,
+                semi_major_axis, eccentricity, orbital_inclination, periapsis,
+                orbital_period, rotation_period, axial_tilt, ascending_node,
+                mean_anomaly, was_mapped, was_discovered)
+            SELECT
+                system_address, name, id, parent_id, parent_ids,
+                parent_types, updated_at, updated_by, body_type,
+                distance_from_arrival, planet_class, tidal_lock, landable,
+                terraform_state, atmosphere, atmosphere_type, volcanism, mass,
+                radius, gravity, temperature, surface_pressure,
+                composition_ice, composition_rock, composition_metal,
+                semi_major_axis, eccentricity, orbital_inclination, periapsis,
+                orbital_period, rotation_period, axial_tilt, ascending_node,
+                mean_anomaly, was_mapped, was_discovered
+              FROM staged
+            ON CONFLICT (system_address, id)
+            DO UPDATE SET
+                name = EXCLUDED.name,
+                parent_id = EXCLUDED.parent_id,
+                parent_ids = EXCLUDED.parent_ids,
+                parent_types = EXCLUDED.parent_types,
+                updated_at = EXCLUDED.updated_at,
+                updated_by = EXCLUDED.updated_by,
+
+                body_type = EXCLUDED.body_type,
+                distance_from_arrival = EXCLUDED.distance_from_arrival,
+                planet_class = EXCLUDED.planet_class,
+                tidal_lock = EXCLUDED.tidal_lock,
+                landable = EXCLUDED.landable,
+                terraform_state = EXCLUDED.terraform_state,
+                atmosphere = EXCLUDED.atmosphere,
+                atmosphere_type = EXCLUDED.atmosphere_type,
+                volcanism = EXCLUDED.volcanism,
+
+                mass = EXCLUDED.mass,
+                radius = EXCLUDED.radius,
+                gravity = EXCLUDED.gravity,
+                temperature = EXCLUDED.temperature,
+                surface_pressure = EXCLUDED.surface_pressure,
+                composition_ice = EXCLUDED.composition_ice,
+                composition_rock = EXCLUDED.composition_rock,
+                composition_metal = EXCLUDED.composition_metal,
+                semi_major_axis = EXCLUDED.semi_major_axis,
+                eccentricity = EXCLUDED.eccentricity,
+                orbital_inclination = EXCLUDED.orbital_inclination,
+                periapsis = EXCLUDED.periapsis,
+                orbital_period = EXCLUDED.orbital_period,
+                rotation_period = EXCLUDED.rotation_period,
+                axial_tilt = EXCLUDED.axial_tilt,
+                ascending_node = EXCLUDED.ascending_node,
+                mean_anomaly = EXCLUDED.mean_anomaly,
+
+                was_mapped = EXCLUDED.was_mapped,
+                was_discovered = EXCLUDED.was_discovered
+            WHERE bodies.updated_at < EXCLUDED.updated_at
+            RETURNING xmax = 0 AS inserted
+        )
+        SELECT
+            (SELECT COUNT(*) FROM staging_bodies) AS "staged!",
+            COUNT(*) FILTER (WHERE inserted) AS "inserted!",
+            COUNT(*) FILTER (WHERE NOT inserted) AS "updated!"
+          FROM merged
+        "#
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+
+    // The materials of whichever scan won, matched to it by when it was. A
+    // material named twice in one scan is read the first time.
+    sqlx::query!(
+        "
+        INSERT INTO body_materials (system_address, body_id, name, percent)
+        SELECT DISTINCT ON (m.system_address, m.body_id, m.name)
+               m.system_address, m.body_id, m.name, m.percent
+          FROM staging_body_materials m
+          JOIN bodies b
+            ON b.system_address = m.system_address AND b.id = m.body_id
+           AND b.updated_at = m.updated_at
+         ORDER BY m.system_address, m.body_id, m.name
+        ON CONFLICT (system_address, body_id, name) DO NOTHING
+        "
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(Report {
+        inserted: row.inserted as u64,
+        updated: row.updated as u64,
+        stale: stale(row.staged, row.inserted, row.updated),
+        rejected,
+    })
+}
@@ -0,0 +1,413 @@
+//! Loading rows by the batch, for the dump importers
+//!
+//! An upsert a row is a round trip a row, which for the tens of millions of
+//! systems and bodies in a dump is most of the time an import takes. A
+//! [`Batch`] is built up here instead, already written out as the text
+//! `COPY ... FROM STDIN` reads, and [`Batch::load`] copies it into unlogged
+//! staging tables and merges it into `systems`, `stars` and `bodies` in a
+//! handful of statements.
+//!
+//! The merge keeps to what the upserts do: a row only replaces one that was
+//! updated before it, and a system's columns left empty keep what was there.
+//! What a load did is said in a [`Report`].
+use crate::bodies::Parent;
+use chrono::{DateTime, Utc};
+use elite_journal::body::{Body as JournalBody, Star as JournalStar};
+use elite_journal::prelude::*;
+use std::fmt::{self, Debug};
+use std::ops::AddAssign;
+
+mod create;
+
+/// Everything staged to be loaded together
+#[derive(Debug, Default)]
+pub struct Batch {
+    systems: String,
+    stars: String,
+    bodies: String,
+    materials: String,
+    len: usize,
+    rejected: u64,
+}
+
+/// What a load did with each row it was given
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Report {
+    /// Rows that were not there before
+    pub inserted: u64,
+    /// Rows that replaced an older one
+    pub updated: u64,
+    /// Rows no newer than the one already there, and left out
+    pub stale: u64,
+    /// Rows that could not be written at all
+    pub rejected: u64,
+}
+
+impl AddAssign for Report {
+    fn add_assign(&mut self, other: Self) {
+        self.inserted += other.inserted;
+        self.updated += other.updated;
+        self.stale += other.stale;
+        self.rejected += other.rejected;
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} inserted, {} updated, {} stale, {} rejected",
+            self.inserted, self.updated, self.stale, self.rejected
+        )
+    }
+}
+
+impl Batch {
+    pub fn new() -> Self {
+        Batch::default()
+    }
+
+    /// How many rows are staged, of every kind
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Count a row the importer could not make anything of, so it is
+    /// reported along with those the database would not take
+    pub fn reject(&mut self) {
+        self.rejected += 1;
+    }
+
+    /// A system, as [`crate::systems::System::create`] takes it
+    #[allow(clippy::too_many_arguments)]
+    pub fn system(
+        &mut self,
+        address: i64,
+        name: &str,
+        position: Option<Coordinate>,
+        primary_star_class: Option<String>,
+        population: Option<u64>,
+        security: Option<Security>,
+        government: Option<Government>,
+        allegiance: Option<Allegiance>,
+        economies: Option<crate::systems::Economies>,
+        updated_at: DateTime<Utc>,
+        updated_by: &str,
+    ) {
+        // An anarchy is stored as no security at all, see `create_systems`.
+        let security = security.filter(|s| *s != Security::Anarchy);
+        row(
+            &mut self.systems,
+            &[
+                &address,
+                &name.to_uppercase(),
+                &primary_star_class,
+                &position.map(Point),
+                &population.map(|n| n as i64),
+                &security.map(Label),
+                &government.map(Label),
+                &allegiance.map(Label),
+                &economies.map(|e| Label(e.primary)),
+                &economies.and_then(|e| e.secondary).map(Label),
+                &updated_at,
+                &updated_by,
+            ],
+        );
+        self.len += 1;
+    }
+
+    /// A star, as [`crate::stars::Star::from_journal`] takes it
+    pub fn star(
+        &mut self,
+        timestamp: DateTime<Utc>,
+        user: &str,
+        star: &JournalStar,
+        system_address: i64,
+    ) {
+        let (parent_ids, parent_types) =
+            Parent::columns(&Parent::chain(&star.parents));
+        let orbit = star.orbit.as_ref();
+        row(
+            &mut self.stars,
+            &[
+                &system_address,
+                &star.id,
+                &star.name,
+                &parent_ids.first().copied(),
+                &Array(&parent_ids),
+                &Array(&parent_types),
+                &timestamp,
+                &user,
+                &star.absolute_magnitude,
+                &star.age_my,
+                &star.distance_from_arrival_ls,
+                &star.luminosity,
+                &star.star_class,
+                &star.stellar_mass,
+                &star.subclass,
+                &orbit.map(|orbit| orbit.ascending_node),
+                &star.spin.tilt,
+                &orbit.map(|orbit| orbit.eccentricity),
+                &orbit.map(|orbit| orbit.mean_anomaly),
+                &orbit.map(|orbit| orbit.orbital_inclination),
+                &orbit.map(|orbit| orbit.orbital_period),
+                &orbit.map(|orbit| orbit.periapsis),
+                &star.radius,
+                &star.spin.period,
+                &orbit.map(|orbit| orbit.semi_major_axis),
+                &star.temperature,
+                &star.discovery.mapped,
+                &star.discovery.discovered,
+            ],
+        );
+        self.len += 1;
+    }
+
+    /// A body and what it is made of, as
+    /// [`crate::bodies::Body::from_journal`] takes it
+    pub fn body(
+        &mut self,
+        timestamp: DateTime<Utc>,
+        user: &str,
+        body: &JournalBody,
+        system_address: i64,
+    ) {
+        let (parent_ids, parent_types) =
+            Parent::columns(&Parent::chain(&body.parents));
+        let surface = body.surface.as_ref();
+        let crust = surface.map(|surface| &surface.composition);
+        row(
+            &mut self.bodies,
+            &[
+                &system_address,
+                &body.name,
+                &body.id,
+                &parent_ids.first().copied(),
+                &Array(&parent_ids),
+                &Array(&parent_types),
+                &timestamp,
+                &user,
+                &body.ty.as_ref().map(|ty| ty.to_string()),
+                &body.distance_from_arrival,
+                &body.planet_class,
+                &body.tidal_lock,
+                &surface.is_some_and(|surface| surface.landable),
+                &surface.and_then(|surface| surface.terraform_state.clone()),
+                &surface.and_then(|surface| surface.atmosphere.clone()),
+                &surface.map(|surface| surface.atmosphere_type.to_string()),
+                &surface.and_then(|surface| surface.volcanism.clone()),
+                &body.mass,
+                &body.radius,
+                &body.gravity,
+                &body.temperature,
+                &surface.map(|surface| surface.pressure),
+                &crust.map(|crust| crust.ice),
+                &crust.map(|crust| crust.rock),
+                &crust.map(|crust| crust.metal),
+                &body.orbit.semi_major_axis,
+                &body.orbit.eccentricity,
+                &body.orbit.orbital_inclination,
+                &body.orbit.periapsis,
+                &body.orbit.orbital_period,
+                &body.spin.period,
+                &body.spin.tilt,
+                &body.orbit.ascending_node,
+                &body.orbit.mean_anomaly,
+                &body.discovery.mapped,
+                &body.discovery.discovered,
+            ],
+        );
+        for material in surface.iter().flat_map(|s| s.materials.iter()) {
+            row(
+                &mut self.materials,
+                &[
+                    &system_address,
+                    &body.id,
+                    &material.name,
+                    &material.percent,
+                    &timestamp,
+                ],
+            );
+        }
+        self.len += 1;
+    }
+}
+
+/// A value as `COPY` reads it in text, or nothing for a null
+trait Cell {
+    fn text(&self) -> Option<String>;
+}
+
+macro_rules! displayed {
+    ($($ty:ty),*) => {
+        $(impl Cell for $ty {
+            fn text(&self) -> Option<String> {
+                Some(self.to_string())
+            }
+        })*
+    };
+}
+
+displayed!(i16, i32, i64, u8, u16, u32, u64, f32, f64, str, String);
+
+impl Cell for bool {
+    fn text(&self) -> Option<String> {
+        Some(if *self { "t" } else { "f" }.to_string())
+    }
+}
+
+impl Cell for DateTime<Utc> {
+    fn text(&self) -> Option<String> {
+        Some(self.naive_utc().format("%Y-%m-%d %H:%M:%S%.f").to_string())
+    }
+}
+
+impl<T: Cell + ?Sized> Cell for &T {
+    fn text(&self) -> Option<String> {
+        (**self).text()
+    }
+}
+
+impl<T: Cell> Cell for Option<T> {
+    fn text(&self) -> Option<String> {
+        self.as_ref().and_then(Cell::text)
+    }
+}
+
+/// An enum, by the name of its variant, which is the label Postgres has
+struct Label<T>(T);
+
+impl<T: Debug> Cell for Label<T> {
+    fn text(&self) -> Option<String> {
+        Some(format!("{:?}", self.0))
+    }
+}
+
+/// A position, as the well-known text PostGIS reads
+struct Point(Coordinate);
+
+impl Cell for Point {
+    fn text(&self) -> Option<String> {
+        let Point(p) = self;
+        Some(format!("POINT Z ({} {} {})", p.x, p.y, p.z))
+    }
+}
+
+/// An array literal, each element quoted so any string reads as itself
+struct Array<'a, T>(&'a [T]);
+
+impl<T: Cell> Cell for Array<'_, T> {
+    fn text(&self) -> Option<String> {
+        let elements: Vec<String> = self
+            .0
+            .iter()
+            .map(|element| {
+                let text = element.text().unwrap_or_default();
+                format!(
+                    "\"{}\"",
+                    text.replace('\\', r"\\").replace('"', "\\\"")
+                )
+            })
+            .collect();
+        Some(format!("{{{}}}", elements.join(",")))
+    }
+}
+
+/// Write a row as `COPY` reads text: tab separated, `\N` for a null, and
+/// the characters that would end a value or a row escaped
+fn row(out: &mut String, cells: &[&dyn Cell]) {
+    for (i, cell) in cells.iter().enumerate() {
+        if i > 0 {
+            out.push('\t');
+        }
+        match cell.text() {
+            Some(text) => {
+                for c in text.chars() {
+                    match c {
+                        '\\' => out.push_str(r"\\"),
+                        '\t' => out.push_str(r"\t"),
+                        '\n' => out.push_str(r"\n"),
+                        '\r' => out.push_str(r"\r"),
+                        c => out.push(c),
+                    }
+                }
+            }
+            None => out.push_str(r"\N"),
+        }
+    }
+    out.push('\n');
+}
+
+/// The columns each staging table is copied into, in the order [`row`] is
+/// given them above
+const SYSTEMS: &str = "address, name, primary_star_class, position, \
+    population, security, government, allegiance, primary_economy, \
+    secondary_economy, updated_at, updated_by";
+
+const STARS: &str = "system_address, id, name, parent_id, parent_ids, \
+    parent_types, updated_at, updated_by, absolute_magnitude, age_my, \
+    distance_from_arrival_ls, luminosity, star_class, stellar_mass, subclass, \
+    ascending_node, axial_tilt, eccentricity, mean_anomaly, \
+    orbital_inclination, orbital_period, periapsis, radius, rotation_period, \
+    semi_major_axis, temperature, was_mapped, was_discovered";
+
+const BODIES: &str = "system_address, name, id, parent_id, parent_ids, \
+    parent_types, updated_at, updated_by, body_type, distance_from_arrival, \
+    planet_class, tidal_lock, landable, terraform_state, atmosphere, \
+    atmosphere_type, volcanism, mass, radius, gravity, temperature, \
+    surface_pressure, composition_ice, composition_rock, composition_metal, \
+    semi_major_axis, eccentricity, orbital_inclination, periapsis, \
+    orbital_period, rotation_period, axial_tilt, ascending_node, mean_anomaly, \
+    was_mapped, was_discovered";
+
+const MATERIALS: &str = "system_address, body_id, name, percent, updated_at";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(cells: &[&dyn Cell]) -> String {
+        let mut out = String::new();
+        row(&mut out, cells);
+        out
+    }
+
+    #[test]
+    fn rows_are_tab_separated_with_nulls_marked() {
+        let none: Option<i64> = None;
+        assert_eq!(text(&[&1i64, &"Sol", &none, &true]), "1\tSol\t\\N\tt\n");
+    }
+
+    /// A tab or newline in a name would otherwise start a new column or row
+    #[test]
+    fn what_would_end_a_value_is_escaped() {
+        assert_eq!(text(&[&"a\tb\nc\\d"]), "a\\tb\\nc\\\\d\n");
+    }
+
+    #[test]
+    fn arrays_quote_every_element() {
+        let types = vec!["Null".to_string(), "Star".to_string()];
+        assert_eq!(text(&[&Array(&types)]), "{\"Null\",\"Star\"}\n");
+        assert_eq!(text(&[&Array(&[31i16, 0])]), "{\"31\",\"0\"}\n");
+    }
+
+    #[test]
+    fn positions_are_well_known_text() {
+        let sol = Point(Coordinate { x: 0., y: 0., z: -1.5 });
+        assert_eq!(text(&[&sol]), "POINT Z (0 0 -1.5)\n");
+    }
+
+    #[test]
+    fn reports_add_up() {
+        let mut report = Report { inserted: 1, ..Report::default() };
+        report += Report { updated: 2, stale: 3, rejected: 4, inserted: 5 };
+        assert_eq!(
+            report.to_string(),
+            "6 inserted, 2 updated, 3 stale, 4 rejected"
+        );
+    }
+}
@@ -8,6 +8,46 @@ pub enum Error {
     Sqlx(sqlx::Error),
 }
 
+impl Error {
+    /// Whether the database could not be reached at all, rather than
+    /// refusing what was asked of it
+    ///
+    /// Asking again later may well work where this is true, and never will
+    /// where it isn't: a row breaking a constraint breaks it next time too.
+    pub fn is_unavailable(&self) -> bool {
+        match self {
+            Error::Env(_) => false,
+            Error::Sqlx(sqlx::Error::Database(e)) => {
+                // Class 08 is a connection failing, 57P01 to 57P03 the
+                // server shutting down or not yet up, and 55P03 and 57014 a
+                // lock or a statement timing out, a table held by something
+                // else for now.
+                e.code().is_some_and(|code| {
+                    code.starts_with("08")
+                        || matches!(
+                            &*code,
+                            "57P01" | "57P02" | "57P03" | "55P03" | "57014"
+                        )
+                })
+            }
+            Error::Sqlx(e) => matches!(
+                e,
+                sqlx::Error::Io(_)
+                    | sqlx::Error::Tls(_)
+                    | sqlx::Error::PoolTimedOut
+                    | sqlx::Error::PoolClosed
+                    | sqlx::Error::WorkerCrashed
+            ),
+        }
+    }
+
+    /// Whether what was asked for is not on record, which is an answer
+    /// rather than a failure to get one
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, Error::Sqlx(sqlx::Error::RowNotFound))
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -0,0 +1,133 @@
+use super::{BodySignals, Event};
+use crate::{Database, Error};
+use chrono::{DateTime, Utc};
+
+impl Event {
+    /// Write whatever the event counts, each count only filling in
+    pub async fn from_journal(
+        db: &Database,
+        timestamp: DateTime<Utc>,
+        user: &str,
+        event: &Event,
+    ) -> Result<(), Error> {
+        let (body_count, non_body_count, all_bodies_found, nav_beacon) =
+            match event {
+                Event::DiscoveryScan { body_count, non_body_count, .. } => {
+                    (Some(*body_count), Some(*non_body_count), false, false)
+                }
+                Event::AllBodiesFound { count, .. } => {
+                    (Some(*count), None, true, false)
+                }
+                Event::NavBeaconScan { num_bodies, .. } => {
+                    (Some(*num_bodies), None, false, true)
+                }
+                Event::BodySignals(signals)
+                | Event::SurfaceSignals(signals) => {
+                    return BodySignals::from_journal(
+                        db, timestamp, user, signals,
+                    )
+                    .await
+                }
+            };
+
+        sqlx::query!(
+            "
+            INSERT INTO system_scans
+                (system_address,
+                 body_count,
+                 non_body_count,
+                 all_bodies_found,
+                 nav_beacon,
+                 updated_at,
+                 updated_by)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (system_address)
+            DO UPDATE SET
+                body_count = COALESCE($2, system_scans.body_count),
+                non_body_count = COALESCE($3, system_scans.non_body_count),
+                all_bodies_found = system_scans.all_bodies_found OR $4,
+                nav_beacon = system_scans.nav_beacon OR $5,
+                updated_at = GREATEST(system_scans.updated_at, $6),
+                updated_by = CASE WHEN system_scans.updated_at < $6
+                    THEN $7 ELSE system_scans.updated_by END
+            ",
+            event.system_address(),
+            body_count,
+            non_body_count,
+            all_bodies_found,
+            nav_beacon,
+            timestamp.naive_utc(),
+            user,
+        )
+        .execute(&db.pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+impl BodySignals {
+    /// Replace the body's signals with these, unless what is on record is
+    /// newer
+    pub async fn from_journal(
+        db: &Database,
+        timestamp: DateTime<Utc>,
+        user: &str,
+        signals: &BodySignals,
+    ) -> Result<(), Error> {
+        let mut tx = db.pool.begin().await?;
+
+        let newer = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM body_signals
+                 WHERE system_address = $1 AND body_id = $2
+                   AND updated_at > $3
+            ) AS "newer!"
+            "#,
+            signals.system_address,
+            signals.body_id,
+            timestamp.naive_utc(),
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if newer {
+            return Ok(());
+        }
+
+        sqlx::query!(
+            "
+            DELETE FROM body_signals
+            WHERE system_address = $1 AND body_id = $2
+            ",
+            signals.system_address,
+            signals.body_id,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        for signal in &signals.signals {
+            sqlx::query!(
+                "
+                INSERT INTO body_signals
+                    (system_address, body_id, kind, count,
+                     updated_at, updated_by)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (system_address, body_id, kind)
+                DO UPDATE SET count = body_signals.count + $4
+                ",
+                signals.system_address,
+                signals.body_id,
+                signal.kind(),
+                signal.count,
+                timestamp.naive_utc(),
+                user,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+use super::{BodySignal, Completeness};
+use crate::{Database, Error};
+
+impl Completeness {
+    /// How much of the system at `address` is on record, which for a system
+    /// never honked is only how many stars and bodies are
+    pub async fn fetch(db: &Database, address: i64) -> Result<Self, Error> {
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                s.body_count,
+                s.non_body_count,
+                COALESCE(s.all_bodies_found, false) AS "all_bodies_found!",
+                COALESCE(s.nav_beacon, false) AS "nav_beacon!",
+                (SELECT count(*) FROM stars WHERE system_address = $1)
+                    + (SELECT count(*) FROM bodies WHERE system_address = $1)
+                    AS "known!"
+            FROM (SELECT $1::bigint AS address) a
+            LEFT JOIN system_scans s ON s.system_address = a.address
+            "#,
+            address
+        )
+        .fetch_one(&db.pool)
+        .await?;
+
+        Ok(Completeness {
+            system_address: address,
+            body_count: row.body_count,
+            non_body_count: row.non_body_count,
+            all_bodies_found: row.all_bodies_found,
+            nav_beacon: row.nav_beacon,
+            known: row.known,
+        })
+    }
+}
+
+impl BodySignal {
+    /// Every signal found on the bodies of the system at `address`
+    pub async fn fetch_all(
+        db: &Database,
+        address: i64,
+    ) -> Result<Vec<Self>, Error> {
+        let rows = sqlx::query!(
+            "
+            SELECT *
+            FROM body_signals
+            WHERE system_address = $1
+            ORDER BY body_id, kind
+            ",
+            address
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| BodySignal {
+                system_address: row.system_address,
+                body_id: row.body_id,
+                kind: row.kind,
+                count: row.count,
+                updated_at: row.updated_at.and_utc(),
+                updated_by: row.updated_by,
+            })
+            .collect())
+    }
+}
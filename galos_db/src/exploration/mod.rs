@@ -0,0 +1,190 @@
+//! How much of a system has been found, and what has been found on its bodies
+//!
+//! A scan says what one body is. These say how many there are to scan, and
+//! what signals each has, which together answer how complete what is on
+//! record for a system is: a system honked as having forty bodies with
+//! twelve of them on record has twenty-eight still to scan.
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+/// An exploration event, as much of it as is kept
+///
+/// Read straight from the journal's own JSON, which is where it comes from
+/// and where nothing else reads it, as a [`crate::ships::Loadout`] is.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Exploration {
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "event")]
+pub enum Event {
+    /// The honk, counting every body in the system
+    #[serde(rename = "FSSDiscoveryScan", rename_all = "PascalCase")]
+    DiscoveryScan { system_address: i64, body_count: i32, non_body_count: i32 },
+    /// Every body the honk counted has been found
+    #[serde(rename = "FSSAllBodiesFound", rename_all = "PascalCase")]
+    AllBodiesFound { system_address: i64, count: i32 },
+    /// A body's signals, as seen from orbit
+    #[serde(rename = "FSSBodySignals")]
+    BodySignals(BodySignals),
+    /// A body's signals, as mapped from close by
+    #[serde(rename = "SAASignalsFound")]
+    SurfaceSignals(BodySignals),
+    /// A nav beacon, which scans the whole system for whoever reads it
+    #[serde(rename = "NavBeaconScan", rename_all = "PascalCase")]
+    NavBeaconScan { system_address: i64, num_bodies: i32 },
+}
+
+impl Event {
+    pub fn system_address(&self) -> i64 {
+        match self {
+            Event::DiscoveryScan { system_address, .. }
+            | Event::AllBodiesFound { system_address, .. }
+            | Event::NavBeaconScan { system_address, .. } => *system_address,
+            Event::BodySignals(signals) | Event::SurfaceSignals(signals) => {
+                signals.system_address
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BodySignals {
+    pub system_address: i64,
+    pub body_name: String,
+    #[serde(rename = "BodyID")]
+    pub body_id: i16,
+    pub signals: Vec<Signal>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Signal {
+    /// `$SAA_SignalType_Biological;`, or a commodity for a ring's hotspots
+    #[serde(rename = "Type")]
+    pub kind: String,
+    pub count: i32,
+}
+
+impl Signal {
+    /// What kind of signal it is, without the game's markup
+    ///
+    /// `$SAA_SignalType_Biological;` is `Biological`, and a hotspot's
+    /// commodity is left as it is.
+    pub fn kind(&self) -> &str {
+        self.kind
+            .strip_prefix("$SAA_SignalType_")
+            .and_then(|kind| kind.strip_suffix(';'))
+            .unwrap_or(&self.kind)
+    }
+}
+
+/// How much of a system is on record, against how much it is known to have
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Completeness {
+    pub system_address: i64,
+    /// Stars and bodies, as the honk or a nav beacon counted them
+    pub body_count: Option<i32>,
+    pub non_body_count: Option<i32>,
+    pub all_bodies_found: bool,
+    pub nav_beacon: bool,
+    /// Stars and bodies on record
+    pub known: i64,
+}
+
+impl Completeness {
+    /// How many bodies there are left to scan, where it is known how many
+    /// there are at all
+    pub fn unscanned(&self) -> Option<i64> {
+        self.body_count.map(|count| (count as i64 - self.known).max(0))
+    }
+
+    /// Whether everything in the system is on record
+    pub fn is_complete(&self) -> bool {
+        self.unscanned() == Some(0)
+    }
+}
+
+/// A kind of signal on a body, and how many
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BodySignal {
+    pub system_address: i64,
+    pub body_id: i16,
+    pub kind: String,
+    pub count: i32,
+    pub updated_at: DateTime<Utc>,
+    pub updated_by: String,
+}
+
+mod create;
+mod fetch;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_read_from_the_journal() {
+        let honk: Exploration = serde_json::from_str(
+            r#"{"timestamp":"2026-10-18T12:00:00Z","event":"FSSDiscoveryScan",
+                "Progress":0.25,"BodyCount":40,"NonBodyCount":3,
+                "SystemName":"Sol","SystemAddress":10477373803}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            honk.event,
+            Event::DiscoveryScan {
+                system_address: 10477373803,
+                body_count: 40,
+                non_body_count: 3,
+            }
+        );
+
+        let mapped: Exploration = serde_json::from_str(
+            r#"{"timestamp":"2026-10-18T12:00:00Z","event":"SAASignalsFound",
+                "BodyName":"Sol 3","SystemAddress":10477373803,"BodyID":3,
+                "Signals":[{"Type":"$SAA_SignalType_Biological;",
+                            "Type_Localised":"Biological","Count":2}]}"#,
+        )
+        .unwrap();
+        let Event::SurfaceSignals(signals) = mapped.event else {
+            panic!("not signals: {:?}", mapped.event);
+        };
+        assert_eq!(signals.body_id, 3);
+        assert_eq!(signals.signals[0].kind(), "Biological");
+        assert_eq!(signals.signals[0].count, 2);
+    }
+
+    #[test]
+    fn other_events_are_not_exploration() {
+        let jump = r#"{"timestamp":"2026-10-18T12:00:00Z","event":"FSDJump"}"#;
+        assert!(serde_json::from_str::<Exploration>(jump).is_err());
+    }
+
+    #[test]
+    fn hotspots_keep_their_commodity() {
+        let signal = Signal { kind: "Painite".into(), count: 1 };
+        assert_eq!(signal.kind(), "Painite");
+    }
+
+    #[test]
+    fn unscanned_bodies_are_those_not_on_record() {
+        let mut system = Completeness::default();
+        assert_eq!(system.unscanned(), None);
+        assert!(!system.is_complete());
+
+        system.body_count = Some(40);
+        system.known = 12;
+        assert_eq!(system.unscanned(), Some(28));
+
+        // A body recorded twice over, or a count short of a belt, is not
+        // less than nothing left.
+        system.known = 41;
+        assert_eq!(system.unscanned(), Some(0));
+        assert!(system.is_complete());
+    }
+}
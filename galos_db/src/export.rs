@@ -0,0 +1,315 @@
+//! Plotted routes, written out for other tools to take
+//!
+//! A route is only as useful as where it can be taken. Each exporter here
+//! writes the same [`Waypoint`]s, so that the command line and the server
+//! hand over the same file for the same route, and the map writes the same
+//! file for a route drawn on it.
+//!
+//! - [`Format::Csv`], a row per system, for a spreadsheet
+//! - [`Format::NavRoute`], shaped like the game's own `NavRoute.json`, which
+//!   anything reading the journal already reads
+//! - [`Format::Spansh`], shaped like the JSON Spansh's plotters answer with,
+//!   which route followers already take
+use crate::systems::nav::fuel::{scoopable, FuelJump};
+use crate::systems::System;
+use crate::{Database, Error};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use serde_json::json;
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// One system along a route, and what it took to get there
+#[derive(Debug, Clone, Serialize)]
+pub struct Waypoint {
+    pub system: System,
+    /// The jump that arrived here, in Ly, nothing for the start
+    pub distance: f64,
+    /// Jumps from the start
+    pub jumps: usize,
+    /// The primary's class, where it is known
+    pub star_class: Option<String>,
+    pub scoopable: bool,
+    /// Whether to scoop here, on a route flown on fuel
+    pub refuel: bool,
+}
+
+impl Waypoint {
+    /// The waypoints of a route through `systems`, start to finish
+    ///
+    /// The primaries are looked up, in one query, for whether each can be
+    /// scooped, and a database that cannot say is an error rather than a
+    /// route with nowhere to scoop. Nothing says to refuel anywhere.
+    pub async fn along(
+        db: &Database,
+        systems: &[System],
+    ) -> Result<Vec<Waypoint>, Error> {
+        let addresses: Vec<i64> = systems.iter().map(|s| s.address).collect();
+        let classes = System::primary_star_classes(db, &addresses).await?;
+
+        let mut waypoints: Vec<Waypoint> = Vec::with_capacity(systems.len());
+        for (jumps, system) in systems.iter().enumerate() {
+            let star_class = classes.get(&system.address).cloned();
+            waypoints.push(Waypoint {
+                system: system.clone(),
+                distance: waypoints
+                    .last()
+                    .map_or(0., |last| last.system.distance(system)),
+                jumps,
+                scoopable: star_class.as_deref().is_some_and(scoopable),
+                star_class,
+                refuel: false,
+            });
+        }
+        Ok(waypoints)
+    }
+
+    /// The waypoints of a route flown on fuel, refuelling where it did
+    pub async fn fuelled(
+        db: &Database,
+        jumps: &[FuelJump],
+    ) -> Result<Vec<Waypoint>, Error> {
+        let systems: Vec<System> = jumps
+            .first()
+            .map(|jump| jump.from.clone())
+            .into_iter()
+            .chain(jumps.iter().map(|jump| jump.to.clone()))
+            .collect();
+        let mut waypoints = Waypoint::along(db, &systems).await?;
+        for (waypoint, jump) in waypoints.iter_mut().skip(1).zip(jumps) {
+            waypoint.refuel = jump.refuel;
+        }
+        Ok(waypoints)
+    }
+}
+
+/// A file a route is written as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    NavRoute,
+    Spansh,
+}
+
+impl Format {
+    /// What the file is, to whoever is downloading it
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Csv => "text/csv",
+            Format::NavRoute | Format::Spansh => "application/json",
+        }
+    }
+
+    /// What to call the file, once downloaded
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            Format::Csv => "route.csv",
+            Format::NavRoute => "NavRoute.json",
+            Format::Spansh => "spansh.json",
+        }
+    }
+
+    /// Write `waypoints` to `out` in this format
+    ///
+    /// `now` is when the route was plotted, which only a nav route says.
+    pub fn write<W: Write>(
+        &self,
+        out: &mut W,
+        waypoints: &[Waypoint],
+        now: DateTime<Utc>,
+    ) -> io::Result<()> {
+        match self {
+            Format::Csv => csv(out, waypoints),
+            Format::NavRoute => nav_route(out, waypoints, now),
+            Format::Spansh => spansh(out, waypoints),
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Format::Csv),
+            "navroute" => Ok(Format::NavRoute),
+            "spansh" => Ok(Format::Spansh),
+            _ => Err("invalid format".to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Csv => write!(f, "csv"),
+            Format::NavRoute => write!(f, "navroute"),
+            Format::Spansh => write!(f, "spansh"),
+        }
+    }
+}
+
+/// A field of a CSV row, quoted where it has to be
+pub fn field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn csv<W: Write>(out: &mut W, waypoints: &[Waypoint]) -> io::Result<()> {
+    writeln!(out, "System,Distance,Jumps,Scoopable,Refuel")?;
+    for waypoint in waypoints {
+        writeln!(
+            out,
+            "{},{:.2},{},{},{}",
+            field(&waypoint.system.name),
+            waypoint.distance,
+            waypoint.jumps,
+            waypoint.scoopable,
+            waypoint.refuel
+        )?;
+    }
+    Ok(())
+}
+
+/// Where a system is, as the game writes it, `[x, y, z]`
+fn star_pos(system: &System) -> [f64; 3] {
+    system.position.map_or([0.; 3], |p| [p.x, p.y, p.z])
+}
+
+fn nav_route<W: Write>(
+    out: &mut W,
+    waypoints: &[Waypoint],
+    now: DateTime<Utc>,
+) -> io::Result<()> {
+    let route: Vec<_> = waypoints
+        .iter()
+        .map(|waypoint| {
+            json!({
+                "StarSystem": waypoint.system.name,
+                "SystemAddress": waypoint.system.address,
+                "StarPos": star_pos(&waypoint.system),
+                "StarClass": waypoint.star_class.as_deref().unwrap_or(""),
+            })
+        })
+        .collect();
+    let file = json!({
+        "timestamp": now.to_rfc3339_opts(SecondsFormat::Secs, true),
+        "event": "NavRoute",
+        "Route": route,
+    });
+    serde_json::to_writer_pretty(&mut *out, &file)?;
+    writeln!(out)
+}
+
+fn spansh<W: Write>(out: &mut W, waypoints: &[Waypoint]) -> io::Result<()> {
+    let end = waypoints.last().map(|last| &last.system);
+    let jumps: Vec<_> = waypoints
+        .iter()
+        .map(|waypoint| {
+            let [x, y, z] = star_pos(&waypoint.system);
+            json!({
+                "system": waypoint.system.name,
+                "id64": waypoint.system.address,
+                "x": x,
+                "y": y,
+                "z": z,
+                "distance_jumped": waypoint.distance,
+                "distance_left": end.map_or(0., |end| waypoint.system.distance(end)),
+                "jumps": waypoint.jumps,
+                "neutron_star": waypoint.star_class.as_deref() == Some("N"),
+                "is_scoopable": waypoint.scoopable,
+                "must_refuel": waypoint.refuel,
+            })
+        })
+        .collect();
+    let file = json!({
+        "result": {
+            "source_system": waypoints.first().map(|w| &w.system.name),
+            "destination_system": end.map(|end| &end.name),
+            "total_jumps": waypoints.len().saturating_sub(1),
+            "system_jumps": jumps,
+        },
+    });
+    serde_json::to_writer_pretty(&mut *out, &file)?;
+    writeln!(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use elite_journal::entry::route::NavRoute;
+    use elite_journal::entry::{Entry, Event};
+    use elite_journal::system::Coordinate;
+
+    fn waypoints() -> Vec<Waypoint> {
+        let at = |name: &str, address: i64, x: f64, class: &str| Waypoint {
+            system: System {
+                address,
+                name: name.to_string(),
+                position: Some(Coordinate { x, y: 0., z: 0. }),
+                population: 0,
+                security: None,
+                government: None,
+                allegiance: None,
+                economies: None,
+                factions: vec![],
+                updated_at: Utc::now(),
+                updated_by: "test".to_string(),
+            },
+            distance: if x == 0. { 0. } else { 5. },
+            jumps: (x / 5.) as usize,
+            star_class: Some(class.to_string()),
+            scoopable: scoopable(class),
+            refuel: false,
+        };
+        vec![
+            at("Sol", 10477373803, 0., "G"),
+            at("Alpha, Centauri", 1458376315610, 5., "N"),
+            at("Luhman 16", 22960358574928, 10., "L"),
+        ]
+    }
+
+    /// What is written as a nav route is read back by the journal's own
+    /// parser, system for system
+    #[test]
+    fn nav_routes_round_trip() {
+        let mut out = Vec::new();
+        Format::NavRoute.write(&mut out, &waypoints(), Utc::now()).unwrap();
+        let entry: Entry<Event> = serde_json::from_slice(&out).unwrap();
+        let Event::NavRoute(NavRoute::Route(route)) = entry.event else {
+            panic!("not a nav route");
+        };
+        let names: Vec<&str> =
+            route.iter().map(|d| d.star_system.as_str()).collect();
+        assert_eq!(names, ["Sol", "Alpha, Centauri", "Luhman 16"]);
+        assert_eq!(route[1].system_address as i64, 1458376315610);
+        assert_eq!(route[1].star_class, "N");
+    }
+
+    /// Names with commas in them stay one field
+    #[test]
+    fn csv_quotes_what_needs_it() {
+        let mut out = Vec::new();
+        Format::Csv.write(&mut out, &waypoints(), Utc::now()).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "System,Distance,Jumps,Scoopable,Refuel");
+        assert_eq!(lines[2], "\"Alpha, Centauri\",5.00,1,false,false");
+        assert_eq!(lines[3], "Luhman 16,5.00,2,false,false");
+    }
+
+    #[test]
+    fn spansh_marks_neutrons() {
+        let mut out = Vec::new();
+        Format::Spansh.write(&mut out, &waypoints(), Utc::now()).unwrap();
+        let file: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        let jumps = &file["result"]["system_jumps"];
+        assert_eq!(jumps[1]["neutron_star"], true);
+        assert_eq!(jumps[2]["distance_left"], 0.);
+        assert_eq!(file["result"]["total_jumps"], 2);
+    }
+}
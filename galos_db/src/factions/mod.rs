@@ -1,14 +1,15 @@
 //! Factions of a system
 use chrono::{DateTime, Utc};
 use elite_journal::{faction::State as JournalState, prelude::*};
+use serde::Serialize;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Serialize)]
 pub struct Faction {
     pub id: i32,
     pub name: String,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize)]
 pub struct SystemFaction {
     pub system_address: i64,
     pub faction_id: u32,
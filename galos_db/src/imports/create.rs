@@ -0,0 +1,43 @@
+use super::{Import, Source};
+use crate::{Database, Error};
+use chrono::Utc;
+
+impl Import {
+    /// Record that `source` has been imported this far
+    ///
+    /// Written every so often as an import goes, so that one stopped partway
+    /// loses only what came after the last of these.
+    pub async fn checkpoint(
+        db: &Database,
+        source: &Source,
+        processed: i64,
+        complete: bool,
+    ) -> Result<(), Error> {
+        sqlx::query!(
+            "
+            INSERT INTO imports
+                (path, size, hash, modified, processed, complete, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (path)
+            DO UPDATE SET
+                size = $2,
+                hash = $3,
+                modified = $4,
+                processed = $5,
+                complete = $6,
+                updated_at = $7
+            ",
+            source.path,
+            source.size,
+            source.hash,
+            source.modified.naive_utc(),
+            processed,
+            complete,
+            Utc::now().naive_utc(),
+        )
+        .execute(&db.pool)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+use super::{Growth, Import, Resume, Source};
+use crate::{Database, Error};
+
+impl Import {
+    pub async fn fetch(
+        db: &Database,
+        path: &str,
+    ) -> Result<Option<Self>, Error> {
+        let row = sqlx::query!(
+            "
+            SELECT *
+            FROM imports
+            WHERE path = $1
+            ",
+            path
+        )
+        .fetch_optional(&db.pool)
+        .await?;
+
+        Ok(row.map(|row| Import {
+            path: row.path,
+            size: row.size,
+            hash: row.hash,
+            modified: row.modified.and_utc(),
+            processed: row.processed,
+            complete: row.complete,
+            updated_at: row.updated_at.and_utc(),
+        }))
+    }
+
+    /// Where to start importing `source`, from the start when `force`d
+    ///
+    /// A file that cannot be read again to be compared is imported whole.
+    pub async fn resume(
+        db: &Database,
+        source: &Source,
+        growth: Growth,
+        force: bool,
+    ) -> Result<Resume, Error> {
+        if force {
+            return Ok(Resume::From(0));
+        }
+        let last = Import::fetch(db, &source.path).await?;
+        Ok(source.since(last.as_ref(), growth).unwrap_or(Resume::From(0)))
+    }
+}
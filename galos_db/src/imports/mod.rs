@@ -0,0 +1,267 @@
+//! How far each file imported has got, so that an import picks up where it
+//! left off
+//!
+//! Years of journals, or an EDSM dump of several gigabytes, take long enough
+//! to import that doing it all again every run, or from the start after
+//! being stopped, is most of the time an import takes. Each importer keeps
+//! an [`Import`] per file, checkpointed as it goes, and asks [`Source::since`]
+//! where to start.
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+
+/// How much of a file is hashed to know it again
+///
+/// All of it would take as long to read as a dump takes to download, and a
+/// file the game or EDSM writes over is different well within this.
+const HEAD: u64 = 1 << 20;
+
+/// A file as it was last imported
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Import {
+    pub path: String,
+    pub size: i64,
+    pub hash: String,
+    /// When the file was last written
+    pub modified: DateTime<Utc>,
+    /// How far the import got, in whatever its importer counts in
+    pub processed: i64,
+    /// Whether it got to the end
+    pub complete: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A file as it is now, about to be imported
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Source {
+    /// Absolute, so the same file given two ways is the one import
+    pub path: String,
+    pub size: i64,
+    /// SHA-256 of the first [`HEAD`] bytes, in hex
+    pub hash: String,
+    /// To the microsecond, which is all the database keeps
+    pub modified: DateTime<Utc>,
+}
+
+/// How a file is changed between one import and the next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Growth {
+    /// Only ever added to, as the game writes a journal
+    Appended,
+    /// Written over whole, as a dump is each night
+    Replaced,
+}
+
+/// Where an import of a file should start
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resume {
+    /// Nothing has changed since it was all imported
+    Skip,
+    /// From this far in, zero being the start
+    From(i64),
+}
+
+impl Source {
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = fs::canonicalize(path)?;
+        let metadata = fs::metadata(&path)?;
+        let modified = DateTime::<Utc>::from(metadata.modified()?);
+        Ok(Source {
+            hash: head(&path, HEAD)?,
+            path: path.to_string_lossy().into_owned(),
+            size: metadata.len() as i64,
+            modified: modified
+                .duration_trunc(TimeDelta::microseconds(1))
+                .unwrap_or(modified),
+        })
+    }
+
+    /// Where to start, given how the last import of this path went
+    ///
+    /// A file whose start has changed is a different file, imported again
+    /// from the start, as is one shorter than it was. One the same is skipped
+    /// if the import got to the end. One grown since has been added to, as a
+    /// journal is while the game is running, and carries on from where it
+    /// was.
+    ///
+    /// Unless it is [`Growth::Replaced`]. A new dump is as likely to be
+    /// larger as not, and to start the same way, without the records it was
+    /// counted to being where they were, so it carries on only if it is the
+    /// same size and was last written at the same time.
+    pub fn since(
+        &self,
+        last: Option<&Import>,
+        growth: Growth,
+    ) -> io::Result<Resume> {
+        self.since_with(last, growth, |len| head(Path::new(&self.path), len))
+    }
+
+    /// [`Source::since`], hashing the first `len` bytes with `head`
+    ///
+    /// A file still smaller than [`HEAD`] when it was imported was hashed
+    /// whole, so what it has grown by since has to be left out to know it
+    /// again.
+    fn since_with(
+        &self,
+        last: Option<&Import>,
+        growth: Growth,
+        head: impl FnOnce(u64) -> io::Result<String>,
+    ) -> io::Result<Resume> {
+        let Some(last) = last else { return Ok(Resume::From(0)) };
+        if growth == Growth::Replaced
+            && (last.size != self.size || last.modified != self.modified)
+        {
+            return Ok(Resume::From(0));
+        }
+        if last.size > self.size {
+            return Ok(Resume::From(0));
+        }
+        let hash = if last.size == self.size || last.size as u64 >= HEAD {
+            self.hash.clone()
+        } else {
+            head(last.size as u64)?
+        };
+        Ok(if hash != last.hash {
+            Resume::From(0)
+        } else if last.complete && last.size == self.size {
+            Resume::Skip
+        } else {
+            Resume::From(last.processed)
+        })
+    }
+}
+
+/// SHA-256 of the first `len` bytes of the file at `path`, in hex
+fn head(path: &Path, len: u64) -> io::Result<String> {
+    let mut bytes = Vec::new();
+    File::open(path)?.take(len).read_to_end(&mut bytes)?;
+    Ok(format!("{:x}", Sha256::digest(&bytes)))
+}
+
+mod create;
+mod fetch;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn written() -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000, 0).unwrap()
+    }
+
+    fn source(size: i64, hash: &str) -> Source {
+        Source {
+            path: "/j".to_string(),
+            size,
+            hash: hash.to_string(),
+            modified: written(),
+        }
+    }
+
+    fn import(size: i64, hash: &str, processed: i64, complete: bool) -> Import {
+        Import {
+            path: "/j".to_string(),
+            size,
+            hash: hash.to_string(),
+            modified: written(),
+            processed,
+            complete,
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// Where to start, the file having been `a` up to each length
+    fn since(source: &Source, last: Option<&Import>) -> Resume {
+        since_as(source, last, Growth::Appended)
+    }
+
+    fn since_as(
+        source: &Source,
+        last: Option<&Import>,
+        growth: Growth,
+    ) -> Resume {
+        source.since_with(last, growth, |_| Ok("a".to_string())).unwrap()
+    }
+
+    #[test]
+    fn new_files_start_at_the_start() {
+        assert_eq!(since(&source(10, "a"), None), Resume::From(0));
+    }
+
+    #[test]
+    fn finished_files_are_skipped() {
+        let last = import(10, "a", 10, true);
+        assert_eq!(since(&source(10, "a"), Some(&last)), Resume::Skip);
+    }
+
+    /// Stopped partway, or grown since, it carries on
+    #[test]
+    fn unfinished_files_resume() {
+        let stopped = import(10, "a", 4, false);
+        assert_eq!(since(&source(10, "a"), Some(&stopped)), Resume::From(4));
+        let grown = import(10, "a", 10, true);
+        assert_eq!(since(&source(15, "b"), Some(&grown)), Resume::From(10));
+    }
+
+    /// A different start, or a file shorter than it was, was written over
+    #[test]
+    fn changed_files_start_again() {
+        let last = import(10, "a", 10, true);
+        assert_eq!(since(&source(10, "b"), Some(&last)), Resume::From(0));
+        assert_eq!(since(&source(5, "a"), Some(&last)), Resume::From(0));
+        let grown = import(10, "c", 10, true);
+        assert_eq!(since(&source(15, "b"), Some(&grown)), Resume::From(0));
+    }
+
+    /// A dump carries on only if it is the one stopped partway through
+    #[test]
+    fn replaced_files_resume_only_unchanged() {
+        let stopped = import(10, "a", 4, false);
+        let same = source(10, "a");
+        assert_eq!(
+            since_as(&same, Some(&stopped), Growth::Replaced),
+            Resume::From(4)
+        );
+        let grown = source(15, "b");
+        assert_eq!(
+            since_as(&grown, Some(&stopped), Growth::Replaced),
+            Resume::From(0)
+        );
+        let rewritten = Source { modified: Utc::now(), ..source(10, "a") };
+        assert_eq!(
+            since_as(&rewritten, Some(&stopped), Growth::Replaced),
+            Resume::From(0)
+        );
+    }
+
+    /// A journal added to is known again by the part of it imported
+    #[test]
+    fn grown_files_are_known_by_what_was_there() {
+        let path = std::env::temp_dir()
+            .join(format!("galos-import-{}", std::process::id()));
+        fs::write(&path, "Sol\n").unwrap();
+        let sol = Source::read(&path).unwrap();
+        let last = Import {
+            path: sol.path.clone(),
+            size: sol.size,
+            hash: sol.hash.clone(),
+            modified: sol.modified,
+            processed: 4,
+            complete: true,
+            updated_at: Utc::now(),
+        };
+        fs::write(&path, "Sol\nAchenar\n").unwrap();
+        let grown = Source::read(&path).unwrap();
+        let resume = grown.since(Some(&last), Growth::Appended).unwrap();
+        fs::write(&path, "Lave\nAchenar\n").unwrap();
+        let replaced = Source::read(&path).unwrap();
+        let restart = replaced.since(Some(&last), Growth::Appended).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_ne!(sol.hash, grown.hash);
+        assert_eq!(resume, Resume::From(4));
+        assert_eq!(restart, Resume::From(0));
+    }
+}
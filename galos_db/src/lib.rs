@@ -8,6 +8,7 @@
 //! the `DATABASE_URL`.
 use sqlx::postgres::{PgPool, PgPoolOptions};
 use std::env;
+use std::time::Duration;
 
 pub mod error;
 pub use self::error::{Error, Result};
@@ -33,6 +34,24 @@ impl Database {
 
         Ok(Database { pool })
     }
+
+    /// The same database, on connections that give up waiting on a lock
+    /// after `lock`, and on any statement after `statement`
+    ///
+    /// For writers that would rather fail and come back than sit behind a
+    /// migration holding a table. Either running out is
+    /// [unavailable](Error::is_unavailable).
+    pub fn with_timeouts(&self, lock: Duration, statement: Duration) -> Self {
+        let options = (*self.pool.connect_options()).clone().options([
+            ("lock_timeout", format!("{}ms", lock.as_millis())),
+            ("statement_timeout", format!("{}ms", statement.as_millis())),
+        ]);
+        let pool = PgPoolOptions::new()
+            .max_connections(self.pool.options().get_max_connections())
+            .connect_lazy_with(options);
+
+        Database { pool }
+    }
 }
 
 pub struct Page {
@@ -106,9 +125,17 @@ mod tests {
 pub mod articles;
 pub mod barycenters;
 pub mod bodies;
+pub mod bulk;
+pub mod exploration;
+pub mod export;
 pub mod factions;
+pub mod imports;
 pub mod markets;
 mod orbit;
+pub mod outfitting;
+pub mod quarantine;
+pub mod search;
+pub mod ships;
 pub mod stars;
 pub mod stations;
 pub mod systems;
@@ -0,0 +1,332 @@
+use super::{Commodity, Criteria, Market, Quote};
+use crate::{Database, Error};
+
+/// Turn a row of `commodities`, joined to where it is traded, into a quote
+///
+/// The queries below differ in what they order by and which side of a trade
+/// they count, and select the same columns, so the mapping is written once.
+/// A macro for the reason `stars/fetch.rs` gives: each `sqlx::query!` row is
+/// a type of its own with no name to put in a signature.
+macro_rules! quote {
+    ($row:expr) => {{
+        let row = $row;
+        Quote {
+            commodity: Commodity {
+                market_id: row.market_id,
+                name: row.name,
+                mean_price: row.mean_price,
+                buy_price: row.buy_price,
+                sell_price: row.sell_price,
+                demand: row.demand,
+                demand_bracket: row.demand_bracket,
+                stock: row.stock,
+                stock_bracket: row.stock_bracket,
+                listed_at: row.listed_at.and_utc(),
+            },
+            system_address: row.system_address,
+            system_name: row.system_name,
+            station_name: row.station_name,
+            dist_from_star_ls: row.dist_from_star_ls,
+            distance: row.distance,
+        }
+    }};
+}
+
+impl Market {
+    pub async fn fetch(db: &Database, id: i64) -> Result<Self, Error> {
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                id,
+                system_address,
+                system_name,
+                station_name,
+                updated_at
+            FROM markets
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_one(&db.pool)
+        .await?;
+
+        Ok(Market {
+            id: row.id,
+            system_address: row.system_address,
+            system_name: row.system_name,
+            station_name: row.station_name,
+            updated_at: row.updated_at.and_utc(),
+        })
+    }
+
+    /// The market at a station, by the station's own key
+    ///
+    /// A market waiting on its system has no address to be found by, and is
+    /// not found here until the system turns up and adopts it.
+    pub async fn fetch_by_station(
+        db: &Database,
+        system_address: i64,
+        station_name: &str,
+    ) -> Result<Self, Error> {
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                id,
+                system_address,
+                system_name,
+                station_name,
+                updated_at
+            FROM markets
+            WHERE system_address = $1 AND station_name = $2
+            "#,
+            system_address,
+            station_name
+        )
+        .fetch_one(&db.pool)
+        .await?;
+
+        Ok(Market {
+            id: row.id,
+            system_address: row.system_address,
+            system_name: row.system_name,
+            station_name: row.station_name,
+            updated_at: row.updated_at.and_utc(),
+        })
+    }
+}
+
+impl Commodity {
+    /// Everything a market trades, by name
+    pub async fn fetch_all(
+        db: &Database,
+        market_id: i64,
+    ) -> Result<Vec<Self>, Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT *
+            FROM commodities
+            WHERE market_id = $1
+            ORDER BY name
+            "#,
+            market_id
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Commodity {
+                market_id: row.market_id,
+                name: row.name,
+                mean_price: row.mean_price,
+                buy_price: row.buy_price,
+                sell_price: row.sell_price,
+                demand: row.demand,
+                demand_bracket: row.demand_bracket,
+                stock: row.stock,
+                stock_bracket: row.stock_bracket,
+                listed_at: row.listed_at.and_utc(),
+            })
+            .collect())
+    }
+
+    /// Where `name` sells for the most within `range` of `center`, best first
+    ///
+    /// A sale is what a market pays for the commodity, and a market that wants
+    /// none of it pays for none of it however good the price, so what counts
+    /// is `sell_price` where the demand is at least what `criteria` asks.
+    /// Ties go to the nearer market.
+    ///
+    /// Reached the way [`crate::systems::System::fetch_in_range_of_point`]
+    /// reaches its systems, by position, and from there to the markets in
+    /// them. A market still waiting on its system has no position and is
+    /// never in range of anything.
+    pub async fn best_sale_in_range_of_point(
+        db: &Database,
+        name: &str,
+        range: f64,
+        center: [f64; 3],
+        criteria: &Criteria,
+        limit: i64,
+    ) -> Result<Vec<Quote>, Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                c.market_id,
+                c.name,
+                c.mean_price,
+                c.buy_price,
+                c.sell_price,
+                c.demand,
+                c.demand_bracket,
+                c.stock,
+                c.stock_bracket,
+                c.listed_at,
+                s.address AS system_address,
+                s.name AS system_name,
+                m.station_name,
+                st.dist_from_star_ls,
+                ST_3DDistance(ST_MakePoint($3, $4, $5), s.position)
+                    AS "distance!"
+            FROM commodities c
+            JOIN markets m ON m.id = c.market_id
+            JOIN systems s ON s.address = m.system_address
+            LEFT JOIN stations st
+              ON st.system_address = m.system_address
+             AND st.name = m.station_name
+            WHERE c.name = LOWER($1)
+              AND ST_3DDWithin(ST_MakePoint($3, $4, $5), s.position, $2)
+              AND c.sell_price > 0
+              AND c.demand >= $6
+              AND ($7::timestamp IS NULL OR c.listed_at >= $7)
+              -- A ship fits any pad at least its own size. Pads nobody has
+              -- recorded fit nothing, as `PadSize::fits` has it.
+              AND ($8::int IS NULL
+                   OR (st.landing_pads).large > 0
+                   OR ($8 < 3 AND (st.landing_pads).medium > 0)
+                   OR ($8 < 2 AND (st.landing_pads).small > 0))
+            ORDER BY c.sell_price DESC, "distance!"
+            LIMIT $9
+            "#,
+            name,
+            range,
+            center[0],
+            center[1],
+            center[2],
+            criteria.min_demand,
+            criteria.listed_since().map(|t| t.naive_utc()),
+            criteria.pad.map(|pad| pad as i32),
+            limit,
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| quote!(row)).collect())
+    }
+
+    /// Where `name` can be bought for the least within `range` of `center`,
+    /// best first
+    ///
+    /// The other side of [`Commodity::best_sale_in_range_of_point`]: what a
+    /// market asks for the commodity, where it holds at least the stock
+    /// `criteria` asks for. A `buy_price` of nothing is a market that does not
+    /// sell it at all, rather than one giving it away.
+    pub async fn best_purchase_in_range_of_point(
+        db: &Database,
+        name: &str,
+        range: f64,
+        center: [f64; 3],
+        criteria: &Criteria,
+        limit: i64,
+    ) -> Result<Vec<Quote>, Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                c.market_id,
+                c.name,
+                c.mean_price,
+                c.buy_price,
+                c.sell_price,
+                c.demand,
+                c.demand_bracket,
+                c.stock,
+                c.stock_bracket,
+                c.listed_at,
+                s.address AS system_address,
+                s.name AS system_name,
+                m.station_name,
+                st.dist_from_star_ls,
+                ST_3DDistance(ST_MakePoint($3, $4, $5), s.position)
+                    AS "distance!"
+            FROM commodities c
+            JOIN markets m ON m.id = c.market_id
+            JOIN systems s ON s.address = m.system_address
+            LEFT JOIN stations st
+              ON st.system_address = m.system_address
+             AND st.name = m.station_name
+            WHERE c.name = LOWER($1)
+              AND ST_3DDWithin(ST_MakePoint($3, $4, $5), s.position, $2)
+              AND c.buy_price > 0
+              AND c.stock >= $6
+              AND ($7::timestamp IS NULL OR c.listed_at >= $7)
+              AND ($8::int IS NULL
+                   OR (st.landing_pads).large > 0
+                   OR ($8 < 3 AND (st.landing_pads).medium > 0)
+                   OR ($8 < 2 AND (st.landing_pads).small > 0))
+            ORDER BY c.buy_price, "distance!"
+            LIMIT $9
+            "#,
+            name,
+            range,
+            center[0],
+            center[1],
+            center[2],
+            criteria.min_stock,
+            criteria.listed_since().map(|t| t.naive_utc()),
+            criteria.pad.map(|pad| pad as i32),
+            limit,
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| quote!(row)).collect())
+    }
+
+    /// Everything traded at every market within `range` of `center`
+    ///
+    /// What a trade planner works from, which has to hold both ends of a
+    /// trade at once and so wants the whole region rather than the best of one
+    /// commodity. Narrowed by the pad and the age `criteria` ask for; demand
+    /// and stock are left to the caller, since which of them matters depends
+    /// on which end of a trade a market turns out to be.
+    pub async fn fetch_in_range_of_point(
+        db: &Database,
+        range: f64,
+        center: [f64; 3],
+        criteria: &Criteria,
+    ) -> Result<Vec<Quote>, Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                c.market_id,
+                c.name,
+                c.mean_price,
+                c.buy_price,
+                c.sell_price,
+                c.demand,
+                c.demand_bracket,
+                c.stock,
+                c.stock_bracket,
+                c.listed_at,
+                s.address AS system_address,
+                s.name AS system_name,
+                m.station_name,
+                st.dist_from_star_ls,
+                ST_3DDistance(ST_MakePoint($2, $3, $4), s.position)
+                    AS "distance!"
+            FROM commodities c
+            JOIN markets m ON m.id = c.market_id
+            JOIN systems s ON s.address = m.system_address
+            LEFT JOIN stations st
+              ON st.system_address = m.system_address
+             AND st.name = m.station_name
+            WHERE ST_3DDWithin(ST_MakePoint($2, $3, $4), s.position, $1)
+              AND ($5::timestamp IS NULL OR c.listed_at >= $5)
+              AND ($6::int IS NULL
+                   OR (st.landing_pads).large > 0
+                   OR ($6 < 3 AND (st.landing_pads).medium > 0)
+                   OR ($6 < 2 AND (st.landing_pads).small > 0))
+            "#,
+            range,
+            center[0],
+            center[1],
+            center[2],
+            criteria.listed_since().map(|t| t.naive_utc()),
+            criteria.pad.map(|pad| pad as i32),
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| quote!(row)).collect())
+    }
+}
@@ -1,5 +1,7 @@
 //! The commodities a station buys and sells
-use chrono::{DateTime, Utc};
+use crate::stations::PadSize;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Market {
@@ -15,7 +17,7 @@ pub struct Market {
 }
 
 /// One commodity as a market trades it
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
 pub struct Commodity {
     pub market_id: i64,
     pub name: String,
@@ -29,5 +31,47 @@ pub struct Commodity {
     pub listed_at: DateTime<Utc>,
 }
 
+/// A commodity where it is traded, and how far that is from where the
+/// question was asked
+///
+/// What a price is worth depends on where it can be had, so an answer to
+/// "where is gold cheapest" names the station and the system along with the
+/// price, and how far off it lies.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Quote {
+    pub commodity: Commodity,
+    pub system_address: i64,
+    pub system_name: String,
+    pub station_name: String,
+    /// How far the station is from its system's arrival star, where known
+    pub dist_from_star_ls: Option<f64>,
+    /// Light years from the point the quote was asked about
+    pub distance: f64,
+}
+
+/// Which markets are worth being told about
+///
+/// A price nobody can land to take, or that was read off a market a month
+/// ago, answers the question without helping whoever asked it. Every field
+/// narrows, and the default narrows nothing.
+#[derive(Debug, Clone, Default)]
+pub struct Criteria {
+    /// The pad the ship needs, so a station without one is passed over
+    pub pad: Option<PadSize>,
+    /// How old a price may be before it stops being believed
+    pub max_age: Option<Duration>,
+    /// How much a market has to want before selling there is counted
+    pub min_demand: i32,
+    /// How much a market has to hold before buying there is counted
+    pub min_stock: i32,
+}
+
+impl Criteria {
+    /// The oldest a listing may be and still be counted, if there is a limit
+    pub(crate) fn listed_since(&self) -> Option<DateTime<Utc>> {
+        self.max_age.map(|age| Utc::now() - age)
+    }
+}
+
 mod create;
-// mod fetch;
+mod fetch;
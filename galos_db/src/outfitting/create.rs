@@ -0,0 +1,211 @@
+use super::{Module, Outfitting, Shipyard};
+use crate::systems::System;
+use crate::{Database, Error};
+use chrono::{DateTime, Utc};
+use sqlx::{Postgres, Transaction};
+
+impl Outfitting {
+    /// Write what a station's outfitting sells, in place of what it sold
+    /// before, unless what is on record is newer
+    pub async fn from_eddn(
+        db: &Database,
+        outfitting: &Outfitting,
+    ) -> Result<(), Error> {
+        let address = address(db, &outfitting.system_name).await?;
+        let mut tx = db.pool.begin().await?;
+        let listed_at = outfitting.timestamp.naive_utc();
+
+        let newer = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM outfitting
+                 WHERE market_id = $1 AND listed_at > $2
+            ) AS "newer!"
+            "#,
+            outfitting.market_id,
+            listed_at,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if newer {
+            return Ok(());
+        }
+
+        locate(
+            &mut tx,
+            outfitting.market_id,
+            address,
+            &outfitting.system_name,
+            &outfitting.station_name,
+            outfitting.timestamp,
+        )
+        .await?;
+
+        sqlx::query!(
+            "DELETE FROM outfitting WHERE market_id = $1",
+            outfitting.market_id,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let modules: Vec<Module> =
+            outfitting.modules.iter().map(|name| Module::parse(name)).collect();
+        let names: Vec<String> =
+            modules.iter().map(|m| m.name.clone()).collect();
+        let items: Vec<String> =
+            modules.iter().map(|m| m.item.clone()).collect();
+        let classes: Vec<Option<i16>> =
+            modules.iter().map(|m| m.class).collect();
+        let ratings: Vec<Option<String>> =
+            modules.iter().map(|m| m.rating.map(String::from)).collect();
+        // The whole list goes in as one statement, each column an array.
+        // The conflict clause answers for a list naming a module twice.
+        sqlx::query!(
+            r#"
+            INSERT INTO outfitting (
+                market_id,
+                module,
+                item,
+                class,
+                rating,
+                listed_at)
+            SELECT $1, module, item, class, rating, $6
+              FROM UNNEST($2::varchar[], $3::varchar[], $4::smallint[],
+                          $5::char(1)[])
+                AS m (module, item, class, rating)
+            ON CONFLICT (market_id, module) DO NOTHING
+            "#,
+            outfitting.market_id,
+            &names,
+            &items,
+            &classes as &[Option<i16>],
+            &ratings as &[Option<String>],
+            listed_at,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+impl Shipyard {
+    /// Write what a station's shipyard sells, in place of what it sold before,
+    /// unless what is on record is newer
+    pub async fn from_eddn(
+        db: &Database,
+        shipyard: &Shipyard,
+    ) -> Result<(), Error> {
+        let address = address(db, &shipyard.system_name).await?;
+        let mut tx = db.pool.begin().await?;
+        let listed_at = shipyard.timestamp.naive_utc();
+
+        let newer = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM shipyard
+                 WHERE market_id = $1 AND listed_at > $2
+            ) AS "newer!"
+            "#,
+            shipyard.market_id,
+            listed_at,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if newer {
+            return Ok(());
+        }
+
+        locate(
+            &mut tx,
+            shipyard.market_id,
+            address,
+            &shipyard.system_name,
+            &shipyard.station_name,
+            shipyard.timestamp,
+        )
+        .await?;
+
+        sqlx::query!(
+            "DELETE FROM shipyard WHERE market_id = $1",
+            shipyard.market_id,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let ships: Vec<String> =
+            shipyard.ships.iter().map(|ship| ship.to_lowercase()).collect();
+        sqlx::query!(
+            r#"
+            INSERT INTO shipyard (market_id, ship, listed_at)
+            SELECT $1, ship, $3
+              FROM UNNEST($2::varchar[]) AS s (ship)
+            ON CONFLICT (market_id, ship) DO NOTHING
+            "#,
+            shipyard.market_id,
+            &ships,
+            listed_at,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+/// The address of the system a list names, if it is known
+///
+/// Asked before the list's transaction is begun, as
+/// [`crate::markets::Market::from_journal`] asks it, and for the same
+/// reasons: a system not known yet leaves the market waiting on it, and
+/// anything else is the database failing to answer.
+async fn address(
+    db: &Database,
+    system_name: &str,
+) -> Result<Option<i64>, Error> {
+    match System::fetch_by_name(db, system_name).await {
+        Ok(system) => Ok(Some(system.address)),
+        Err(Error::Sqlx(sqlx::Error::RowNotFound)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Put the market a list hangs off where the list says it is
+///
+/// What the market last priced is left alone, and so is when it did, a
+/// station's outfitting saying nothing of its commodities.
+async fn locate(
+    tx: &mut Transaction<'_, Postgres>,
+    market_id: i64,
+    address: Option<i64>,
+    system_name: &str,
+    station_name: &str,
+    timestamp: DateTime<Utc>,
+) -> Result<(), Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO markets (
+            id,
+            system_address,
+            system_name,
+            station_name,
+            updated_at)
+        VALUES ($1, $2, UPPER($3), $4, $5)
+        ON CONFLICT (id)
+        DO UPDATE SET
+            system_address = $2,
+            system_name = UPPER($3),
+            station_name = $4
+        "#,
+        market_id,
+        address,
+        system_name,
+        station_name,
+        timestamp.naive_utc(),
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
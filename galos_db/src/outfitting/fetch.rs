@@ -0,0 +1,167 @@
+use super::{ship_key, Module, Offer};
+use crate::markets::Criteria;
+use crate::{escaped, Database, Error};
+
+impl Offer {
+    /// The nearest stations within `range` of `center` selling `module`,
+    /// nearest first
+    ///
+    /// `module` is either the whole name, `Int_Hyperdrive_Size5_Class5`, or
+    /// the item, `Int_Hyperdrive` or just `hyperdrive`, narrowed by `class`
+    /// and `rating` where they are given, or where the name gives them. Of
+    /// `criteria`, only the pad and the age count, a station selling a module
+    /// or not.
+    ///
+    /// Reached the way
+    /// [`crate::markets::Commodity::best_sale_in_range_of_point`] reaches its
+    /// markets, and a market still waiting on its system is never in range
+    /// of anything here either.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn nearest_module(
+        db: &Database,
+        module: &str,
+        class: Option<i16>,
+        rating: Option<char>,
+        range: f64,
+        center: [f64; 3],
+        criteria: &Criteria,
+        limit: i64,
+    ) -> Result<Vec<Self>, Error> {
+        let module = Module::parse(module);
+        let class = class.or(module.class);
+        let rating = rating.or(module.rating);
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                o.module,
+                o.class,
+                o.rating,
+                o.market_id,
+                o.listed_at,
+                s.address AS system_address,
+                s.name AS system_name,
+                m.station_name,
+                st.dist_from_star_ls,
+                ST_3DDistance(ST_MakePoint($6, $7, $8), s.position)
+                    AS "distance!"
+            FROM outfitting o
+            JOIN markets m ON m.id = o.market_id
+            JOIN systems s ON s.address = m.system_address
+            LEFT JOIN stations st
+              ON st.system_address = m.system_address
+             AND st.name = m.station_name
+            WHERE (o.item = $1 OR o.item LIKE $2)
+              AND ($3::smallint IS NULL OR o.class = $3)
+              AND ($4::char(1) IS NULL OR o.rating = $4)
+              AND ST_3DDWithin(ST_MakePoint($6, $7, $8), s.position, $5)
+              AND ($9::timestamp IS NULL OR o.listed_at >= $9)
+              AND ($10::int IS NULL
+                   OR (st.landing_pads).large > 0
+                   OR ($10 < 3 AND (st.landing_pads).medium > 0)
+                   OR ($10 < 2 AND (st.landing_pads).small > 0))
+            ORDER BY "distance!", st.dist_from_star_ls NULLS LAST
+            LIMIT $11
+            "#,
+            module.item,
+            // Or the end of one, `hyperdrive` finding `int_hyperdrive`.
+            format!("%\\_{}", escaped(&module.item)),
+            class,
+            rating.map(|rating| rating.to_ascii_uppercase().to_string()),
+            range,
+            center[0],
+            center[1],
+            center[2],
+            criteria.listed_since().map(|t| t.naive_utc()),
+            criteria.pad.map(|pad| pad as i32),
+            limit,
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Offer {
+                name: row.module,
+                class: row.class,
+                rating: row.rating,
+                market_id: row.market_id,
+                system_address: row.system_address,
+                system_name: row.system_name,
+                station_name: row.station_name,
+                dist_from_star_ls: row.dist_from_star_ls,
+                listed_at: row.listed_at.and_utc(),
+                distance: row.distance,
+            })
+            .collect())
+    }
+
+    /// The nearest stations within `range` of `center` selling `ship`,
+    /// nearest first
+    ///
+    /// `ship` is matched by its letters and digits, so `Krait Mk II` finds
+    /// `krait_mkii`. Of `criteria`, only the pad and the age count.
+    pub async fn nearest_ship(
+        db: &Database,
+        ship: &str,
+        range: f64,
+        center: [f64; 3],
+        criteria: &Criteria,
+        limit: i64,
+    ) -> Result<Vec<Self>, Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                y.ship,
+                y.market_id,
+                y.listed_at,
+                s.address AS system_address,
+                s.name AS system_name,
+                m.station_name,
+                st.dist_from_star_ls,
+                ST_3DDistance(ST_MakePoint($3, $4, $5), s.position)
+                    AS "distance!"
+            FROM shipyard y
+            JOIN markets m ON m.id = y.market_id
+            JOIN systems s ON s.address = m.system_address
+            LEFT JOIN stations st
+              ON st.system_address = m.system_address
+             AND st.name = m.station_name
+            WHERE regexp_replace(y.ship, '[^a-z0-9]', '', 'g') = $1
+              AND ST_3DDWithin(ST_MakePoint($3, $4, $5), s.position, $2)
+              AND ($6::timestamp IS NULL OR y.listed_at >= $6)
+              AND ($7::int IS NULL
+                   OR (st.landing_pads).large > 0
+                   OR ($7 < 3 AND (st.landing_pads).medium > 0)
+                   OR ($7 < 2 AND (st.landing_pads).small > 0))
+            ORDER BY "distance!", st.dist_from_star_ls NULLS LAST
+            LIMIT $8
+            "#,
+            ship_key(ship),
+            range,
+            center[0],
+            center[1],
+            center[2],
+            criteria.listed_since().map(|t| t.naive_utc()),
+            criteria.pad.map(|pad| pad as i32),
+            limit,
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Offer {
+                name: row.ship,
+                class: None,
+                rating: None,
+                market_id: row.market_id,
+                system_address: row.system_address,
+                system_name: row.system_name,
+                station_name: row.station_name,
+                dist_from_star_ls: row.dist_from_star_ls,
+                listed_at: row.listed_at.and_utc(),
+                distance: row.distance,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,152 @@
+//! The modules a station fits and the ships it sells
+//!
+//! EDDN carries each as a list, keyed by the station's market the way its
+//! prices are, so they are kept beside [`crate::markets`] and found the way
+//! its quotes are: near somewhere, nearest first.
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A station's outfitting, as an `outfitting/2` message lists it
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Outfitting {
+    pub timestamp: DateTime<Utc>,
+    pub market_id: i64,
+    pub system_name: String,
+    pub station_name: String,
+    pub modules: Vec<String>,
+}
+
+/// A station's shipyard, as a `shipyard/2` message lists it
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Shipyard {
+    pub timestamp: DateTime<Utc>,
+    pub market_id: i64,
+    pub system_name: String,
+    pub station_name: String,
+    pub ships: Vec<String>,
+}
+
+/// A module, and what its name says of it
+///
+/// The game names a module by what it is and how big and how good it is,
+/// all at once: `Int_Hyperdrive_Size5_Class5` is a 5A frame shift drive,
+/// and `Hpt_PulseLaser_Gimbal_Medium` a class 2 gimballed pulse laser.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Module {
+    /// The whole name, lowercased
+    pub name: String,
+    /// The name without its size and class, `int_hyperdrive`
+    pub item: String,
+    /// How big it is, 1 to 8, or 0 for a utility mount
+    pub class: Option<i16>,
+    /// How good it is, A to E, which hardpoints don't say
+    pub rating: Option<char>,
+}
+
+impl Module {
+    pub fn parse(name: &str) -> Module {
+        let name = name.to_lowercase();
+        let mut item = vec![];
+        let (mut class, mut rating) = (None, None);
+        for part in name.split('_') {
+            let number = |prefix| {
+                part.strip_prefix(prefix).and_then(|n| n.parse::<i16>().ok())
+            };
+            match (part, number("size"), number("class")) {
+                (_, Some(size), _) => class = Some(size),
+                // Class 1 is rated E, up to class 5 rated A.
+                (_, _, Some(n @ 1..=5)) => {
+                    rating = Some((b'F' - n as u8) as char)
+                }
+                ("tiny", _, _) => class = Some(0),
+                ("small", _, _) => class = Some(1),
+                ("medium", _, _) => class = Some(2),
+                ("large", _, _) => class = Some(3),
+                ("huge", _, _) => class = Some(4),
+                _ => item.push(part),
+            }
+        }
+        Module { item: item.join("_"), name, class, rating }
+    }
+}
+
+/// Somewhere a module or ship is sold, and how far that is from where the
+/// question was asked
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Offer {
+    /// The module or ship, as the station lists it
+    pub name: String,
+    /// A module's class and rating, where its name gives them
+    pub class: Option<i16>,
+    pub rating: Option<String>,
+    pub market_id: i64,
+    pub system_address: i64,
+    pub system_name: String,
+    pub station_name: String,
+    /// How far the station is from its system's arrival star, where known
+    pub dist_from_star_ls: Option<f64>,
+    pub listed_at: DateTime<Utc>,
+    /// Light years from the point the offer was asked about
+    pub distance: f64,
+}
+
+/// A ship's name as it is looked up, its letters and digits lowercased
+///
+/// `Krait Mk II` and `krait_mkii` are the same ship.
+pub fn ship_key(name: &str) -> String {
+    name.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+mod create;
+mod fetch;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_module_name_says_its_class_and_rating() {
+        let drive = Module::parse("Int_Hyperdrive_Size5_Class5");
+        assert_eq!(drive.name, "int_hyperdrive_size5_class5");
+        assert_eq!(drive.item, "int_hyperdrive");
+        assert_eq!((drive.class, drive.rating), (Some(5), Some('A')));
+
+        let scoop = Module::parse("Int_FuelScoop_Size3_Class1");
+        assert_eq!((scoop.class, scoop.rating), (Some(3), Some('E')));
+
+        let laser = Module::parse("Hpt_PulseLaser_Gimbal_Medium");
+        assert_eq!(laser.item, "hpt_pulselaser_gimbal");
+        assert_eq!((laser.class, laser.rating), (Some(2), None));
+
+        let armour = Module::parse("Krait_MkII_Armour_Grade3");
+        assert_eq!(armour.item, "krait_mkii_armour_grade3");
+        assert_eq!((armour.class, armour.rating), (None, None));
+    }
+
+    #[test]
+    fn a_ship_is_found_by_its_letters() {
+        assert_eq!(ship_key("Krait Mk II"), ship_key("krait_mkii"));
+        assert_eq!(ship_key("Type-9 Heavy"), "type9heavy");
+    }
+
+    #[test]
+    fn outfitting_reads_from_eddn() {
+        let json = r#"{
+            "timestamp": "2026-10-18T12:00:00Z",
+            "marketId": 128016640,
+            "systemName": "Sol",
+            "stationName": "Abraham Lincoln",
+            "horizons": true,
+            "modules": ["Int_Hyperdrive_Size5_Class5"]
+        }"#;
+        let outfitting: Outfitting = serde_json::from_str(json).unwrap();
+        assert_eq!(outfitting.market_id, 128016640);
+        assert_eq!(outfitting.modules, vec!["Int_Hyperdrive_Size5_Class5"]);
+        assert!(serde_json::from_str::<Shipyard>(json).is_err());
+    }
+}
@@ -0,0 +1,34 @@
+use super::Quarantined;
+use crate::{Database, Error};
+use chrono::{DateTime, Utc};
+
+impl Quarantined {
+    pub async fn create(
+        db: &Database,
+        received: DateTime<Utc>,
+        schema: &str,
+        uploader: &str,
+        software: &str,
+        reason: &str,
+        envelope: &str,
+    ) -> Result<i64, Error> {
+        let row = sqlx::query!(
+            "
+            INSERT INTO quarantine
+                (received, schema, uploader, software, reason, envelope)
+            VALUES ($1, $2, $3, $4, $5, $6::text::jsonb)
+            RETURNING id
+            ",
+            received.naive_utc(),
+            schema,
+            uploader,
+            software,
+            reason,
+            envelope,
+        )
+        .fetch_one(&db.pool)
+        .await?;
+
+        Ok(row.id)
+    }
+}
@@ -0,0 +1,107 @@
+use super::Quarantined;
+use crate::{Database, Error, Page};
+use chrono::{DateTime, Utc};
+
+impl Quarantined {
+    pub async fn fetch(db: &Database, id: i64) -> Result<Self, Error> {
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                id,
+                received,
+                schema,
+                uploader,
+                software,
+                reason,
+                envelope::text AS "envelope!"
+            FROM quarantine
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_one(&db.pool)
+        .await?;
+
+        Ok(Quarantined {
+            id: row.id,
+            received: row.received.and_utc(),
+            schema: row.schema,
+            uploader: row.uploader,
+            software: row.software,
+            reason: row.reason,
+            envelope: row.envelope,
+        })
+    }
+
+    /// Oldest first, the order they arrived and would be released in
+    pub async fn fetch_all(
+        db: &Database,
+        page: Page,
+    ) -> Result<Vec<Self>, Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                id,
+                received,
+                schema,
+                uploader,
+                software,
+                reason,
+                envelope::text AS "envelope!"
+            FROM quarantine
+            ORDER BY received, id
+            LIMIT $1 OFFSET $2
+            "#,
+            page.limit,
+            page.offset
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Quarantined {
+                id: row.id,
+                received: row.received.and_utc(),
+                schema: row.schema,
+                uploader: row.uploader,
+                software: row.software,
+                reason: row.reason,
+                envelope: row.envelope,
+            })
+            .collect())
+    }
+
+    pub async fn delete(&self, db: &Database) -> Result<(), Error> {
+        sqlx::query!(
+            "
+            DELETE FROM quarantine
+            WHERE id = $1
+            ",
+            self.id
+        )
+        .execute(&db.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Delete everything received before `before`, or everything at all,
+    /// saying how many
+    pub async fn purge(
+        db: &Database,
+        before: Option<DateTime<Utc>>,
+    ) -> Result<u64, Error> {
+        let result = sqlx::query!(
+            "
+            DELETE FROM quarantine
+            WHERE $1::timestamp IS NULL OR received < $1
+            ",
+            before.map(|before| before.naive_utc())
+        )
+        .execute(&db.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
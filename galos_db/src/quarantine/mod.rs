@@ -0,0 +1,24 @@
+//! EDDN messages held back from ingest as impossible or untrustworthy
+//!
+//! `galos-sync eddn` checks each message before it is written, and one that
+//! fails is kept here whole with the reason, rather than written or thrown
+//! away. `galos-sync quarantine` lists them, releases those that turn out to
+//! be fine into the ingest they were kept from, and purges the rest.
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Quarantined {
+    pub id: i64,
+    pub received: DateTime<Utc>,
+    /// `journal`, `commodity` and so on, as EDDN names its schemas
+    pub schema: String,
+    pub uploader: String,
+    /// The uploading software's name and version, `EDMarketConnector 5.12.1`
+    pub software: String,
+    pub reason: String,
+    /// The envelope as it arrived, in JSON
+    pub envelope: String,
+}
+
+mod create;
+mod fetch;
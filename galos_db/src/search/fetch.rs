@@ -0,0 +1,463 @@
+//! A query, as the SQL that answers it
+//!
+//! The statement is written only from the program's own text, each value the
+//! query holds going in as a parameter. [`Statement::push`] takes nothing but
+//! a `&'static str` to keep it that way.
+use super::{
+    Attribute, Cmp, FactionAttribute, FactionTerm, Order, OrderBy, Query,
+    Region, Term,
+};
+use crate::systems::{Economies, System};
+use crate::{Database, Error};
+use chrono::NaiveDateTime;
+use elite_journal::prelude::*;
+use futures::{Stream, StreamExt};
+use geozero::wkb;
+use sqlx::postgres::{PgArguments, PgRow};
+use sqlx::query::Query as Sql;
+use sqlx::{Postgres, Row};
+
+const SYSTEMS: &str = r#"
+    SELECT
+        s.address,
+        s.name,
+        s.position,
+        s.population,
+        s.security,
+        s.government,
+        s.allegiance,
+        s.primary_economy,
+        s.secondary_economy,
+        s.updated_at,
+        s.updated_by,
+        COALESCE((
+            SELECT array_agg(faction_id)
+            FROM system_factions
+            WHERE system_address = s.address
+        ), ARRAY[]::integer[]) AS factions
+    FROM systems s"#;
+
+/// The factions present in `s`, as `sf` and `f`
+const FACTIONS: &str = "
+    FROM system_factions sf
+    JOIN factions f ON f.id = sf.faction_id
+    WHERE sf.system_address = s.address";
+
+/// A value a statement's parameter is bound to
+#[derive(Debug, Clone, PartialEq)]
+pub enum Bind {
+    Texts(Vec<String>),
+    Int(i64),
+    Real(f64),
+    Bool(bool),
+}
+
+/// SQL, and what its parameters are bound to
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Statement {
+    sql: String,
+    binds: Vec<Bind>,
+}
+
+impl Statement {
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    pub fn binds(&self) -> &[Bind] {
+        &self.binds
+    }
+
+    fn push(&mut self, sql: &'static str) {
+        self.sql.push_str(sql);
+    }
+
+    /// The next parameter, bound to `value`
+    fn bind(&mut self, value: Bind) {
+        self.binds.push(value);
+        self.sql.push_str(&format!("${}", self.binds.len()));
+    }
+
+    /// What `write` writes, or that it is not so
+    ///
+    /// Not so is a comparison that is false or unknown, which is what a
+    /// system with nothing on record is: an anarchy is not a democracy, and a
+    /// system of no known government is not a democracy either.
+    fn negated(&mut self, negated: bool, write: impl FnOnce(&mut Self)) {
+        self.push(if negated { "NOT coalesce((" } else { "(" });
+        write(self);
+        self.push(if negated { "), false)" } else { ")" });
+    }
+
+    fn cmp(&mut self, cmp: Cmp) {
+        self.push(match cmp {
+            Cmp::Eq => " = ",
+            Cmp::Lt => " < ",
+            Cmp::Le => " <= ",
+            Cmp::Gt => " > ",
+            Cmp::Ge => " >= ",
+        });
+    }
+
+    fn query(&self) -> Sql<'_, Postgres, PgArguments> {
+        self.binds.iter().fold(sqlx::query(&self.sql), |query, bind| match bind
+        {
+            Bind::Texts(texts) => query.bind(texts.clone()),
+            Bind::Int(n) => query.bind(*n),
+            Bind::Real(x) => query.bind(*x),
+            Bind::Bool(b) => query.bind(*b),
+        })
+    }
+
+    /// The systems a [`Query::select`] finds, one at a time
+    pub fn systems<'a>(
+        &'a self,
+        db: &'a Database,
+    ) -> impl Stream<Item = Result<System, Error>> + 'a {
+        self.query().fetch(&db.pool).map(|row| read(row?))
+    }
+
+    /// How many systems a [`Query::count`] counts
+    pub async fn count(&self, db: &Database) -> Result<i64, Error> {
+        let row = self.query().fetch_one(&db.pool).await?;
+        Ok(row.try_get(0)?)
+    }
+}
+
+fn read(row: PgRow) -> Result<System, Error> {
+    let position: Option<wkb::Decode<Coordinate>> = row.try_get("position")?;
+    let population: Option<i64> = row.try_get("population")?;
+    let updated_at: NaiveDateTime = row.try_get("updated_at")?;
+    Ok(System {
+        address: row.try_get("address")?,
+        name: row.try_get("name")?,
+        position: position.map(|p| p.geometry.expect("not null or invalid")),
+        population: population.map(|n| n as u64).unwrap_or(0),
+        security: row.try_get("security")?,
+        government: row.try_get("government")?,
+        allegiance: row.try_get("allegiance")?,
+        economies: Economies::new(
+            row.try_get("primary_economy")?,
+            row.try_get("secondary_economy")?,
+        ),
+        factions: row.try_get("factions")?,
+        updated_at: updated_at.and_utc(),
+        updated_by: row.try_get("updated_by")?,
+    })
+}
+
+impl Query {
+    /// The statement finding these systems, in `order` and no more than
+    /// `limit` of them
+    ///
+    /// Without an order, nearest first when searching around systems, and by
+    /// name otherwise.
+    pub fn select(
+        &self,
+        order: Option<Order>,
+        limit: Option<i64>,
+    ) -> Result<Statement, String> {
+        let mut statement = Statement::default();
+        statement.push(SYSTEMS);
+        self.filter(&mut statement)?;
+
+        let order = order.unwrap_or(Order {
+            by: if self.region()?.is_some() {
+                OrderBy::Distance
+            } else {
+                OrderBy::Name
+            },
+            descending: false,
+        });
+        statement.push("\n    ORDER BY ");
+        match order.by {
+            OrderBy::Name => statement.push("s.name"),
+            OrderBy::Population => statement.push("coalesce(s.population, 0)"),
+            OrderBy::Updated => statement.push("s.updated_at"),
+            OrderBy::Distance => {
+                if !self.names() {
+                    return Err(
+                        "distance is from the systems named".to_string()
+                    );
+                }
+                statement.push(
+                    "(SELECT min(ST_3DDistance(s.position, a.position)) \
+                     FROM systems a WHERE ",
+                );
+                self.anchors(&mut statement);
+                statement.push(")");
+            }
+            OrderBy::Influence => {
+                // Of the factions asked about, if any are.
+                statement.push("(SELECT max(sf.influence)");
+                statement.push(FACTIONS);
+                let asked = self.clauses.iter().find_map(|c| match &c.term {
+                    Term::Factions(clauses) if !c.negated => Some(clauses),
+                    _ => None,
+                });
+                for clause in asked.into_iter().flatten() {
+                    statement.push(" AND ");
+                    statement
+                        .negated(clause.negated, |s| faction(s, &clause.term));
+                }
+                statement.push(")");
+            }
+        }
+        statement.push(if order.descending {
+            " DESC NULLS LAST, s.name"
+        } else {
+            " ASC NULLS LAST, s.name"
+        });
+
+        if let Some(limit) = limit {
+            statement.push("\n    LIMIT ");
+            statement.bind(Bind::Int(limit));
+        }
+        Ok(statement)
+    }
+
+    /// The statement counting these systems
+    pub fn count(&self) -> Result<Statement, String> {
+        let mut statement = Statement::default();
+        statement.push("SELECT count(*) FROM systems s");
+        self.filter(&mut statement)?;
+        Ok(statement)
+    }
+
+    /// The region searched around the systems named, if it is one
+    fn region(&self) -> Result<Option<Region>, String> {
+        let mut regions = self.clauses.iter().filter_map(|c| match c.term {
+            Term::Within(region) => Some(region),
+            _ => None,
+        });
+        let region = regions.next();
+        if regions.next().is_some() {
+            return Err("only one sphere or cube at a time".to_string());
+        }
+        Ok(region)
+    }
+
+    /// Whether any systems are named
+    fn names(&self) -> bool {
+        self.clauses
+            .iter()
+            .any(|c| !c.negated && matches!(c.term, Term::Named(_)))
+    }
+
+    /// The systems named, as `a`
+    ///
+    /// Only those named outright. A name negated is a system not to list,
+    /// rather than one not to search around.
+    fn anchors(&self, statement: &mut Statement) {
+        statement.push("true");
+        for clause in &self.clauses {
+            match &clause.term {
+                Term::Named(names) if !clause.negated => {
+                    statement.push(" AND a.name ILIKE ANY(");
+                    statement.bind(Bind::Texts(names.clone()));
+                    statement.push(")");
+                }
+                _ => continue,
+            }
+        }
+    }
+
+    fn filter(&self, statement: &mut Statement) -> Result<(), String> {
+        let region = self.region()?;
+        statement.push("\n    WHERE true");
+        for clause in &self.clauses {
+            match &clause.term {
+                // Names say where to search around, when there is a region,
+                // and negated they still say which systems not to list.
+                Term::Named(_) if region.is_some() && !clause.negated => {
+                    continue
+                }
+                Term::Within(_) => continue,
+                term => {
+                    statement.push("\n    AND ");
+                    statement.negated(clause.negated, |s| system(s, term));
+                }
+            }
+        }
+
+        let Some(region) = region else { return Ok(()) };
+        if !self.names() {
+            return Err("a sphere or cube is around systems named".to_string());
+        }
+        statement.push("\n    AND EXISTS (SELECT 1 FROM systems a WHERE ");
+        self.anchors(statement);
+        match region {
+            Region::Sphere(radius) => {
+                statement.push(" AND ST_3DDWithin(s.position, a.position, ");
+                statement.bind(Bind::Real(radius));
+                statement.push(")");
+            }
+            Region::Cube(side) => {
+                for axis in [
+                    "abs(ST_X(s.position) - ST_X(a.position)) <= ",
+                    "abs(ST_Y(s.position) - ST_Y(a.position)) <= ",
+                    "abs(ST_Z(s.position) - ST_Z(a.position)) <= ",
+                ] {
+                    statement.push(" AND ");
+                    statement.push(axis);
+                    statement.bind(Bind::Real(side / 2.));
+                }
+            }
+        }
+        statement.push(")");
+        Ok(())
+    }
+}
+
+/// Whether `column` is any of `values`, read as lowercase text
+fn any_of(statement: &mut Statement, column: &'static str, values: &[String]) {
+    statement.push("lower(");
+    statement.push(column);
+    statement.push("::text) = ANY(");
+    statement.bind(Bind::Texts(values.to_vec()));
+    statement.push(")");
+}
+
+fn system(statement: &mut Statement, term: &Term) {
+    match term {
+        Term::Named(names) => {
+            statement.push("s.name ILIKE ANY(");
+            statement.bind(Bind::Texts(names.clone()));
+            statement.push(")");
+        }
+        Term::Population(cmp, n) => {
+            statement.push("coalesce(s.population, 0)");
+            statement.cmp(*cmp);
+            statement.bind(Bind::Int(*n));
+        }
+        Term::Is(Attribute::Security, values) => {
+            // An anarchy is stored as no security at all.
+            any_of(statement, "s.security", values);
+            statement.push(" OR (s.security IS NULL AND ");
+            statement.bind(Bind::Bool(values.iter().any(|v| v == "anarchy")));
+            statement.push(")");
+        }
+        Term::Is(Attribute::Government, values) => {
+            any_of(statement, "s.government", values)
+        }
+        Term::Is(Attribute::Allegiance, values) => {
+            any_of(statement, "s.allegiance", values)
+        }
+        Term::Is(Attribute::Economy, values) => {
+            any_of(statement, "s.primary_economy", values);
+            statement.push(" OR ");
+            any_of(statement, "s.secondary_economy", values);
+        }
+        Term::Factions(clauses) => {
+            statement.push("EXISTS (SELECT 1");
+            statement.push(FACTIONS);
+            for clause in clauses {
+                statement.push(" AND ");
+                statement.negated(clause.negated, |s| faction(s, &clause.term));
+            }
+            statement.push(")");
+        }
+        Term::Within(_) => statement.push("true"),
+    }
+}
+
+fn faction(statement: &mut Statement, term: &FactionTerm) {
+    match term {
+        FactionTerm::Named(names) => {
+            statement.push("f.name ILIKE ANY(");
+            statement.bind(Bind::Texts(names.clone()));
+            statement.push(")");
+        }
+        FactionTerm::Influence(cmp, share) => {
+            statement.push("sf.influence");
+            statement.cmp(*cmp);
+            statement.bind(Bind::Real(*share));
+        }
+        FactionTerm::Is(attribute, values) => {
+            let column = match attribute {
+                FactionAttribute::State => "sf.state",
+                FactionAttribute::Happiness => "sf.happiness",
+                FactionAttribute::Government => "sf.government",
+                FactionAttribute::Allegiance => "sf.allegiance",
+            };
+            any_of(statement, column, values);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn select(query: &str) -> Statement {
+        query.parse::<Query>().unwrap().select(None, None).unwrap()
+    }
+
+    /// Nothing typed is ever part of the SQL, only ever bound to it
+    #[test]
+    fn values_are_bound_not_spliced() {
+        let statement = select(
+            "\"Sol'); DROP TABLE systems; --\" \
+             factions={name=\"x' OR 1=1\" state=boom}",
+        );
+        assert!(!statement.sql().contains("DROP"));
+        assert!(!statement.sql().contains("OR 1=1"));
+        assert!(!statement.sql().contains("boom"));
+        assert_eq!(
+            statement.binds(),
+            [
+                Bind::Texts(vec!["Sol'); DROP TABLE systems; --".into()]),
+                Bind::Texts(vec!["x' OR 1=1".into()]),
+                Bind::Texts(vec!["boom".into()]),
+            ]
+        );
+    }
+
+    /// Each value has a parameter of its own, numbered in order
+    #[test]
+    fn parameters_number_each_bind() {
+        let statement = "Meliae cube=40Ly population>1k"
+            .parse::<Query>()
+            .unwrap()
+            .select(None, Some(10))
+            .unwrap();
+        let binds = statement.binds().len();
+        for n in 1..=binds {
+            assert!(statement.sql().contains(&format!("${}", n)));
+        }
+        assert!(!statement.sql().contains(&format!("${}", binds + 1)));
+        assert_eq!(statement.binds().last(), Some(&Bind::Int(10)));
+    }
+
+    /// Searching around systems needs systems to search around
+    #[test]
+    fn regions_need_names() {
+        let query: Query = "sphere=10Ly".parse().unwrap();
+        assert!(query.select(None, None).is_err());
+        let query: Query = "Sol sphere=10Ly cube=5Ly".parse().unwrap();
+        assert!(query.count().is_err());
+    }
+
+    /// A negated clause keeps the systems it has nothing on record for, as
+    /// not being what it negates
+    #[test]
+    fn negations_cover_the_unknown() {
+        let statement = select("-allegiance=empire");
+        assert!(statement.sql().contains("NOT coalesce(("));
+    }
+
+    /// Around systems, a name negated drops the systems found by it
+    #[test]
+    fn negated_names_filter_regions() {
+        let statement = select("HD* -\"HD 1*\" sphere=10Ly");
+        assert!(statement.sql().contains("NOT coalesce((s.name ILIKE"));
+        assert!(!statement.sql().contains("NOT coalesce((a.name"));
+        assert_eq!(
+            &statement.binds()[..2],
+            [
+                Bind::Texts(vec!["HD 1%".into()]),
+                Bind::Texts(vec!["HD%".into()]),
+            ]
+        );
+    }
+}
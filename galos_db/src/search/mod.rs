@@ -0,0 +1,601 @@
+//! Searches for systems, as `galos search` is typed
+//!
+//! A query is a list of clauses, every one of which a system must meet.
+//! Looser to tighter:
+//!
+//! ```notrust
+//! query   := clause*
+//! clause  := '-'? (compare | names)
+//! compare := field op values
+//!          | 'factions' '=' '{' ('-'? field op values)* '}'
+//! names   := name ('|' name)* | '(' names ')'
+//! name    := <word>+ | '"' <anything> '"'
+//! values  := value (',' value)*
+//! op      := '=' | '!=' | '<' | '<=' | '>' | '>='
+//! ```
+//!
+//! A name is a glob, `*` standing for anything and `?` for any one letter,
+//! read without regard to case. Words run together into one name, so
+//! `Alpha Centauri` is the system and not two. `-` in front of a clause asks
+//! for the systems it does not match.
+//!
+//! - `sphere=500Ly` and `cube=40Ly` search around the systems named, rather
+//!   than for them: within 500 Ly of any, or in a cube 40 Ly on a side
+//!   centred on one
+//! - `population` compares as a number, which may end in `k`, `m` or `b`
+//! - `security`, `government`, `allegiance` and `economy` are any of the
+//!   values listed, spelt as the database spells them, in any case.
+//!   `economy` is either of a system's two, and an anarchy has no security
+//! - `factions={...}` is a faction present meeting every clause in the braces:
+//!   `name`, `influence` (a fraction, or a percentage ending in `%`), `state`,
+//!   `happiness`, `government` and `allegiance`
+//!
+//! ```notrust
+//! HD* sphere=500Ly
+//! Meliae cube=40Ly factions={influence<7.5%}
+//! (HD*|HIP*) population>=1m -security=anarchy
+//! "Col 285 Sector*" factions={name="Sirius*" state=boom,expansion}
+//! ```
+//!
+//! A query is only ever read into the clauses below. What is asked of the
+//! database is written from them, each value the user typed bound as a
+//! parameter, see [`fetch`].
+use std::iter::Peekable;
+use std::str::{Chars, FromStr};
+
+pub mod fetch;
+pub use self::fetch::Statement;
+
+/// Systems meeting every clause
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub clauses: Vec<Clause<Term>>,
+}
+
+/// One thing asked of a system, or of a faction, or its opposite
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clause<T> {
+    pub negated: bool,
+    pub term: T,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    /// Named like any of these, each a `LIKE` pattern
+    Named(Vec<String>),
+    /// Around the systems named, rather than them
+    Within(Region),
+    Population(Cmp, i64),
+    /// Any of these values, lowercase
+    Is(Attribute, Vec<String>),
+    /// A faction present meeting every clause
+    Factions(Vec<Clause<FactionTerm>>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Region {
+    /// Within this many Ly
+    Sphere(f64),
+    /// Within a cube this many Ly on a side
+    Cube(f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attribute {
+    Security,
+    Government,
+    Allegiance,
+    Economy,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FactionTerm {
+    /// Named like any of these, each a `LIKE` pattern
+    Named(Vec<String>),
+    /// As a fraction of the system's
+    Influence(Cmp, f64),
+    /// Any of these values, lowercase
+    Is(FactionAttribute, Vec<String>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FactionAttribute {
+    State,
+    Happiness,
+    Government,
+    Allegiance,
+}
+
+/// A comparison, `!=` being a negated `=`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cmp {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// What the systems found are listed in order of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Order {
+    pub by: OrderBy,
+    pub descending: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderBy {
+    Name,
+    Population,
+    /// From the nearest system named
+    Distance,
+    /// Of the most influential faction, of those the query asks for
+    Influence,
+    Updated,
+}
+
+impl FromStr for Order {
+    type Err = String;
+
+    /// A key, `-` in front for the greatest first
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (descending, key) = match s.strip_prefix('-') {
+            Some(key) => (true, key),
+            None => (false, s),
+        };
+        let by = match key.to_lowercase().as_str() {
+            "name" => OrderBy::Name,
+            "population" => OrderBy::Population,
+            "distance" => OrderBy::Distance,
+            "influence" | "factions.influence" => OrderBy::Influence,
+            "updated" | "updated_at" => OrderBy::Updated,
+            _ => return Err("invalid order".to_string()),
+        };
+        Ok(Order { by, descending })
+    }
+}
+
+/// A glob, as the `LIKE` pattern matching the same names
+///
+/// What `LIKE` reads into `%` and `_` is [`escaped`](crate::escaped) first,
+/// so that only the glob's own wildcards are wild.
+pub fn like(glob: &str) -> String {
+    crate::escaped(glob).replace('*', "%").replace('?', "_")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Op(Cmp),
+    NotEq,
+    Not,
+    Or,
+    Comma,
+    Open,
+    Close,
+    OpenBrace,
+    CloseBrace,
+}
+
+/// Whether `c` ends a word
+fn special(c: char) -> bool {
+    c.is_whitespace() || "\"(){}|,=!<>".contains(c)
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars: Peekable<Chars> = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '"' => {
+                let quoted: String =
+                    chars.by_ref().take_while(|&c| c != '"').collect();
+                Token::Quoted(quoted)
+            }
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '{' => Token::OpenBrace,
+            '}' => Token::CloseBrace,
+            '|' => Token::Or,
+            ',' => Token::Comma,
+            '=' => Token::Op(Cmp::Eq),
+            '!' if chars.next_if_eq(&'=').is_some() => Token::NotEq,
+            '!' => return Err("expected '=' after '!'".to_string()),
+            '<' if chars.next_if_eq(&'=').is_some() => Token::Op(Cmp::Le),
+            '<' => Token::Op(Cmp::Lt),
+            '>' if chars.next_if_eq(&'=').is_some() => Token::Op(Cmp::Ge),
+            '>' => Token::Op(Cmp::Gt),
+            // Only at the start of a clause, names having dashes in them.
+            '-' if chars
+                .peek()
+                .is_some_and(|&c| !special(c) || c == '(' || c == '"') =>
+            {
+                Token::Not
+            }
+            c => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|&c| !special(c)) {
+                    word.push(c);
+                }
+                Token::Word(word)
+            }
+        };
+        tokens.push(token);
+    }
+    if s.matches('"').count() % 2 == 1 {
+        return Err("unclosed '\"'".to_string());
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    at: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.at)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.at).cloned();
+        self.at += 1;
+        token
+    }
+
+    /// Whether what comes next is a field and how it is compared
+    fn comparing(&self) -> bool {
+        matches!(
+            (self.tokens.get(self.at), self.tokens.get(self.at + 1)),
+            (Some(Token::Word(_)), Some(Token::Op(_) | Token::NotEq))
+        )
+    }
+
+    fn clause<T>(
+        &mut self,
+        term: fn(&mut Parser) -> Result<(bool, T), String>,
+    ) -> Result<Clause<T>, String> {
+        let mut negated = false;
+        if self.peek() == Some(&Token::Not) {
+            self.next();
+            negated = true;
+        }
+        let (unequal, term) = term(self)?;
+        Ok(Clause { negated: negated != unequal, term })
+    }
+
+    /// A field and how it compares, `!=` read as `=` and saying so
+    fn compare(&mut self) -> Result<(String, Cmp, bool), String> {
+        let field = match self.next() {
+            Some(Token::Word(field)) => field.to_lowercase(),
+            _ => return Err("expected a field".to_string()),
+        };
+        match self.next() {
+            Some(Token::Op(cmp)) => Ok((field, cmp, false)),
+            Some(Token::NotEq) => Ok((field, Cmp::Eq, true)),
+            _ => Err(format!("expected a comparison after {}", field)),
+        }
+    }
+
+    /// Values separated by commas
+    fn values(&mut self) -> Result<Vec<String>, String> {
+        let mut values = Vec::new();
+        loop {
+            match self.next() {
+                Some(Token::Word(value) | Token::Quoted(value)) => {
+                    values.push(value)
+                }
+                _ => return Err("expected a value".to_string()),
+            }
+            if self.peek() != Some(&Token::Comma) {
+                return Ok(values);
+            }
+            self.next();
+        }
+    }
+
+    /// One value, where a list would make no sense
+    fn value(&mut self, field: &str) -> Result<String, String> {
+        match self.values()?.as_slice() {
+            [value] => Ok(value.clone()),
+            _ => Err(format!("{} takes one value", field)),
+        }
+    }
+
+    fn term(&mut self) -> Result<(bool, Term), String> {
+        if !self.comparing() {
+            return Ok((false, Term::Named(self.names()?)));
+        }
+        let (field, cmp, unequal) = self.compare()?;
+        let term = match field.as_str() {
+            "sphere" | "cube" => {
+                if cmp != Cmp::Eq || unequal {
+                    return Err(format!("{} is only ever =", field));
+                }
+                let size = distance(&self.value(&field)?)?;
+                Term::Within(if field == "sphere" {
+                    Region::Sphere(size)
+                } else {
+                    Region::Cube(size)
+                })
+            }
+            "population" => Term::Population(cmp, count(&self.value(&field)?)?),
+            "security" => {
+                Term::Is(Attribute::Security, self.listed(&field, cmp)?)
+            }
+            "government" => {
+                Term::Is(Attribute::Government, self.listed(&field, cmp)?)
+            }
+            "allegiance" => {
+                Term::Is(Attribute::Allegiance, self.listed(&field, cmp)?)
+            }
+            "economy" => {
+                Term::Is(Attribute::Economy, self.listed(&field, cmp)?)
+            }
+            "factions" | "faction" => {
+                if cmp != Cmp::Eq {
+                    return Err("factions is = or != a {...}".to_string());
+                }
+                if self.next() != Some(Token::OpenBrace) {
+                    return Err("expected '{' after factions=".to_string());
+                }
+                let mut clauses = Vec::new();
+                while self.peek() != Some(&Token::CloseBrace) {
+                    if self.peek().is_none() {
+                        return Err("unclosed '{'".to_string());
+                    }
+                    clauses.push(self.clause(Parser::faction_term)?);
+                }
+                self.next();
+                Term::Factions(clauses)
+            }
+            _ => return Err(format!("unknown field {}", field)),
+        };
+        Ok((unequal, term))
+    }
+
+    fn faction_term(&mut self) -> Result<(bool, FactionTerm), String> {
+        let (field, cmp, unequal) = self.compare()?;
+        let is = |parser: &mut Parser, attribute| -> Result<_, String> {
+            Ok(FactionTerm::Is(attribute, parser.listed(&field, cmp)?))
+        };
+        let term = match field.as_str() {
+            "name" => {
+                if cmp != Cmp::Eq {
+                    return Err("a name is = or != a value".to_string());
+                }
+                FactionTerm::Named(
+                    self.values()?.iter().map(|n| like(n)).collect(),
+                )
+            }
+            "influence" => {
+                FactionTerm::Influence(cmp, share(&self.value(&field)?)?)
+            }
+            "state" => is(self, FactionAttribute::State)?,
+            "happiness" => is(self, FactionAttribute::Happiness)?,
+            "government" => is(self, FactionAttribute::Government)?,
+            "allegiance" => is(self, FactionAttribute::Allegiance)?,
+            _ => return Err(format!("unknown faction field {}", field)),
+        };
+        Ok((unequal, term))
+    }
+
+    /// Values something is one of, lowercase
+    fn listed(&mut self, field: &str, cmp: Cmp) -> Result<Vec<String>, String> {
+        if cmp != Cmp::Eq {
+            return Err(format!("{} is = or != a value", field));
+        }
+        Ok(self.values()?.iter().map(|v| v.to_lowercase()).collect())
+    }
+
+    /// Names, either of them, as `LIKE` patterns
+    fn names(&mut self) -> Result<Vec<String>, String> {
+        if self.peek() == Some(&Token::Open) {
+            self.next();
+            let names = self.names()?;
+            return match self.next() {
+                Some(Token::Close) => Ok(names),
+                _ => Err("unclosed '('".to_string()),
+            };
+        }
+        let mut names = vec![like(&self.name()?)];
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            names.push(like(&self.name()?));
+        }
+        Ok(names)
+    }
+
+    /// Words up to whatever is not a name, or one quoted name
+    fn name(&mut self) -> Result<String, String> {
+        if let Some(Token::Quoted(name)) = self.peek() {
+            let name = name.clone();
+            self.next();
+            return Ok(name);
+        }
+        let mut words = Vec::new();
+        while let Some(Token::Word(word)) = self.peek() {
+            if self.comparing() {
+                break;
+            }
+            words.push(word.clone());
+            self.next();
+        }
+        if words.is_empty() {
+            Err("expected a name".to_string())
+        } else {
+            Ok(words.join(" "))
+        }
+    }
+}
+
+/// Light years, `Ly` on the end or not
+fn distance(s: &str) -> Result<f64, String> {
+    let n = s.strip_suffix("Ly").or(s.strip_suffix("ly")).unwrap_or(s);
+    n.parse().map_err(|_| format!("invalid distance {}", s))
+}
+
+/// A whole number, possibly of thousands, millions or billions
+fn count(s: &str) -> Result<i64, String> {
+    let (n, scale) = match s.chars().last().map(|c| c.to_ascii_lowercase()) {
+        Some('k') => (&s[..s.len() - 1], 1e3),
+        Some('m') => (&s[..s.len() - 1], 1e6),
+        Some('b') => (&s[..s.len() - 1], 1e9),
+        _ => (s, 1.),
+    };
+    let n: f64 = n.parse().map_err(|_| format!("invalid number {}", s))?;
+    Ok((n * scale).round() as i64)
+}
+
+/// A fraction, or a percentage of one
+fn share(s: &str) -> Result<f64, String> {
+    match s.strip_suffix('%') {
+        Some(n) => n.parse::<f64>().map(|n| n / 100.),
+        None => s.parse(),
+    }
+    .map_err(|_| format!("invalid influence {}", s))
+}
+
+impl FromStr for Query {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { tokens: tokenize(s)?, at: 0 };
+        let mut clauses = Vec::new();
+        while let Some(token) = parser.peek() {
+            match token {
+                Token::Close => return Err("unopened ')'".to_string()),
+                Token::CloseBrace => return Err("unopened '}'".to_string()),
+                _ => clauses.push(parser.clause(Parser::term)?),
+            }
+        }
+        Ok(Query { clauses })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clause<T>(term: T) -> Clause<T> {
+        Clause { negated: false, term }
+    }
+
+    fn not<T>(term: T) -> Clause<T> {
+        Clause { negated: true, term }
+    }
+
+    fn parse(s: &str) -> Vec<Clause<Term>> {
+        s.parse::<Query>().unwrap().clauses
+    }
+
+    #[test]
+    fn globs_read_as_like_patterns() {
+        assert_eq!(parse("HD*"), [clause(Term::Named(vec!["HD%".into()]))]);
+        assert_eq!(like("Col_285 ?"), r"Col\_285 _");
+    }
+
+    /// Words are one name until something else comes along
+    #[test]
+    fn words_run_together_into_a_name() {
+        assert_eq!(
+            parse("Alpha Centauri sphere=20Ly"),
+            [
+                clause(Term::Named(vec!["Alpha Centauri".into()])),
+                clause(Term::Within(Region::Sphere(20.))),
+            ]
+        );
+    }
+
+    #[test]
+    fn names_can_be_either() {
+        assert_eq!(
+            parse("(HD*|HIP*) -\"Col 285*\""),
+            [
+                clause(Term::Named(vec!["HD%".into(), "HIP%".into()])),
+                not(Term::Named(vec!["Col 285%".into()])),
+            ]
+        );
+    }
+
+    #[test]
+    fn fields_compare() {
+        assert_eq!(
+            parse("population>=1.5m security!=Anarchy"),
+            [
+                clause(Term::Population(Cmp::Ge, 1_500_000)),
+                not(Term::Is(Attribute::Security, vec!["anarchy".into()])),
+            ]
+        );
+        assert_eq!(
+            parse("economy=Refinery,Extraction"),
+            [clause(Term::Is(
+                Attribute::Economy,
+                vec!["refinery".into(), "extraction".into()]
+            ))]
+        );
+    }
+
+    /// The crate docs' own example
+    #[test]
+    fn factions_nest() {
+        assert_eq!(
+            parse("Meliae cube=40Ly factions={influence<7.5%}"),
+            [
+                clause(Term::Named(vec!["Meliae".into()])),
+                clause(Term::Within(Region::Cube(40.))),
+                clause(Term::Factions(vec![clause(FactionTerm::Influence(
+                    Cmp::Lt,
+                    0.075
+                ))])),
+            ]
+        );
+        assert_eq!(
+            parse("-factions={name=\"Sirius*\" -state=boom}"),
+            [not(Term::Factions(vec![
+                clause(FactionTerm::Named(vec!["Sirius%".into()])),
+                not(FactionTerm::Is(
+                    FactionAttribute::State,
+                    vec!["boom".into()]
+                )),
+            ]))]
+        );
+    }
+
+    /// A dash inside a name is part of it
+    #[test]
+    fn dashes_in_names_are_not_negations() {
+        assert_eq!(
+            parse("HIP 12345-2"),
+            [clause(Term::Named(vec!["HIP 12345-2".into()]))]
+        );
+    }
+
+    #[test]
+    fn mistakes_are_errors() {
+        for query in [
+            "population>lots",
+            "security<high",
+            "sphere>5",
+            "factions={influence<5%",
+            "(HD*",
+            "HD*)",
+            "colour=blue",
+            "\"Sol",
+        ] {
+            assert!(query.parse::<Query>().is_err(), "{}", query);
+        }
+    }
+
+    #[test]
+    fn orders_read_descending_with_a_dash() {
+        assert_eq!(
+            "-factions.influence".parse(),
+            Ok(Order { by: OrderBy::Influence, descending: true })
+        );
+        assert!("colour".parse::<Order>().is_err());
+    }
+}
@@ -0,0 +1,91 @@
+use super::{Loadout, Ship};
+use crate::systems::nav::Fsd;
+use crate::{Database, Error};
+use chrono::{DateTime, Utc};
+
+impl Ship {
+    /// Keep the ship a loadout describes, replacing what was known of it
+    ///
+    /// Unless what was known is newer. Journals are read in any order, and a
+    /// refit read before the loadout it replaced should stay the refit.
+    pub async fn from_loadout(
+        db: &Database,
+        timestamp: DateTime<Utc>,
+        commander: &str,
+        loadout: &Loadout,
+    ) -> Result<Ship, Error> {
+        let fsd = loadout.fsd();
+
+        let row = sqlx::query!(
+            "
+            INSERT INTO ships (
+                commander,
+                ship_id,
+                ship,
+                name,
+                ident,
+
+                fsd_size,
+                fsd_class,
+                optimal_mass,
+                max_fuel_per_jump,
+                booster,
+
+                unladen_mass,
+                fuel_capacity,
+                fuel_reserve,
+                cargo_capacity,
+                max_jump_range,
+
+                updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13,
+                    $14, $15, $16)
+            ON CONFLICT (commander, ship_id)
+            DO UPDATE SET
+                ship = $3,
+                name = $4,
+                ident = $5,
+
+                fsd_size = $6,
+                fsd_class = $7,
+                optimal_mass = $8,
+                max_fuel_per_jump = $9,
+                booster = $10,
+
+                unladen_mass = $11,
+                fuel_capacity = $12,
+                fuel_reserve = $13,
+                cargo_capacity = $14,
+                max_jump_range = $15,
+
+                updated_at = $16
+            WHERE ships.updated_at < $16
+            RETURNING *
+            ",
+            commander,
+            loadout.ship_id,
+            loadout.ship,
+            loadout.ship_name,
+            loadout.ship_ident,
+            fsd.map(|fsd| fsd.size as i16),
+            fsd.map(|fsd| fsd.class.to_string()),
+            fsd.map(|fsd| fsd.optimal_mass),
+            fsd.map(|fsd| fsd.max_fuel_per_jump),
+            fsd.map_or(0., |fsd| fsd.booster),
+            loadout.unladen_mass,
+            loadout.fuel_capacity.main,
+            loadout.fuel_capacity.reserve,
+            loadout.cargo_capacity,
+            loadout.max_jump_range,
+            timestamp.naive_utc(),
+        )
+        .fetch_optional(&db.pool)
+        .await?;
+
+        match row {
+            Some(row) => Ok(ship!(row)),
+            // Something newer was already kept, and is what the ship is.
+            None => Ship::fetch(db, commander, loadout.ship_id).await,
+        }
+    }
+}
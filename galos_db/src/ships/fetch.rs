@@ -0,0 +1,70 @@
+use super::Ship;
+use crate::systems::nav::Fsd;
+use crate::{Database, Error};
+
+impl Ship {
+    pub async fn fetch(
+        db: &Database,
+        commander: &str,
+        ship_id: i64,
+    ) -> Result<Self, Error> {
+        let row = sqlx::query!(
+            "
+            SELECT *
+            FROM ships
+            WHERE commander = $1 AND ship_id = $2
+            ",
+            commander,
+            ship_id
+        )
+        .fetch_one(&db.pool)
+        .await?;
+
+        Ok(ship!(row))
+    }
+
+    /// The ship `commander` calls `name`, by its name, its ident or its hull
+    ///
+    /// The one most recently flown where more than one answers, a commander
+    /// with two Kraits meaning the one they are in. With no commander, a
+    /// database holding more than one of them, it is whoever's was.
+    pub async fn fetch_by_name(
+        db: &Database,
+        commander: Option<&str>,
+        name: &str,
+    ) -> Result<Self, Error> {
+        let row = sqlx::query!(
+            "
+            SELECT *
+            FROM ships
+            WHERE ($2::text IS NULL OR commander = $2)
+              AND (lower(name) = $1
+                   OR lower(ident) = $1
+                   OR lower(ship) = $1)
+            ORDER BY (lower(name) = $1) IS TRUE DESC, updated_at DESC
+            LIMIT 1
+            ",
+            name.to_lowercase(),
+            commander
+        )
+        .fetch_one(&db.pool)
+        .await?;
+
+        Ok(ship!(row))
+    }
+
+    /// Every ship on record, the most recently flown first
+    pub async fn fetch_all(db: &Database) -> Result<Vec<Self>, Error> {
+        let rows = sqlx::query!(
+            "
+            SELECT *
+            FROM ships
+            ORDER BY updated_at DESC
+            "
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| ship!(row)).collect())
+    }
+}
@@ -0,0 +1,322 @@
+//! The ships a commander flies, and how far each of them jumps
+//!
+//! The journal writes a `Loadout` whenever a ship is boarded or refitted,
+//! listing every module fitted and the masses the ship flies with. That is
+//! all a route needs to know about a ship, so a route can be asked for in a
+//! ship rather than in a jump range typed by hand and remembered wrong.
+use crate::systems::nav::{booster_range, optimal_mass, Fsd, ModuleClass};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+/// A `Loadout` event, as much of it as a ship is kept for
+///
+/// Read straight from the journal's own JSON, which is where it comes from
+/// and where nothing else reads it.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Loadout {
+    /// The hull, as the game names it internally, `krait_mkii`
+    pub ship: String,
+    #[serde(rename = "ShipID")]
+    pub ship_id: i64,
+    pub ship_name: Option<String>,
+    pub ship_ident: Option<String>,
+    /// Hull and modules, without fuel or cargo, in tons
+    #[serde(default)]
+    pub unladen_mass: f64,
+    #[serde(default)]
+    pub cargo_capacity: i32,
+    #[serde(default)]
+    pub fuel_capacity: FuelCapacity,
+    /// The game's own figure, for a full tank and an empty hold
+    #[serde(default)]
+    pub max_jump_range: f64,
+    #[serde(default)]
+    pub modules: Vec<Module>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct FuelCapacity {
+    pub main: f64,
+    pub reserve: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Module {
+    pub slot: String,
+    /// What is fitted, `int_hyperdrive_size5_class5`
+    pub item: String,
+    pub engineering: Option<Engineering>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Engineering {
+    #[serde(default)]
+    pub modifiers: Vec<Modifier>,
+}
+
+/// One engineered figure of a module, what it is now and what it was
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Modifier {
+    pub label: String,
+    /// Missing for the few that are words rather than numbers
+    pub value: Option<f64>,
+}
+
+impl Module {
+    /// The engineered value of `label`, where it has been engineered
+    fn modified(&self, label: &str) -> Option<f64> {
+        self.engineering
+            .as_ref()?
+            .modifiers
+            .iter()
+            .find(|m| m.label == label)?
+            .value
+    }
+}
+
+/// The size and class of an item named `prefix` then `_sizeN_classM`
+///
+/// Classes are numbered from E at 1 up to A at 5.
+fn sized(item: &str, prefix: &str) -> Option<(u8, Option<ModuleClass>)> {
+    let rest = item.to_lowercase();
+    let rest = rest.strip_prefix(prefix)?.strip_prefix("_size")?;
+    let (size, class) = match rest.split_once("_class") {
+        Some((size, class)) => (size, Some(class)),
+        None => (rest, None),
+    };
+    let class = match class.map(str::parse::<u8>) {
+        None => None,
+        Some(Ok(1)) => Some(ModuleClass::E),
+        Some(Ok(2)) => Some(ModuleClass::D),
+        Some(Ok(3)) => Some(ModuleClass::C),
+        Some(Ok(4)) => Some(ModuleClass::B),
+        Some(Ok(5)) => Some(ModuleClass::A),
+        Some(_) => return None,
+    };
+    Some((size.parse().ok()?, class))
+}
+
+impl Loadout {
+    /// The drive fitted, as engineered, with any Guardian booster
+    ///
+    /// A supercruise overcharge drive is read as a stock drive of its size
+    /// and class, unless engineering says otherwise. Nothing where there is
+    /// no drive the fuel equation knows.
+    pub fn fsd(&self) -> Option<Fsd> {
+        let drive = self.modules.iter().find_map(|module| {
+            let sizing = sized(&module.item, "int_hyperdrive_overcharge")
+                .or_else(|| sized(&module.item, "int_hyperdrive"));
+            match sizing {
+                Some((size, Some(class))) => Some((module, size, class)),
+                _ => None,
+            }
+        });
+        let (module, size, class) = drive?;
+
+        let mut fsd = Fsd::new(
+            size,
+            class,
+            match module.modified("FSDOptimalMass") {
+                Some(mass) => mass,
+                None => optimal_mass(size, class)?,
+            },
+        )?;
+        if let Some(most) = module.modified("MaxFuelPerJump") {
+            fsd.max_fuel_per_jump = most;
+        }
+        fsd.booster = self
+            .modules
+            .iter()
+            .find_map(|module| {
+                let (size, _) = sized(&module.item, "int_guardianfsdbooster")?;
+                booster_range(size)
+            })
+            .unwrap_or(0.);
+        Some(fsd)
+    }
+}
+
+/// A commander's ship, as last seen in the journal
+#[derive(Debug, Clone)]
+pub struct Ship {
+    pub commander: String,
+    pub ship_id: i64,
+    pub ship: String,
+    pub name: Option<String>,
+    pub ident: Option<String>,
+    /// [`None`] for a ship with no drive the fuel equation knows
+    pub fsd: Option<Fsd>,
+    pub unladen_mass: f64,
+    pub fuel_capacity: f64,
+    pub fuel_reserve: f64,
+    pub cargo_capacity: i32,
+    /// The range the game last reported, unladen
+    pub max_jump_range: f64,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Ship {
+    /// What to call the ship: its name, or its hull where it has none
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.ship)
+    }
+
+    /// How far a jump goes with a full tank and an empty hold, in Ly
+    pub fn unladen_range(&self) -> Option<f64> {
+        self.range(0.)
+    }
+
+    /// How far a jump goes with a full tank and a full hold, in Ly
+    pub fn laden_range(&self) -> Option<f64> {
+        self.range(self.cargo_capacity as f64)
+    }
+
+    /// How far a jump goes with a full tank and `cargo` tons aboard, in Ly
+    pub fn range(&self, cargo: f64) -> Option<f64> {
+        let fsd = self.fsd?;
+        let mass = self.unladen_mass + cargo + self.fuel_capacity;
+        Some(fsd.range(mass, fsd.max_fuel_per_jump))
+    }
+}
+
+/// A [`Ship`] from any row of `ships`, the query's row type being its own
+macro_rules! ship {
+    ($row:expr) => {{
+        let row = $row;
+        let fsd = match (
+            row.fsd_size,
+            row.fsd_class.as_deref().map(str::parse),
+            row.optimal_mass,
+            row.max_fuel_per_jump,
+        ) {
+            (Some(size), Some(Ok(class)), Some(optimal), Some(most)) => {
+                Fsd::new(size as u8, class, optimal).map(|mut fsd| {
+                    fsd.max_fuel_per_jump = most;
+                    fsd.booster = row.booster;
+                    fsd
+                })
+            }
+            _ => None,
+        };
+        Ship {
+            commander: row.commander,
+            ship_id: row.ship_id,
+            ship: row.ship,
+            name: row.name,
+            ident: row.ident,
+            fsd,
+            unladen_mass: row.unladen_mass,
+            fuel_capacity: row.fuel_capacity,
+            fuel_reserve: row.fuel_reserve,
+            cargo_capacity: row.cargo_capacity,
+            max_jump_range: row.max_jump_range,
+            updated_at: row.updated_at.and_utc(),
+        }
+    }};
+}
+
+mod create;
+mod fetch;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KRAIT: &str = r#"{
+        "timestamp": "2026-10-01T12:00:00Z", "event": "Loadout",
+        "Ship": "krait_mkii", "ShipID": 7, "ShipName": "Wayfarer",
+        "ShipIdent": "WF-07", "HullValue": 42000000, "UnladenMass": 498.2,
+        "CargoCapacity": 64, "MaxJumpRange": 49.9,
+        "FuelCapacity": { "Main": 32.0, "Reserve": 0.63 },
+        "Modules": [
+            { "Slot": "FrameShiftDrive",
+              "Item": "int_hyperdrive_size5_class5", "On": true,
+              "Engineering": { "BlueprintName": "FSD_LongRange",
+                "Modifiers": [
+                    { "Label": "FSDOptimalMass", "Value": 1692.6,
+                      "OriginalValue": 1050.0, "LessIsGood": 0 },
+                    { "Label": "Mass", "Value": 26.0,
+                      "OriginalValue": 20.0, "LessIsGood": 1 }
+                ] } },
+            { "Slot": "Slot06_Size5",
+              "Item": "int_guardianfsdbooster_size5", "On": true },
+            { "Slot": "Slot01_Size6",
+              "Item": "int_cargorack_size6_class1", "On": true }
+        ]
+    }"#;
+
+    fn ship(loadout: &Loadout) -> Ship {
+        Ship {
+            commander: "Jameson".into(),
+            ship_id: loadout.ship_id,
+            ship: loadout.ship.clone(),
+            name: loadout.ship_name.clone(),
+            ident: loadout.ship_ident.clone(),
+            fsd: loadout.fsd(),
+            unladen_mass: loadout.unladen_mass,
+            fuel_capacity: loadout.fuel_capacity.main,
+            fuel_reserve: loadout.fuel_capacity.reserve,
+            cargo_capacity: loadout.cargo_capacity,
+            max_jump_range: loadout.max_jump_range,
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// The drive comes out engineered, with the booster fitted beside it
+    #[test]
+    fn the_drive_is_read_as_fitted() {
+        let loadout: Loadout = serde_json::from_str(KRAIT).unwrap();
+        let fsd = loadout.fsd().unwrap();
+        assert_eq!((fsd.size, fsd.class), (5, ModuleClass::A));
+        assert_eq!(fsd.optimal_mass, 1692.6);
+        assert_eq!(fsd.max_fuel_per_jump, 5.);
+        assert_eq!(fsd.booster, 10.5);
+    }
+
+    /// A hold full of cargo jumps shorter than an empty one
+    #[test]
+    fn cargo_costs_range() {
+        let loadout: Loadout = serde_json::from_str(KRAIT).unwrap();
+        let ship = ship(&loadout);
+        let (laden, unladen) =
+            (ship.laden_range().unwrap(), ship.unladen_range().unwrap());
+        assert!(laden < unladen);
+    }
+
+    /// Sizes and classes come out of the item's name, classes lettered
+    #[test]
+    fn items_are_sized() {
+        assert_eq!(
+            sized("int_hyperdrive_size3_class1", "int_hyperdrive"),
+            Some((3, Some(ModuleClass::E)))
+        );
+        assert_eq!(
+            sized("Int_GuardianFSDBooster_Size2", "int_guardianfsdbooster"),
+            Some((2, None))
+        );
+        assert_eq!(sized("int_fuelscoop_size3_class5", "int_hyperdrive"), None);
+    }
+
+    /// No drive the equation knows is no drive
+    #[test]
+    fn without_a_drive_there_is_no_range() {
+        let loadout = Loadout {
+            ship: "sidewinder".into(),
+            ship_id: 1,
+            ship_name: None,
+            ship_ident: None,
+            unladen_mass: 25.,
+            cargo_capacity: 4,
+            fuel_capacity: FuelCapacity { main: 2., reserve: 0.3 },
+            max_jump_range: 7.5,
+            modules: vec![],
+        };
+        assert!(loadout.fsd().is_none());
+        assert!(ship(&loadout).unladen_range().is_none());
+    }
+}
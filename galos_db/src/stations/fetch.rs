@@ -1,7 +1,8 @@
-use super::Station;
+use super::{PadSize, Station};
 use crate::{Database, Error};
 use elite_journal::station::{EconomyShare, LandingPads, Service, StationType};
 use elite_journal::{Allegiance, Government};
+use std::collections::HashSet;
 
 impl Station {
     pub async fn fetch(
@@ -98,4 +99,30 @@ impl Station {
             })
             .collect())
     }
+
+    /// Which of `addresses` have a station with a pad `pad` fits
+    ///
+    /// Pads nobody has recorded fit nothing, as [`PadSize::fits`] has it.
+    pub async fn systems_with_pad(
+        db: &Database,
+        addresses: &[i64],
+        pad: PadSize,
+    ) -> Result<HashSet<i64>, Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT DISTINCT system_address
+            FROM stations
+            WHERE system_address = ANY($1)
+              AND ((landing_pads).large > 0
+                   OR ($2 < 3 AND (landing_pads).medium > 0)
+                   OR ($2 < 2 AND (landing_pads).small > 0))
+            "#,
+            addresses,
+            pad as i32,
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| row.system_address).collect())
+    }
 }
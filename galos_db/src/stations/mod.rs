@@ -2,8 +2,10 @@
 use chrono::{DateTime, Utc};
 use elite_journal::station::{EconomyShare, LandingPads, Service, StationType};
 use elite_journal::{Allegiance, Government};
+use serde::Serialize;
+use std::str::FromStr;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize)]
 pub struct Station {
     pub system_address: i64,
     pub name: String,
@@ -24,3 +26,78 @@ impl Eq for Station {}
 
 mod create;
 mod fetch;
+
+/// The smallest pad a ship can put down on
+///
+/// A ship fits any pad at least its own size, so a small ship lands anywhere
+/// and a large one only where there is a large pad. Ordered by size so that
+/// what fits is a comparison.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PadSize {
+    Small = 1,
+    Medium = 2,
+    Large = 3,
+}
+
+impl PadSize {
+    /// Whether a station with `pads` has one a ship of this size fits
+    ///
+    /// A station whose pads are not on record has nowhere it can be said to
+    /// fit, which is read as no: a route that sends a large ship to an outpost
+    /// is worse than one that passes over a station that would have done.
+    pub fn fits(&self, pads: Option<&LandingPads>) -> bool {
+        let Some(pads) = pads else { return false };
+        match self {
+            PadSize::Small => {
+                pads.small > 0 || pads.medium > 0 || pads.large > 0
+            }
+            PadSize::Medium => pads.medium > 0 || pads.large > 0,
+            PadSize::Large => pads.large > 0,
+        }
+    }
+}
+
+impl FromStr for PadSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "s" | "small" => Ok(PadSize::Small),
+            "m" | "medium" => Ok(PadSize::Medium),
+            "l" | "large" => Ok(PadSize::Large),
+            _ => Err("invalid pad size".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A pad size is read from its letter or its word, in any case
+    #[test]
+    fn a_pad_size_reads_as_a_letter_or_a_word() {
+        assert_eq!("L".parse(), Ok(PadSize::Large));
+        assert_eq!("medium".parse(), Ok(PadSize::Medium));
+        assert_eq!("Small".parse(), Ok(PadSize::Small));
+        assert!("huge".parse::<PadSize>().is_err());
+    }
+
+    /// A ship fits any pad at least its own size
+    ///
+    /// So an outpost, which has no large pads, takes small and medium ships
+    /// and turns a large one away.
+    #[test]
+    fn a_ship_fits_a_pad_at_least_its_size() {
+        let outpost = LandingPads { large: 0, medium: 1, small: 2 };
+        assert!(PadSize::Small.fits(Some(&outpost)));
+        assert!(PadSize::Medium.fits(Some(&outpost)));
+        assert!(!PadSize::Large.fits(Some(&outpost)));
+    }
+
+    /// Pads nobody has recorded fit nothing
+    #[test]
+    fn unknown_pads_fit_nothing() {
+        assert!(!PadSize::Small.fits(None));
+    }
+}
@@ -482,6 +482,72 @@ impl System {
             .collect())
     }
 
+    /// Every system inside the box from `low` to `high`, edges included
+    ///
+    /// What the routing index loads a cell at a time. A box rather than a
+    /// sphere, since cells are boxes, and `&&&` is what the n-dimensional
+    /// index on `position` answers directly.
+    pub async fn fetch_in_box(
+        db: &Database,
+        low: [f64; 3],
+        high: [f64; 3],
+    ) -> Result<Vec<Self>, Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                address,
+                name,
+                position AS "position!: Option<wkb::Decode<Coordinate>>",
+                population,
+                security as "security: Security",
+                government as "government: Government",
+                allegiance as "allegiance: Allegiance",
+                primary_economy as "primary_economy: Economy",
+                secondary_economy as "secondary_economy: Economy",
+                updated_at,
+                updated_by
+            FROM systems
+            WHERE position &&& ST_3DMakeBox(
+                ST_MakePoint($1, $2, $3),
+                ST_MakePoint($4, $5, $6)
+            )
+            "#,
+            low[0],
+            low[1],
+            low[2],
+            high[0],
+            high[1],
+            high[2],
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        let found: Vec<i64> = rows.iter().map(|row| row.address).collect();
+        let mut present = Self::system_factions(db, &found).await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| System {
+                factions: present.remove(&row.address).unwrap_or_default(),
+                address: row.address,
+                name: row.name,
+                position: row
+                    .position
+                    .map(|p| p.geometry.expect("not null or invalid")),
+                population: row.population.map(|n| n as u64).unwrap_or(0),
+                security: row.security,
+                government: row.government,
+                allegiance: row.allegiance,
+                economies: Economies::new(
+                    row.primary_economy,
+                    row.secondary_economy,
+                ),
+                updated_at: row.updated_at.and_utc(),
+                updated_by: row.updated_by,
+            })
+            .collect())
+    }
+
     /// Which systems within `range` of `center` are admitted, by address
     ///
     /// Admitted by standing at one of `addresses`, or by having one of
@@ -567,6 +633,44 @@ impl System {
         Ok(present)
     }
 
+    /// The class of each system's primary star, by address
+    ///
+    /// Asked of a whole neighbourhood at once, since what wants to know is a
+    /// route deciding which of the systems around it are worth jumping to.
+    ///
+    /// The class a nav route gave the system where there is one, and otherwise
+    /// the class of the star scanned nearest the arrival point, which is the
+    /// one a ship drops in beside. Systems with neither are absent, which the
+    /// caller reads as not knowing.
+    pub async fn primary_star_classes(
+        db: &Database,
+        addresses: &[i64],
+    ) -> Result<HashMap<i64, String>, Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                address,
+                COALESCE(primary_star_class, (
+                    SELECT star_class
+                    FROM stars
+                    WHERE system_address = systems.address
+                    ORDER BY distance_from_arrival_ls, id
+                    LIMIT 1
+                )) AS star_class
+            FROM systems
+            WHERE address = ANY($1)
+            "#,
+            addresses,
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| Some((row.address, row.star_class?)))
+            .collect())
+    }
+
     /// The systems at any of `addresses`
     ///
     /// One query for a set of them, since what asks is holding a list it
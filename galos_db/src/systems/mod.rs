@@ -1,9 +1,10 @@
 //! Systems represent star systems in the Milky Way galaxy
 use chrono::{DateTime, Utc};
 use elite_journal::prelude::*;
+use serde::Serialize;
 use std::fmt;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct System {
     pub address: i64,
     // TODO: We need to support multiple names
@@ -37,7 +38,7 @@ pub struct System {
 /// economy on record or it has none at all. Within one, the primary is what
 /// makes it worth having, and the secondary is what a system may or may not
 /// carry besides.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 pub struct Economies {
     pub primary: Economy,
     pub secondary: Option<Economy>,
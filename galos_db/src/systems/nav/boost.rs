@@ -0,0 +1,224 @@
+//! Routes that supercharge the drive in the jet cones of stellar remnants
+//!
+//! Scooping the cone of a neutron star quadruples a ship's next jump, and a
+//! white dwarf's cone adds half again. A route that knows where they are can
+//! cross in a handful of jumps what takes dozens on the drive alone.
+use super::constraints::Admission;
+use super::{Failure, Index, Plotted, RouteConstraints};
+use crate::systems::System;
+use crate::Database;
+use async_std::task;
+use ordered_float::OrderedFloat;
+use pathfinding::prelude::*;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+/// What a star's jet cone does to the jump after it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum Boost {
+    Neutron,
+    WhiteDwarf,
+}
+
+impl Boost {
+    /// The boost a star of this class gives, if any
+    ///
+    /// The journal writes a neutron star as `N` and the white dwarfs as `D`
+    /// followed by their spectra, `DA`, `DAB`, `DQ` and the rest, and that
+    /// is what is kept, the EDSM dumps' names for them being read into the
+    /// journal's. Each is named here, rather than anything starting with a
+    /// `D` being taken for one.
+    pub fn of(class: &str) -> Option<Boost> {
+        match class {
+            "N" => Some(Boost::Neutron),
+            "D" | "DA" | "DAB" | "DAO" | "DAZ" | "DAV" | "DB" | "DBZ"
+            | "DBV" | "DO" | "DOV" | "DQ" | "DC" | "DCV" | "DX" => {
+                Some(Boost::WhiteDwarf)
+            }
+            _ => None,
+        }
+    }
+
+    /// What the jump range is multiplied by
+    pub fn factor(&self) -> f64 {
+        match self {
+            Boost::Neutron => 4.,
+            Boost::WhiteDwarf => 1.5,
+        }
+    }
+}
+
+impl fmt::Display for Boost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Boost::Neutron => write!(f, "Neutron"),
+            Boost::WhiteDwarf => write!(f, "White Dwarf"),
+        }
+    }
+}
+
+/// One jump of a boosted route
+#[derive(Debug, Clone, Serialize)]
+pub struct BoostJump {
+    pub from: System,
+    pub to: System,
+    pub distance: f64,
+    /// The cone supercharged in before the jump, where the drive alone
+    /// would not have reached
+    pub boost: Option<Boost>,
+}
+
+impl System {
+    /// The fewest jumps to `end`, supercharging where it helps
+    ///
+    /// As [`System::route_to`], but leaving a system whose primary is a
+    /// neutron star or a white dwarf reaches [`Boost::factor`] times as far.
+    /// The primary is what the ship arrives beside, and so is the only cone
+    /// reachable without flying across the system first.
+    ///
+    /// Only systems `constraints` admit are arrived at. Which primaries are
+    /// remnants is asked of the database as the search goes, and a failure
+    /// to answer ends it.
+    pub fn route_boosted_to(
+        &self,
+        db: &Database,
+        end: &System,
+        range: f64,
+        constraints: &RouteConstraints,
+    ) -> Plotted<BoostJump> {
+        let longest = range * Boost::Neutron.factor();
+
+        // Each system is expanded once, but the boost of every system around
+        // it is wanted as soon as it is, so what is known is kept.
+        let failure = Failure::default();
+        let boosts: RefCell<HashMap<i64, Option<Boost>>> = RefCell::default();
+        let boost = |s: &System| -> Option<Boost> {
+            if let Some(known) = boosts.borrow().get(&s.address) {
+                return *known;
+            }
+            let classes = failure.keep(task::block_on(
+                System::primary_star_classes(db, &[s.address]),
+            ))?;
+            let found = classes.get(&s.address).and_then(|c| Boost::of(c));
+            boosts.borrow_mut().insert(s.address, found);
+            found
+        };
+        let learn = |systems: &[System]| {
+            let unknown: Vec<i64> = systems
+                .iter()
+                .map(|s| s.address)
+                .filter(|a| !boosts.borrow().contains_key(a))
+                .collect();
+            if unknown.is_empty() {
+                return;
+            }
+            let Some(classes) = failure.keep(task::block_on(
+                System::primary_star_classes(db, &unknown),
+            )) else {
+                return;
+            };
+            let mut boosts = boosts.borrow_mut();
+            for address in unknown {
+                let found = classes.get(&address).and_then(|c| Boost::of(c));
+                boosts.insert(address, found);
+            }
+        };
+
+        // Sized for a neutron's jump, the longest asked for.
+        let index = RefCell::new(Index::new(db, longest));
+        let admission = Admission::new(db, constraints);
+        let nearby: RefCell<HashMap<i64, Rc<Vec<System>>>> = RefCell::default();
+        let successors = |s: &System| {
+            if failure.failed() {
+                return vec![];
+            }
+            let reach = range * boost(s).map_or(1., |b| b.factor());
+            let around = nearby.borrow().get(&s.address).cloned();
+            let around = match around {
+                Some(around) => around,
+                None => {
+                    let Some(found) =
+                        failure.keep(index.borrow_mut().neighbors(s, reach))
+                    else {
+                        return vec![];
+                    };
+                    let Some(found) = failure.keep(admission.admit(found, end))
+                    else {
+                        return vec![];
+                    };
+                    let found = Rc::new(found);
+                    learn(&found);
+                    nearby.borrow_mut().insert(s.address, found.clone());
+                    found
+                }
+            };
+            around
+                .iter()
+                .filter(|next| *next != s)
+                .map(|next| (next.clone(), OrderedFloat(1.)))
+                .collect::<Vec<_>>()
+        };
+
+        // Every jump could be a neutron's, so dividing by the longest of them
+        // is the only way the estimate never overshoots.
+        let heuristic =
+            |s: &System| OrderedFloat((s.distance(end) / longest).ceil());
+
+        let success = |s: &System| s == end;
+
+        let found = astar(self, successors, heuristic, success);
+        let Some((path, cost)) = failure.or(found)? else {
+            return Ok(None);
+        };
+
+        let jumps = path
+            .windows(2)
+            .map(|pair| {
+                let (a, b) = (&pair[0], &pair[1]);
+                let distance = a.distance(b);
+                BoostJump {
+                    from: a.clone(),
+                    to: b.clone(),
+                    distance,
+                    // Every system left along the path was expanded, and
+                    // its boost learned then.
+                    boost: if distance > range {
+                        boosts.borrow().get(&a.address).copied().flatten()
+                    } else {
+                        None
+                    },
+                }
+            })
+            .collect();
+
+        Ok(Some((jumps, cost)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Neutron stars and every kind of white dwarf boost, nothing else does
+    #[test]
+    fn only_remnants_boost() {
+        assert_eq!(Boost::of("N"), Some(Boost::Neutron));
+        for class in ["D", "DA", "DAB", "DAV", "DB", "DC", "DQ", "DX"] {
+            assert_eq!(Boost::of(class), Some(Boost::WhiteDwarf), "{}", class);
+        }
+        for class in
+            ["K", "M_RedGiant", "H", "SupermassiveBlackHole", "TTS", "Dwarf"]
+        {
+            assert_eq!(Boost::of(class), None, "{}", class);
+        }
+    }
+
+    /// A neutron star does more than a white dwarf
+    #[test]
+    fn neutrons_reach_further() {
+        assert!(Boost::Neutron.factor() > Boost::WhiteDwarf.factor());
+    }
+}
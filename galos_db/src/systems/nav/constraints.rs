@@ -0,0 +1,337 @@
+//! Which systems a route may pass through
+//!
+//! A route is free to jump to anything in range unless told otherwise. What
+//! it can be told is written down once here, and each of the routers asks the
+//! same [`RouteConstraints`] about each system it would arrive at.
+//!
+//! Only the systems arrived at along the way are asked. The destination is
+//! where the user is going whatever it is, and the start is where they
+//! already are. The pad is the exception: it is asked of the stops, where
+//! the ship docks, and not of the systems it only jumps through.
+use super::fuel::scoopable;
+use crate::stations::{PadSize, Station};
+use crate::systems::System;
+use crate::{Database, Error};
+use async_std::task;
+use elite_journal::prelude::{Allegiance, Security};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::mem;
+
+/// Systems that need a permit to enter
+///
+/// Those best known, by name, lower case, as the Elite Dangerous Wiki lists
+/// them at <https://elite-dangerous.fandom.com/wiki/Permits>. Not every one
+/// in the game: new ones are locked as the story goes, and the journal says
+/// nothing about a permit until a ship is turned away. One missing from here
+/// is kept out of with [`RouteConstraints::avoid`], and one whose permit is
+/// held is let back in with [`RouteConstraints::permits`].
+pub const PERMIT_LOCKED: &[&str] = &[
+    "sol",
+    "achenar",
+    "alioth",
+    "shinrarta dezhra",
+    "sirius",
+    "summerland",
+    "beta hydri",
+    "vega",
+    "plx 695",
+    "ross 128",
+    "exbeur",
+    "hors",
+    "van maanen's star",
+    "mbooni",
+    "isinor",
+];
+
+/// What a route may and may not pass through
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RouteConstraints {
+    /// Systems never to arrive in, by address
+    pub avoid: Vec<i64>,
+    /// Keep out of systems on [`PERMIT_LOCKED`]
+    pub avoid_permits: bool,
+    /// Systems whose permit is held, by name, which are not kept out of
+    pub permits: Vec<String>,
+    pub avoid_security: Vec<Security>,
+    pub avoid_allegiance: Vec<Allegiance>,
+    /// Keep out of systems any of these factions is present in, by id
+    pub avoid_factions: Vec<i32>,
+    /// Arrive only beside a primary star that can be scooped
+    pub scoopable: bool,
+    /// Stop only where a station has a pad this ship fits
+    ///
+    /// Asked of the stops alone, by [`RouteConstraints::stops_at`], a ship
+    /// only docking where it stops.
+    pub pad: Option<PadSize>,
+}
+
+impl Eq for RouteConstraints {}
+
+/// Hashed so that a map can key a fetch by it, the security and allegiance
+/// by which of them they are.
+impl Hash for RouteConstraints {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.avoid.hash(state);
+        self.avoid_permits.hash(state);
+        self.permits.hash(state);
+        for security in &self.avoid_security {
+            mem::discriminant(security).hash(state);
+        }
+        for allegiance in &self.avoid_allegiance {
+            mem::discriminant(allegiance).hash(state);
+        }
+        self.avoid_factions.hash(state);
+        self.scoopable.hash(state);
+        self.pad.hash(state);
+    }
+}
+
+impl RouteConstraints {
+    /// Whether nothing is constrained, and every system is admitted and
+    /// stopped at
+    pub fn is_empty(&self) -> bool {
+        *self == RouteConstraints::default()
+    }
+
+    /// Whether anything is asked of the systems arrived in along the way
+    fn constrains_arrivals(&self) -> bool {
+        *self != RouteConstraints { pad: self.pad, ..Default::default() }
+    }
+
+    /// Whether a route may arrive in `system`
+    ///
+    /// `scoopable` is what the database knows of its primary star, which is
+    /// only looked up when asked about. A system with no security or
+    /// allegiance on record is admitted: nothing known against it is not
+    /// something known against it.
+    pub fn admits(&self, system: &System, scoopable: bool) -> bool {
+        if self.avoid.contains(&system.address) {
+            return false;
+        }
+        let name = system.name.to_lowercase();
+        if self.avoid_permits
+            && PERMIT_LOCKED.contains(&name.as_str())
+            && !self.permits.iter().any(|held| held.eq_ignore_ascii_case(&name))
+        {
+            return false;
+        }
+        if let Some(security) = &system.security {
+            if self.avoid_security.contains(security) {
+                return false;
+            }
+        }
+        if let Some(allegiance) = &system.allegiance {
+            if self.avoid_allegiance.contains(allegiance) {
+                return false;
+            }
+        }
+        if system.factions.iter().any(|f| self.avoid_factions.contains(f)) {
+            return false;
+        }
+        !self.scoopable || scoopable
+    }
+
+    /// Whether a route may stop at `system`, which is where the ship docks
+    ///
+    /// Only the pad is asked: a system with no station on record with a pad
+    /// the ship fits is no stop, where one is asked for.
+    pub async fn stops_at(
+        &self,
+        db: &Database,
+        system: &System,
+    ) -> Result<bool, Error> {
+        let Some(pad) = self.pad else { return Ok(true) };
+        let fits =
+            Station::systems_with_pad(db, &[system.address], pad).await?;
+        Ok(fits.contains(&system.address))
+    }
+}
+
+/// Parse a security level by name, as the journal spells it
+pub fn parse_security(s: &str) -> Result<Security, String> {
+    match s.to_lowercase().as_str() {
+        "high" => Ok(Security::High),
+        "medium" => Ok(Security::Medium),
+        "low" => Ok(Security::Low),
+        "anarchy" => Ok(Security::Anarchy),
+        "none" => Ok(Security::None),
+        _ => Err("invalid security".to_string()),
+    }
+}
+
+/// Parse a superpower by name, as the journal spells it
+pub fn parse_allegiance(s: &str) -> Result<Allegiance, String> {
+    match s.to_lowercase().as_str() {
+        "alliance" => Ok(Allegiance::Alliance),
+        "empire" => Ok(Allegiance::Empire),
+        "federation" => Ok(Allegiance::Federation),
+        "independent" => Ok(Allegiance::Independent),
+        "pilotsfederation" => Ok(Allegiance::PilotsFederation),
+        "playerpilots" => Ok(Allegiance::PlayerPilots),
+        "guardian" => Ok(Allegiance::Guardian),
+        "thargoid" => Ok(Allegiance::Thargoid),
+        "none" => Ok(Allegiance::None),
+        _ => Err("invalid allegiance".to_string()),
+    }
+}
+
+/// The constraints of one route, and what has been looked up to apply them
+///
+/// The same systems are offered again and again as a search expands the
+/// systems around them, so each is judged once.
+pub(crate) struct Admission<'a> {
+    db: &'a Database,
+    constraints: &'a RouteConstraints,
+    judged: RefCell<HashMap<i64, bool>>,
+}
+
+impl<'a> Admission<'a> {
+    pub(crate) fn new(
+        db: &'a Database,
+        constraints: &'a RouteConstraints,
+    ) -> Admission<'a> {
+        Admission { db, constraints, judged: RefCell::default() }
+    }
+
+    /// Those of `systems` a route may arrive in, `end` always among them
+    ///
+    /// Nothing is judged where the database fails to say what is needed to
+    /// judge it, and its error is returned instead.
+    pub(crate) fn admit(
+        &self,
+        systems: Vec<System>,
+        end: &System,
+    ) -> Result<Vec<System>, Error> {
+        if !self.constraints.constrains_arrivals() {
+            return Ok(systems);
+        }
+
+        let unjudged: Vec<i64> = systems
+            .iter()
+            .map(|s| s.address)
+            .filter(|a| !self.judged.borrow().contains_key(a))
+            .collect();
+        if !unjudged.is_empty() {
+            let classes = if self.constraints.scoopable {
+                task::block_on(System::primary_star_classes(
+                    self.db, &unjudged,
+                ))?
+            } else {
+                HashMap::new()
+            };
+            let mut judged = self.judged.borrow_mut();
+            for system in systems.iter() {
+                if judged.contains_key(&system.address) {
+                    continue;
+                }
+                let scoop = classes
+                    .get(&system.address)
+                    .is_some_and(|class| scoopable(class));
+                judged.insert(
+                    system.address,
+                    self.constraints.admits(system, scoop),
+                );
+            }
+        }
+
+        let judged = self.judged.borrow();
+        Ok(systems
+            .into_iter()
+            .filter(|s| s == end || judged.get(&s.address) == Some(&true))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn system(name: &str) -> System {
+        System {
+            address: 1,
+            name: name.to_string(),
+            position: None,
+            population: 0,
+            security: None,
+            government: None,
+            allegiance: None,
+            economies: None,
+            factions: vec![],
+            updated_at: Utc::now(),
+            updated_by: "test".to_string(),
+        }
+    }
+
+    /// Nothing asked is everything admitted
+    #[test]
+    fn no_constraints_admit_anything() {
+        assert!(RouteConstraints::default().admits(&system("Sol"), false));
+    }
+
+    /// Avoiding permits keeps out of the locked systems, whatever the case
+    #[test]
+    fn permits_are_avoided_by_name() {
+        let constraints =
+            RouteConstraints { avoid_permits: true, ..Default::default() };
+        assert!(!constraints.admits(&system("SOL"), true));
+        assert!(constraints.admits(&system("Wolf 397"), true));
+    }
+
+    /// A permit held lets the route back into its system
+    #[test]
+    fn permits_held_are_not_avoided() {
+        let constraints = RouteConstraints {
+            avoid_permits: true,
+            permits: vec!["Sol".to_string()],
+            ..Default::default()
+        };
+        assert!(constraints.admits(&system("Sol"), true));
+        assert!(!constraints.admits(&system("Achenar"), true));
+    }
+
+    /// Anarchies are kept out of, and systems of no known security are not
+    #[test]
+    fn anarchies_are_avoided() {
+        let constraints = RouteConstraints {
+            avoid_security: vec![Security::Anarchy],
+            ..Default::default()
+        };
+        let mut anarchy = system("Anarchy");
+        anarchy.security = Some(Security::Anarchy);
+        assert!(!constraints.admits(&anarchy, true));
+        assert!(constraints.admits(&system("Unknown"), true));
+    }
+
+    /// A faction present anywhere in a system keeps the route out of it
+    #[test]
+    fn factions_are_avoided_where_present() {
+        let constraints =
+            RouteConstraints { avoid_factions: vec![7], ..Default::default() };
+        let mut held = system("Held");
+        held.factions = vec![3, 7];
+        assert!(!constraints.admits(&held, true));
+    }
+
+    /// What is required has to be known to be there
+    #[test]
+    fn requirements_need_what_they_require() {
+        let constraints =
+            RouteConstraints { scoopable: true, ..Default::default() };
+        assert!(constraints.admits(&system("A"), true));
+        assert!(!constraints.admits(&system("A"), false));
+    }
+
+    /// The pad is asked of the stops, not of the systems passed through
+    #[test]
+    fn pads_do_not_constrain_arrivals() {
+        let constraints = RouteConstraints {
+            pad: Some(PadSize::Large),
+            ..Default::default()
+        };
+        assert!(!constraints.constrains_arrivals());
+        assert!(constraints.admits(&system("A"), false));
+    }
+}
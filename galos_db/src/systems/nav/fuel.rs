@@ -0,0 +1,408 @@
+//! What a jump burns, and routes flown on the fuel actually in the tank
+//!
+//! A jump range on its own is what a ship could do with a full tank and
+//! nothing else to think about. The tank empties as it goes, and a route that
+//! leaves it dry in a system with nothing to scoop is no route at all.
+use super::constraints::Admission;
+use super::{Failure, Index, ModuleClass, Plotted, RouteConstraints};
+use crate::systems::System;
+use crate::Database;
+use async_std::task;
+use ordered_float::OrderedFloat;
+use pathfinding::prelude::*;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// The multiplier in the fuel equation, set by a drive's class
+fn linear_constant(class: ModuleClass) -> f64 {
+    match class {
+        ModuleClass::A => 12.,
+        ModuleClass::B => 10.,
+        ModuleClass::C => 8.,
+        ModuleClass::D => 10.,
+        ModuleClass::E => 11.,
+    }
+}
+
+/// The power in the fuel equation, set by a drive's size
+///
+/// Nothing for a size no drive comes in.
+fn power_constant(size: u8) -> Option<f64> {
+    match size {
+        2 => Some(2.),
+        3 => Some(2.15),
+        4 => Some(2.3),
+        5 => Some(2.45),
+        6 => Some(2.6),
+        7 => Some(2.75),
+        8 => Some(2.9),
+        _ => None,
+    }
+}
+
+/// The most a stock drive of this size and class burns in one jump, in tons
+///
+/// Size 8 carries on the step from 6 to 7, as its power constant does.
+fn max_fuel_per_jump(size: u8, class: ModuleClass) -> Option<f64> {
+    let (e, b, a) = match size {
+        2 => (0.6, 0.8, 0.9),
+        3 => (1.2, 1.5, 1.8),
+        4 => (2.0, 2.5, 3.0),
+        5 => (3.3, 4.1, 5.0),
+        6 => (5.3, 6.6, 8.0),
+        7 => (8.5, 10.6, 12.8),
+        8 => (13.6, 17.0, 20.5),
+        _ => return None,
+    };
+    Some(match class {
+        ModuleClass::A => a,
+        ModuleClass::B => b,
+        ModuleClass::C | ModuleClass::D | ModuleClass::E => e,
+    })
+}
+
+// https://www.reddit.com/r/EliteDangerous/comments/30nx4u/the_hyperspace_fuel_equation_documented
+pub fn fuel_cost(
+    distance: f64,
+    mass: f64,
+    optimal_mass: f64,
+    size: u8,
+    class: ModuleClass,
+) -> Option<f64> {
+    let l = linear_constant(class);
+    let p = power_constant(size)?;
+    Some(l * 0.001 * (distance * mass / optimal_mass).powf(p))
+}
+
+/// The mass a stock drive of this size and class is rated for, in tons
+pub fn optimal_mass(size: u8, class: ModuleClass) -> Option<f64> {
+    let (e, d, c, b, a) = match size {
+        2 => (48., 54., 60., 75., 90.),
+        3 => (80., 90., 100., 125., 150.),
+        4 => (280., 315., 350., 438., 525.),
+        5 => (560., 630., 700., 875., 1050.),
+        6 => (960., 1080., 1200., 1500., 1800.),
+        7 => (1440., 1620., 1800., 2250., 2700.),
+        8 => (2160., 2430., 2700., 3375., 4050.),
+        _ => return None,
+    };
+    Some(match class {
+        ModuleClass::A => a,
+        ModuleClass::B => b,
+        ModuleClass::C => c,
+        ModuleClass::D => d,
+        ModuleClass::E => e,
+    })
+}
+
+/// The light years a Guardian FSD booster of this size adds to a full jump
+pub fn booster_range(size: u8) -> Option<f64> {
+    match size {
+        1 => Some(4.),
+        2 => Some(6.),
+        3 => Some(7.75),
+        4 => Some(9.25),
+        5 => Some(10.5),
+        _ => None,
+    }
+}
+
+/// A frame shift drive, as the fuel equation sees one
+///
+/// Only made by [`Fsd::new`], for a size the equation knows, which is what
+/// lets the rest of it never ask again.
+#[derive(Debug, Clone, Copy)]
+pub struct Fsd {
+    pub size: u8,
+    pub class: ModuleClass,
+    /// The mass the drive is rated for, in tons
+    pub optimal_mass: f64,
+    /// The most the drive will burn in a single jump, in tons
+    pub max_fuel_per_jump: f64,
+    /// What a Guardian FSD booster adds to a jump on the most fuel, in Ly
+    pub booster: f64,
+    /// The power in the fuel equation, from the size
+    power: f64,
+}
+
+impl Fsd {
+    /// A drive of this size and class, burning what a stock one does
+    ///
+    /// Nothing for a size no drive comes in.
+    pub fn new(size: u8, class: ModuleClass, optimal_mass: f64) -> Option<Fsd> {
+        Some(Fsd {
+            size,
+            class,
+            optimal_mass,
+            max_fuel_per_jump: max_fuel_per_jump(size, class)?,
+            booster: 0.,
+            power: power_constant(size)?,
+        })
+    }
+
+    /// What jumping `distance` light years burns with the ship at `mass`
+    pub fn fuel(&self, distance: f64, mass: f64) -> f64 {
+        let distance = distance / self.stretch(mass);
+        let l = linear_constant(self.class);
+        l * 0.001 * (distance * mass / self.optimal_mass).powf(self.power)
+    }
+
+    /// How far the ship at `mass` jumps on `fuel` tons, in light years
+    ///
+    /// The fuel equation run backwards. No more than the drive's most per jump
+    /// is ever burned, so fuel past that goes no further.
+    pub fn range(&self, mass: f64, fuel: f64) -> f64 {
+        self.unboosted(mass, fuel) * self.stretch(mass)
+    }
+
+    fn unboosted(&self, mass: f64, fuel: f64) -> f64 {
+        let fuel = fuel.min(self.max_fuel_per_jump).max(0.);
+        let l = linear_constant(self.class);
+        self.optimal_mass / mass * (fuel / (l * 0.001)).powf(1. / self.power)
+    }
+
+    /// What a booster multiplies every jump at `mass` by
+    ///
+    /// The game adds the booster's light years to a jump on the most fuel, and
+    /// is coy about the jumps short of that. Spread in proportion over all of
+    /// them, range and fuel stay each other's inverse, and the longest jump
+    /// is what the game says it is.
+    fn stretch(&self, mass: f64) -> f64 {
+        if self.booster <= 0. {
+            return 1.;
+        }
+        1. + self.booster / self.unboosted(mass, self.max_fuel_per_jump)
+    }
+}
+
+/// Whether a star of this class can be scooped for fuel
+///
+/// The main sequence, KGBFOAM, and the giants of those same classes, which
+/// the journal writes as the class and what it has grown into, `M_RedGiant`.
+/// Everything else, the dwarfs and the remnants and the stars still forming,
+/// gives nothing.
+pub fn scoopable(class: &str) -> bool {
+    matches!(
+        class.split('_').next(),
+        Some("K" | "G" | "B" | "F" | "O" | "A" | "M")
+    )
+}
+
+/// One jump of a route flown on fuel
+#[derive(Debug, Clone, Serialize)]
+pub struct FuelJump {
+    pub from: System,
+    pub to: System,
+    pub distance: f64,
+    /// Tons burned on the jump
+    pub fuel_used: f64,
+    /// Tons left in the tank on arrival, before any scooping
+    pub fuel_left: f64,
+    /// Whether to scoop at the star arrived at
+    pub refuel: bool,
+}
+
+/// A system, and the fuel a ship arrives there with
+///
+/// In hundredths of a ton, rounded down, so that two arrivals with next to
+/// the same fuel are the same place to have got to rather than the search
+/// telling them apart forever. Rounding down errs towards an empty tank.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Tanked {
+    system: System,
+    fuel: u32,
+}
+
+/// The systems around one, each with whether its star can be scooped
+type Scoops = Rc<Vec<(System, bool)>>;
+
+fn hundredths(tons: f64) -> u32 {
+    (tons.max(0.) * 100.).floor() as u32
+}
+
+impl System {
+    /// The fewest jumps to `end` that the tank can pay for
+    ///
+    /// As [`System::route_to`], but a jump is only open to a ship with the
+    /// fuel in hand for it, and the ship's range shrinks as the tank does. A
+    /// ship arriving at a scoopable primary fills up. It sets out full.
+    ///
+    /// `mass` is everything but the fuel, which is added to it jump by jump
+    /// as what is left in the tank. Only systems `constraints` admit are
+    /// arrived at. Which stars scoop is asked of the database as the search
+    /// goes, and a failure to answer ends it.
+    pub fn route_with_fuel(
+        &self,
+        db: &Database,
+        end: &System,
+        fsd: &Fsd,
+        mass: f64,
+        tank: f64,
+        constraints: &RouteConstraints,
+    ) -> Plotted<FuelJump> {
+        // As far as any jump could go, which is an empty tank burning the
+        // most the drive allows. Every jump taken is shorter, so it is what
+        // neighbours are looked for within, and what the heuristic divides by.
+        let reach = fsd.range(mass, fsd.max_fuel_per_jump);
+        let full = hundredths(tank);
+
+        // A system is arrived at with many different tanks, and what is
+        // around it is the same each time.
+        let index = RefCell::new(Index::new(db, reach));
+        let admission = Admission::new(db, constraints);
+        let failure = Failure::default();
+        let nearby: RefCell<HashMap<i64, Scoops>> = RefCell::default();
+        let around = |s: &System| -> Scoops {
+            if let Some(found) = nearby.borrow().get(&s.address) {
+                return found.clone();
+            }
+            if failure.failed() {
+                return Rc::default();
+            }
+            let Some(around) =
+                failure.keep(index.borrow_mut().neighbors(s, reach))
+            else {
+                return Rc::default();
+            };
+            let Some(systems) = failure.keep(admission.admit(around, end))
+            else {
+                return Rc::default();
+            };
+            let addresses: Vec<i64> =
+                systems.iter().map(|s| s.address).collect();
+            let Some(classes) = failure.keep(task::block_on(
+                System::primary_star_classes(db, &addresses),
+            )) else {
+                return Rc::default();
+            };
+            let found: Scoops = Rc::new(
+                systems
+                    .into_iter()
+                    .map(|s| {
+                        let scoop = classes
+                            .get(&s.address)
+                            .is_some_and(|class| scoopable(class));
+                        (s, scoop)
+                    })
+                    .collect(),
+            );
+            nearby.borrow_mut().insert(s.address, found.clone());
+            found
+        };
+
+        let successors = |at: &Tanked| {
+            let fuel = at.fuel as f64 / 100.;
+            let can = fsd.range(mass + fuel, fuel);
+            around(&at.system)
+                .iter()
+                .filter(|(next, _)| *next != at.system)
+                .filter_map(|(next, scoop)| {
+                    let distance = at.system.distance(next);
+                    if distance > can {
+                        return None;
+                    }
+                    let left = fuel - fsd.fuel(distance, mass + fuel);
+                    let fuel = if *scoop { full } else { hundredths(left) };
+                    Some((
+                        Tanked { system: next.clone(), fuel },
+                        OrderedFloat(1.),
+                    ))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let heuristic = |at: &Tanked| {
+            OrderedFloat((at.system.distance(end) / reach).ceil())
+        };
+
+        let success = |at: &Tanked| at.system == *end;
+
+        let start = Tanked { system: self.clone(), fuel: full };
+        let found = astar(&start, successors, heuristic, success);
+        let Some((path, cost)) = failure.or(found)? else {
+            return Ok(None);
+        };
+
+        let jumps = path
+            .windows(2)
+            .map(|pair| {
+                let (a, b) = (&pair[0], &pair[1]);
+                let fuel = a.fuel as f64 / 100.;
+                let distance = a.system.distance(&b.system);
+                let fuel_used = fsd.fuel(distance, mass + fuel);
+                let fuel_left = fuel - fuel_used;
+                FuelJump {
+                    from: a.system.clone(),
+                    to: b.system.clone(),
+                    distance,
+                    fuel_used,
+                    fuel_left,
+                    refuel: b.fuel > hundredths(fuel_left),
+                }
+            })
+            .collect();
+
+        Ok(Some((jumps, cost)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A jump burns what the equation says, and twice the mass burns more
+    /// than twice the fuel
+    #[test]
+    fn heavier_jumps_burn_more_than_in_proportion() {
+        let fsd = Fsd::new(2, ModuleClass::E, 48.).unwrap();
+        let light = fsd.fuel(5., 25.);
+        let heavy = fsd.fuel(5., 50.);
+        assert!((light - 0.011 * (5. * 25. / 48f64).powf(2.)).abs() < 1e-9);
+        assert!(heavy > light * 2.);
+    }
+
+    /// The range on some fuel burns exactly that fuel
+    #[test]
+    fn range_is_the_fuel_equation_backwards() {
+        let fsd = Fsd::new(5, ModuleClass::A, 1050.).unwrap();
+        let range = fsd.range(400., 2.);
+        assert!((fsd.fuel(range, 400.) - 2.).abs() < 1e-9);
+    }
+
+    /// No more than the drive's most per jump is burned, however full the
+    /// tank
+    #[test]
+    fn range_is_capped_by_the_most_a_jump_burns() {
+        let fsd = Fsd::new(2, ModuleClass::E, 48.).unwrap();
+        assert_eq!(fsd.range(25., 0.6), fsd.range(25., 2.));
+        assert!(fsd.range(25., 0.3) < fsd.range(25., 0.6));
+    }
+
+    /// A booster adds its light years to the longest jump, and the fuel
+    /// equation still runs backwards
+    #[test]
+    fn boosters_add_to_the_longest_jump() {
+        let stock = Fsd::new(5, ModuleClass::A, 1050.).unwrap();
+        let boosted = Fsd { booster: booster_range(5).unwrap(), ..stock };
+        let most = stock.max_fuel_per_jump;
+        assert!(
+            (boosted.range(400., most) - stock.range(400., most) - 10.5).abs()
+                < 1e-9
+        );
+        let range = boosted.range(400., 2.);
+        assert!((boosted.fuel(range, 400.) - 2.).abs() < 1e-9);
+    }
+
+    /// KGBFOAM scoops, giants included, and nothing else does
+    #[test]
+    fn only_the_main_sequence_scoops() {
+        for class in ["K", "G", "B", "F", "O", "A", "M", "M_RedGiant"] {
+            assert!(scoopable(class), "{}", class);
+        }
+        for class in ["N", "DA", "H", "TTS", "L", "T", "Y", "AeBe", "W", "MS"] {
+            assert!(!scoopable(class), "{}", class);
+        }
+    }
+}
@@ -0,0 +1,244 @@
+//! The systems a route is searched over, held in memory
+//!
+//! A* asks for the neighbours of every system it expands, and asked of the
+//! database that is a query per expansion: thousands of them for a long
+//! route, each finding mostly what the last one found. Here space is cut
+//! into cubes, each loaded whole the first time anything in it is wanted and
+//! answered from memory after that.
+//!
+//! The cubes a search has moved on from are let go once more systems are
+//! held than the budget allows, least recently wanted first, so a route across
+//! the galaxy streams through it rather than piling up behind it.
+use crate::systems::System;
+use crate::{Database, Error};
+use async_std::task;
+use std::collections::HashMap;
+
+/// The most systems held at once, unless asked otherwise
+///
+/// A few hundred bytes each, so this is some hundreds of megabytes at worst.
+/// The core holds more than this within a couple of jumps, and a search that
+/// needs more than the budget to stand where it is keeps what it needs.
+pub const BUDGET: usize = 1_000_000;
+
+type Cell = (i64, i64, i64);
+
+/// The cell `at` falls in, on a grid of cells `side` Ly a side
+fn cell_of(side: f64, at: [f64; 3]) -> Cell {
+    (
+        (at[0] / side).floor() as i64,
+        (at[1] / side).floor() as i64,
+        (at[2] / side).floor() as i64,
+    )
+}
+
+/// Every cell with some part within `range` of `at`
+///
+/// The cells the sphere's bounding box touches, which is a few more than the
+/// sphere does, at the corners. They are cheap, and are the next
+/// neighbourhood's anyway.
+fn cells_around(side: f64, at: [f64; 3], range: f64) -> Vec<Cell> {
+    let low = cell_of(side, [at[0] - range, at[1] - range, at[2] - range]);
+    let high = cell_of(side, [at[0] + range, at[1] + range, at[2] + range]);
+    let mut cells = Vec::new();
+    for x in low.0..=high.0 {
+        for y in low.1..=high.1 {
+            for z in low.2..=high.2 {
+                cells.push((x, y, z));
+            }
+        }
+    }
+    cells
+}
+
+/// A uniform grid of systems, loaded a cell at a time
+pub struct Index<'a> {
+    db: &'a Database,
+    /// The length of a cell's edge, in Ly
+    side: f64,
+    budget: usize,
+    /// Each loaded cell, and when it was last wanted
+    cells: HashMap<Cell, (Vec<System>, u64)>,
+    held: usize,
+    now: u64,
+}
+
+impl<'a> Index<'a> {
+    /// An empty index for searches jumping at most `range` at a time
+    ///
+    /// A cell's edge is the range, so a neighbourhood spans no more than three
+    /// cells a side, and a cell loaded is mostly a cell used. A search that
+    /// jumps further some of the time wants the longest of its jumps here,
+    /// or those neighbourhoods are loaded as many small cells.
+    pub fn new(db: &'a Database, range: f64) -> Index<'a> {
+        Index::with_budget(db, range, BUDGET)
+    }
+
+    /// An empty index holding no more than `budget` systems it can let go of
+    pub fn with_budget(
+        db: &'a Database,
+        range: f64,
+        budget: usize,
+    ) -> Index<'a> {
+        Index {
+            db,
+            side: range.max(1.),
+            budget,
+            cells: HashMap::new(),
+            held: 0,
+            now: 0,
+        }
+    }
+
+    /// The database cells are loaded from
+    pub(crate) fn db(&self) -> &'a Database {
+        self.db
+    }
+
+    /// How many systems are held
+    pub fn held(&self) -> usize {
+        self.held
+    }
+
+    /// Every system within `range` of `system`, itself included
+    ///
+    /// The same systems [`System::neighbors`] finds, in the same order, by
+    /// address, so that a search over either makes the same choices between
+    /// equally good routes. A system with no position is within range of
+    /// nothing.
+    pub fn neighbors(
+        &mut self,
+        system: &System,
+        range: f64,
+    ) -> Result<Vec<System>, Error> {
+        let Some(p) = system.position else {
+            return Ok(Vec::new());
+        };
+        self.now += 1;
+        let wanted = cells_around(self.side, [p.x, p.y, p.z], range);
+        self.load(&wanted)?;
+
+        let mut found = Vec::new();
+        for cell in &wanted {
+            let Some((systems, used)) = self.cells.get_mut(cell) else {
+                continue;
+            };
+            *used = self.now;
+            found.extend(
+                systems.iter().filter(|s| system.distance(s) <= range).cloned(),
+            );
+        }
+        self.evict();
+        found.sort_unstable_by_key(|s| s.address);
+        Ok(found)
+    }
+
+    /// Load whichever of `cells` are not yet held, in one query
+    ///
+    /// The box around all of them is asked for and what comes back is sorted
+    /// into the cells it falls in. A system on the edge between two cells is
+    /// in the box of both, and goes in only the one its position floors to.
+    fn load(&mut self, cells: &[Cell]) -> Result<(), Error> {
+        let missing: Vec<Cell> = cells
+            .iter()
+            .filter(|cell| !self.cells.contains_key(cell))
+            .copied()
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let low = missing.iter().fold(missing[0], |a, c| {
+            (a.0.min(c.0), a.1.min(c.1), a.2.min(c.2))
+        });
+        let high = missing.iter().fold(missing[0], |a, c| {
+            (a.0.max(c.0), a.1.max(c.1), a.2.max(c.2))
+        });
+        let side = self.side;
+        let systems = task::block_on(System::fetch_in_box(
+            self.db,
+            [low.0 as f64 * side, low.1 as f64 * side, low.2 as f64 * side],
+            [
+                (high.0 + 1) as f64 * side,
+                (high.1 + 1) as f64 * side,
+                (high.2 + 1) as f64 * side,
+            ],
+        ))?;
+
+        let mut sorted: HashMap<Cell, Vec<System>> =
+            missing.iter().map(|cell| (*cell, Vec::new())).collect();
+        for system in systems {
+            let Some(p) = system.position else { continue };
+            if let Some(cell) = sorted.get_mut(&cell_of(side, [p.x, p.y, p.z]))
+            {
+                cell.push(system);
+            }
+        }
+        for (cell, systems) in sorted {
+            self.held += systems.len();
+            self.cells.insert(cell, (systems, self.now));
+        }
+        Ok(())
+    }
+
+    /// Let go of the least recently wanted cells until within budget
+    ///
+    /// Never a cell wanted by the neighbourhood just answered, which is
+    /// needed whatever the budget says.
+    fn evict(&mut self) {
+        if self.held <= self.budget {
+            return;
+        }
+        let mut stale: Vec<(u64, Cell)> = self
+            .cells
+            .iter()
+            .filter(|(_, (_, used))| *used < self.now)
+            .map(|(cell, (_, used))| (*used, *cell))
+            .collect();
+        stale.sort();
+        for (_, cell) in stale {
+            if self.held <= self.budget {
+                break;
+            }
+            if let Some((systems, _)) = self.cells.remove(&cell) {
+                self.held -= systems.len();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cells floor, so the one below zero is -1 rather than a second 0
+    #[test]
+    fn cells_either_side_of_zero_are_different() {
+        assert_eq!(cell_of(10., [0., 0., 0.]), (0, 0, 0));
+        assert_eq!(cell_of(10., [-0.5, 9.9, 10.]), (-1, 0, 1));
+    }
+
+    /// With cells a range a side, a neighbourhood is at most three a side
+    #[test]
+    fn a_neighbourhood_spans_at_most_three_cells() {
+        for at in [[0., 0., 0.], [5., 5., 5.], [-12.3, 40.1, 7.]] {
+            assert!(cells_around(10., at, 10.).len() <= 27);
+        }
+        assert_eq!(cells_around(10., [5., 5., 5.], 1.), vec![(0, 0, 0)]);
+    }
+
+    /// Everywhere in range of a point is in one of the cells around it
+    #[test]
+    fn nothing_in_range_falls_outside_the_cells_around_it() {
+        let at = [3., -7., 18.];
+        let cells = cells_around(7.5, at, 7.5);
+        for dx in [-7.5, 0., 7.5] {
+            for dy in [-7.5, 0., 7.5] {
+                for dz in [-7.5, 0., 7.5] {
+                    let there = [at[0] + dx, at[1] + dy, at[2] + dz];
+                    assert!(cells.contains(&cell_of(7.5, there)));
+                }
+            }
+        }
+    }
+}
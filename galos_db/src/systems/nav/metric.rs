@@ -0,0 +1,296 @@
+//! What a route is made cheapest in
+//!
+//! Fewest jumps is what a route is plotted in by default, and is rarely what
+//! anyone is actually short of. A route can instead be the shortest, the
+//! quickest, or the one that burns least, each jump costing what it costs in
+//! that and the search steered by an estimate of what is left. The estimate
+//! never overshoots, so that what comes back is the cheapest there is.
+//!
+//! The time a flight takes is reckoned here, for routes and for
+//! [`super::trade`] alike.
+use super::constraints::Admission;
+use super::{Failure, Fsd, Index, Plotted, RouteConstraints};
+use crate::stations::Station;
+use crate::systems::System;
+use crate::{Database, Error};
+use ordered_float::OrderedFloat;
+use pathfinding::prelude::*;
+use std::cell::RefCell;
+use std::fmt;
+
+/// Seconds spent on one jump, from the charge to the drop at the far end
+///
+/// Charging, the tunnel itself, and lining up on the next star. Scooping
+/// back what the jump burned is counted apart, where the drive is known.
+pub const JUMP: f64 = 50.;
+
+/// Seconds spent docking, trading and leaving again at a station
+pub const DOCKING: f64 = 90.;
+
+/// Tons a second a fuel scoop takes in, skimming a star's corona
+///
+/// A 5A scoop's, the one most ships that go far are fitted with.
+pub const SCOOP: f64 = 0.342;
+
+/// How long supercruise to a station `ls` light seconds out takes, in seconds
+///
+/// Supercruise speeds up the further there is to go, so the time grows far
+/// slower than the distance. A fit to flown times rather than a model of the
+/// drive: about two minutes to a station at a thousand light seconds and ten
+/// to one at a hundred thousand.
+pub fn supercruise(ls: f64) -> f64 {
+    14.3 * ls.max(1.).powf(0.33)
+}
+
+/// What a route is made cheapest in
+#[derive(Debug, Clone, Copy)]
+pub enum Metric {
+    Jumps,
+    /// Light years, jump by jump
+    Distance,
+    /// Seconds, [`JUMP`] for each jump, and supercruise to a station at each
+    /// stop
+    ///
+    /// With `fsd` known, scooping back what each jump burned with the ship at
+    /// `mass`, fuel included, as well: a long jump burns more than a short
+    /// one, and is longer at the star after it.
+    Time {
+        fsd: Option<Fsd>,
+        mass: f64,
+    },
+    /// Tons, burned by `fsd` with the ship at `mass`, fuel included
+    Fuel {
+        fsd: Fsd,
+        mass: f64,
+    },
+}
+
+impl Metric {
+    /// What a single jump of `distance` Ly costs
+    pub fn jump(&self, distance: f64) -> f64 {
+        match self {
+            Metric::Jumps => 1.,
+            Metric::Distance => distance,
+            Metric::Time { fsd, mass } => {
+                let burned = fsd.map_or(0., |fsd| fsd.fuel(distance, *mass));
+                JUMP + burned / SCOOP
+            }
+            Metric::Fuel { fsd, mass } => fsd.fuel(distance, *mass),
+        }
+    }
+
+    /// What the jumps through `systems` cost, first to last
+    pub fn path(&self, systems: &[System]) -> f64 {
+        systems
+            .windows(2)
+            .map(|pair| self.jump(pair[0].distance(&pair[1])))
+            .sum()
+    }
+
+    /// About what getting `distance` Ly in jumps of `range` costs
+    ///
+    /// However many jumps, they are at least as many as the range divides
+    /// the distance into, each taking at least [`JUMP`]. However they go,
+    /// they are at least as long as the straight line. Those are never more
+    /// than it costs.
+    ///
+    /// Fuel is the odd one out. A jump burns less than in proportion to its
+    /// length, so splitting one in two always burns less, and the least a
+    /// distance could burn, in jumps as short as they come, is nothing. So
+    /// nothing is what it is estimated as, and the search goes out evenly in
+    /// every direction rather than give up the route that burns least.
+    pub fn estimate(&self, distance: f64, range: f64) -> f64 {
+        let jumps = (distance / range).ceil();
+        match self {
+            Metric::Jumps => jumps,
+            Metric::Distance => distance,
+            Metric::Time { .. } => jumps * JUMP,
+            Metric::Fuel { .. } => 0.,
+        }
+    }
+
+    /// A cost in this metric, written the way it is read
+    pub fn show(&self, cost: f64) -> String {
+        match self {
+            Metric::Jumps => format!("{}", cost),
+            Metric::Distance => format!("{:.2} Ly", cost),
+            Metric::Time { .. } => {
+                let secs = cost.round() as u64;
+                format!(
+                    "{}:{:02}:{:02}",
+                    secs / 3600,
+                    secs / 60 % 60,
+                    secs % 60
+                )
+            }
+            Metric::Fuel { .. } => format!("{:.2} t", cost),
+        }
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Metric::Jumps => write!(f, "Jumps"),
+            Metric::Distance => write!(f, "Distance"),
+            Metric::Time { .. } => write!(f, "Time"),
+            Metric::Fuel { .. } => write!(f, "Fuel"),
+        }
+    }
+}
+
+/// Seconds in supercruise from arrival to a station in `system`
+///
+/// To `station` if it is named and known, and otherwise to whichever is
+/// nearest the arrival star. Nothing for a system with no station known to
+/// be anywhere in particular, which is not the database failing to say.
+pub async fn docking(
+    db: &Database,
+    system: &System,
+    station: Option<&str>,
+) -> Result<f64, Error> {
+    let stations = Station::fetch_all(db, system.address).await?;
+    let named = station.and_then(|name| {
+        stations
+            .iter()
+            .find(|s| s.name.eq_ignore_ascii_case(name))
+            .and_then(|s| s.dist_from_star_ls)
+    });
+    let nearest = stations
+        .iter()
+        .filter_map(|s| s.dist_from_star_ls)
+        .min_by(|a, b| a.total_cmp(b));
+    Ok(named.or(nearest).map_or(0., supercruise))
+}
+
+impl System {
+    /// The cheapest route to `end` in `metric`, in jumps of `range`
+    ///
+    /// As [`System::route_constrained`], each jump costing what `metric`
+    /// says rather than one. What comes back costs only the jumps: the time
+    /// spent getting to a station at the end is not a choice of route, and
+    /// is the caller's to add.
+    pub fn route_measured(
+        &self,
+        db: &Database,
+        end: &System,
+        range: f64,
+        metric: &Metric,
+        constraints: &RouteConstraints,
+    ) -> Plotted<Self> {
+        let index = RefCell::new(Index::new(db, range));
+        let admission = Admission::new(db, constraints);
+        let failure = Failure::default();
+        let successors = |s: &System| {
+            if failure.failed() {
+                return vec![];
+            }
+            let around = failure
+                .keep(index.borrow_mut().neighbors(s, range))
+                .and_then(|around| failure.keep(admission.admit(around, end)))
+                .unwrap_or_default();
+            around
+                .into_iter()
+                .filter(|next| next != s)
+                .map(|next| {
+                    let cost = metric.jump(s.distance(&next));
+                    (next, OrderedFloat(cost))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let heuristic =
+            |s: &System| OrderedFloat(metric.estimate(s.distance(end), range));
+
+        let success = |s: &System| s == end;
+
+        let found = astar(self, successors, heuristic, success);
+        failure.or(found)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::nav::ModuleClass;
+
+    /// Every estimate is no more than a straight run of full jumps costs
+    #[test]
+    fn estimates_never_overshoot_a_straight_run() {
+        let fsd = Fsd::new(2, ModuleClass::E, 48.).unwrap();
+        let range = fsd.range(27., fsd.max_fuel_per_jump);
+        for metric in [
+            Metric::Jumps,
+            Metric::Distance,
+            Metric::Time { fsd: None, mass: 27. },
+            Metric::Time { fsd: Some(fsd), mass: 27. },
+            Metric::Fuel { fsd, mass: 27. },
+        ] {
+            for distance in [0., 1., range, range * 2.5, range * 10.] {
+                let jumps = (distance / range).ceil();
+                let straight = if jumps > 0. {
+                    jumps * metric.jump(distance / jumps)
+                } else {
+                    0.
+                };
+                assert!(
+                    metric.estimate(distance, range) <= straight + 1e-9,
+                    "{} over {} Ly",
+                    metric,
+                    distance
+                );
+            }
+        }
+    }
+
+    /// Two short jumps burn less than one long one over the same distance
+    #[test]
+    fn shorter_jumps_burn_less() {
+        let fsd = Fsd::new(2, ModuleClass::E, 48.).unwrap();
+        let metric = Metric::Fuel { fsd, mass: 27. };
+        assert!(2. * metric.jump(3.) < metric.jump(6.));
+    }
+
+    /// However many shorter jumps a route splits a distance into, burning
+    /// less the more there are, the estimate is no more than they burn
+    #[test]
+    fn fuel_estimates_never_overshoot_shorter_jumps() {
+        let fsd = Fsd::new(2, ModuleClass::E, 48.).unwrap();
+        let metric = Metric::Fuel { fsd, mass: 27. };
+        let range = fsd.range(27., fsd.max_fuel_per_jump);
+        for distance in [range, range * 2.5, range * 10.] {
+            let fewest = (distance / range).ceil();
+            let straight = fewest * metric.jump(distance / fewest);
+            let hops = 4. * fewest;
+            let roundabout = hops * metric.jump(distance / hops);
+            assert!(roundabout < straight);
+            assert!(metric.estimate(distance, range) <= roundabout);
+        }
+    }
+
+    /// A longer jump takes longer, there being more to scoop after it
+    #[test]
+    fn longer_jumps_take_longer_to_scoop_back() {
+        let fsd = Fsd::new(2, ModuleClass::E, 48.).unwrap();
+        let metric = Metric::Time { fsd: Some(fsd), mass: 27. };
+        assert!(metric.jump(3.) > JUMP);
+        assert!(metric.jump(6.) > metric.jump(3.));
+        let unknown = Metric::Time { fsd: None, mass: 27. };
+        assert_eq!(unknown.jump(6.), JUMP);
+    }
+
+    /// Supercruise takes longer to further stations, but far from in step
+    #[test]
+    fn supercruise_grows_slower_than_distance() {
+        let near = supercruise(1000.);
+        let far = supercruise(100_000.);
+        assert!(near < far);
+        assert!(far < near * 10.);
+    }
+
+    #[test]
+    fn times_read_as_a_clock() {
+        let metric = Metric::Time { fsd: None, mass: 0. };
+        assert_eq!(metric.show(3725.), "1:02:05");
+    }
+}
@@ -0,0 +1,231 @@
+use self::constraints::Admission;
+use super::{Economies, System};
+use crate::{Database, Error};
+use async_std::task;
+use elite_journal::prelude::*;
+use geozero::wkb;
+use ordered_float::OrderedFloat;
+use pathfinding::prelude::*;
+use std::cell::RefCell;
+use std::fmt;
+use std::str::FromStr;
+
+pub mod boost;
+pub mod constraints;
+pub mod fuel;
+pub mod index;
+pub mod metric;
+pub mod tour;
+pub mod trade;
+
+pub use self::constraints::RouteConstraints;
+pub use self::fuel::{booster_range, fuel_cost, optimal_mass, Fsd};
+pub use self::index::Index;
+pub use self::metric::Metric;
+
+/// A route and what it cost, or that there is none, unless the database
+/// failed to say
+pub type Plotted<T> = Result<Option<(Vec<T>, OrderedFloat<f64>)>, Error>;
+
+/// The first error a search ran into, kept for after it
+///
+/// A* asks its closures for successors and nothing else, so one that fails
+/// to find them keeps why here and offers none. The search runs dry, and
+/// what it ran into is returned in place of what it found.
+#[derive(Debug, Default)]
+pub(crate) struct Failure(RefCell<Option<Error>>);
+
+impl Failure {
+    /// What `result` holds, or nothing, keeping its error if it is the first
+    pub(crate) fn keep<T>(&self, result: Result<T, Error>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.0.borrow_mut().get_or_insert(err);
+                None
+            }
+        }
+    }
+
+    /// Whether anything has failed yet, so that no more is asked for
+    pub(crate) fn failed(&self) -> bool {
+        self.0.borrow().is_some()
+    }
+
+    /// What the search found, unless it ran into an error on the way
+    pub(crate) fn or<T>(self, found: T) -> Result<T, Error> {
+        match self.0.into_inner() {
+            Some(err) => Err(err),
+            None => Ok(found),
+        }
+    }
+}
+
+impl System {
+    /// Every system within `range` of this one, itself included, by address
+    pub fn neighbors(&self, db: &Database, range: f64) -> Vec<System> {
+        let rows = task::block_on(async {
+            sqlx::query!(
+                r#"
+                SELECT
+                    address,
+                    name,
+                    position AS "position!: Option<wkb::Decode<Coordinate>>",
+                    population,
+                    security as "security: Security",
+                    government as "government: Government",
+                    allegiance as "allegiance: Allegiance",
+                    primary_economy as "primary_economy: Economy",
+                    secondary_economy as "secondary_economy: Economy",
+                    updated_at,
+                    updated_by,
+                    COALESCE((
+                        SELECT array_agg(faction_id)
+                        FROM system_factions
+                        WHERE system_address = systems.address
+                    ), ARRAY[]::integer[]) AS "factions!"
+                FROM systems
+                WHERE ST_3DDWithin(position, $1, $2)
+                ORDER BY address;
+                "#,
+                self.position.map(|p| wkb::Encode(p)) as _,
+                range
+            )
+            .fetch_all(&db.pool)
+            .await
+            .unwrap()
+        });
+
+        rows.into_iter()
+            .map(|row| System {
+                address: row.address,
+                name: row.name,
+                position: row
+                    .position
+                    .map(|p| p.geometry.expect("not null or invalid")),
+                population: row.population.map(|n| n as u64).unwrap_or(0),
+                security: row.security,
+                government: row.government,
+                allegiance: row.allegiance,
+                economies: Economies::new(
+                    row.primary_economy,
+                    row.secondary_economy,
+                ),
+                factions: row.factions,
+                updated_at: row.updated_at.and_utc(),
+                updated_by: row.updated_by,
+            })
+            .collect()
+    }
+
+    pub fn distance(&self, other: &System) -> f64 {
+        if let (Some(p1), Some(p2)) = (self.position, other.position) {
+            ((p2.x - p1.x).powi(2)
+                + (p2.y - p1.y).powi(2)
+                + (p2.z - p1.z).powi(2))
+            .sqrt()
+        } else {
+            0.
+        }
+    }
+
+    pub fn route_to(
+        &self,
+        db: &Database,
+        end: &System,
+        range: f64,
+    ) -> Plotted<Self> {
+        // Neighbourhoods overlap almost entirely from one system to the
+        // next, so they are looked up in memory rather than asked of the
+        // database each time.
+        self.route_constrained(db, end, range, &RouteConstraints::default())
+    }
+
+    /// As [`System::route_to`], arriving only where `constraints` admit
+    pub fn route_constrained(
+        &self,
+        db: &Database,
+        end: &System,
+        range: f64,
+        constraints: &RouteConstraints,
+    ) -> Plotted<Self> {
+        let index = RefCell::new(Index::new(db, range));
+        self.route_within(&index, end, range, constraints)
+    }
+
+    /// As [`System::route_constrained`], over systems already in `index`
+    ///
+    /// For plotting many routes through one region, which then loads it once
+    /// between all of them rather than once each. A region the database
+    /// fails to load ends the search with its error.
+    pub fn route_within(
+        &self,
+        index: &RefCell<Index>,
+        end: &System,
+        range: f64,
+        constraints: &RouteConstraints,
+    ) -> Plotted<Self> {
+        let db = index.borrow().db();
+        let admission = Admission::new(db, constraints);
+        let failure = Failure::default();
+        let successors = |s: &System| {
+            if failure.failed() {
+                return vec![];
+            }
+            let around = failure
+                .keep(index.borrow_mut().neighbors(s, range))
+                .and_then(|around| failure.keep(admission.admit(around, end)))
+                .unwrap_or_default();
+            around
+                .into_iter()
+                .map(|s| (s, OrderedFloat(1.)))
+                .collect::<Vec<_>>()
+        };
+
+        // Making the heuristic much larger than the successor's jump cost makes things run
+        // faster, but is not optimal...
+        let heuristic =
+            |s: &System| OrderedFloat((s.distance(end) / range).ceil());
+
+        let success = |s: &System| s == end;
+
+        let found = astar(self, successors, heuristic, success);
+        failure.or(found)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModuleClass {
+    A,
+    B,
+    C,
+    D,
+    E,
+}
+
+impl FromStr for ModuleClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "A" => Ok(Self::A),
+            "B" => Ok(Self::B),
+            "C" => Ok(Self::C),
+            "D" => Ok(Self::D),
+            "E" => Ok(Self::E),
+            _ => Err("invalid class".to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ModuleClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::A => write!(f, "A"),
+            Self::B => write!(f, "B"),
+            Self::C => write!(f, "C"),
+            Self::D => write!(f, "D"),
+            Self::E => write!(f, "E"),
+        }
+    }
+}
@@ -0,0 +1,352 @@
+//! The order to visit a set of systems in
+//!
+//! Background simulation work and exploration both come down to a list of
+//! systems to get round, in whatever order is quickest. What is quickest is
+//! counted in jumps, plotted between every pair, rather than guessed at from
+//! the straight lines between them: a short hop across a gap in the stars can
+//! take more jumps than a long run down a crowded arm.
+//!
+//! Small sets are ordered exactly. Past [`EXACT`] stops that is too many
+//! orders to try, so the order is built greedily and then improved with 2-opt
+//! and Or-opt moves until neither finds anything shorter.
+use super::{Index, RouteConstraints};
+use crate::systems::System;
+use crate::{Database, Error};
+use async_std::task;
+use std::cell::RefCell;
+
+/// The most stops ordered exactly
+///
+/// Held-Karp keeps a cost for every subset of the stops and every stop it
+/// could end on, which at twelve is some fifty thousand of them and at twenty
+/// is twenty million.
+pub const EXACT: usize = 12;
+
+/// A visiting order, and the legs flown between the stops
+#[derive(Debug, Clone)]
+pub struct Tour {
+    /// Each stop, in the order visited
+    pub stops: Vec<System>,
+    /// Every system each leg passes through, both of its stops included
+    pub legs: Vec<Vec<System>>,
+}
+
+impl Tour {
+    /// The jumps each leg takes
+    pub fn jumps(&self) -> Vec<usize> {
+        self.legs.iter().map(|leg| leg.len().saturating_sub(1)).collect()
+    }
+
+    /// Every system the tour passes through, each stop once
+    pub fn path(&self) -> Vec<System> {
+        let mut path: Vec<System> =
+            self.stops.iter().take(1).cloned().collect();
+        for leg in &self.legs {
+            path.extend(leg.iter().skip(1).cloned());
+        }
+        path
+    }
+}
+
+impl System {
+    /// The fewest jumps that get round every one of `stops`
+    ///
+    /// Starting from the first of them if `fixed_start`, and from whichever
+    /// makes for the shortest tour otherwise. The tour ends wherever it ends,
+    /// not back where it began. Stops named twice are visited once.
+    ///
+    /// A route is plotted between every pair of stops, against one index so
+    /// that the region is loaded once, and nothing comes back if any stop
+    /// cannot be reached at all. Each keeps to `constraints`, and nothing
+    /// comes back either if a stop is not one they would stop at.
+    pub fn tour(
+        db: &Database,
+        stops: &[System],
+        range: f64,
+        fixed_start: bool,
+        constraints: &RouteConstraints,
+    ) -> Result<Option<Tour>, Error> {
+        let mut unique: Vec<System> = Vec::new();
+        for stop in stops {
+            if !unique.contains(stop) {
+                unique.push(stop.clone());
+            }
+        }
+        for stop in &unique {
+            if !task::block_on(constraints.stops_at(db, stop))? {
+                return Ok(None);
+            }
+        }
+        let n = unique.len();
+
+        // Jumps are as many one way as the other, a system being in range of
+        // another exactly when that one is in range of it, so each pair is
+        // plotted once. Constraints leave that so, asking nothing of either
+        // end of a route.
+        let index = RefCell::new(Index::new(db, range));
+        let mut routes: Vec<Vec<Option<Vec<System>>>> = vec![vec![None; n]; n];
+        let mut costs = vec![vec![0.; n]; n];
+        for i in 0..n {
+            for j in i + 1..n {
+                let found = unique[i].route_within(
+                    &index,
+                    &unique[j],
+                    range,
+                    constraints,
+                )?;
+                let cost = found
+                    .as_ref()
+                    .map_or(f64::INFINITY, |(_, jumps)| jumps.into_inner());
+                costs[i][j] = cost;
+                costs[j][i] = cost;
+                if let Some((systems, _)) = found {
+                    let mut back = systems.clone();
+                    back.reverse();
+                    routes[i][j] = Some(systems);
+                    routes[j][i] = Some(back);
+                }
+            }
+        }
+
+        let order = order(&costs, fixed_start);
+        if !cost_of(&costs, &order).is_finite() {
+            return Ok(None);
+        }
+
+        let legs = order
+            .windows(2)
+            .map(|pair| routes[pair[0]][pair[1]].clone())
+            .collect::<Option<Vec<_>>>();
+        let stops = order.into_iter().map(|i| unique[i].clone()).collect();
+        Ok(legs.map(|legs| Tour { stops, legs }))
+    }
+}
+
+/// The cheapest order to visit every stop in, by index into `costs`
+///
+/// `costs[i][j]` is what it costs to go from `i` to `j`, infinite where it
+/// cannot be done. The order starts at `0` when `fixed_start`.
+pub fn order(costs: &[Vec<f64>], fixed_start: bool) -> Vec<usize> {
+    let n = costs.len();
+    if n <= 2 {
+        return (0..n).collect();
+    }
+    if n <= EXACT {
+        return held_karp(costs, fixed_start);
+    }
+
+    let mut order = nearest_neighbour(costs, fixed_start);
+    while two_opt(costs, &mut order, fixed_start)
+        || or_opt(costs, &mut order, fixed_start)
+    {}
+    order
+}
+
+/// What visiting the stops in `order` costs
+pub fn cost_of(costs: &[Vec<f64>], order: &[usize]) -> f64 {
+    order.windows(2).map(|pair| costs[pair[0]][pair[1]]).sum()
+}
+
+/// The exact answer, by dynamic programming over subsets
+///
+/// `best[set][end]` is the cheapest path through the stops in `set` that
+/// ends at `end`, built up a stop at a time from paths of a single stop.
+fn held_karp(costs: &[Vec<f64>], fixed_start: bool) -> Vec<usize> {
+    let n = costs.len();
+    let full = (1 << n) - 1;
+    let mut best = vec![vec![f64::INFINITY; n]; 1 << n];
+    let mut came_from = vec![vec![usize::MAX; n]; 1 << n];
+    for start in 0..n {
+        if !fixed_start || start == 0 {
+            best[1 << start][start] = 0.;
+        }
+    }
+
+    for set in 1..=full {
+        for end in 0..n {
+            let here = best[set][end];
+            if set & (1 << end) == 0 || !here.is_finite() {
+                continue;
+            }
+            for next in 0..n {
+                if set & (1 << next) != 0 {
+                    continue;
+                }
+                let there = set | (1 << next);
+                let cost = here + costs[end][next];
+                if cost < best[there][next] {
+                    best[there][next] = cost;
+                    came_from[there][next] = end;
+                }
+            }
+        }
+    }
+
+    let mut end = (0..n)
+        .min_by(|a, b| best[full][*a].total_cmp(&best[full][*b]))
+        .expect("more than two stops");
+    let mut set = full;
+    let mut order = vec![end];
+    while came_from[set][end] != usize::MAX {
+        let before = came_from[set][end];
+        set &= !(1 << end);
+        end = before;
+        order.push(end);
+    }
+    // Nothing reached the whole set, so no path was recorded. Any order is
+    // as impossible as any other.
+    if order.len() < n {
+        return (0..n).collect();
+    }
+    order.reverse();
+    order
+}
+
+/// Always on to the cheapest stop not yet visited
+///
+/// From every stop the tour could start at, keeping whichever start went
+/// furthest for least.
+fn nearest_neighbour(costs: &[Vec<f64>], fixed_start: bool) -> Vec<usize> {
+    let n = costs.len();
+    let starts = if fixed_start { 0..1 } else { 0..n };
+    starts
+        .map(|start| {
+            let mut order = vec![start];
+            let mut left: Vec<usize> = (0..n).filter(|i| *i != start).collect();
+            while !left.is_empty() {
+                let at = *order.last().expect("started somewhere");
+                let (i, _) = left
+                    .iter()
+                    .enumerate()
+                    .min_by(|(_, a), (_, b)| {
+                        costs[at][**a].total_cmp(&costs[at][**b])
+                    })
+                    .expect("not empty");
+                order.push(left.remove(i));
+            }
+            order
+        })
+        .min_by(|a, b| cost_of(costs, a).total_cmp(&cost_of(costs, b)))
+        .expect("at least one start")
+}
+
+/// Reverse the first run of stops that comes out cheaper reversed
+fn two_opt(costs: &[Vec<f64>], order: &mut [usize], fixed_start: bool) -> bool {
+    let before = cost_of(costs, order);
+    let first = fixed_start as usize;
+    for i in first..order.len() {
+        for k in i + 1..order.len() {
+            order[i..=k].reverse();
+            if cost_of(costs, order) < before {
+                return true;
+            }
+            order[i..=k].reverse();
+        }
+    }
+    false
+}
+
+/// Move the first run of up to three stops that comes out cheaper elsewhere
+fn or_opt(
+    costs: &[Vec<f64>],
+    order: &mut Vec<usize>,
+    fixed_start: bool,
+) -> bool {
+    let before = cost_of(costs, order);
+    let first = fixed_start as usize;
+    for len in 1..=3 {
+        for i in first..order.len().saturating_sub(len - 1) {
+            let run: Vec<usize> = order.drain(i..i + len).collect();
+            for j in first..=order.len() {
+                if j == i {
+                    continue;
+                }
+                let mut moved = order.clone();
+                moved.splice(j..j, run.iter().copied());
+                if cost_of(costs, &moved) < before {
+                    *order = moved;
+                    return true;
+                }
+            }
+            order.splice(i..i, run);
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stops along a line, where the cost is how far apart they are
+    fn line(at: &[f64]) -> Vec<Vec<f64>> {
+        at.iter().map(|a| at.iter().map(|b| (a - b).abs()).collect()).collect()
+    }
+
+    /// Along a line the best order is along the line, whichever way
+    #[test]
+    fn a_line_is_toured_end_to_end() {
+        let costs = line(&[3., 0., 4., 1., 2.]);
+        let order = order(&costs, false);
+        assert_eq!(cost_of(&costs, &order), 4.);
+    }
+
+    /// A fixed start is kept, even where starting elsewhere is cheaper
+    #[test]
+    fn a_fixed_start_stays_first() {
+        let costs = line(&[2., 0., 4., 1., 3.]);
+        let order = order(&costs, true);
+        assert_eq!(order[0], 0);
+        assert_eq!(cost_of(&costs, &order), 6.);
+    }
+
+    /// The heuristics find the same tour along a line as the exact search
+    #[test]
+    fn heuristics_match_exact_on_a_line() {
+        let at: Vec<f64> = (0..30).map(|i| ((i * 7) % 30) as f64).collect();
+        let costs = line(&at);
+        let order = order(&costs, false);
+        assert_eq!(order.len(), 30);
+        assert_eq!(cost_of(&costs, &order), 29.);
+    }
+
+    /// Held-Karp agrees with trying every order
+    #[test]
+    fn exact_is_exact() {
+        let costs = vec![
+            vec![0., 2., 9., 10., 7.],
+            vec![2., 0., 6., 4., 3.],
+            vec![9., 6., 0., 8., 5.],
+            vec![10., 4., 8., 0., 6.],
+            vec![7., 3., 5., 6., 0.],
+        ];
+        let mut cheapest = f64::INFINITY;
+        let mut order: Vec<usize> = (0..5).collect();
+        permute(&mut order, 0, &mut |o| {
+            cheapest = cheapest.min(cost_of(&costs, o))
+        });
+        assert_eq!(cost_of(&costs, &held_karp(&costs, false)), cheapest);
+    }
+
+    fn permute(order: &mut Vec<usize>, k: usize, f: &mut impl FnMut(&[usize])) {
+        if k == order.len() {
+            return f(order);
+        }
+        for i in k..order.len() {
+            order.swap(k, i);
+            permute(order, k + 1, f);
+            order.swap(k, i);
+        }
+    }
+
+    /// A stop nothing reaches makes every order impossible
+    #[test]
+    fn an_unreachable_stop_costs_everything() {
+        let mut costs = line(&[0., 1., 2.]);
+        for row in &mut costs[..2] {
+            row[2] = f64::INFINITY;
+        }
+        costs[2][..2].fill(f64::INFINITY);
+        assert!(!cost_of(&costs, &order(&costs, false)).is_finite());
+    }
+}
@@ -0,0 +1,478 @@
+//! Buying in one market and selling in another
+//!
+//! A trade is planned against the prices on record around a system, and
+//! flown over the same jump-range model [`System::route_to`] plots with. What
+//! comes back is ranked by whichever of its returns the trader cares about.
+use super::metric::{supercruise, DOCKING, JUMP};
+use super::{Index, RouteConstraints};
+use crate::markets::{Commodity, Criteria, Quote};
+use crate::systems::System;
+use crate::{Database, Error};
+use async_std::task;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Light seconds out a station with no distance on record is taken to be,
+/// which is about where most of them are
+const UNKNOWN_LS: f64 = 1000.;
+
+/// What a trade is ranked by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rank {
+    /// Credits made for each ton carried
+    Ton,
+    /// Credits made for each jump flown
+    Jump,
+    /// Credits made for each hour spent
+    Hour,
+}
+
+impl FromStr for Rank {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ton" => Ok(Rank::Ton),
+            "jump" => Ok(Rank::Jump),
+            "hour" => Ok(Rank::Hour),
+            _ => Err("invalid rank, expected ton, jump or hour".to_string()),
+        }
+    }
+}
+
+/// The ship and the purse a trade is planned for
+#[derive(Debug, Clone)]
+pub struct Trader {
+    /// Tons of cargo the hold carries
+    pub capacity: i64,
+    /// Credits on hand to buy with
+    pub credits: i64,
+    /// How far the ship jumps, in light years
+    pub range: f64,
+    /// How far from the start to look for markets, in light years
+    pub radius: f64,
+    /// Which markets are worth trading at
+    pub criteria: Criteria,
+    /// Whether to come back with a cargo of something else
+    pub round_trip: bool,
+    pub rank: Rank,
+}
+
+/// One cargo, bought at one market and sold at another
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Trade {
+    /// Where the cargo is bought
+    pub from: Quote,
+    /// Where it is sold
+    pub to: Quote,
+    /// Tons of it carried
+    pub units: i64,
+    pub profit_per_ton: i64,
+}
+
+impl Trade {
+    /// The most a hold can make carrying one commodity from `buy` to `sell`
+    ///
+    /// As much as fits, as much as can be paid for, as much as the one market
+    /// holds and as much as the other wants, whichever is least: selling past
+    /// the demand drives the price down, so what it would fetch is no longer
+    /// what the quote says. Nothing at all where there is no margin, or no
+    /// room for a single ton.
+    pub fn between(
+        buy: &Quote,
+        sell: &Quote,
+        capacity: i64,
+        credits: i64,
+    ) -> Option<Trade> {
+        let buying = &buy.commodity;
+        let selling = &sell.commodity;
+        if buying.name != selling.name
+            || buying.buy_price <= 0
+            || selling.sell_price <= 0
+        {
+            return None;
+        }
+
+        let profit_per_ton =
+            selling.sell_price as i64 - buying.buy_price as i64;
+        if profit_per_ton <= 0 {
+            return None;
+        }
+
+        let units = capacity
+            .min(credits / buying.buy_price as i64)
+            .min(buying.stock as i64)
+            .min(selling.demand as i64);
+        if units <= 0 {
+            return None;
+        }
+
+        Some(Trade {
+            from: buy.clone(),
+            to: sell.clone(),
+            units,
+            profit_per_ton,
+        })
+    }
+
+    pub fn profit(&self) -> i64 {
+        self.units * self.profit_per_ton
+    }
+}
+
+/// A trade out, the trade back if there is one, and what flying it costs
+#[derive(Debug, PartialEq, Serialize)]
+pub struct TradeRoute {
+    pub outbound: Trade,
+    /// The cargo carried home on a round trip
+    pub inbound: Option<Trade>,
+    /// Whether the ship flies home again, with cargo or without
+    pub round_trip: bool,
+    /// Jumps flown, both ways on a round trip
+    pub jumps: u32,
+}
+
+impl TradeRoute {
+    pub fn profit(&self) -> i64 {
+        self.outbound.profit()
+            + self.inbound.as_ref().map(Trade::profit).unwrap_or(0)
+    }
+
+    /// What one ton of hold earns over the whole route
+    pub fn profit_per_ton(&self) -> i64 {
+        self.outbound.profit_per_ton
+            + self.inbound.as_ref().map(|t| t.profit_per_ton).unwrap_or(0)
+    }
+
+    /// What the route earns for each jump flown
+    ///
+    /// Two stations in the same system are no jumps apart, which is counted
+    /// as one so that the best trade of all does not divide by nothing.
+    pub fn profit_per_jump(&self) -> f64 {
+        self.profit() as f64 / self.jumps.max(1) as f64
+    }
+
+    /// How long flying the route takes, in hours
+    ///
+    /// The jumps, the supercruise out to each station traded at, and the time
+    /// spent at each. A round trip ends docked where it started, whether or
+    /// not there was anything to carry home.
+    pub fn hours(&self) -> f64 {
+        let to_station =
+            |ls: Option<f64>| supercruise(ls.unwrap_or(UNKNOWN_LS));
+        let mut seconds = self.jumps as f64 * JUMP;
+        seconds += to_station(self.outbound.to.dist_from_star_ls);
+        seconds += DOCKING;
+        if self.round_trip {
+            seconds += to_station(self.outbound.from.dist_from_star_ls);
+            seconds += DOCKING;
+        }
+        seconds / 3600.
+    }
+
+    pub fn profit_per_hour(&self) -> f64 {
+        self.profit() as f64 / self.hours()
+    }
+
+    /// How the route scores under `rank`, more being better
+    pub fn score(&self, rank: Rank) -> f64 {
+        match rank {
+            Rank::Ton => self.profit_per_ton() as f64,
+            Rank::Jump => self.profit_per_jump(),
+            Rank::Hour => self.profit_per_hour(),
+        }
+    }
+}
+
+/// The best single cargo from one market to another
+fn best_trade(
+    from: &HashMap<&str, &Quote>,
+    to: &HashMap<&str, &Quote>,
+    trader: &Trader,
+) -> Option<Trade> {
+    from.iter()
+        .filter(|(_, buy)| buy.commodity.stock >= trader.criteria.min_stock)
+        .filter_map(|(name, buy)| {
+            let sell = to.get(name)?;
+            if sell.commodity.demand < trader.criteria.min_demand {
+                return None;
+            }
+            Trade::between(buy, sell, trader.capacity, trader.credits)
+        })
+        .max_by_key(Trade::profit)
+}
+
+/// The trade out and the trade back between two markets, if either pays
+///
+/// One way, only from `from` to `to`. On a round trip both directions are
+/// weighed, and a pair that only pays from `to` back to `from` is flown out
+/// that way instead, with nothing to carry home.
+fn pair(
+    from: &HashMap<&str, &Quote>,
+    to: &HashMap<&str, &Quote>,
+    trader: &Trader,
+) -> Option<(Trade, Option<Trade>)> {
+    let there = best_trade(from, to, trader);
+    if !trader.round_trip {
+        return there.map(|trade| (trade, None));
+    }
+    match (there, best_trade(to, from, trader)) {
+        (Some(there), back) => Some((there, back)),
+        (None, Some(back)) => Some((back, None)),
+        (None, None) => None,
+    }
+}
+
+/// Sort `routes` best first under `rank`
+fn rank(routes: &mut [TradeRoute], rank: Rank) {
+    routes.sort_by(|a, b| {
+        b.score(rank).partial_cmp(&a.score(rank)).unwrap_or(Ordering::Equal)
+    });
+}
+
+impl System {
+    /// The `limit` best trades among the markets within reach of here
+    ///
+    /// Every pair of markets within the trader's radius is weighed, each way
+    /// round, by the most its best cargo makes. How far apart they are is
+    /// first estimated as the fewest jumps the straight line could take,
+    /// which is as few as [`System::route_to`] could ever find; the best of
+    /// those are then plotted for real, and ranked again on what the routes
+    /// turned out to cost. A pair the estimate ranked too low to be plotted
+    /// stays unplotted, which is what keeps this to a handful of routes
+    /// rather than one for every pair in the region.
+    ///
+    /// A pair with no route between them at the trader's range is dropped.
+    pub fn trades(
+        &self,
+        db: &Database,
+        trader: &Trader,
+        limit: usize,
+    ) -> Result<Vec<TradeRoute>, Error> {
+        let Some(here) = self.position else { return Ok(vec![]) };
+        let (quotes, systems) = task::block_on(async {
+            let quotes = Commodity::fetch_in_range_of_point(
+                db,
+                trader.radius,
+                [here.x, here.y, here.z],
+                &trader.criteria,
+            )
+            .await?;
+            let mut addresses: Vec<i64> =
+                quotes.iter().map(|q| q.system_address).collect();
+            addresses.sort_unstable();
+            addresses.dedup();
+            let systems = System::fetch_many(db, &addresses).await?;
+            Ok::<_, Error>((quotes, systems))
+        })?;
+
+        let systems: HashMap<i64, System> =
+            systems.into_iter().map(|s| (s.address, s)).collect();
+        let mut markets: HashMap<i64, HashMap<&str, &Quote>> = HashMap::new();
+        for quote in &quotes {
+            markets
+                .entry(quote.commodity.market_id)
+                .or_default()
+                .insert(quote.commodity.name.as_str(), quote);
+        }
+        let markets: Vec<(&i64, &HashMap<&str, &Quote>)> =
+            markets.iter().collect();
+
+        let jumps_between = |a: i64, b: i64| -> u32 {
+            match (systems.get(&a), systems.get(&b)) {
+                (Some(a), Some(b)) => {
+                    (a.distance(b) / trader.range).ceil() as u32
+                }
+                _ => 0,
+            }
+        };
+
+        let mut routes = Vec::new();
+        for (i, (_, from)) in markets.iter().enumerate() {
+            for (j, (_, to)) in markets.iter().enumerate() {
+                // A round trip is the same pair either way round, so it is
+                // weighed once, both ways.
+                if i == j || (trader.round_trip && j < i) {
+                    continue;
+                }
+                let Some((outbound, inbound)) = pair(from, to, trader) else {
+                    continue;
+                };
+                let one_way = jumps_between(
+                    outbound.from.system_address,
+                    outbound.to.system_address,
+                );
+                let round_trip = trader.round_trip;
+                let jumps = if round_trip { one_way * 2 } else { one_way };
+                routes.push(TradeRoute {
+                    outbound,
+                    inbound,
+                    round_trip,
+                    jumps,
+                });
+            }
+        }
+
+        rank(&mut routes, trader.rank);
+        routes.truncate(limit);
+
+        // Plotted for real, and dropped where there is no route to plot. The
+        // routes are all in the one region, loaded once between them.
+        let index = RefCell::new(Index::new(db, trader.range));
+        let anywhere = RouteConstraints::default();
+        let mut plotted = Vec::with_capacity(routes.len());
+        for mut route in routes {
+            let from = route.outbound.from.system_address;
+            let to = route.outbound.to.system_address;
+            if from != to {
+                let (Some(a), Some(b)) = (systems.get(&from), systems.get(&to))
+                else {
+                    continue;
+                };
+                let found =
+                    a.route_within(&index, b, trader.range, &anywhere)?;
+                let Some((_, cost)) = found else { continue };
+                let one_way = cost.0 as u32;
+                route.jumps =
+                    if route.round_trip { one_way * 2 } else { one_way };
+            }
+            plotted.push(route);
+        }
+        rank(&mut plotted, trader.rank);
+
+        Ok(plotted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn quote(
+        name: &str,
+        buy: i32,
+        sell: i32,
+        stock: i32,
+        demand: i32,
+    ) -> Quote {
+        Quote {
+            commodity: Commodity {
+                market_id: 1,
+                name: name.into(),
+                mean_price: 0,
+                buy_price: buy,
+                sell_price: sell,
+                demand,
+                demand_bracket: 0,
+                stock,
+                stock_bracket: 0,
+                listed_at: Utc::now(),
+            },
+            system_address: 1,
+            system_name: "SOL".into(),
+            station_name: "Abraham Lincoln".into(),
+            dist_from_star_ls: Some(500.),
+            distance: 0.,
+        }
+    }
+
+    /// A trade carries as much as every limit on it allows, and no more
+    ///
+    /// The hold, the purse, what the market has and what the other wants are
+    /// each a ceiling, and the lowest of them is the cargo.
+    #[test]
+    fn a_trade_is_held_to_its_lowest_limit() {
+        let buy = quote("gold", 9000, 0, 1000, 0);
+        let sell = quote("gold", 0, 10000, 0, 1000);
+
+        let trade = Trade::between(&buy, &sell, 100, 10_000_000).unwrap();
+        assert_eq!(trade.units, 100);
+        assert_eq!(trade.profit_per_ton, 1000);
+        assert_eq!(trade.profit(), 100_000);
+
+        let poor = Trade::between(&buy, &sell, 100, 90_000).unwrap();
+        assert_eq!(poor.units, 10);
+
+        let scarce = quote("gold", 9000, 0, 4, 0);
+        assert_eq!(
+            Trade::between(&scarce, &sell, 100, 10_000_000).unwrap().units,
+            4
+        );
+    }
+
+    /// There is no trade without a margin, or between two commodities
+    #[test]
+    fn a_trade_needs_a_margin() {
+        let buy = quote("gold", 9000, 0, 1000, 0);
+        let cheap = quote("gold", 0, 8000, 0, 1000);
+        assert!(Trade::between(&buy, &cheap, 100, 10_000_000).is_none());
+
+        let other = quote("silver", 0, 20000, 0, 1000);
+        assert!(Trade::between(&buy, &other, 100, 10_000_000).is_none());
+    }
+
+    /// Stations in the same system earn per jump as though one were flown
+    #[test]
+    fn no_jumps_counts_as_one() {
+        let buy = quote("gold", 9000, 0, 1000, 0);
+        let sell = quote("gold", 0, 10000, 0, 1000);
+        let route = TradeRoute {
+            outbound: Trade::between(&buy, &sell, 10, 1_000_000).unwrap(),
+            inbound: None,
+            round_trip: false,
+            jumps: 0,
+        };
+        assert_eq!(route.profit_per_jump(), 10_000.);
+    }
+
+    /// A round trip takes the time to fly home, with nothing to carry or not
+    #[test]
+    fn a_round_trip_is_timed_home() {
+        let buy = quote("gold", 9000, 0, 1000, 0);
+        let sell = quote("gold", 0, 10000, 0, 1000);
+        let one_way = TradeRoute {
+            outbound: Trade::between(&buy, &sell, 10, 1_000_000).unwrap(),
+            inbound: None,
+            round_trip: false,
+            jumps: 3,
+        };
+        let round_trip = TradeRoute {
+            outbound: Trade::between(&buy, &sell, 10, 1_000_000).unwrap(),
+            inbound: None,
+            round_trip: true,
+            jumps: 6,
+        };
+        let home = (3. * JUMP + supercruise(500.) + DOCKING) / 3600.;
+        assert!((round_trip.hours() - one_way.hours() - home).abs() < 1e-9);
+    }
+
+    /// A round trip between two markets pays even if only one way does
+    #[test]
+    fn a_round_trip_weighs_both_directions() {
+        let buy = quote("gold", 9000, 0, 1000, 0);
+        let sell = quote("gold", 0, 10000, 0, 1000);
+        // Gold is only worth carrying from `there` back to `here`.
+        let here: HashMap<&str, &Quote> = [("gold", &sell)].into();
+        let there: HashMap<&str, &Quote> = [("gold", &buy)].into();
+        let mut trader = Trader {
+            capacity: 10,
+            credits: 1_000_000,
+            range: 10.,
+            radius: 50.,
+            criteria: Criteria::default(),
+            round_trip: false,
+            rank: Rank::Ton,
+        };
+        assert!(pair(&here, &there, &trader).is_none());
+
+        trader.round_trip = true;
+        let (outbound, inbound) = pair(&here, &there, &trader).unwrap();
+        assert_eq!(outbound.from, buy);
+        assert_eq!(outbound.units, 10);
+        assert!(inbound.is_none());
+    }
+}
@@ -7,6 +7,7 @@ use bevy::tasks::{AsyncComputeTaskPool, Task, block_on};
 use elite_journal::system::Coordinate;
 use galos_db::Database;
 use galos_db::systems::System as DbSystem;
+use galos_db::systems::nav::RouteConstraints;
 use std::time::{Duration, Instant};
 
 pub fn plugin(app: &mut App) {
@@ -98,8 +99,30 @@ pub enum Plot {
 /// by [`crate::systems::filter::Wanted`] instead.
 #[derive(Message, Debug)]
 pub enum Searched {
-    System { name: String },
-    Route { start: String, end: String, range: String },
+    System {
+        name: String,
+    },
+    /// A route between two named systems, in jumps of `range`
+    ///
+    /// Boosted where a neutron star's or a white dwarf's jet cone would
+    /// carry it further, if `boost` is asked for, and through only the
+    /// systems `constraints` admit.
+    Route {
+        start: String,
+        end: String,
+        range: String,
+        boost: bool,
+        constraints: RouteConstraints,
+    },
+    /// The fewest jumps round every one of `stops`, in whatever order
+    ///
+    /// Each leg through only the systems `constraints` admit, and every stop
+    /// one they would stop at.
+    Tour {
+        stops: Vec<String>,
+        range: String,
+        constraints: RouteConstraints,
+    },
 }
 
 /// The row for a named system a route may run to, or why it may not
@@ -238,7 +261,8 @@ fn located(plot: &mut Plot, trouble: Option<String>) {
 /// everything gathered before it. The camera is left where it is for the same
 /// reason, and the map has a control of its own for going there.
 /// - On [`Searched::Route`] both ends are resolved, and which of them could
-/// not be is what the form is told.
+/// not be is what the form is told. [`Searched::Tour`] is the same for every
+/// stop.
 ///
 /// Every one of them is asked of the database off the main thread and read
 /// back here when it lands, so that a search the database takes its time over
@@ -303,6 +327,24 @@ fn searched(
                     }),
                 );
             }
+            // The same for every stop of a tour, the first that cannot be
+            // found being the one said.
+            Searched::Tour { stops, .. } => {
+                let db = db.0.clone();
+                let stops = stops.clone();
+                locating.ask(
+                    (),
+                    now,
+                    pool.spawn(async move {
+                        for stop in &stops {
+                            if let Err(why) = locate(&db, stop).await {
+                                return Some(why);
+                            }
+                        }
+                        None
+                    }),
+                );
+            }
         };
     }
 
@@ -4,7 +4,7 @@
 //! answers rows of `systems` and only those. A second kind of answer on the
 //! same map would make it a map of two unrelated things keyed alike.
 
-use super::{Contents, Held};
+use super::{Contents, Held, Scanned};
 use crate::Db;
 use crate::camera::OrbitCamera;
 use crate::schedule::MapSet;
@@ -16,6 +16,7 @@ use bevy::tasks::futures_lite::future;
 use bevy::tasks::{AsyncComputeTaskPool, Task, block_on};
 use galos_db::barycenters::Barycenter as DbBarycenter;
 use galos_db::bodies::Body as DbBody;
+use galos_db::exploration::Completeness;
 use galos_db::stars::Star as DbStar;
 use std::time::Instant;
 
@@ -99,6 +100,7 @@ impl Polling {
                 centers: DbBarycenter::fetch_all(&db, address)
                     .await
                     .unwrap_or_default(),
+                scanned: Completeness::fetch(&db, address).await.ok(),
             }
         });
 
@@ -119,6 +121,9 @@ pub(super) struct Answer {
     /// drawn at the middle of the system. The ellipse the pair rides is drawn
     /// from the same row.
     centers: Vec<DbBarycenter>,
+    /// How many bodies the system is known to have, against how many of them
+    /// are among the rows above
+    scanned: Option<Completeness>,
 }
 
 /// Decide which system the map is standing in, and ask about it
@@ -195,6 +200,7 @@ fn choose(
 /// Take in whatever has come back
 pub(super) fn collect(
     mut contents: ResMut<Contents>,
+    mut scanned: ResMut<Scanned>,
     mut polling: ResMut<Polling>,
 ) {
     let Some((address, task)) = polling.query.as_mut() else { return };
@@ -214,5 +220,10 @@ pub(super) fn collect(
         answer.bodies.len(),
         answer.centers.len()
     );
+    // Only where it says something new, so that what reads it is not told
+    // it changed on every poll.
+    if scanned.0 != answer.scanned {
+        scanned.0 = answer.scanned;
+    }
     contents.know(answer.stars, answer.bodies, answer.centers);
 }
@@ -14,6 +14,7 @@ use bevy::math::DVec3;
 use bevy::prelude::*;
 use galos_db::barycenters::Barycenter as DbBarycenter;
 use galos_db::bodies::Body as DbBody;
+use galos_db::exploration::Completeness;
 use galos_db::stars::Star as DbStar;
 use orbit::{Orbit, Orbits};
 use std::collections::HashSet;
@@ -24,6 +25,7 @@ pub mod spawn;
 
 pub fn plugin(app: &mut App) {
     app.init_resource::<Contents>();
+    app.init_resource::<Scanned>();
     app.add_plugins(fetch::plugin);
     app.add_plugins(spawn::plugin);
 }
@@ -48,6 +50,23 @@ pub struct Contents {
     revision: u32,
 }
 
+/// How much of the system being held is on record, against how much it is
+/// known to have
+///
+/// Apart from [`Contents`], since nothing drawn depends on it. A body never
+/// scanned has nowhere to be drawn, so all the map can do with one is say
+/// how many there are, and that is a panel's business.
+#[derive(Resource, Default)]
+pub struct Scanned(Option<Completeness>);
+
+impl Scanned {
+    /// How complete what is on record for `address` is, if it is the system
+    /// being held and the database could say
+    pub fn of(&self, address: i64) -> Option<&Completeness> {
+        self.0.as_ref().filter(|scanned| scanned.system_address == address)
+    }
+}
+
 /// How far along the asking has got
 #[derive(Default)]
 enum Held {
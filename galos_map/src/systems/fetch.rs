@@ -2,11 +2,15 @@ use crate::camera::OrbitCamera;
 use crate::schedule::MapSet;
 use crate::systems::filter::{Admitting, DimTo, Filters};
 use crate::systems::selection::Selection;
-use crate::systems::{Spyglass, System, route::fetch::fetch_route};
+use crate::systems::{
+    Spyglass, System,
+    route::fetch::{fetch_route, fetch_tour},
+};
 use crate::{Db, search::Searched};
 use bevy::prelude::*;
 use bevy::tasks::{AsyncComputeTaskPool, Task};
 use galos_db::systems::System as DbSystem;
+use galos_db::systems::nav::RouteConstraints;
 use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::time::{Duration, Instant};
@@ -87,7 +91,10 @@ pub enum FetchIndex {
     /// answered at the throttle rather than waiting out the poll.
     Region(IVec3, i32, Option<Admitting>),
     // View<Frustum>,
-    Route(String, String, String),
+    /// A route's ends, its jump range, and whether it boosts
+    Route(String, String, String, bool, RouteConstraints),
+    /// A tour's stops, as named, its jump range, and what it keeps to
+    Tour(Vec<String>, String, RouteConstraints),
     /// Named systems, by address
     ///
     /// What the map is asked for a row at a time rather than by where it is:
@@ -148,8 +155,22 @@ impl fmt::Debug for FetchIndex {
                 }
                 write!(f, ">")
             }
-            Route(start, end, range) => {
-                write!(f, "<{}-{}>{}>", start, end, range)
+            Tour(stops, range, constraints) => {
+                write!(f, "<tour of {}>{}>", stops.len(), range)?;
+                if !constraints.is_empty() {
+                    write!(f, " constrained")?;
+                }
+                Ok(())
+            }
+            Route(start, end, range, boost, constraints) => {
+                write!(f, "<{}-{}>{}>", start, end, range)?;
+                if *boost {
+                    write!(f, " boosted")?;
+                }
+                if !constraints.is_empty() {
+                    write!(f, " constrained")?;
+                }
+                Ok(())
             }
             Systems(addresses) => write!(f, "<{} named>", addresses.len()),
         }
@@ -198,11 +219,24 @@ pub fn fetch(
             // here to fetch yet. Whatever the user picks out of what it
             // found is asked for by `fetch_selected`.
             Searched::System { .. } => {}
-            Searched::Route { start, end, range } => {
+            Searched::Route { start, end, range, boost, constraints } => {
                 fetch_route(
                     start.into(),
                     end.into(),
                     range.into(),
+                    *boost,
+                    constraints.clone(),
+                    &mut tasks,
+                    &time,
+                    &mut last_fetched_at,
+                    &db,
+                );
+            }
+            Searched::Tour { stops, range, constraints } => {
+                fetch_tour(
+                    stops.clone(),
+                    range.into(),
+                    constraints.clone(),
                     &mut tasks,
                     &time,
                     &mut last_fetched_at,
@@ -433,7 +467,13 @@ mod tests {
     /// map asking again for most of what it already holds.
     #[test]
     fn a_route_under_way_does_not_hold_the_spyglass_up() {
-        let route = FetchIndex::Route("A".into(), "B".into(), "10".into());
+        let route = FetchIndex::Route(
+            "A".into(),
+            "B".into(),
+            "10".into(),
+            false,
+            RouteConstraints::default(),
+        );
 
         assert!(!region_asked([route].iter()));
     }
@@ -513,7 +553,13 @@ mod tests {
     /// A route is never a refresh of anything, nor refreshed by one
     #[test]
     fn a_route_is_always_a_new_question() {
-        let route = FetchIndex::Route("A".into(), "B".into(), "10".into());
+        let route = FetchIndex::Route(
+            "A".into(),
+            "B".into(),
+            "10".into(),
+            false,
+            RouteConstraints::default(),
+        );
         assert!(!route.refreshes(&region(0, 10)));
         assert!(!region(0, 10).refreshes(&route));
         assert!(!route.refreshes(&route));
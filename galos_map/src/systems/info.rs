@@ -15,7 +15,9 @@ use crate::Db;
 use crate::camera::{MoveCamera, OrbitCamera};
 use crate::schedule::MapSet;
 use crate::systems::System;
+use crate::systems::bodies::Scanned;
 use crate::systems::filter::{Filter, Filters};
+use crate::systems::route::export::{Export, Exporting};
 use crate::systems::selection::{Picked, Selection};
 use crate::ui::Chose;
 use crate::ui::MARGIN;
@@ -26,6 +28,8 @@ use bevy_egui::egui::{Context, Ui};
 use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
 use elite_journal::body::{Discovery, Orbit, Spin};
 use galos_db::bodies::{Body as DbBody, Surface};
+use galos_db::exploration::Completeness;
+use galos_db::export::Format;
 use galos_db::factions::Faction as DbFaction;
 use galos_db::stars::Star as DbStar;
 use galos_db::systems::Economies;
@@ -496,11 +500,14 @@ fn panels(
     mut contexts: EguiContexts,
     mut panels: ResMut<Panels>,
     names: Res<FactionNames>,
+    scanned: Res<Scanned>,
     mut selection: ResMut<Selection>,
     mut filters: ResMut<Filters>,
     mut selected: ResMut<crate::systems::route::Selected>,
+    exporting: Res<Exporting>,
     orbit: Query<&OrbitCamera>,
     mut camera: MessageWriter<MoveCamera>,
+    mut export: MessageWriter<Export>,
 ) -> Result {
     if panels.open.is_empty() {
         return Ok(());
@@ -538,6 +545,7 @@ fn panels(
     let mut picked = None;
     let mut opening = None;
     let mut wanted = None;
+    let mut exported = None;
     // Which panel the user pressed, if they pressed one. Asked once for the
     // whole pass, since a press is one press however many windows are drawn.
     let pressed = ctx.input(|input| input.pointer.any_pressed());
@@ -561,9 +569,14 @@ fn panels(
         let window = window.show(ctx, |ui| {
             spread(ui);
             match &panel.subject {
-                Subject::System(system) => {
-                    described(ui, system, &names, &mut centered, &mut wanted)
-                }
+                Subject::System(system) => described(
+                    ui,
+                    system,
+                    &names,
+                    scanned.of(system.address),
+                    &mut centered,
+                    &mut wanted,
+                ),
                 Subject::Star(star) => star_described(ui, star),
                 Subject::Body(body) => body_described(ui, body),
                 Subject::Filter { filter, systems } => admitted(
@@ -571,9 +584,11 @@ fn panels(
                     filter,
                     systems.as_deref(),
                     center,
+                    exporting.said(filter),
                     &mut picked,
                     &mut opening,
                     &mut centered,
+                    &mut exported,
                 ),
             }
         });
@@ -624,6 +639,9 @@ fn panels(
     if let Some(filter) = wanted {
         filters.add(filter);
     }
+    if let Some(asked) = exported {
+        export.write(asked);
+    }
     // Pressing a route's panel is how the user says which of several drawn
     // routes they mean, and the map draws that one in front of the rest.
     // Only a route: the other filters have no line to put forward, and a
@@ -654,6 +672,7 @@ fn described(
     ui: &mut Ui,
     system: &System,
     names: &FactionNames,
+    scanned: Option<&Completeness>,
     centered: &mut Option<DVec3>,
     wanted: &mut Option<Filter>,
 ) {
@@ -667,6 +686,11 @@ fn described(
             field(ui, "Government", named(&system.government));
             field(ui, "Security", named(&system.security));
             economies(ui, &system.economies);
+            // Only for the system the camera is in, the one whose bodies
+            // the map has asked after.
+            if let Some(scanned) = scanned {
+                field(ui, "Bodies", bodies(scanned));
+            }
             field(
                 ui,
                 "Updated",
@@ -686,6 +710,20 @@ fn described(
     }
 }
 
+/// How many of a system's bodies are on record, and how many are left
+///
+/// A system nobody has honked says only how many are on record, there being
+/// nothing to hold that against.
+fn bodies(scanned: &Completeness) -> String {
+    match (scanned.body_count, scanned.unscanned()) {
+        (Some(count), Some(0)) => format!("All {count} scanned"),
+        (Some(count), Some(left)) => {
+            format!("{} of {count} scanned, {left} not", count as i64 - left)
+        }
+        _ => format!("{} scanned, of unknown", scanned.known),
+    }
+}
+
 /// Metres in a solar radius
 ///
 /// What a star's size is read in. Metres are what the database holds and what
@@ -924,14 +962,20 @@ fn summary(filter: &Filter, held: usize) -> String {
 /// Each line ends in a distance, and which distance it is follows from what
 /// the list is: the jump that reaches the system where the filter is flown,
 /// and how far off it is from the camera where it is not.
+///
+/// A route can be written out from here as well, `said` being what became of
+/// the last time it was.
+#[allow(clippy::too_many_arguments)]
 fn admitted(
     ui: &mut Ui,
     filter: &Filter,
     systems: Option<&[System]>,
     center: Option<DVec3>,
+    said: Option<&str>,
     picked: &mut Option<(System, bool)>,
     described: &mut Option<System>,
     centered: &mut Option<DVec3>,
+    exported: &mut Option<Export>,
 ) {
     let Some(systems) = systems else {
         ui.label(egui::RichText::new("Looking...").weak());
@@ -944,6 +988,9 @@ fn admitted(
     }
 
     ui.label(egui::RichText::new(summary(filter, systems.len())).weak());
+    if filter.is_route() {
+        exports(ui, filter, said, exported);
+    }
     ui.add_space(MARGIN);
 
     let line = ui.text_style_height(&egui::TextStyle::Body)
@@ -1036,6 +1083,28 @@ fn admitted(
     });
 }
 
+/// The files a route can be written out as, and what became of the last
+///
+/// A button a format, named for the file it writes, which is what the user
+/// goes looking for afterwards.
+fn exports(
+    ui: &mut Ui,
+    route: &Filter,
+    said: Option<&str>,
+    exported: &mut Option<Export>,
+) {
+    ui.horizontal(|ui| {
+        for format in [Format::NavRoute, Format::Csv, Format::Spansh] {
+            if ui.small_button(format.file_name()).clicked() {
+                *exported = Some(Export { route: route.clone(), format });
+            }
+        }
+    });
+    if let Some(said) = said {
+        ui.label(egui::RichText::new(said).weak());
+    }
+}
+
 /// Every faction present in the system, one to a line
 ///
 /// A list under a heading rather than rows in the grid above, because a
@@ -2115,4 +2184,15 @@ mod tests {
         assert_eq!(named(&Some(Allegiance::Empire)), "Empire");
         assert_eq!(named::<Allegiance>(&None), "Unknown");
     }
+
+    /// Bodies never scanned have nowhere to be drawn, so they are counted
+    #[test]
+    fn unscanned_bodies_are_counted() {
+        let mut scanned = Completeness { known: 12, ..Default::default() };
+        assert_eq!(bodies(&scanned), "12 scanned, of unknown");
+        scanned.body_count = Some(40);
+        assert_eq!(bodies(&scanned), "12 of 40 scanned, 28 not");
+        scanned.known = 40;
+        assert_eq!(bodies(&scanned), "All 40 scanned");
+    }
 }
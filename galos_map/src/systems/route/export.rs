@@ -0,0 +1,121 @@
+//! Writing a route drawn on the map out as a file
+//!
+//! The same files, by the same code in [`galos_db::export`], that `galos route
+//! --format` and the server's download link write, so a route taken off the
+//! map is the file either of those would have handed over for it.
+//!
+//! Written into the directory the map was started from, under the name each
+//! format goes by. A route exported twice in one format is the newer route,
+//! which is the one the user is looking at.
+
+use crate::Db;
+use crate::schedule::MapSet;
+use crate::systems::filter::Filter;
+use bevy::prelude::*;
+use bevy::tasks::futures_lite::future;
+use bevy::tasks::{AsyncComputeTaskPool, Task, block_on};
+use chrono::Utc;
+use galos_db::Database;
+use galos_db::export::{Format, Waypoint};
+use galos_db::systems::System as DbSystem;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<Exporting>();
+    app.add_message::<Export>();
+    app.add_systems(Update, (export, exported).chain().in_set(MapSet::Fetch));
+}
+
+/// Write the route a filter runs through as a file in `format`
+///
+/// Asked for from the route's panel. Anything but a route is not flown, and
+/// has nothing to write.
+#[derive(Message, Debug)]
+pub struct Export {
+    pub route: Filter,
+    pub format: Format,
+}
+
+/// The file being written, and what became of the last one
+///
+/// One at a time. A second press while the first is writing is the one the
+/// user wants, and dropping the task is what stops the first.
+#[derive(Resource, Default)]
+pub struct Exporting {
+    out: Option<(Filter, Task<Result<PathBuf, String>>)>,
+    said: Option<(Filter, String)>,
+}
+
+impl Exporting {
+    /// Where `route` was last written, or why it was not
+    ///
+    /// For its panel to say, and only its own: a route's panel saying where
+    /// another was written would be answering a question nobody asked it.
+    pub fn said(&self, route: &Filter) -> Option<&str> {
+        self.said
+            .as_ref()
+            .filter(|(about, _)| about == route)
+            .map(|(_, said)| said.as_str())
+    }
+}
+
+/// Put the last route asked for to the database, and write it out
+fn export(
+    mut asked: MessageReader<Export>,
+    mut exporting: ResMut<Exporting>,
+    db: Res<Db>,
+) {
+    let Some(Export { route, format }) = asked.read().last() else { return };
+    let Filter::Route { systems, .. } = route else { return };
+    let (addresses, format) = (systems.clone(), *format);
+    let db = db.0.clone();
+    let task = AsyncComputeTaskPool::get()
+        .spawn(async move { write(&db, &addresses, format).await });
+    exporting.out = Some((route.clone(), task));
+    exporting.said = Some((route.clone(), "Writing...".to_owned()));
+}
+
+/// Take in what became of the file, once it has been written
+fn exported(mut exporting: ResMut<Exporting>) {
+    let Some((_, task)) = exporting.out.as_mut() else { return };
+    let Some(answer) = block_on(future::poll_once(task)) else { return };
+    let Some((route, _)) = exporting.out.take() else { return };
+    let said = match answer {
+        Ok(path) => format!("Written to {}", path.display()),
+        Err(why) => format!("Not written: {why}"),
+    };
+    exporting.said = Some((route, said));
+}
+
+/// Write the route through `addresses` to its file in `format`
+///
+/// The rows are looked up again rather than read off the map, which holds
+/// only what the spyglass has reached and none of the primaries a file says
+/// whether to scoop at.
+async fn write(
+    db: &Database,
+    addresses: &[i64],
+    format: Format,
+) -> Result<PathBuf, String> {
+    let mut systems = DbSystem::fetch_many(db, addresses)
+        .await
+        .map_err(|why| why.to_string())?;
+    // In the order the route is travelled, which is not the order they come
+    // back in.
+    systems.sort_by_key(|system| {
+        addresses.iter().position(|address| *address == system.address)
+    });
+    let waypoints =
+        Waypoint::along(db, &systems).await.map_err(|why| why.to_string())?;
+
+    let path = PathBuf::from(format.file_name());
+    let file = File::create(&path).map_err(|why| why.to_string())?;
+    let mut out = BufWriter::new(file);
+    format
+        .write(&mut out, &waypoints, Utc::now())
+        .and_then(|_| out.flush())
+        .map_err(|why| why.to_string())?;
+    Ok(path)
+}
@@ -3,11 +3,14 @@ use crate::systems::fetch::{FetchIndex, FetchTasks, LastFetchedAt};
 use bevy::prelude::*;
 use bevy::tasks::AsyncComputeTaskPool;
 use galos_db::systems::System as DbSystem;
+use galos_db::systems::nav::RouteConstraints;
 
 pub fn fetch_route(
     start: String,
     end: String,
     range: String,
+    boost: bool,
+    constraints: RouteConstraints,
     tasks: &mut ResMut<FetchTasks>,
     time: &Res<Time<Real>>,
     last_fetched_at: &mut ResMut<LastFetchedAt>,
@@ -17,9 +20,17 @@ pub fn fetch_route(
     // rather than racing it: two of them landing would draw one line over
     // the other, and whichever finished last would answer for the one the
     // user is waiting on. Dropping the task is what stops it.
-    tasks.fetched.retain(|index, _| !matches!(index, FetchIndex::Route(..)));
+    tasks.fetched.retain(|index, _| {
+        !matches!(index, FetchIndex::Route(..) | FetchIndex::Tour(..))
+    });
 
-    let index = FetchIndex::Route(start.clone(), end.clone(), range.clone());
+    let index = FetchIndex::Route(
+        start.clone(),
+        end.clone(),
+        range.clone(),
+        boost,
+        constraints.clone(),
+    );
     let now = time.last_update().unwrap_or(time.startup());
     let task_pool = AsyncComputeTaskPool::get();
     let db = db.0.clone();
@@ -29,7 +40,25 @@ pub fn fetch_route(
             DbSystem::fetch_by_name(&db, &end).await,
             range.parse::<f64>(),
         ) {
-            if let Some(route) = a.route_to(&db, &b, r) {
+            // The end is the one stop, and the pad is asked of it alone.
+            if !matches!(constraints.stops_at(&db, &b).await, Ok(true)) {
+                return vec![];
+            }
+            // A boosted route comes back as its jumps, and what is drawn is
+            // the systems they run through. Which legs were boosted is read
+            // back off the line by how long they are.
+            if boost {
+                if let Ok(Some((jumps, _))) =
+                    a.route_boosted_to(&db, &b, r, &constraints)
+                {
+                    let mut systems: Vec<DbSystem> =
+                        jumps.iter().map(|jump| jump.from.clone()).collect();
+                    systems.extend(jumps.last().map(|jump| jump.to.clone()));
+                    return systems;
+                }
+            } else if let Ok(Some(route)) =
+                a.route_constrained(&db, &b, r, &constraints)
+            {
                 return route.0;
             }
         }
@@ -38,3 +67,45 @@ pub fn fetch_route(
     tasks.fetched.insert(index, (task, now));
     **last_fetched_at = LastFetchedAt(now);
 }
+
+/// Ask for the fewest jumps round every one of `stops`
+///
+/// Drawn as a route, which is all a tour is once its order is settled: the
+/// systems it runs through, in the order travelled. One at a time with the
+/// routes, for the same reason.
+pub fn fetch_tour(
+    stops: Vec<String>,
+    range: String,
+    constraints: RouteConstraints,
+    tasks: &mut ResMut<FetchTasks>,
+    time: &Res<Time<Real>>,
+    last_fetched_at: &mut ResMut<LastFetchedAt>,
+    db: &Res<Db>,
+) {
+    tasks.fetched.retain(|index, _| {
+        !matches!(index, FetchIndex::Route(..) | FetchIndex::Tour(..))
+    });
+
+    let index =
+        FetchIndex::Tour(stops.clone(), range.clone(), constraints.clone());
+    let now = time.last_update().unwrap_or(time.startup());
+    let task_pool = AsyncComputeTaskPool::get();
+    let db = db.0.clone();
+    let task = task_pool.spawn(async move {
+        let Ok(r) = range.parse::<f64>() else { return vec![] };
+        let mut systems = Vec::new();
+        for stop in &stops {
+            let Ok(system) = DbSystem::fetch_by_name(&db, stop).await else {
+                return vec![];
+            };
+            systems.push(system);
+        }
+        DbSystem::tour(&db, &systems, r, false, &constraints)
+            .ok()
+            .flatten()
+            .map(|tour| tour.path())
+            .unwrap_or_default()
+    });
+    tasks.fetched.insert(index, (task, now));
+    **last_fetched_at = LastFetchedAt(now);
+}
@@ -12,6 +12,7 @@ use bevy::prelude::*;
 use super::system_to_vec;
 
 pub fn plugin(app: &mut App) {
+    app.add_plugins(export::plugin);
     app.add_message::<Plotted>();
     app.init_resource::<Selected>();
     // After the fetch it answers has been drawn, and before the camera is
@@ -484,11 +485,15 @@ pub fn strength(is_active: bool) -> f32 {
 /// system at its own size, and a line laid over it is a light year wide and
 /// runs out through the walls. So it fades on exactly the band the mark
 /// standing for that system fades on, and the two go together.
+///
+/// The boosted legs laid over a line go with it, at the same strength in their
+/// own color.
 fn emphasise(
     filters: Res<Filters>,
     selected: Res<Selected>,
     seen_as: Res<crate::systems::bodies::spawn::Apparent>,
     lines: Query<(&Route, &MeshMaterial3d<StandardMaterial>)>,
+    boosts: Query<(&ChildOf, &MeshMaterial3d<StandardMaterial>), With<Boosted>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
     let active = active(&filters, &selected.0);
@@ -506,8 +511,44 @@ fn emphasise(
             material.base_color = wanted;
         }
     }
+
+    for (child_of, material) in &boosts {
+        let Ok((line, _)) = lines.get(child_of.parent()) else { continue };
+        let Some(mut material) = materials.get_mut(&material.0) else {
+            continue;
+        };
+        let wanted =
+            spawn::boost_color(strength(Some(&line.0) == active) * standing);
+        if material.base_color != wanted {
+            material.base_color = wanted;
+        }
+    }
 }
 
+/// The legs of a route's line that a jet cone carried, laid over it
+///
+/// A child of the line, so that it is shown, hidden and taken away with it.
+#[derive(Component)]
+pub struct Boosted;
+
+/// The legs longer than `range`, as pairs of points
+///
+/// A route runs in jumps no longer than its range, so one that is longer was
+/// supercharged: the only way a ship gets further than its drive allows is a
+/// neutron star's or a white dwarf's cone. Read off the line rather than
+/// carried with it, since the line is all a plotted route keeps.
+///
+/// Each boosted leg whole, whatever of it [`trim`] has cut back underneath.
+pub(super) fn boosted(points: &[Vec3], range: f32) -> Vec<Vec3> {
+    points
+        .windows(2)
+        .filter(|ends| ends[0].distance(ends[1]) > range)
+        .flatten()
+        .copied()
+        .collect()
+}
+
+pub mod export;
 pub mod fetch;
 pub mod spawn;
 
@@ -715,6 +756,20 @@ mod tests {
     const A: Vec3 = Vec3::ZERO;
     const B: Vec3 = Vec3::new(20. * DASH / 0.5, 0., 0.);
 
+    /// Only the legs longer than the range are boosted
+    #[test]
+    fn legs_past_the_range_are_boosted() {
+        let range = B.x / 2.;
+        let far = B * 3.;
+
+        assert!(boosted(&[A, B], B.x).is_empty());
+        assert_eq!(boosted(&[A, B, far], range), vec![A, B, B, far]);
+        assert_eq!(
+            boosted(&[A, A + Vec3::X, far], range),
+            vec![A + Vec3::X, far]
+        );
+    }
+
     /// A leg between two systems on the map is drawn whole
     #[test]
     fn a_leg_between_two_drawn_systems_is_one_line() {
@@ -19,6 +19,14 @@ pub fn line_color(strength: f32) -> Color {
     Color::srgba(1., 1., 1., 0.25 * strength)
 }
 
+/// What a boosted leg is painted over its line, at `strength` of the full
+///
+/// The blue of a neutron star's jets, and stronger than the line it lies on
+/// so that the stretch a cone carried stands out of it.
+pub fn boost_color(strength: f32) -> Color {
+    Color::srgba(0.5, 0.8, 1., 0.6 * strength)
+}
+
 /// Where a route sits, and how far it reaches from there
 ///
 /// The middle of what it spans and the distance from there to whichever of
@@ -117,7 +125,17 @@ pub fn spawn_route(
     // anything is seen of it.
     let whole = path.whole();
     let shown = vec![true; whole.len()];
-    commands.spawn((
+    let boosted = match route {
+        Filter::Route { range, .. } => range
+            .parse::<f64>()
+            // A hair over the range, since a leg exactly as long as it comes
+            // back a rounding either way once it is in single precision.
+            .map(|range| range * crate::space::LIGHT_YEAR * 1.0001)
+            .map(|reach| super::boosted(&whole, reach as f32))
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    let line = commands.spawn((
         Mesh3d(
             meshes.add(super::LineList { points: super::legs(&whole, &shown) }),
         ),
@@ -147,6 +165,25 @@ pub fn spawn_route(
         path,
         ChildOf(galaxy.0),
     ));
+
+    // An empty mesh is the slab allocator's trouble again, so a route with no
+    // boosted legs has nothing laid over it at all.
+    if !boosted.is_empty() {
+        let line = line.id();
+        commands.spawn((
+            Mesh3d(meshes.add(super::LineList { points: boosted })),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: boost_color(super::strength(true)),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            })),
+            Transform::default(),
+            Visibility::Inherited,
+            super::Boosted,
+            ChildOf(line),
+        ));
+    }
 }
 
 #[cfg(test)]
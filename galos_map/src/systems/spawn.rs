@@ -422,7 +422,18 @@ pub fn spawn(
         let status = block_on(future::poll_once(task));
         let retain = status.is_none();
         if let Some(new_systems) = status {
-            if let FetchIndex::Route(start, end, range) = index {
+            // What the route was asked to do, for saying it could not, and
+            // the range it was asked in.
+            let asked = match index {
+                FetchIndex::Route(start, end, range, ..) => {
+                    Some((format!("from {start} to {end}"), range))
+                }
+                FetchIndex::Tour(stops, range, _) => {
+                    Some((format!("through {} systems", stops.len()), range))
+                }
+                _ => None,
+            };
+            if let Some((between, range)) = asked {
                 // A route is a line between systems, so one system is
                 // no route. Coming back with nothing is how the
                 // database says it could not get from one end to the
@@ -438,7 +449,7 @@ pub fn spawn(
                 if *plot == Plot::Working {
                     *plot = if new_systems.len() < 2 {
                         Plot::Trouble(format!(
-                            "No route from {start} to {end} at {range} Ly"
+                            "No route {between} at {range} Ly"
                         ))
                     } else {
                         Plot::Nothing
@@ -12,6 +12,7 @@
 //! route is the third, and asks only what it may be flown in: which systems it
 //! runs between is what is picked out on the map.
 
+use crate::Db;
 use crate::camera::{MoveCamera, OrbitCamera};
 use crate::search::{Plot, SearchNote, SearchResults, Searched, Searching};
 use crate::systems::bodies::Contents;
@@ -32,20 +33,58 @@ use crate::systems::{InReach, Spyglass};
 use bevy::ecs::system::SystemParam;
 use bevy::math::DVec3;
 use bevy::prelude::*;
+use bevy::tasks::futures_lite::future;
+use bevy::tasks::{AsyncComputeTaskPool, Task, block_on};
 use bevy_egui::egui::{Context, Response, Ui};
 use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use elite_journal::Security;
 use galos_db::factions::Faction as DbFaction;
+use galos_db::ships::Ship as DbShip;
+use galos_db::stations::PadSize;
 use galos_db::systems::System as DbSystem;
+use galos_db::systems::nav::RouteConstraints;
 
 pub fn plugin(app: &mut App) {
     app.init_resource::<PointerOverUi>();
     app.init_resource::<SettingsOpen>();
     app.init_resource::<Grasp>();
     app.init_resource::<BarFields>();
+    app.init_resource::<Shipyard>();
+    app.add_systems(Startup, ask_ships);
+    app.add_systems(Update, ships);
     // The lettering leads, being what everything after it is drawn in.
     app.add_systems(EguiPrimaryContextPass, (lettering, chrome).chain());
 }
 
+/// The ships asked for as the map opened, until they come back
+#[derive(Resource, Default)]
+struct Shipyard(Option<Task<Result<Vec<DbShip>, galos_db::Error>>>);
+
+/// Ask for the ships the journal has described, for the route form to offer
+///
+/// Once, as the map opens. A ship is refitted at a station rather than while
+/// its commander is looking at the map. Off the main thread all the same, a
+/// database slow to answer being no reason for the map to be slow to open.
+fn ask_ships(db: Res<Db>, mut shipyard: ResMut<Shipyard>) {
+    let db = db.0.clone();
+    let task = AsyncComputeTaskPool::get()
+        .spawn(async move { DbShip::fetch_all(&db).await });
+    shipyard.0 = Some(task);
+}
+
+/// Take in the ships, once they have come back
+fn ships(mut shipyard: ResMut<Shipyard>, mut fields: ResMut<BarFields>) {
+    let Some(task) = shipyard.0.as_mut() else { return };
+    let Some(answer) = block_on(future::poll_once(task)) else { return };
+    shipyard.0 = None;
+    match answer {
+        Ok(ships) => fields.ships = ships,
+        // A form with no ships on it is a form asking for a range, which is
+        // what it asked for before there were any.
+        Err(why) => debug!("could not read ships: {why}"),
+    }
+}
+
 /// Set every style the chrome is drawn in
 ///
 /// Once. A style set on the context is the style it keeps, and a font asked
@@ -454,6 +493,21 @@ pub struct BarFields {
     /// between is picked out on the map, and a range is a fact about a ship
     /// with nothing on the map to point at.
     route_range: Option<String>,
+    /// Whether a route may supercharge in the jet cones along the way
+    ///
+    /// Ticked rather than typed, and kept with the range for the same
+    /// reason: it is a fact about how the ship is flown.
+    route_boost: bool,
+    /// What a route may not pass through, and what it has to stop at
+    ///
+    /// Ticked, as the boost is. The systems and factions a route could be
+    /// told to keep out of are typed at the command line, where there is
+    /// room to name them.
+    route_constraints: RouteConstraints,
+    /// The ships a range can be taken from, and which was, if the range is
+    /// still the one it gave
+    ships: Vec<DbShip>,
+    route_ship: Option<String>,
     /// Whether the rest of the form is out below the input
     ///
     /// Turned on when a field takes focus and off when a press lands off the
@@ -1635,6 +1689,8 @@ fn gathered(ui: &mut Ui, selection: &Selection, filters: &mut Filters) -> bool {
         }
         if ends_of(selection).is_ok() {
             routing = ui.button("Route").clicked();
+        } else if tour_of(selection).is_some() {
+            routing = ui.button("Tour").clicked();
         }
     });
     routing
@@ -1808,7 +1864,8 @@ fn jump_range(asked: &str) -> Result<f64, &'static str> {
 ///
 /// Two of them. A longer set is a route running through every system in it,
 /// leg by leg, and that is a different plot from this one rather than this one
-/// done several times.
+/// done several times. The one on offer for now is the set in no particular
+/// order, as a tour, which [`tour_of`] answers.
 // TODO: Plot through the whole selection, leg by leg in the order it was
 // picked, instead of refusing every set but the pair.
 fn ends_of(selection: &Selection) -> Result<(&str, &str), &'static str> {
@@ -1823,6 +1880,44 @@ fn ends_of(selection: &Selection) -> Result<(&str, &str), &'static str> {
     }
 }
 
+/// The systems a tour would visit, when there are more than two of them
+///
+/// A set picked out in no order worth keeping: the tour is whichever order
+/// gets round them in the fewest jumps. Two are a route, which [`ends_of`]
+/// answers, and the order they were picked in is the one they are flown in.
+fn tour_of(selection: &Selection) -> Option<Vec<&str>> {
+    let stops: Vec<&str> = selection.systems().map(|s| s.name()).collect();
+    (stops.len() > 2).then_some(stops)
+}
+
+/// What to ask for, given what is picked out and the range typed
+///
+/// Nothing where neither a tour nor a route can be made of the selection,
+/// which the form has already said.
+fn question(
+    tour: &Option<Vec<&str>>,
+    ends: Result<(&str, &str), &str>,
+    range: String,
+    boost: bool,
+    constraints: &RouteConstraints,
+) -> Option<Searched> {
+    match (tour, ends) {
+        (Some(stops), _) => Some(Searched::Tour {
+            stops: stops.iter().map(|stop| stop.to_string()).collect(),
+            range,
+            constraints: constraints.clone(),
+        }),
+        (None, Ok((start, end))) => Some(Searched::Route {
+            start: start.to_owned(),
+            end: end.to_owned(),
+            range,
+            boost,
+            constraints: constraints.clone(),
+        }),
+        (None, Err(_)) => None,
+    }
+}
+
 /// How far apart the two systems a route would run between are
 ///
 /// In a straight line, which is as short as a route between them could be and
@@ -1871,13 +1966,19 @@ fn route_section(
     // Which two systems it runs between. Said rather than asked for, since
     // what answers it is a gesture out on the map, and a form with nothing on
     // it about the ends is a form that plots between systems it never names.
+    //
+    // More than two is a tour of them, in whatever order is fewest jumps.
+    let tour = tour_of(selection);
     let ends = ends_of(selection);
-    match ends {
-        Ok((start, end)) => ui.label(format!("{start} -> {end}")),
+    match (&tour, ends) {
+        (Some(stops), _) => {
+            ui.label(format!("A tour of {} systems", stops.len()))
+        }
+        (None, Ok((start, end))) => ui.label(format!("{start} -> {end}")),
         // Weakly. Nothing has gone wrong: the user is part way through
         // asking, and a form in red before it has been filled in is a form
         // scolding whoever fills it in.
-        Err(why) => ui.label(egui::RichText::new(why).weak()),
+        (None, Err(why)) => ui.label(egui::RichText::new(why).weak()),
     };
     // And how far apart they are, which is the one thing about the plot the
     // map can say before it is asked for. Under the names rather than at the
@@ -1888,6 +1989,11 @@ fn route_section(
     }
     ui.add_space(FIELD_GAP);
 
+    // A ship from the journal, for whoever would rather not remember its
+    // range. Picking one fills the range in, where it is as much the user's
+    // to change as one typed.
+    ship_choice(ui, search);
+
     // The range is typed rather than looked up, so it never waits on
     // anything.
     let range =
@@ -1906,6 +2012,14 @@ fn route_section(
     if range.changed() && matches!(*plot, Plot::Trouble(_)) {
         *plot = Plot::Nothing;
     }
+    // Nor is it the ship's any more, once it has been typed over.
+    if range.changed() {
+        search.route_ship = None;
+    }
+    // Beside the range, since it changes what the range buys: a neutron
+    // star's cone carries the jump after it four times as far.
+    ui.checkbox(&mut search.route_boost, "Neutron Boost");
+    constraints(ui, &mut search.route_constraints);
 
     // How the last route asked for is getting on. Only ever a route that
     // was asked for: a field being typed into is not an attempt at
@@ -1920,14 +2034,19 @@ fn route_section(
     // The two things a route is made of: which systems it runs between, and
     // what it may be flown in. The button is dead until both are in hand,
     // since a plot missing one of them is nothing to ask the database about.
-    let asked = ends.ok().zip(typed(&search.route_range));
+    let asked =
+        typed(&search.route_range).filter(|_| tour.is_some() || ends.is_ok());
     // Egui lays a button's contents out as atoms, and a custom atom is a
     // slot of a given size that hands its rect back to be painted into. So
     // the spinner takes a place in the row beside the label rather than
     // being painted over the top of it, and asks for no room at all on a
     // button that has nothing to say.
     let slot = ui.id().with("plotting");
-    let mut atoms = egui::Atoms::new("Plot Route");
+    let mut atoms = egui::Atoms::new(if tour.is_some() {
+        "Plot Tour"
+    } else {
+        "Plot Route"
+    });
     if *plot == Plot::Working {
         let turning = ui.text_style_height(&egui::TextStyle::Button) * SPINNER;
         atoms.push_left(egui::Atom::custom(slot, egui::Vec2::splat(turning)));
@@ -1945,20 +2064,24 @@ fn route_section(
     }
 
     if (button.response.clicked() || submitted)
-        && let Some(((start, end), range)) = asked
+        && let Some(range) = asked
     {
         *plot = match jump_range(range) {
-            Ok(range) => {
-                searched.write(Searched::Route {
-                    start: start.to_owned(),
-                    end: end.to_owned(),
-                    // Back to text, since a route is fetched under a key
-                    // made of what was asked for and a float is no kind of
-                    // key.
-                    range: range.to_string(),
-                });
-                Plot::Working
-            }
+            // Back to text, since a route is fetched under a key made of
+            // what was asked for and a float is no kind of key.
+            Ok(range) => match question(
+                &tour,
+                ends,
+                range.to_string(),
+                search.route_boost,
+                &search.route_constraints,
+            ) {
+                Some(asking) => {
+                    searched.write(asking);
+                    Plot::Working
+                }
+                None => Plot::Nothing,
+            },
             Err(trouble) => Plot::Trouble(trouble.to_owned()),
         };
     }
@@ -1966,6 +2089,66 @@ fn route_section(
     taken
 }
 
+/// The ships a route could be flown in, and the range of each
+///
+/// Only those with a drive the fuel equation knows, whose range can be worked
+/// out. Unladen, that being how a ship is flown between jobs.
+fn offered(ships: &[DbShip]) -> Vec<(&str, f64)> {
+    ships
+        .iter()
+        .filter_map(|ship| Some((ship.label(), ship.unladen_range()?)))
+        .collect()
+}
+
+/// Offer the ships from the journal, filling the range in from the one picked
+///
+/// Nothing at all without any, rather than a list with nothing in it.
+fn ship_choice(ui: &mut Ui, search: &mut BarFields) {
+    let offered = offered(&search.ships);
+    if offered.is_empty() {
+        return;
+    }
+    let mut picked = None;
+    egui::ComboBox::from_id_salt("route-ship")
+        .width(ui.available_width())
+        .selected_text(search.route_ship.as_deref().unwrap_or("Ship"))
+        .show_ui(ui, |ui| {
+            for (name, range) in &offered {
+                let chosen = search.route_ship.as_deref() == Some(*name);
+                let label = format!("{name} ({range:.2} Ly)");
+                if ui.selectable_label(chosen, label).clicked() {
+                    picked = Some((name.to_string(), *range));
+                }
+            }
+        });
+    if let Some((name, range)) = picked {
+        search.route_range = Some(format!("{range:.2}"));
+        search.route_ship = Some(name);
+    }
+    ui.add_space(FIELD_GAP);
+}
+
+/// Ask what a route has to keep out of, and what it has to stop at
+///
+/// Under the boost, and like it a fact about how the ship is flown rather
+/// than where. A tour keeps to them too, on every leg between the systems
+/// picked out, and finds nothing if one of those is not a stop they allow.
+fn constraints(ui: &mut Ui, constraints: &mut RouteConstraints) {
+    ui.checkbox(&mut constraints.avoid_permits, "Avoid Permit Systems");
+    let mut anarchy = constraints.avoid_security.contains(&Security::Anarchy);
+    if ui.checkbox(&mut anarchy, "Avoid Anarchies").changed() {
+        constraints.avoid_security.retain(|s| *s != Security::Anarchy);
+        if anarchy {
+            constraints.avoid_security.push(Security::Anarchy);
+        }
+    }
+    ui.checkbox(&mut constraints.scoopable, "Scoopable Stars Only");
+    let mut large = constraints.pad == Some(PadSize::Large);
+    if ui.checkbox(&mut large, "Large Pads Only").changed() {
+        constraints.pad = large.then_some(PadSize::Large);
+    }
+}
+
 /// Say which filters are being applied, and how much is getting through
 ///
 /// Drawn whether or not the form is out. A filter changes what the whole map
@@ -3601,6 +3784,16 @@ mod tests {
         assert!(several.contains(&"Filter".to_owned()), "{several:?}");
     }
 
+    /// Three or more picked out are offered a tour of them instead
+    #[test]
+    fn a_longer_set_is_offered_a_tour() {
+        let several = selection_said(&["SOL", "BARNARD", "WOLF 359"]);
+        let two = selection_said(&["SOL", "BARNARD"]);
+
+        assert!(several.contains(&"Tour".to_owned()), "{several:?}");
+        assert!(!two.contains(&"Tour".to_owned()), "{two:?}");
+    }
+
     /// A selection holding a system at each of `places`, on the x axis
     fn strung_out(places: &[f64]) -> Selection {
         let mut selection = Selection::default();
@@ -5236,6 +5429,46 @@ mod tests {
         assert!(ends_of(&holding(&["SOL", "SOLATI", "SOLLARO"])).is_err());
     }
 
+    /// More than two picked out are a tour, in no order in particular
+    #[test]
+    fn a_longer_set_is_a_tour() {
+        assert_eq!(
+            tour_of(&holding(&["SOL", "SOLATI", "SOLLARO"])),
+            Some(vec!["SOL", "SOLATI", "SOLLARO"])
+        );
+        assert_eq!(tour_of(&holding(&["SOL", "SOLATI"])), None);
+    }
+
+    /// A ship from the journal, with a drive or without
+    fn ship(name: Option<&str>, drive: bool) -> DbShip {
+        use galos_db::systems::nav::{Fsd, ModuleClass};
+        DbShip {
+            commander: "Jameson".into(),
+            ship_id: 1,
+            ship: "sidewinder".into(),
+            name: name.map(str::to_owned),
+            ident: None,
+            fsd: drive.then(|| Fsd::new(2, ModuleClass::E, 48.).unwrap()),
+            unladen_mass: 25.,
+            fuel_capacity: 2.,
+            fuel_reserve: 0.3,
+            cargo_capacity: 4,
+            max_jump_range: 7.5,
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    /// Only a ship whose range can be worked out is offered, by its name or
+    /// its hull
+    #[test]
+    fn ships_without_a_drive_are_not_offered() {
+        let ships = [ship(Some("Wayfarer"), true), ship(None, true)];
+        let names: Vec<&str> =
+            offered(&ships).into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["Wayfarer", "sidewinder"]);
+        assert!(offered(&[ship(Some("Wreck"), false)]).is_empty());
+    }
+
     /// Each of those says something of its own
     ///
     /// They are read out of the one line, so two of them saying the same
@@ -1,13 +1,21 @@
 use askama::Template;
 use axum::{
     extract,
-    http::StatusCode,
+    http::{header, StatusCode},
     response::{Html, IntoResponse, Response},
     routing::get,
     Router,
 };
+use chrono::Utc;
+use elite_journal::prelude::Security;
 use galos_db::bodies::{Body, Parent, Surface};
-use galos_db::stations::Station;
+use galos_db::export::{Format, Waypoint};
+use galos_db::factions::Faction;
+use galos_db::stations::{PadSize, Station};
+use galos_db::systems::nav::{
+    boost::Boost, constraints::parse_allegiance, metric::docking, Metric,
+    RouteConstraints,
+};
 use galos_db::systems::System;
 use galos_db::Database;
 use itertools::Itertools;
@@ -114,6 +122,13 @@ struct RouteTemplate {
     to: System,
     from: System,
     route: (Vec<System>, OrderedFloat<f64>),
+    /// Which jet cone, if any, each leg was supercharged in
+    boosts: Vec<Option<Boost>>,
+    /// What the route was made cheapest in, and each leg's cost in it
+    metric: Metric,
+    costs: Vec<String>,
+    /// The query asked, for links to the same route as a file
+    query: String,
 }
 
 struct HtmlTemplate<T>(T);
@@ -151,6 +166,77 @@ struct RouteParams {
     to: Option<String>,
     from: Option<String>,
     range: Option<f64>,
+    /// Present, as `on`, when the form's checkbox is ticked
+    boost: Option<String>,
+    /// `jumps`, `distance` or `time`, fuel needing a ship the form has not
+    /// got
+    metric: Option<String>,
+    /// Systems to keep out of, by name, separated by commas
+    avoid: Option<String>,
+    avoid_permits: Option<String>,
+    /// Systems whose permit is held, by name, separated by commas
+    permits: Option<String>,
+    avoid_anarchy: Option<String>,
+    avoid_allegiance: Option<String>,
+    avoid_faction: Option<String>,
+    scoopable: Option<String>,
+    large_pad: Option<String>,
+    /// `csv`, `spansh` or `navroute`, to download the route rather than see it
+    format: Option<String>,
+}
+
+impl RouteParams {
+    /// What the form asks of the systems a route passes through
+    ///
+    /// Empty fields ask nothing, and anything named that cannot be found is
+    /// an error rather than quietly allowed.
+    async fn constraints(
+        &self,
+        db: &Database,
+    ) -> Result<RouteConstraints, String> {
+        let named = |field: &Option<String>| -> Vec<String> {
+            field
+                .iter()
+                .flat_map(|f| f.split(','))
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect()
+        };
+
+        let mut avoid = Vec::new();
+        for name in named(&self.avoid) {
+            let system = System::fetch_by_name(db, &name)
+                .await
+                .map_err(|_| format!("No system named {}.", name))?;
+            avoid.push(system.address);
+        }
+        let mut avoid_factions = Vec::new();
+        for name in named(&self.avoid_faction) {
+            let faction = Faction::fetch_by_name(db, &name)
+                .await
+                .map_err(|_| format!("No faction named {}.", name))?;
+            avoid_factions.push(faction.id);
+        }
+        let avoid_allegiance = named(&self.avoid_allegiance)
+            .iter()
+            .map(|name| parse_allegiance(name))
+            .collect::<Result<_, _>>()?;
+
+        Ok(RouteConstraints {
+            avoid,
+            avoid_permits: self.avoid_permits.is_some(),
+            permits: named(&self.permits),
+            avoid_security: if self.avoid_anarchy.is_some() {
+                vec![Security::Anarchy]
+            } else {
+                vec![]
+            },
+            avoid_allegiance,
+            avoid_factions,
+            scoopable: self.scoopable.is_some(),
+            pad: self.large_pad.as_ref().map(|_| PadSize::Large),
+        })
+    }
 }
 
 async fn systems(
@@ -238,15 +324,139 @@ async fn body(
 
 async fn route(
     extract::Query(params): extract::Query<RouteParams>,
+    extract::RawQuery(query): extract::RawQuery,
 ) -> impl IntoResponse {
     if let Ok(db) = Database::new().await {
+        let constraints = match params.constraints(&db).await {
+            Ok(constraints) => constraints,
+            Err(err) => {
+                return (StatusCode::BAD_REQUEST, err).into_response();
+            }
+        };
+        let format = match params.format.as_deref().map(str::parse::<Format>) {
+            Some(Err(err)) => {
+                return (StatusCode::BAD_REQUEST, err).into_response();
+            }
+            format => format.and_then(Result::ok),
+        };
         if let (Some(to), Some(from), Some(range)) =
             (params.to, params.from, params.range)
         {
             if let Ok(to) = System::fetch_by_name(&db, &to).await {
                 if let Ok(from) = System::fetch_by_name(&db, &from).await {
-                    if let Some(route) = from.route_to(&db, &to, range) {
-                        let template = RouteTemplate { to, from, route };
+                    // The end is the one stop, and the pad is asked of it.
+                    match constraints.stops_at(&db, &to).await {
+                        Ok(true) => {}
+                        Ok(false) => {
+                            return (
+                                StatusCode::BAD_REQUEST,
+                                format!("{} has no large pad.", to.name),
+                            )
+                                .into_response();
+                        }
+                        Err(err) => {
+                            return (
+                                StatusCode::INTERNAL_SERVER_ERROR,
+                                err.to_string(),
+                            )
+                                .into_response();
+                        }
+                    }
+                    // Boosted, a route is fewest jumps whatever was asked.
+                    let metric = match params.metric.as_deref() {
+                        _ if params.boost.is_some() => Metric::Jumps,
+                        Some("distance") => Metric::Distance,
+                        // Timed without a ship, a jump is as long however
+                        // far it goes.
+                        Some("time") => Metric::Time { fsd: None, mass: 0. },
+                        _ => Metric::Jumps,
+                    };
+                    let route = if params.boost.is_some() {
+                        from.route_boosted_to(&db, &to, range, &constraints)
+                            .map(|found| {
+                                found.map(|(jumps, cost)| {
+                                    let boosts =
+                                        jumps.iter().map(|j| j.boost).collect();
+                                    let mut systems: Vec<System> = jumps
+                                        .iter()
+                                        .map(|j| j.from.clone())
+                                        .collect();
+                                    systems.extend(
+                                        jumps.last().map(|j| j.to.clone()),
+                                    );
+                                    ((systems, cost), boosts)
+                                })
+                            })
+                    } else {
+                        from.route_measured(
+                            &db,
+                            &to,
+                            range,
+                            &metric,
+                            &constraints,
+                        )
+                        .map(|found| {
+                            found.map(|route| {
+                                let boosts =
+                                    vec![None; route.0.len().saturating_sub(1)];
+                                (route, boosts)
+                            })
+                        })
+                    };
+                    let route = match route {
+                        Ok(route) => route,
+                        Err(err) => {
+                            return (
+                                StatusCode::INTERNAL_SERVER_ERROR,
+                                err.to_string(),
+                            )
+                                .into_response();
+                        }
+                    };
+                    if let Some((route, boosts)) = route {
+                        if let Some(format) = format {
+                            return download(&db, &route.0, format).await;
+                        }
+                        // Timed, the last leg carries the supercruise to the
+                        // nearest station at the end of it.
+                        let docked = match metric {
+                            Metric::Time { .. } => {
+                                match docking(&db, &to, None).await {
+                                    Ok(secs) => secs,
+                                    Err(err) => {
+                                        return (
+                                            StatusCode::INTERNAL_SERVER_ERROR,
+                                            err.to_string(),
+                                        )
+                                            .into_response();
+                                    }
+                                }
+                            }
+                            _ => 0.,
+                        };
+                        let legs = route.0.len().saturating_sub(1);
+                        let costs = route
+                            .0
+                            .iter()
+                            .tuple_windows()
+                            .enumerate()
+                            .map(|(i, (a, b))| {
+                                let mut cost = metric.jump(a.distance(b));
+                                if i + 1 == legs {
+                                    cost += docked;
+                                }
+                                metric.show(cost)
+                            })
+                            .collect();
+                        let template = RouteTemplate {
+                            to,
+                            from,
+                            route,
+                            boosts,
+                            metric,
+                            costs,
+                            query: query.unwrap_or_default(),
+                        };
                         HtmlTemplate(template).into_response()
                     } else {
                         (
@@ -282,6 +492,33 @@ async fn route(
     }
 }
 
+/// A route as a file, written just as `galos route --format` writes it
+async fn download(db: &Database, route: &[System], format: Format) -> Response {
+    let waypoints = match Waypoint::along(db, route).await {
+        Ok(waypoints) => waypoints,
+        Err(err) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+                .into_response();
+        }
+    };
+    let mut file = Vec::new();
+    if let Err(err) = format.write(&mut file, &waypoints, Utc::now()) {
+        return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+            .into_response();
+    }
+    (
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", format.file_name()),
+            ),
+        ],
+        file,
+    )
+        .into_response()
+}
+
 // async fn create_user(
 //     // this argument tells axum to parse the request body
 //     // as JSON into a `CreateUser` type
@@ -0,0 +1,544 @@
+//! The interactive `galos`, run with no subcommand
+//!
+//! Laid out as the crate docs sketch it: where the commander is, a filter
+//! line, the systems it finds, and the stops of a route being built, with a
+//! running total of the distance to fly along it. Whatever is highlighted in
+//! the search is described beside it, bodies, stations and factions.
+//!
+//! [`App`] only holds what is on screen and says what to fetch next, as an
+//! [`Action`], so that it can be drawn and driven without a database. [`run`]
+//! is the loop doing the fetching, reading keys from the terminal.
+//!
+//! - Typing edits the filter, searching as it goes
+//! - `Up` and `Down` move through the focused list
+//! - `Enter` adds the highlighted system to the route
+//! - `Tab` moves between the search and the route
+//! - `Space` checks or unchecks a stop, `Backspace` drops it
+//! - `Esc` quits
+use async_std::task;
+use galos_db::bodies::Body;
+use galos_db::factions::SystemFaction;
+use galos_db::stations::Station;
+use galos_db::systems::System;
+use galos_db::Database;
+use std::io;
+use termion::event::Key;
+use termion::input::TermRead;
+use termion::raw::IntoRawMode;
+use termion::screen::AlternateScreen;
+use tui::backend::{Backend, TermionBackend};
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::{Color, Modifier, Style};
+use tui::text::Spans;
+use tui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use tui::{Frame, Terminal};
+
+/// How many systems a search lists, nearest first
+const RESULTS: i64 = 50;
+
+/// The list keys are going to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Focus {
+    Search,
+    Route,
+}
+
+/// What the app needs fetched, or to stop
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Nothing,
+    /// Systems named like this, see [`App::found`]
+    Search(String),
+    /// Everything about the system at this address, see [`App::describe`]
+    Describe(i64),
+    Quit,
+}
+
+/// A system, and what is in it
+#[derive(Debug)]
+pub struct Detail {
+    pub system: System,
+    pub bodies: Vec<Body>,
+    pub stations: Vec<Station>,
+    /// Named, most influential first
+    pub factions: Vec<(String, SystemFaction)>,
+}
+
+/// A stop along the route being built, flown to only when checked
+#[derive(Debug, Clone)]
+pub struct Stop {
+    pub system: System,
+    pub checked: bool,
+}
+
+#[derive(Debug)]
+pub struct App {
+    /// Where the commander is, and where the route starts from
+    pub location: Option<System>,
+    pub filter: String,
+    pub results: Vec<System>,
+    pub detail: Option<Detail>,
+    pub stops: Vec<Stop>,
+    pub focus: Focus,
+    /// The last thing to go wrong, until something else happens
+    pub message: Option<String>,
+    searched: ListState,
+    routed: ListState,
+}
+
+impl App {
+    pub fn new(location: Option<System>) -> Self {
+        App {
+            location,
+            filter: String::new(),
+            results: Vec::new(),
+            detail: None,
+            stops: Vec::new(),
+            focus: Focus::Search,
+            message: None,
+            searched: ListState::default(),
+            routed: ListState::default(),
+        }
+    }
+
+    /// Take one key, and say what to do about it
+    pub fn key(&mut self, key: Key) -> Action {
+        self.message = None;
+        match (self.focus, key) {
+            (_, Key::Esc) | (_, Key::Ctrl('c')) => Action::Quit,
+            (Focus::Search, Key::Char('\t')) => {
+                self.focus = Focus::Route;
+                // The first stop is where the route's selection starts.
+                if self.routed.selected().is_none() && !self.stops.is_empty() {
+                    self.routed.select(Some(0));
+                }
+                Action::Nothing
+            }
+            (Focus::Route, Key::Char('\t')) => {
+                self.focus = Focus::Search;
+                Action::Nothing
+            }
+
+            (Focus::Search, Key::Char('\n')) => {
+                if let Some(system) = self.highlighted() {
+                    let system = system.clone();
+                    self.stops.push(Stop { system, checked: true });
+                }
+                Action::Nothing
+            }
+            (Focus::Search, Key::Up) => self.highlight(-1),
+            (Focus::Search, Key::Down) => self.highlight(1),
+            (Focus::Search, Key::Backspace) => {
+                self.filter.pop();
+                self.search()
+            }
+            (Focus::Search, Key::Char(c)) => {
+                self.filter.push(c);
+                self.search()
+            }
+
+            (Focus::Route, Key::Up) => {
+                step(&mut self.routed, self.stops.len(), -1);
+                Action::Nothing
+            }
+            (Focus::Route, Key::Down) => {
+                step(&mut self.routed, self.stops.len(), 1);
+                Action::Nothing
+            }
+            (Focus::Route, Key::Char(' ')) => {
+                if let Some(i) = self.routed.selected() {
+                    self.stops[i].checked = !self.stops[i].checked;
+                }
+                Action::Nothing
+            }
+            (Focus::Route, Key::Backspace) | (Focus::Route, Key::Delete) => {
+                if let Some(i) = self.routed.selected() {
+                    self.stops.remove(i);
+                    let last = self.stops.len().checked_sub(1);
+                    self.routed.select(last.map(|last| i.min(last)));
+                }
+                Action::Nothing
+            }
+            _ => Action::Nothing,
+        }
+    }
+
+    /// The systems a search found, the first highlighted
+    pub fn found(&mut self, systems: Vec<System>) -> Action {
+        self.results = systems;
+        self.searched.select(None);
+        self.highlight(1)
+    }
+
+    pub fn describe(&mut self, detail: Detail) {
+        self.detail = Some(detail);
+    }
+
+    /// The system highlighted in the search
+    pub fn highlighted(&self) -> Option<&System> {
+        self.searched.selected().and_then(|i| self.results.get(i))
+    }
+
+    /// How far the route flies, in Ly
+    ///
+    /// Straight lines from the location, where it is known, through each
+    /// checked stop in turn. The jumps it takes are a route's to plot, this
+    /// only says how far there is to go.
+    pub fn hyperspace(&self) -> f64 {
+        let stops = self.stops.iter().filter(|s| s.checked).map(|s| &s.system);
+        let mut systems = self.location.iter().chain(stops);
+        let Some(mut from) = systems.next() else { return 0. };
+        let mut total = 0.;
+        for to in systems {
+            total += from.distance(to);
+            from = to;
+        }
+        total
+    }
+
+    fn search(&mut self) -> Action {
+        if self.filter.trim().is_empty() {
+            self.results.clear();
+            self.searched.select(None);
+            Action::Nothing
+        } else {
+            Action::Search(self.filter.trim().to_string())
+        }
+    }
+
+    fn highlight(&mut self, by: isize) -> Action {
+        step(&mut self.searched, self.results.len(), by);
+        match self.highlighted() {
+            Some(system) => Action::Describe(system.address),
+            None => Action::Nothing,
+        }
+    }
+
+    pub fn draw<B: Backend>(&mut self, f: &mut Frame<B>) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(3),
+                    Constraint::Length(3),
+                    Constraint::Min(6),
+                    Constraint::Length(3),
+                ]
+                .as_ref(),
+            )
+            .split(f.size());
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(
+                [Constraint::Percentage(40), Constraint::Percentage(60)]
+                    .as_ref(),
+            )
+            .split(rows[2]);
+        let lists = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [Constraint::Percentage(60), Constraint::Percentage(40)]
+                    .as_ref(),
+            )
+            .split(columns[0]);
+
+        let location = match &self.location {
+            Some(system) => system.name.as_str(),
+            None => "unknown",
+        };
+        let location = format!("Current Location: {}", location);
+        f.render_widget(Paragraph::new(location).block(bordered()), rows[0]);
+
+        let filter = format!("Filter: {}", self.filter);
+        let filter_block = focused(bordered(), self.focus == Focus::Search);
+        f.render_widget(Paragraph::new(filter).block(filter_block), rows[1]);
+
+        let results: Vec<ListItem> = self
+            .results
+            .iter()
+            .map(|system| ListItem::new(system.name.clone()))
+            .collect();
+        let results = List::new(results)
+            .block(focused(
+                bordered().title("Systems"),
+                self.focus == Focus::Search,
+            ))
+            .highlight_style(highlighted());
+        f.render_stateful_widget(results, lists[0], &mut self.searched);
+
+        let stops: Vec<ListItem> = self
+            .stops
+            .iter()
+            .map(|stop| {
+                let check = if stop.checked { 'x' } else { ' ' };
+                ListItem::new(format!("[{}] {}", check, stop.system.name))
+            })
+            .collect();
+        let stops = List::new(stops)
+            .block(focused(
+                bordered().title("Route"),
+                self.focus == Focus::Route,
+            ))
+            .highlight_style(highlighted());
+        f.render_stateful_widget(stops, lists[1], &mut self.routed);
+
+        self.draw_detail(f, columns[1]);
+
+        let totals = match &self.message {
+            Some(message) => message.clone(),
+            None => format!(
+                "Totals: Hyperspace {:.2}Ly, {} stops",
+                self.hyperspace(),
+                self.stops.iter().filter(|s| s.checked).count()
+            ),
+        };
+        f.render_widget(Paragraph::new(totals).block(bordered()), rows[3]);
+    }
+
+    fn draw_detail<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let mut lines: Vec<Spans> = Vec::new();
+        if let Some(detail) = &self.detail {
+            let system = &detail.system;
+            lines.push(Spans::from(system.name.clone()));
+            if let Some(p) = system.position {
+                lines.push(
+                    format!("({:.2}, {:.2}, {:.2})", p.x, p.y, p.z).into(),
+                );
+            }
+            if system.population > 0 {
+                lines.push(format!("Population: {}", system.population).into());
+            }
+            if let Some(security) = system.security {
+                lines.push(format!("Security: {:?}", security).into());
+            }
+            if let Some(government) = system.government {
+                lines.push(format!("Government: {:?}", government).into());
+            }
+            if let Some(allegiance) = system.allegiance {
+                lines.push(format!("Allegiance: {:?}", allegiance).into());
+            }
+            if let Some(economies) = system.economies {
+                let mut economy = format!("Economy: {:?}", economies.primary);
+                if let Some(secondary) = economies.secondary {
+                    economy.push_str(&format!("/{:?}", secondary));
+                }
+                lines.push(economy.into());
+            }
+
+            if !detail.bodies.is_empty() {
+                lines.push(Spans::from(""));
+                lines.push(Spans::from("Bodies:"));
+                for body in &detail.bodies {
+                    lines.push(format!("  {}", body.name).into());
+                }
+            }
+            if !detail.stations.is_empty() {
+                lines.push(Spans::from(""));
+                lines.push(Spans::from("Stations:"));
+                for station in &detail.stations {
+                    let mut line = format!("  {}", station.name);
+                    if let Some(ls) = station.dist_from_star_ls {
+                        line.push_str(&format!(" ({:.0}Ls)", ls));
+                    }
+                    lines.push(line.into());
+                }
+            }
+            if !detail.factions.is_empty() {
+                lines.push(Spans::from(""));
+                lines.push(Spans::from("Factions:"));
+                for (name, faction) in &detail.factions {
+                    let mut line =
+                        format!("  {} {:.1}%", name, faction.influence * 100.);
+                    if let Some(state) = &faction.state {
+                        line.push_str(&format!(" {:?}", state));
+                    }
+                    lines.push(line.into());
+                }
+            }
+        }
+        let detail = Paragraph::new(lines).block(bordered().title("System"));
+        f.render_widget(detail, area);
+    }
+}
+
+/// Move the selection of a list of `len` by `by`, staying within it
+fn step(state: &mut ListState, len: usize, by: isize) {
+    if len == 0 {
+        return state.select(None);
+    }
+    let at = match state.selected() {
+        Some(i) => (i as isize + by).clamp(0, len as isize - 1) as usize,
+        None => 0,
+    };
+    state.select(Some(at));
+}
+
+fn bordered() -> Block<'static> {
+    Block::default().borders(Borders::ALL)
+}
+
+fn focused(block: Block<'static>, focus: bool) -> Block<'static> {
+    if focus {
+        block.border_style(Style::default().fg(Color::Yellow))
+    } else {
+        block
+    }
+}
+
+fn highlighted() -> Style {
+    Style::default().add_modifier(Modifier::REVERSED)
+}
+
+/// Run the app in the terminal until it quits
+pub fn run(db: &Database, location: Option<System>) -> io::Result<()> {
+    let screen = AlternateScreen::from(io::stdout().into_raw_mode()?);
+    let mut terminal = Terminal::new(TermionBackend::new(screen))?;
+    let mut app = App::new(location);
+    let mut keys = io::stdin().keys();
+
+    loop {
+        terminal.draw(|f| app.draw(f))?;
+        let Some(key) = keys.next() else { break };
+        let mut action = app.key(key?);
+        // A search answers with a system to describe, so this goes until
+        // there is nothing left to fetch.
+        loop {
+            action = match action {
+                Action::Nothing => break,
+                Action::Quit => return Ok(()),
+                Action::Search(query) => {
+                    let near = app.location.as_ref().and_then(|s| s.position);
+                    let found = task::block_on(System::search_by_name(
+                        db, &query, near, RESULTS,
+                    ));
+                    match found {
+                        Ok(systems) => app.found(systems),
+                        Err(err) => {
+                            app.message = Some(err.to_string());
+                            Action::Nothing
+                        }
+                    }
+                }
+                Action::Describe(address) => {
+                    match task::block_on(detail(db, address)) {
+                        Ok(detail) => app.describe(detail),
+                        Err(err) => app.message = Some(err.to_string()),
+                    }
+                    Action::Nothing
+                }
+            };
+        }
+    }
+    Ok(())
+}
+
+async fn detail(
+    db: &Database,
+    address: i64,
+) -> Result<Detail, galos_db::Error> {
+    Ok(Detail {
+        system: System::fetch(db, address).await?,
+        bodies: Body::fetch_all(db, address).await?,
+        stations: Station::fetch_all(db, address).await?,
+        factions: SystemFaction::fetch_all(db, Some(address)).await?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use elite_journal::system::Coordinate;
+    use tui::backend::TestBackend;
+
+    fn system(name: &str, address: i64, x: f64) -> System {
+        System {
+            address,
+            name: name.to_string(),
+            position: Some(Coordinate { x, y: 0., z: 0. }),
+            population: 0,
+            security: None,
+            government: None,
+            allegiance: None,
+            economies: None,
+            factions: vec![],
+            updated_at: Utc::now(),
+            updated_by: "test".to_string(),
+        }
+    }
+
+    /// The screen, a line of text to a row
+    fn screen(app: &mut App) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(60, 20)).unwrap();
+        terminal.draw(|f| app.draw(f)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content
+            .chunks(buffer.area.width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol.as_str()).collect())
+            .collect()
+    }
+
+    fn shows(screen: &[String], text: &str) -> bool {
+        screen.iter().any(|line| line.contains(text))
+    }
+
+    #[test]
+    fn typing_searches() {
+        let mut app = App::new(None);
+        assert_eq!(app.key(Key::Char('S')), Action::Search("S".into()));
+        assert_eq!(app.key(Key::Backspace), Action::Nothing);
+        assert_eq!(app.key(Key::Char('S')), Action::Search("S".into()));
+        assert_eq!(app.key(Key::Char('o')), Action::Search("So".into()));
+        let found =
+            vec![system("Sol", 10477373803, 0.), system("Sothis", 1, 9.)];
+        assert_eq!(app.found(found), Action::Describe(10477373803));
+        assert_eq!(app.key(Key::Down), Action::Describe(1));
+        assert_eq!(app.key(Key::Esc), Action::Quit);
+
+        let screen = screen(&mut app);
+        assert!(shows(&screen, "Current Location: unknown"));
+        assert!(shows(&screen, "Filter: So"));
+        assert!(shows(&screen, "Sothis"));
+    }
+
+    #[test]
+    fn the_route_totals_checked_stops() {
+        let mut app = App::new(Some(system("Sol", 0, 0.)));
+        app.found(vec![system("Near", 1, 3.), system("Far", 2, 10.)]);
+        app.key(Key::Char('\n'));
+        app.key(Key::Down);
+        app.key(Key::Char('\n'));
+        assert_eq!(app.hyperspace(), 10.);
+
+        app.key(Key::Char('\t'));
+        app.key(Key::Down);
+        app.key(Key::Char(' '));
+        assert_eq!(app.hyperspace(), 3.);
+
+        let screen = screen(&mut app);
+        assert!(shows(&screen, "Current Location: Sol"));
+        assert!(shows(&screen, "[x] Near"));
+        assert!(shows(&screen, "[ ] Far"));
+        assert!(shows(&screen, "Totals: Hyperspace 3.00Ly, 1 stops"));
+
+        app.key(Key::Backspace);
+        assert_eq!(app.stops.len(), 1);
+        assert_eq!(app.hyperspace(), 3.);
+    }
+
+    #[test]
+    fn the_highlighted_system_is_described() {
+        let mut app = App::new(None);
+        let mut sol = system("Sol", 10477373803, 0.);
+        sol.population = 22780919531;
+        app.describe(Detail {
+            system: sol,
+            bodies: vec![],
+            stations: vec![],
+            factions: vec![],
+        });
+        let screen = screen(&mut app);
+        assert!(shows(&screen, "Population: 22780919531"));
+    }
+}
@@ -0,0 +1,251 @@
+//! Everything EDDN has said, kept to be said again
+//!
+//! `galos-sync eddn --archive <DIR>` writes each envelope as it arrives, a
+//! line of JSON to a message, into gzipped files for each hour:
+//!
+//! ```notrust
+//! eddn-2026101811-000.ndjson.gz
+//! eddn-2026101812-000.ndjson.gz
+//! eddn-2026101812-001.ndjson.gz
+//! ```
+//!
+//! and `galos-sync replay` reads them back through the same ingest, so what
+//! arrived while an ingest was broken can be ingested again once it is fixed,
+//! and an ingest can be tested end to end without a network.
+//!
+//! Each line says when the envelope was received and which schema it is,
+//! alongside the envelope itself, exactly as EDDN sent it, so a replay can
+//! pick out a time or a schema without reading any envelope it then skips,
+//! and reads what a newer parser would have made of it.
+//!
+//! A process started again within the hour starts a file of its own, rather
+//! than appending to one a crash may have left cut off partway through.
+use crate::dumps::{self, Records};
+use chrono::{DateTime, Utc};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// An envelope, as it is archived
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record<T> {
+    pub received: DateTime<Utc>,
+    /// `journal`, `commodity` and so on, as EDDN names its schemas
+    pub schema: String,
+    pub envelope: T,
+}
+
+/// A record of an envelope exactly as EDDN sent it
+pub type Raw = Record<Box<RawValue>>;
+
+/// An archive being written, a file to an hour
+pub struct Archive {
+    dir: PathBuf,
+    /// The hour being written, and its file
+    file: Option<(String, GzEncoder<File>)>,
+}
+
+impl Archive {
+    pub fn new<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Archive { dir: dir.as_ref().to_path_buf(), file: None })
+    }
+
+    /// A new file for `hour`, numbered after any already there
+    fn create(&self, hour: &str) -> io::Result<File> {
+        for n in 0.. {
+            let name = format!("eddn-{}-{:03}.ndjson.gz", hour, n);
+            let path = self.dir.join(name);
+            match OpenOptions::new().write(true).create_new(true).open(path) {
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+                file => return file,
+            }
+        }
+        unreachable!("ran out of numbers")
+    }
+
+    /// Append a record to its hour's file
+    ///
+    /// Flushed as it is written, so a process killed partway loses nothing
+    /// written before, and what is written can be read while it is still
+    /// being written to.
+    pub fn write<T: Serialize>(
+        &mut self,
+        record: &Record<T>,
+    ) -> io::Result<()> {
+        let hour = record.received.format("%Y%m%d%H").to_string();
+        if self.file.as_ref().map(|(open, _)| open) != Some(&hour) {
+            if let Some((_, gz)) = self.file.take() {
+                gz.finish()?;
+            }
+            let file = self.create(&hour)?;
+            self.file =
+                Some((hour, GzEncoder::new(file, Compression::default())));
+        }
+        let (_, gz) = self.file.as_mut().expect("opened above");
+        serde_json::to_writer(&mut *gz, record)?;
+        gz.write_all(b"\n")?;
+        gz.flush()
+    }
+}
+
+/// The records of an archived file, in the order they were received
+///
+/// A file cut off by a crash, or still being written, ends in an error after
+/// the last whole record.
+pub fn read<P: AsRef<Path>>(
+    path: P,
+) -> io::Result<Records<Box<dyn Read>, Raw>> {
+    let path = path.as_ref();
+    let file = File::open(path)?;
+    Ok(Records::new(dumps::open(path, file)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::envelope::{Envelope, Message};
+    use chrono::TimeZone;
+    use elite_journal::entry::{Entry, Event};
+
+    fn dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "galos-archive-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn record(hour: u32, minute: u32) -> Raw {
+        let json = r#"{"message":{"event":"FSDJump"}}"#.to_string();
+        Record {
+            received: Utc
+                .with_ymd_and_hms(2026, 10, 18, hour, minute, 0)
+                .unwrap(),
+            schema: "journal".into(),
+            envelope: RawValue::from_string(json).unwrap(),
+        }
+    }
+
+    /// When each record in a file was received, as far as the file can be
+    /// read
+    fn received(path: PathBuf) -> Vec<DateTime<Utc>> {
+        read(path)
+            .unwrap()
+            .map_while(Result::ok)
+            .map(|record| record.unwrap().received)
+            .collect()
+    }
+
+    #[test]
+    fn records_are_filed_by_the_hour() {
+        let dir = dir("hours");
+        let mut archive = Archive::new(&dir).unwrap();
+        for r in [record(11, 0), record(11, 59), record(12, 0)] {
+            archive.write(&r).unwrap();
+        }
+        drop(archive);
+
+        assert_eq!(
+            received(dir.join("eddn-2026101811-000.ndjson.gz")),
+            [record(11, 0).received, record(11, 59).received]
+        );
+        assert_eq!(
+            received(dir.join("eddn-2026101812-000.ndjson.gz")),
+            [record(12, 0).received]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Each record can be read back as soon as it is written, however the
+    /// process then ends
+    #[test]
+    fn nothing_written_is_held_back() {
+        let dir = dir("flushed");
+        let path = dir.join("eddn-2026101811-000.ndjson.gz");
+        let mut archive = Archive::new(&dir).unwrap();
+        for minute in 0..3 {
+            archive.write(&record(11, minute)).unwrap();
+            assert_eq!(received(path.clone()).len(), minute as usize + 1);
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Started again within the hour, after a crash left the hour's file cut
+    /// off, a new file is started rather than one that can't be read past
+    /// the cut being appended to
+    #[test]
+    fn a_restart_starts_a_file_of_its_own() {
+        let dir = dir("restart");
+        let mut crashed = Archive::new(&dir).unwrap();
+        crashed.write(&record(11, 0)).unwrap();
+        // Killed, its gzip never finished.
+        std::mem::forget(crashed);
+
+        Archive::new(&dir).unwrap().write(&record(11, 30)).unwrap();
+        assert_eq!(
+            received(dir.join("eddn-2026101811-000.ndjson.gz")),
+            [record(11, 0).received]
+        );
+        assert_eq!(
+            received(dir.join("eddn-2026101811-001.ndjson.gz")),
+            [record(11, 30).received]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// An archive of one message of each schema galos takes, as EDDN sent
+    /// them, read back into what each is ingested as
+    #[test]
+    fn archived_envelopes_replay() {
+        let records: Vec<_> = read("src/fixtures/eddn.ndjson")
+            .unwrap()
+            .map(|record| record.unwrap().unwrap())
+            .collect();
+        let envelopes: Vec<Envelope> = records
+            .iter()
+            .map(|record| serde_json::from_str(record.envelope.get()).unwrap())
+            .collect();
+        for (record, envelope) in records.iter().zip(&envelopes) {
+            assert_eq!(record.schema, envelope.schema());
+        }
+
+        let messages: Vec<Message> = envelopes
+            .iter()
+            .map(|envelope| envelope.message().unwrap())
+            .collect();
+        let Message::Journal(Entry { event: Event::FsdJump(jump), .. }) =
+            &messages[0]
+        else {
+            panic!("not a jump: {:?}", messages[0]);
+        };
+        assert_eq!(jump.system.name, "Sol");
+        let Message::Commodity(market) = &messages[1] else {
+            panic!("not a market: {:?}", messages[1]);
+        };
+        assert_eq!(market.event.commodities.len(), 2);
+        let Message::Outfitting(outfitting) = &messages[2] else {
+            panic!("not outfitting: {:?}", messages[2]);
+        };
+        assert_eq!(outfitting.modules.len(), 4);
+        let Message::Shipyard(shipyard) = &messages[3] else {
+            panic!("not a shipyard: {:?}", messages[3]);
+        };
+        assert_eq!(shipyard.ships, ["krait_mkii", "python", "sidewinder"]);
+        let Message::Exploration(honk) = &messages[4] else {
+            panic!("not exploration: {:?}", messages[4]);
+        };
+        assert_eq!(honk.event.system_address(), 10477373803);
+        assert!(matches!(
+            &messages[5],
+            Message::Journal(Entry { event: Event::NavRoute(_), .. })
+        ));
+        assert_eq!(envelopes[5].header.software_version, "5.12.1");
+    }
+}
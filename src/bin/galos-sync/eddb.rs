@@ -1,11 +1,14 @@
-use crate::Run;
+use crate::{checkpoint, load, resume, Run, BATCH, CHECKPOINT};
 use async_std::task;
 use elite_journal::system::Coordinate;
+use galos_db::bulk::{Batch, Report};
+use galos_db::imports::{Growth, Resume, Source};
 use galos_db::{
     systems::{Economies, System},
     Database,
 };
 use indicatif::{ProgressBar, ProgressStyle};
+use std::path::Path;
 use structopt::StructOpt;
 
 #[derive(StructOpt, Debug)]
@@ -14,10 +17,35 @@ pub struct Cli {
     // TODO: Default, when not provided?
     #[structopt(name = "PATH")]
     pub path: String,
+    #[structopt(
+        long,
+        help = "Import the dump again, even if it was imported already"
+    )]
+    pub force: bool,
+    #[structopt(
+        long,
+        help = "Upsert each system on its own rather than copying them in \
+                by the batch"
+    )]
+    pub row_by_row: bool,
 }
 
 impl Run for Cli {
     fn run(&self, db: &Database) {
+        let path = Path::new(&self.path);
+        let (source, from) =
+            match resume(db, path, Growth::Replaced, self.force) {
+                Ok((source, Resume::From(from))) => (source, from as usize),
+                Ok((_, Resume::Skip)) => {
+                    return println!(
+                        "{} is unchanged since it was imported, --force to \
+                     import it again",
+                        self.path
+                    );
+                }
+                Err(err) => return eprintln!("{}", err),
+            };
+
         let mut dump = match eddb::Dump::csv(&self.path) {
             Ok(d) => d,
             Err(err) => panic!("{}", err),
@@ -28,7 +56,16 @@ impl Run for Cli {
             .template("[{elapsed_precise}/{eta_precise}] {bar:40} {pos:>7}/{len:7} ({percent}%) {msg}")
             .unwrap()
             .progress_chars("##-"));
-        for result in bar.wrap_iter(dump.into_iter()) {
+        let len = dump.len();
+        bar.set_position(from as u64);
+        if !self.row_by_row {
+            return self.bulk(db, dump, &source, from, &bar);
+        }
+        for (n, result) in dump.into_iter().enumerate().skip(from) {
+            bar.inc(1);
+            if n % CHECKPOINT == 0 {
+                checkpoint(db, &source, n as i64, false);
+            }
             if let Ok(system) = result {
                 if let Some(address) = system.ed_system_address {
                     let position = Coordinate {
@@ -63,5 +100,62 @@ impl Run for Cli {
                 }
             }
         }
+        checkpoint(db, &source, len as i64, true);
+    }
+}
+
+impl Cli {
+    /// Copy the systems in a batch at a time, checkpointing after each
+    fn bulk(
+        &self,
+        db: &Database,
+        dump: eddb::Dump,
+        source: &Source,
+        from: usize,
+        bar: &ProgressBar,
+    ) {
+        let len = dump.len() as usize;
+        let mut batch = Batch::new();
+        let mut report = Report::default();
+        for (n, result) in dump.into_iter().enumerate().skip(from) {
+            bar.inc(1);
+            match result {
+                Ok(system) if system.ed_system_address.is_some() => {
+                    batch.system(
+                        system.ed_system_address.unwrap() as i64,
+                        &system.name,
+                        Some(Coordinate {
+                            x: system.coords.x,
+                            y: system.coords.y,
+                            z: system.coords.z,
+                        }),
+                        None,
+                        system.population,
+                        system.security,
+                        system.government,
+                        system.allegiance,
+                        Economies::new(system.primary_economy, None),
+                        system.updated_at,
+                        "EDDB dump",
+                    );
+                }
+                _ => batch.reject(),
+            }
+            if batch.len() >= BATCH {
+                match load(db, &mut batch) {
+                    Ok(loaded) => report += loaded,
+                    Err(err) => return bar.println(err),
+                }
+                checkpoint(db, source, n as i64 + 1, false);
+                bar.set_message(format!("[EDDB] {}", report));
+            }
+        }
+        match load(db, &mut batch) {
+            Ok(loaded) => report += loaded,
+            Err(err) => return bar.println(err),
+        }
+        checkpoint(db, source, len as i64, true);
+        bar.finish();
+        println!("Systems: {}.", report);
     }
 }
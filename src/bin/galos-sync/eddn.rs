@@ -1,17 +1,23 @@
 #![cfg(unix)]
 use crate::Run;
 use async_std::task;
-use eddn::{subscribe, Message, URL};
-use elite_journal::entry::incremental::exploration::ScanTarget;
-use elite_journal::entry::market::Market as JournalMarket;
+use chrono::Utc;
+use eddn::URL;
 use elite_journal::entry::route::NavRoute;
-use elite_journal::entry::{Entry, Event};
-use elite_journal::system::System as JournalSystem;
-use galos_db::{
-    barycenters::Barycenter, bodies::Body, markets::Market, stars::Star,
-    stations::Station, systems::System, Database,
-};
-use std::time::Duration;
+use elite_journal::entry::Event;
+use galos::archive::{Archive, Raw, Record};
+use galos::envelope::{Envelope, Message};
+use galos::feed::subscribe;
+use galos::ingest;
+use galos::pool::Pool;
+use galos::spool::Spool;
+use galos::validate::{self, Software};
+use galos_db::quarantine::Quarantined;
+use galos_db::{Database, Error};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
 use structopt::StructOpt;
 use tracing::{info, warn};
 
@@ -21,6 +27,26 @@ use tracing::{info, warn};
 /// without one, so two minutes of quiet is not EDDN being quiet.
 const STALL: Duration = Duration::from_secs(120);
 
+/// How long a database found unavailable is left before it is tried again
+const RETRY: Duration = Duration::from_secs(5);
+
+/// How long a write waits on a lock, a table held by a migration say, before
+/// the database is taken as unavailable and the message spooled
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long any one statement may take, well past what a message's writes
+/// ever do
+const STATEMENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How many spooled messages are drained for each that arrives, enough to
+/// catch up on an hour's outage in a few minutes without leaving new ones
+/// waiting on old
+const DRAIN: usize = 100;
+
+/// How many messages each worker holds waiting before the socket is left
+/// unread, about two seconds of a busy hour between four of them
+const QUEUE: usize = 16;
+
 #[derive(StructOpt, Debug)]
 pub struct Cli {
     // Type as a URL? ZMQ doesn't bother :(
@@ -32,308 +58,376 @@ pub struct Cli {
         help = "Seconds of silence before the connection is replaced, or 0 to leave it alone"
     )]
     pub stall: Option<u64>,
+
+    #[structopt(
+        long = "archive",
+        help = "Keep every message in gzipped hourly files here, for replay"
+    )]
+    pub archive: Option<PathBuf>,
+
+    #[structopt(
+        long = "spool",
+        default_value = "eddn-spool",
+        help = "Keep messages here while the database is unavailable"
+    )]
+    pub spool: PathBuf,
+    #[structopt(
+        long = "spool-limit",
+        default_value = "1024",
+        help = "MiB the spool may hold, past which messages are dropped"
+    )]
+    pub spool_limit: u64,
+
+    #[structopt(
+        long = "workers",
+        default_value = "4",
+        help = "How many messages are written at once"
+    )]
+    pub workers: usize,
+
+    #[structopt(
+        long = "bad-software",
+        help = "Quarantine messages from this software, NAME or NAME@VERSION"
+    )]
+    pub bad_software: Vec<Software>,
     // TODO: Filters?
 }
 
 impl Run for Cli {
     fn run(&self, db: &Database) {
+        let db = &db.with_timeouts(LOCK_TIMEOUT, STATEMENT_TIMEOUT);
         let stall = match self.stall {
             None => Some(STALL),
             Some(0) => None,
             Some(secs) => Some(Duration::from_secs(secs)),
         };
 
-        for result in subscribe(&self.url, stall) {
-            if let Ok(envelop) = result {
-                process_message(
-                    db,
-                    envelop.message,
-                    envelop.header.uploader_id,
-                );
-            } else if let Err(err) = result {
-                warn!(error = %err, "unreadable message");
-            }
-        }
-    }
-}
+        let mut archive = match self.archive.as_ref().map(Archive::new) {
+            Some(Ok(archive)) => Some(archive),
+            Some(Err(err)) => return eprintln!("bad archive: {}", err),
+            None => None,
+        };
 
-fn process_message(db: &Database, message: Message, user: String) {
-    task::block_on(async {
-        match message {
-            Message::Journal(entry) => match entry.event {
-                Event::Scan(scan) => {
-                    let mut system = JournalSystem::new(
-                        scan.system_address,
-                        &scan.star_system,
-                    );
-                    system.pos = Some(scan.star_pos);
-                    match System::from_journal(
-                        db,
-                        entry.timestamp,
-                        &user,
-                        &system,
-                    )
-                    .await
-                    {
-                        Ok(_) => info!(system = %system.name, "scan"),
-                        Err(err) => {
-                            warn!(system = %system.name, error = %err, "scan")
-                        }
-                    }
+        let spool = match Spool::open(&self.spool, self.spool_limit << 20) {
+            Ok(spool) => spool,
+            Err(err) => return eprintln!("bad spool: {}", err),
+        };
+        if !spool.is_empty() {
+            info!(
+                spooled = spool.len(),
+                bytes = spool.size(),
+                "draining spool"
+            );
+        }
+        let spooled =
+            Mutex::new(Spooled { spool, retry: Instant::now(), dropped: 0 });
 
-                    match scan.target {
-                        ScanTarget::Star(star) => match Star::from_journal(
-                            db,
-                            entry.timestamp,
-                            &user,
-                            &star,
-                            scan.system_address,
-                        )
-                        .await
-                        {
-                            Ok(_) => {
-                                info!(star = %star.name, "scan")
-                            }
-                            Err(err) => {
-                                warn!(star = %star.name, error = %err, "scan")
-                            }
-                        },
-                        ScanTarget::Body(body) => match Body::from_journal(
-                            db,
-                            entry.timestamp,
-                            &user,
-                            &body,
-                            scan.system_address,
-                        )
-                        .await
-                        {
-                            Ok(_) => {
-                                info!(body = %body.name, "scan")
-                            }
-                            Err(err) => {
-                                warn!(body = %body.name, error = %err, "scan")
-                            }
-                        },
+        thread::scope(|scope| {
+            let pool = Pool::new(scope, self.workers, QUEUE, |job| {
+                write(db, &self.bad_software, &spooled, job)
+            });
+            for result in subscribe(&self.url, stall) {
+                let raw = match result {
+                    Ok(raw) => raw,
+                    Err(err) => {
+                        warn!(error = %err, "unreadable message");
+                        continue;
                     }
-                }
-                // A barycenter is not a body and is not drawn. It is stored so
-                // that a body naming it as an ancestor can be placed where it
-                // belongs rather than at the middle of its system.
-                Event::ScanBaryCentre(scan) => {
-                    let mut system = JournalSystem::new(
-                        scan.system_address,
-                        &scan.star_system,
-                    );
-                    system.pos = Some(scan.star_pos);
-                    match System::from_journal(
-                        db,
-                        entry.timestamp,
-                        &user,
-                        &system,
-                    )
-                    .await
-                    {
-                        Ok(_) => {
-                            info!(system = %system.name, "scan barycenter")
-                        }
-                        Err(err) => {
-                            warn!(system = %system.name, error = %err, "scan barycenter")
-                        }
-                    }
-
-                    match Barycenter::from_journal(
-                        db,
-                        entry.timestamp,
-                        &user,
-                        &scan,
-                    )
-                    .await
-                    {
-                        // A barycenter has no name of its own, so the id it is
-                        // known by within its system is said along with the
-                        // system, neither meaning much without the other.
-                        Ok(_) => {
-                            info!(system = %system.name, barycenter = scan.body_id, "scan barycenter")
-                        }
-                        Err(err) => {
-                            warn!(system = %system.name, barycenter = scan.body_id, error = %err, "scan barycenter")
-                        }
+                };
+                let envelope = serde_json::from_str::<Envelope>(raw.get());
+                let record = Record {
+                    received: Utc::now(),
+                    schema: envelope
+                        .as_ref()
+                        .map_or("", |envelope| envelope.schema())
+                        .to_string(),
+                    envelope: raw,
+                };
+                // Kept as it came, before it is processed, so a message that
+                // an ingest bug chokes on is still there to replay once it's
+                // fixed.
+                if let Some(archive) = archive.as_mut() {
+                    if let Err(err) = archive.write(&record) {
+                        warn!(error = %err, "unarchived message");
                     }
                 }
-                Event::Location(e) => {
-                    match System::from_journal(
-                        db,
-                        entry.timestamp,
-                        &user,
-                        &e.system,
-                    )
-                    .await
-                    {
-                        Ok(_) => info!(system = %e.system.name, "location"),
-                        Err(err) => {
-                            warn!(system = %e.system.name, error = %err, "location")
-                        }
-                    }
-
-                    if let Some(ref body) = e.body {
-                        match Body::from_journal(
-                            db,
-                            entry.timestamp,
-                            &user,
-                            &body,
-                            e.system.address,
-                        )
-                        .await
-                        {
-                            Ok(_) => info!(body = %body.name, "location"),
-                            Err(err) => {
-                                warn!(body = %body.name, error = %err, "location")
-                            }
-                        }
+                let read = envelope.and_then(|envelope| {
+                    envelope.message().map(|message| (envelope, message))
+                });
+                let (envelope, message) = match read {
+                    Ok(read) => read,
+                    Err(err) => {
+                        warn!(error = %err, "unreadable message");
+                        continue;
                     }
+                };
 
-                    if let Some(ref station) = e.station {
-                        match Station::from_journal(
-                            db,
-                            entry.timestamp,
-                            &user,
-                            &station,
-                            e.system.address,
-                        )
-                        .await
-                        {
-                            Ok(_) => {
-                                info!(station = %station.name, "location")
-                            }
-                            Err(err) => {
-                                warn!(station = %station.name, error = %err, "location")
-                            }
-                        }
-                    }
+                let key = key(&message);
+                let job = Job { record, envelope, message };
+                if pool.send(&key, job).is_err() {
+                    return eprintln!("an ingest worker died");
                 }
-                Event::Docked(e) => {
-                    let system =
-                        JournalSystem::new(e.system_address, &e.system_name);
-                    match System::from_journal(
-                        db,
-                        entry.timestamp,
-                        &user,
-                        &system,
-                    )
-                    .await
-                    {
-                        Ok(_) => info!(system = %system.name, "docked"),
-                        Err(err) => {
-                            warn!(system = %system.name, error = %err, "docked")
-                        }
-                    }
 
-                    match Station::from_journal(
-                        db,
-                        entry.timestamp,
-                        &user,
-                        &e.station,
-                        e.system_address,
-                    )
-                    .await
-                    {
-                        Ok(_) => {
-                            info!(station = %e.station.name, "docked")
-                        }
-                        Err(err) => {
-                            warn!(station = %e.station.name, error = %err, "docked")
-                        }
-                    }
-                }
-                Event::FsdJump(e) => {
-                    match System::from_journal(
-                        db,
-                        entry.timestamp,
-                        &user,
-                        &e.system,
-                    )
-                    .await
-                    {
-                        Ok(_) => info!(system = %e.system.name, "fsd jump"),
-                        Err(err) => {
-                            warn!(system = %e.system.name, error = %err, "fsd jump")
-                        }
-                    }
-                }
-                Event::NavRoute(NavRoute::Route(destinations)) => {
-                    for destination in destinations {
-                        match System::create(
-                            db,
-                            destination.system_address as i64,
-                            &destination.star_system,
-                            Some(destination.star_pos),
-                            Some(destination.star_class),
-                            None,
-                            None,
-                            None,
-                            None,
-                            None,
-                            entry.timestamp,
-                            &user,
-                        )
-                        .await
-                        {
-                            Ok(_) => {
-                                info!(system = %destination.star_system, "nav route")
-                            }
-                            Err(err) => {
-                                warn!(system = %destination.star_system, error = %err, "nav route")
-                            }
-                        }
-                    }
-                }
-                _ => {}
-            },
-            Message::Commodity(
-                ref e @ Entry { event: ref m @ JournalMarket { .. }, .. },
-            ) => {
-                // A market message cannot name its system by address, only
-                // by name, so the system may well be one we have never seen.
-                // Record the prices regardless. The station and the link to
-                // the system follow whenever the system itself turns up.
-                if let Ok(system) =
-                    System::fetch_by_name(db, &m.system_name).await
+                let mut spooled = spooled.lock().expect("a worker panicked");
+                if !spooled.spool.is_empty() && Instant::now() >= spooled.retry
                 {
-                    match Station::create(
-                        db,
-                        e.timestamp,
-                        &user,
-                        system.address,
-                        &m.station_name,
-                    )
-                    .await
-                    {
-                        Ok(_) => {
-                            info!(station = %m.station_name, "commodity")
-                        }
-                        Err(err) => {
-                            warn!(station = %m.station_name, error = %err, "commodity")
-                        }
-                    }
+                    spooled.drain(db, &self.bad_software);
                 }
+            }
+        });
+    }
+}
 
-                match Market::from_journal(db, e.timestamp, &m).await {
-                    // A market can arrive before anything that would create
-                    // the system it names, and is recorded with no system to
-                    // belong to until that turns up. The name it gave is all
-                    // there is to go on in the meantime.
-                    Ok(market) => info!(
-                        market = %m.station_name,
-                        system = %m.system_name,
-                        orphan = market.system_address.is_none(),
-                        "commodity",
-                    ),
-                    Err(err) => {
-                        warn!(market = %m.station_name, error = %err, "commodity")
+/// A message for a worker to write, and the record to spool if it can't
+struct Job {
+    record: Raw,
+    envelope: Envelope,
+    message: Message,
+}
+
+/// What a message is ordered by: messages with the same key are written in
+/// the order they arrived
+#[derive(Hash)]
+enum Key {
+    Address(i64),
+    /// A market names its system, but doesn't give its address
+    Name(String),
+    /// Nothing is written of it
+    None,
+}
+
+fn key(message: &Message) -> Key {
+    match message {
+        Message::Journal(entry) => match &entry.event {
+            Event::Scan(scan) => Key::Address(scan.system_address),
+            Event::ScanBaryCentre(scan) => Key::Address(scan.system_address),
+            Event::Location(e) => Key::Address(e.system.address),
+            Event::Docked(e) => Key::Address(e.system_address),
+            Event::FsdJump(e) => Key::Address(e.system.address),
+            // A route is ordered by where it starts, its other systems
+            // being no more than their names, classes and positions, which
+            // don't change.
+            Event::NavRoute(NavRoute::Route(destinations)) => {
+                destinations.first().map_or(Key::None, |first| {
+                    Key::Address(first.system_address as i64)
+                })
+            }
+            _ => Key::None,
+        },
+        Message::Commodity(entry) => Key::Name(entry.event.system_name.clone()),
+        Message::Exploration(entry) => {
+            Key::Address(entry.event.system_address())
+        }
+        Message::Outfitting(outfitting) => {
+            Key::Name(outfitting.system_name.clone())
+        }
+        Message::Shipyard(shipyard) => Key::Name(shipyard.system_name.clone()),
+        Message::Other => Key::None,
+    }
+}
+
+/// Write a message out, or spool it where the database can't be reached
+///
+/// Anything already spooled arrived before it, so while the spool isn't
+/// empty it is spooled too, rather than written out of order.
+fn write(db: &Database, bad: &[Software], spooled: &Mutex<Spooled>, job: Job) {
+    let Job { record, envelope, message } = job;
+    if spooled.lock().expect("a worker panicked").spool.is_empty() {
+        match receive(db, bad, &record, &envelope, message) {
+            Ok(()) => return,
+            Err(err) => warn!(error = %err, "database unavailable, spooling"),
+        }
+        spooled.lock().expect("a worker panicked").retry =
+            Instant::now() + RETRY;
+    }
+    spooled.lock().expect("a worker panicked").push(&record);
+}
+
+/// The spool, shared between the workers filling it and the drain emptying it
+struct Spooled {
+    spool: Spool,
+    /// When the database is next tried
+    retry: Instant,
+    dropped: u64,
+}
+
+impl Spooled {
+    fn push(&mut self, record: &Raw) {
+        let line = match serde_json::to_string(record) {
+            Ok(line) => line,
+            Err(err) => return warn!(error = %err, "unspoolable message"),
+        };
+        let spool = &mut self.spool;
+        match spool.push(&line) {
+            Ok(true) if spool.len().is_multiple_of(1000) => warn!(
+                spooled = spool.len(),
+                bytes = spool.size(),
+                "database still unavailable"
+            ),
+            Ok(true) => {}
+            Ok(false) => {
+                self.dropped += 1;
+                warn!(
+                    dropped = self.dropped,
+                    bytes = spool.size(),
+                    "spool full"
+                );
+            }
+            Err(err) => warn!(error = %err, "unspooled message"),
+        }
+    }
+
+    /// Write out up to [`DRAIN`] spooled messages, oldest first, until the
+    /// database is found still unavailable
+    ///
+    /// Workers wait on the spool meanwhile, so nothing is written alongside
+    /// a spooled message of the same system.
+    fn drain(&mut self, db: &Database, bad: &[Software]) {
+        if let Err(err) = self.try_drain(db, bad) {
+            warn!(error = %err, "unreadable spool");
+        }
+    }
+
+    fn try_drain(
+        &mut self,
+        db: &Database,
+        bad: &[Software],
+    ) -> std::io::Result<()> {
+        let spool = &mut self.spool;
+        for _ in 0..DRAIN {
+            let line = match spool.peek()? {
+                Some(line) => line,
+                None => break,
+            };
+            let read = serde_json::from_str::<Raw>(&line).and_then(|record| {
+                let (envelope, message) = read(&record)?;
+                Ok((record, envelope, message))
+            });
+            match read {
+                Ok((record, envelope, message)) => {
+                    let written = receive(db, bad, &record, &envelope, message);
+                    if let Err(err) = written {
+                        warn!(
+                            error = %err,
+                            spooled = spool.len(),
+                            "database still unavailable"
+                        );
+                        self.retry = Instant::now() + RETRY;
+                        return Ok(());
                     }
                 }
+                Err(err) => warn!(error = %err, "unreadable spooled message"),
             }
-            _ => {}
+            spool.pop()?;
         }
+        if spool.is_empty() {
+            info!("spool drained");
+        }
+        Ok(())
+    }
+}
+
+/// The envelope a record holds, and its message read as its schema says
+pub fn read(record: &Raw) -> serde_json::Result<(Envelope, Message)> {
+    let envelope: Envelope = serde_json::from_str(record.envelope.get())?;
+    let message = envelope.message()?;
+    Ok((envelope, message))
+}
+
+/// Write the message `record` holds out, unless it can't be trusted, in
+/// which case it is quarantined with why, as it arrived
+///
+/// Fails only where the database could not be reached, whether to check the
+/// message, write it, or quarantine it.
+pub fn receive(
+    db: &Database,
+    bad: &[Software],
+    record: &Raw,
+    envelope: &Envelope,
+    message: Message,
+) -> Result<(), Error> {
+    let received = record.received;
+    task::block_on(async {
+        let header = &envelope.header;
+        let software = validate::software(
+            bad,
+            &header.software_name,
+            &header.software_version,
+        );
+        let reason = match (software, &message) {
+            (Some(reason), _) => Some(reason),
+            (None, Message::Journal(entry)) => {
+                validate::journal(db, entry, received).await?
+            }
+            (None, Message::Commodity(entry)) => {
+                validate::timestamp(entry.timestamp, received)
+            }
+            (None, Message::Exploration(entry)) => {
+                validate::timestamp(entry.timestamp, received)
+            }
+            // A station's lists say when they were read, and are held to it
+            // as a market is.
+            (None, Message::Outfitting(outfitting)) => {
+                validate::timestamp(outfitting.timestamp, received)
+            }
+            (None, Message::Shipyard(shipyard)) => {
+                validate::timestamp(shipyard.timestamp, received)
+            }
+            (None, Message::Other) => None,
+        };
+        let Some(reason) = reason else {
+            return process(db, envelope, message).await;
+        };
+
+        let software =
+            format!("{} {}", header.software_name, header.software_version);
+        let quarantined = Quarantined::create(
+            db,
+            received,
+            envelope.schema(),
+            &header.uploader_id,
+            &software,
+            &reason,
+            record.envelope.get(),
+        )
+        .await;
+        match quarantined {
+            Ok(id) => warn!(id, %reason, "quarantined"),
+            Err(err) if err.is_unavailable() => return Err(err),
+            Err(err) => warn!(error = %err, %reason, "unquarantined message"),
+        }
+        Ok(())
     })
 }
+
+/// Write a message out as its uploader's, unchecked, saying so only where
+/// the database could not be reached
+pub fn process_message(
+    db: &Database,
+    envelope: &Envelope,
+    message: Message,
+) -> Result<(), Error> {
+    task::block_on(process(db, envelope, message))
+}
+
+async fn process(
+    db: &Database,
+    envelope: &Envelope,
+    message: Message,
+) -> Result<(), Error> {
+    let user = &envelope.header.uploader_id;
+    match message {
+        Message::Journal(entry) => ingest::journal(db, entry, user).await,
+        Message::Commodity(entry) => ingest::commodity(db, &entry, user).await,
+        Message::Exploration(entry) => {
+            ingest::exploration(db, &entry, user).await
+        }
+        Message::Outfitting(outfitting) => {
+            ingest::outfitting(db, &outfitting, user).await
+        }
+        Message::Shipyard(shipyard) => {
+            ingest::shipyard(db, &shipyard, user).await
+        }
+        Message::Other => Ok(()),
+    }
+}
@@ -1,10 +1,22 @@
-use crate::Run;
+use crate::{checkpoint, load, resume, Run, BATCH, CHECKPOINT};
 use async_std::task;
 use chrono::offset::Utc;
+use elite_journal::entry::incremental::exploration::ScanTarget;
+use elite_journal::entry::Event;
+use elite_journal::system::Coordinate;
+use galos::dumps::{self, events, records, Kind, Records};
+use galos::ingest;
+use galos_db::bulk::{Batch, Report};
+use galos_db::imports::{Growth, Resume, Source};
 use galos_db::systems::{Economies, System};
-use galos_db::Database;
+use galos_db::{Database, Error};
 use indicatif::{ProgressBar, ProgressStyle};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
 use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
 use structopt::StructOpt;
 
 #[derive(StructOpt, Debug)]
@@ -18,6 +30,23 @@ pub struct FileCli {
     // TODO: Type as a path.
     #[structopt(name = "PATH")]
     pub path: String,
+    #[structopt(
+        long,
+        help = "Which dump it is, systems, bodies, stations or populated, \
+                when its name doesn't say"
+    )]
+    pub kind: Option<Kind>,
+    #[structopt(
+        long,
+        help = "Import the dump again, even if it was imported already"
+    )]
+    pub force: bool,
+    #[structopt(
+        long,
+        help = "Upsert each system or body on its own rather than copying \
+                them in by the batch"
+    )]
+    pub row_by_row: bool,
 }
 
 #[derive(StructOpt, Debug)]
@@ -100,11 +129,7 @@ impl Cli {
 impl Run for Cli {
     fn run(&self, db: &Database) {
         match self {
-            Cli::File(fc) => {
-                let systems = edsm::json(&fc.path);
-                let updated_by = format!("EDSM file: {}", fc.path);
-                Cli::create_vec(db, &updated_by, systems);
-            }
+            Cli::File(fc) => fc.run(db),
             Cli::Api(ac) => {
                 let systems = if let Some(n) = ac.sphere {
                     edsm::api::systems_sphere(&ac.name, Some(n as f64), None)
@@ -119,3 +144,407 @@ impl Run for Cli {
         }
     }
 }
+
+impl Run for FileCli {
+    /// Stream the dump into the database a record at a time
+    ///
+    /// Systems and bodies are copied in by the batch, see
+    /// [`galos_db::bulk`]. Stations and populated systems, which are few and
+    /// carry factions, are written back into the journal event they were sent
+    /// to EDSM as, and written by [`ingest`] like any journal's.
+    fn run(&self, db: &Database) {
+        let path = Path::new(&self.path);
+        let (source, from) =
+            match resume(db, path, Growth::Replaced, self.force) {
+                Ok((source, Resume::From(from))) => (source, from as usize),
+                Ok((_, Resume::Skip)) => {
+                    return println!(
+                        "{} is unchanged since it was imported, \
+                     --force to import it again",
+                        self.path
+                    );
+                }
+                Err(err) => return eprintln!("{}", err),
+            };
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) => return eprintln!("{}: {}", self.path, err),
+        };
+
+        // Compressed, the records are not where the bytes are, so progress
+        // is counted in bytes of the file read so far.
+        let bar = ProgressBar::new(source.size as u64);
+        bar.set_style(ProgressStyle::default_bar()
+            .template("[{elapsed_precise}/{eta_precise}] {bar:40} {bytes:>9}/{total_bytes:9} ({percent}%) {msg}")
+            .unwrap()
+            .progress_chars("##-"));
+        let read = dumps::open(path, bar.wrap_read(file));
+        let kind = self.kind.unwrap_or_else(|| Kind::of(path));
+        let dump = Dump {
+            db,
+            bar: &bar,
+            source: &source,
+            kind,
+            updated_by: format!("EDSM file: {}", self.path),
+        };
+
+        // Bodies come a system at a time, so the system last asked after is
+        // nearly always the one wanted.
+        let mut last = None;
+        match kind {
+            Kind::Systems if !self.row_by_row => {
+                dump.bulk(read, from, |batch, s| dump.stage_system(batch, s))
+            }
+            Kind::Bodies if !self.row_by_row => {
+                dump.bulk(read, from, |batch, b| {
+                    dump.stage_body(batch, b, &mut last)
+                })
+            }
+            Kind::Systems => dump.each(read, from, |s| dump.system(s)),
+            Kind::Bodies => dump.each(read, from, |b| dump.body(b, &mut last)),
+            Kind::Stations => dump.each(read, from, |s| dump.station(s)),
+            Kind::Populated => dump.each(read, from, |s| dump.populated(s)),
+        }
+    }
+}
+
+/// An import of one of EDSM's dumps
+struct Dump<'a> {
+    db: &'a Database,
+    bar: &'a ProgressBar,
+    source: &'a Source,
+    kind: Kind,
+    updated_by: String,
+}
+
+impl<'a> Dump<'a> {
+    /// Write each record from the `from`th, checkpointing as it goes, and
+    /// say how many were written and how many rejected
+    ///
+    /// `write` says what it wrote, or why it could not. The database being
+    /// unavailable stops the import where it is, to be picked up from the
+    /// last checkpoint, rather than have every record after it rejected.
+    fn each<T, R, F>(&self, read: R, from: usize, mut write: F)
+    where
+        T: DeserializeOwned,
+        R: Read,
+        F: FnMut(T) -> Result<String, Unwritten>,
+    {
+        let mut records = Records::<_, T>::new(read);
+        if let Err(err) = records.skip_raw(from) {
+            self.bar.println(format!("{}: {}", self.source.path, err));
+            return;
+        }
+
+        let (mut imported, mut rejected) = (0, 0);
+        let complete = loop {
+            let n = records.read();
+            if n % CHECKPOINT == 0 {
+                checkpoint(self.db, self.source, n as i64, false);
+            }
+            let record = match records.next() {
+                Some(Ok(record)) => record,
+                Some(Err(err)) => {
+                    self.bar.println(format!("{}: {}", self.source.path, err));
+                    break false;
+                }
+                None => break true,
+            };
+            let written = record
+                .map_err(|rejected| Unwritten::from(rejected.error.to_string()))
+                .and_then(&mut write);
+            match written {
+                Ok(name) => {
+                    self.bar.set_message(format!("[EDSM] {}", name));
+                    imported += 1;
+                }
+                Err(Unwritten::Rejected(err)) => {
+                    self.bar.println(format!("[EDSM REJECTED {}] {}", n, err));
+                    rejected += 1;
+                }
+                Err(Unwritten::Unavailable(err)) => {
+                    self.bar.abandon();
+                    return eprintln!("{}: {}", self.source.path, err);
+                }
+            }
+        };
+        checkpoint(self.db, self.source, records.read() as i64, complete);
+        self.bar.finish();
+        println!("Imported {} {}, rejected {}.", imported, self.kind, rejected);
+    }
+
+    /// Stage each record from the `from`th and load them by the batch,
+    /// checkpointing after each load, and say what the loads did
+    ///
+    /// `stage` says what it staged, or why it could not, and as with
+    /// [`Dump::each`] the database being unavailable stops the import.
+    fn bulk<T, R, F>(&self, read: R, from: usize, mut stage: F)
+    where
+        T: DeserializeOwned,
+        R: Read,
+        F: FnMut(&mut Batch, T) -> Result<String, Unwritten>,
+    {
+        let mut records = Records::<_, T>::new(read);
+        if let Err(err) = records.skip_raw(from) {
+            self.bar.println(format!("{}: {}", self.source.path, err));
+            return;
+        }
+
+        let mut batch = Batch::new();
+        let mut report = Report::default();
+        let mut failed = false;
+        let complete = loop {
+            let n = records.read();
+            let record = match records.next() {
+                Some(Ok(record)) => record,
+                Some(Err(err)) => {
+                    self.bar.println(format!("{}: {}", self.source.path, err));
+                    break false;
+                }
+                None => break true,
+            };
+            let staged = record
+                .map_err(|rejected| Unwritten::from(rejected.error.to_string()))
+                .and_then(|record| stage(&mut batch, record));
+            match staged {
+                Ok(name) => self.bar.set_message(format!("[EDSM] {}", name)),
+                Err(Unwritten::Rejected(err)) => {
+                    self.bar.println(format!("[EDSM REJECTED {}] {}", n, err));
+                    batch.reject();
+                }
+                Err(Unwritten::Unavailable(err)) => {
+                    self.bar.abandon();
+                    return eprintln!("{}: {}", self.source.path, err);
+                }
+            }
+
+            // Only what has been loaded is checkpointed, so an import
+            // stopped partway picks up from the last whole batch.
+            if batch.len() >= BATCH {
+                match load(self.db, &mut batch) {
+                    Ok(loaded) => report += loaded,
+                    Err(err) => {
+                        self.bar.println(err);
+                        failed = true;
+                        break false;
+                    }
+                }
+                let n = records.read() as i64;
+                checkpoint(self.db, self.source, n, false);
+            }
+        };
+        if !failed {
+            match load(self.db, &mut batch) {
+                Ok(loaded) => {
+                    report += loaded;
+                    let n = records.read() as i64;
+                    checkpoint(self.db, self.source, n, complete);
+                }
+                Err(err) => self.bar.println(err),
+            }
+        }
+        self.bar.finish();
+        println!("Imported {}: {}.", self.kind, report);
+    }
+
+    fn stage_system(
+        &self,
+        batch: &mut Batch,
+        system: records::System,
+    ) -> Result<String, Unwritten> {
+        batch.system(
+            system.id64.ok_or("no address")?,
+            &system.name,
+            Some(system.coords.ok_or("no coordinates")?.into()),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            dumps::updated_at(system.date.as_deref())?,
+            &self.updated_by,
+        );
+        Ok(system.name)
+    }
+
+    /// A body, placed as [`Dump::body`] places it, staged as whichever of a
+    /// star or a planet its scan reads as
+    fn stage_body(
+        &self,
+        batch: &mut Batch,
+        body: records::Body,
+        last: &mut Option<(i64, Option<Coordinate>)>,
+    ) -> Result<String, Unwritten> {
+        let address = body.system_id64.ok_or("no system address")?;
+        let system = body.system_name.as_deref().ok_or("no system name")?;
+        let position = self.position(address, system, last)?;
+        let entry =
+            events::entry(events::scan(&body, address, system, position)?)?;
+        let Event::Scan(scan) = entry.event else {
+            return Err("not a scan".into());
+        };
+        match scan.target {
+            ScanTarget::Star(star) => {
+                batch.star(entry.timestamp, &self.updated_by, &star, address)
+            }
+            ScanTarget::Body(planet) => {
+                batch.body(entry.timestamp, &self.updated_by, &planet, address)
+            }
+        }
+        Ok(body.name)
+    }
+
+    fn system(&self, system: records::System) -> Result<String, Unwritten> {
+        let address = system.id64.ok_or("no address")?;
+        let position = system.coords.ok_or("no coordinates")?;
+        let updated_at = dumps::updated_at(system.date.as_deref())?;
+        task::block_on(System::create(
+            self.db,
+            address,
+            &system.name,
+            Some(position.into()),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            updated_at,
+            &self.updated_by,
+        ))?;
+        Ok(system.name)
+    }
+
+    /// A body, placed by its system's position on record
+    fn body(
+        &self,
+        body: records::Body,
+        last: &mut Option<(i64, Option<Coordinate>)>,
+    ) -> Result<String, Unwritten> {
+        let address = body.system_id64.ok_or("no system address")?;
+        let system = body.system_name.as_deref().ok_or("no system name")?;
+        let position = self.position(address, system, last)?;
+        self.write(events::scan(&body, address, system, position)?)?;
+        Ok(body.name)
+    }
+
+    /// Where the system at `address` is on record, which `last` keeps for
+    /// the next body in the same system
+    fn position(
+        &self,
+        address: i64,
+        system: &str,
+        last: &mut Option<(i64, Option<Coordinate>)>,
+    ) -> Result<Coordinate, Unwritten> {
+        let position = match *last {
+            Some((cached, position)) if cached == address => position,
+            _ => {
+                // Not on record is said below. Not knowing, because the
+                // database did not answer, is a different failure.
+                let position =
+                    match task::block_on(System::fetch(self.db, address)) {
+                        Ok(system) => system.position,
+                        Err(err) if err.is_not_found() => None,
+                        Err(err) => return Err(err.into()),
+                    };
+                *last = Some((address, position));
+                position
+            }
+        };
+        position.ok_or_else(|| {
+            Unwritten::Rejected(format!(
+                "{} is not a system on record, import systems first",
+                system
+            ))
+        })
+    }
+
+    fn station(&self, station: records::Station) -> Result<String, Unwritten> {
+        let address = station.system_id64.ok_or("no system address")?;
+        let system = station.system_name.as_deref().ok_or("no system name")?;
+        self.write(events::docked(&station, address, system)?)?;
+        Ok(station.name)
+    }
+
+    /// A system with its factions, and the stations and bodies in it
+    ///
+    /// What of it could be written is, and the first of what could not is
+    /// the reason it is rejected. The database being unavailable is the
+    /// reason at once.
+    fn populated(
+        &self,
+        system: records::Populated,
+    ) -> Result<String, Unwritten> {
+        self.write(events::location(&system)?)?;
+        let address = system.id64.ok_or("no address")?;
+        let position = system.coords.ok_or("no coordinates")?.into();
+        let mut failed = None;
+        for station in &system.stations {
+            let written = events::docked(station, address, &system.name)
+                .map_err(Unwritten::from)
+                .and_then(|event| self.write(event));
+            match written {
+                Ok(()) => {}
+                Err(Unwritten::Rejected(err)) => {
+                    failed.get_or_insert(format!("{}: {}", station.name, err));
+                }
+                Err(unavailable) => return Err(unavailable),
+            }
+        }
+        for body in &system.bodies {
+            let written = events::scan(body, address, &system.name, position)
+                .map_err(Unwritten::from)
+                .and_then(|event| self.write(event));
+            match written {
+                Ok(()) => {}
+                Err(Unwritten::Rejected(err)) => {
+                    failed.get_or_insert(format!("{}: {}", body.name, err));
+                }
+                Err(unavailable) => return Err(unavailable),
+            }
+        }
+        match failed {
+            Some(err) => Err(Unwritten::Rejected(err)),
+            None => Ok(system.name),
+        }
+    }
+
+    fn write(&self, event: Value) -> Result<(), Unwritten> {
+        let entry = events::entry(event)?;
+        task::block_on(ingest::journal(self.db, entry, &self.updated_by))?;
+        Ok(())
+    }
+}
+
+/// Why a record was not written
+enum Unwritten {
+    /// Something of the record itself, which the records after it are
+    /// written in spite of
+    Rejected(String),
+    /// The database could not be reached, and the records after it would
+    /// fail the same way
+    Unavailable(Error),
+}
+
+impl From<String> for Unwritten {
+    fn from(reason: String) -> Self {
+        Unwritten::Rejected(reason)
+    }
+}
+
+impl From<&str> for Unwritten {
+    fn from(reason: &str) -> Self {
+        Unwritten::Rejected(reason.into())
+    }
+}
+
+impl From<Error> for Unwritten {
+    fn from(err: Error) -> Self {
+        if err.is_unavailable() {
+            Unwritten::Unavailable(err)
+        } else {
+            Unwritten::Rejected(err.to_string())
+        }
+    }
+}
@@ -1,64 +1,203 @@
-use crate::Run;
+use crate::{checkpoint, resume, Run, CHECKPOINT};
 use async_std::task;
-use elite_journal::entry::{self, Event};
-use galos_db::{systems::System, Database};
+use chrono::{DateTime, Utc};
+use elite_journal::entry::{Entry, Event};
+use galos::ingest;
+use galos_db::{
+    exploration::Exploration,
+    imports::{Growth, Resume},
+    ships::{Loadout, Ship},
+    Database,
+};
 use indicatif::{ProgressBar, ProgressStyle};
+use serde::Deserialize;
 use std::fs;
+use std::path::{Path, PathBuf};
 use structopt::StructOpt;
 
+/// Written as, until a journal says whose it is
+const UNKNOWN: &str = "JOURNAL";
+
 #[derive(StructOpt, Debug)]
 pub struct Cli {
     #[structopt(name = "PATH")]
     pub path: String,
+    #[structopt(
+        long,
+        help = "Import every journal again, even those imported already"
+    )]
+    pub force: bool,
+}
+
+/// The lines saying whose journal it is, and what they fly, which are read
+/// here from the JSON itself
+///
+/// A loadout is only the commander's once it is known whose journal it is,
+/// which the `Commander` and `LoadGame` events at the start of a session say.
+/// Everything else written from the journal is attributed to them too.
+#[derive(Deserialize)]
+#[serde(tag = "event")]
+enum Line {
+    Commander {
+        #[serde(rename = "Name")]
+        name: String,
+    },
+    LoadGame {
+        #[serde(rename = "Commander")]
+        commander: String,
+    },
+    Loadout {
+        timestamp: String,
+        #[serde(flatten)]
+        loadout: Loadout,
+    },
+    #[serde(other)]
+    Other,
 }
 
 impl Run for Cli {
+    /// Write everything the journals say, a line at a time, oldest first
+    ///
+    /// Each event goes through the same [`ingest`] as EDDN's do, so a
+    /// journal fills in bodies, stars, barycenters, stations and factions
+    /// as well as systems.
     fn run(&self, db: &Database) {
-        let entries = if let Ok(m) = fs::metadata(&self.path) {
-            if m.is_dir() {
-                entry::parse_journal_dir(&self.path).unwrap()
-            } else {
-                entry::parse_journal_file(&self.path).unwrap()
-            }
-        } else {
-            panic!("bad path: {}", self.path);
-        };
+        let path = Path::new(&self.path);
+        if !path.exists() {
+            return eprintln!("bad path: {}", self.path);
+        }
+        let journals = journals(path);
+        let size = journals
+            .iter()
+            .filter_map(|path| fs::metadata(path).ok())
+            .map(|m| m.len())
+            .sum();
 
-        let bar = ProgressBar::new(entries.len() as u64);
+        let bar = ProgressBar::new(size);
         bar.set_style(ProgressStyle::default_bar()
-            .template("[{elapsed_precise}/{eta_precise}] {bar:40} {pos:>7}/{len:7} ({percent}%) {msg}")
+            .template("[{elapsed_precise}/{eta_precise}] {bar:40} {bytes:>9}/{total_bytes:9} ({percent}%) {msg}")
             .unwrap()
             .progress_chars("##-"));
-        for entry in bar.wrap_iter(entries.into_iter()) {
-            task::block_on(async {
-                let option = match entry.event {
-                    Event::Location(e) => Some(e.system),
-                    Event::FsdJump(e) => Some(e.system),
-                    _ => None,
-                };
 
-                if let Some(system) = option {
-                    // TODO: Take user as arg or something.
-                    let result = System::from_journal(
-                        db,
-                        entry.timestamp,
-                        "JOURNAL",
-                        &system,
-                    )
-                    .await;
-                    match result {
-                        Ok(_) => bar.set_message(format!(
-                            "[{}] {}",
-                            entry.timestamp, system.name
-                        )),
-                        Err(err) => bar.set_message(format!(
-                            "[ERROR {}] {}",
-                            entry.timestamp, err
-                        )),
+        let mut ships = 0;
+        let mut skipped = 0;
+        for path in journals {
+            let (source, from) = match resume(db, &path, Growth::Appended, self.force) {
+                Ok((source, Resume::From(from))) => (source, from as usize),
+                Ok((source, Resume::Skip)) => {
+                    bar.inc(source.size as u64);
+                    skipped += 1;
+                    continue;
+                }
+                Err(err) => {
+                    bar.println(err);
+                    continue;
+                }
+            };
+            let Ok(text) = fs::read_to_string(&path) else {
+                bar.println(format!("could not read {}", path.display()));
+                continue;
+            };
+
+            // Each session's journal says whose it is at its start, which a
+            // journal picked up partway still has to be read for.
+            let mut commander: Option<String> = None;
+            let mut at = 0;
+            for (n, line) in text.split_inclusive('\n').enumerate() {
+                // The game may be partway through writing the last line,
+                // which is left for the next import to read whole.
+                if !line.ends_with('\n') {
+                    break;
+                }
+                let resumed = at < from;
+                at += line.len();
+                bar.inc(line.len() as u64);
+                match serde_json::from_str(line) {
+                    Ok(Line::Commander { name }) => commander = Some(name),
+                    Ok(Line::LoadGame { commander: name }) => {
+                        commander = Some(name)
+                    }
+                    Ok(Line::Loadout { timestamp, loadout }) if !resumed => {
+                        if let Some(commander) = &commander {
+                            if self.ship(db, commander, &timestamp, &loadout) {
+                                ships += 1;
+                            }
+                        }
                     }
+                    Ok(_) | Err(_) => {}
                 }
-            });
+                if resumed {
+                    continue;
+                }
+
+                // Exploration events are read apart, the journal parser not
+                // knowing them. Events neither knows are passed over like any
+                // other saying nothing to be written.
+                let user = commander.as_deref().unwrap_or(UNKNOWN);
+                let written = if let Ok(entry) =
+                    serde_json::from_str::<Exploration>(line)
+                {
+                    task::block_on(ingest::exploration(db, &entry, user))
+                } else if let Ok(entry) =
+                    serde_json::from_str::<Entry<Event>>(line)
+                {
+                    bar.set_message(format!("[{}] {}", entry.timestamp, user));
+                    task::block_on(ingest::journal(db, entry, user))
+                } else {
+                    Ok(())
+                };
+                // Carried on from the last checkpoint once it's back.
+                if let Err(err) = written {
+                    bar.abandon();
+                    return eprintln!("{}: {}", path.display(), err);
+                }
+
+                if n % CHECKPOINT == 0 {
+                    checkpoint(db, &source, at as i64, false);
+                }
+            }
+            checkpoint(db, &source, at as i64, at == text.len());
         }
         bar.finish();
+        println!("{} loadouts read", ships);
+        if skipped > 0 {
+            println!("{} journals unchanged since imported", skipped);
+        }
+    }
+}
+
+impl Cli {
+    /// Keep a ship as of its loadout, saying whether it was kept
+    fn ship(
+        &self,
+        db: &Database,
+        commander: &str,
+        timestamp: &str,
+        loadout: &Loadout,
+    ) -> bool {
+        let Ok(timestamp) = DateTime::parse_from_rfc3339(timestamp) else {
+            return false;
+        };
+        let result = task::block_on(Ship::from_loadout(
+            db,
+            timestamp.with_timezone(&Utc),
+            commander,
+            loadout,
+        ));
+        match result {
+            Ok(_) => true,
+            Err(err) => {
+                eprintln!("[ERROR {}] {}", timestamp, err);
+                false
+            }
+        }
+    }
+}
+
+/// The journal files at `path`, oldest first, or the one file it is
+fn journals(path: &Path) -> Vec<PathBuf> {
+    if !path.is_dir() {
+        return vec![path.to_path_buf()];
     }
+    galos::journal::journals(path).unwrap_or_default()
 }
@@ -1,12 +1,18 @@
+use async_std::task;
 use galos::Run;
+use galos_db::bulk::{Batch, Report};
+use galos_db::imports::{Growth, Import, Resume, Source};
 use galos_db::{Database, Error};
 use std::io::{stderr, IsTerminal};
+use std::path::Path;
 use structopt::StructOpt;
 
 mod eddb;
 mod eddn;
 mod edsm;
 mod journal;
+mod quarantine;
+mod replay;
 
 #[derive(StructOpt, Debug)]
 pub enum Cli {
@@ -17,6 +23,12 @@ pub enum Cli {
     )]
     #[cfg(unix)]
     Eddn(eddn::Cli),
+    #[structopt(about = "Replay messages archived from EDDN")]
+    #[cfg(unix)]
+    Replay(replay::Cli),
+    #[structopt(about = "Manage EDDN messages held back as suspicious")]
+    #[cfg(unix)]
+    Quarantine(quarantine::Cli),
     #[structopt(about = "Sync from EDSM's nightly dumps")]
     Edsm(edsm::Cli),
     #[structopt(about = "Sync from EDDB's nightly dumps")]
@@ -29,12 +41,51 @@ impl Run for Cli {
             Cli::Journal(cli) => cli.run(db),
             #[cfg(unix)]
             Cli::Eddn(cli) => cli.run(db),
+            #[cfg(unix)]
+            Cli::Replay(cli) => cli.run(db),
+            #[cfg(unix)]
+            Cli::Quarantine(cli) => cli.run(db),
             Cli::Edsm(cli) => cli.run(db),
             Cli::Eddb(cli) => cli.run(db),
         }
     }
 }
 
+/// How many lines or records an import goes between checkpoints
+const CHECKPOINT: usize = 1000;
+
+/// How many rows a bulk import stages before loading them, and checkpoints
+/// after
+const BATCH: usize = 10_000;
+
+/// The file at `path` as it is now, and where to start importing it
+fn resume(
+    db: &Database,
+    path: &Path,
+    growth: Growth,
+    force: bool,
+) -> Result<(Source, Resume), String> {
+    let source =
+        Source::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let resume = task::block_on(Import::resume(db, &source, growth, force))
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok((source, resume))
+}
+
+/// Record how far into `source` the import is, complaining of failure only
+fn checkpoint(db: &Database, source: &Source, processed: i64, complete: bool) {
+    let result =
+        task::block_on(Import::checkpoint(db, source, processed, complete));
+    if let Err(err) = result {
+        eprintln!("{}: {}", source.path, err);
+    }
+}
+
+/// Load what is staged, saying why not where it could not be
+fn load(db: &Database, batch: &mut Batch) -> Result<Report, String> {
+    task::block_on(batch.load(db)).map_err(|e| e.to_string())
+}
+
 #[async_std::main]
 async fn main() -> Result<(), Error> {
     // Nothing a crate traces goes anywhere until something is listening for
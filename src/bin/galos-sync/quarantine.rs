@@ -0,0 +1,150 @@
+#![cfg(unix)]
+use crate::eddn::process_message;
+use crate::Run;
+use async_std::task;
+use chrono::{DateTime, Utc};
+use galos::envelope::Envelope;
+use galos_db::quarantine::Quarantined;
+use galos_db::{Database, Page};
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+pub enum Cli {
+    #[structopt(about = "List quarantined messages, oldest first")]
+    List(ListCli),
+    #[structopt(about = "Ingest quarantined messages after all, unchecked")]
+    Release(ReleaseCli),
+    #[structopt(about = "Delete quarantined messages")]
+    Purge(PurgeCli),
+}
+
+#[derive(StructOpt, Debug)]
+pub struct ListCli {
+    #[structopt(long, default_value = "50")]
+    pub limit: i64,
+    #[structopt(long, default_value = "0")]
+    pub offset: i64,
+    #[structopt(long, help = "Print each message's envelope too")]
+    pub envelope: bool,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct ReleaseCli {
+    #[structopt(name = "IDS", required = true)]
+    pub ids: Vec<i64>,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct PurgeCli {
+    #[structopt(
+        name = "IDS",
+        required_unless_one = &["before", "all"],
+        conflicts_with_all = &["before", "all"]
+    )]
+    pub ids: Vec<i64>,
+    #[structopt(
+        long,
+        help = "Purge everything received before this, e.g. \
+                2026-10-18T11:00:00Z"
+    )]
+    pub before: Option<DateTime<Utc>>,
+    #[structopt(long, conflicts_with = "before", help = "Purge everything")]
+    pub all: bool,
+}
+
+impl Run for Cli {
+    fn run(&self, db: &Database) {
+        match self {
+            Cli::List(cli) => cli.run(db),
+            Cli::Release(cli) => cli.run(db),
+            Cli::Purge(cli) => cli.run(db),
+        }
+    }
+}
+
+impl Run for ListCli {
+    fn run(&self, db: &Database) {
+        let page = Page { limit: self.limit, offset: self.offset };
+        let quarantined = match task::block_on(Quarantined::fetch_all(db, page))
+        {
+            Ok(quarantined) => quarantined,
+            Err(err) => return eprintln!("{}", err),
+        };
+        for q in quarantined {
+            println!(
+                "{}\t{}\t{}\t{}\t{}\t{}",
+                q.id,
+                q.received.to_rfc3339(),
+                q.schema,
+                q.uploader,
+                q.software,
+                q.reason
+            );
+            if self.envelope {
+                println!("{}", q.envelope);
+            }
+        }
+    }
+}
+
+impl Run for ReleaseCli {
+    /// Write each message as it would have been, had it not been held back,
+    /// then let it go
+    ///
+    /// A message that no longer reads is left where it is.
+    fn run(&self, db: &Database) {
+        let mut released = 0;
+        for &id in &self.ids {
+            let quarantined = match task::block_on(Quarantined::fetch(db, id)) {
+                Ok(quarantined) => quarantined,
+                Err(err) => {
+                    eprintln!("{}: {}", id, err);
+                    continue;
+                }
+            };
+            let read = serde_json::from_str::<Envelope>(&quarantined.envelope)
+                .and_then(|envelope| {
+                    envelope.message().map(|message| (envelope, message))
+                });
+            let (envelope, message) = match read {
+                Ok(read) => read,
+                Err(err) => {
+                    eprintln!("{}: {}", id, err);
+                    continue;
+                }
+            };
+            let written = process_message(db, &envelope, message)
+                .and_then(|()| task::block_on(quarantined.delete(db)));
+            match written {
+                Ok(()) => released += 1,
+                Err(err) => eprintln!("{}: {}", id, err),
+            }
+        }
+        println!("Released {} messages.", released);
+    }
+}
+
+impl Run for PurgeCli {
+    fn run(&self, db: &Database) {
+        if self.ids.is_empty() {
+            let before = if self.all { None } else { self.before };
+            match task::block_on(Quarantined::purge(db, before)) {
+                Ok(purged) => println!("Purged {} messages.", purged),
+                Err(err) => eprintln!("{}", err),
+            }
+            return;
+        }
+
+        let mut purged = 0;
+        for &id in &self.ids {
+            let deleted = task::block_on(async {
+                Quarantined::fetch(db, id).await?.delete(db).await
+            });
+            match deleted {
+                Ok(()) => purged += 1,
+                Err(err) => eprintln!("{}: {}", id, err),
+            }
+        }
+        println!("Purged {} messages.", purged);
+    }
+}
@@ -0,0 +1,126 @@
+#![cfg(unix)]
+use crate::eddn::{read, receive};
+use crate::Run;
+use chrono::{DateTime, Utc};
+use galos::archive;
+use galos::validate::Software;
+use galos_db::Database;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+pub struct Cli {
+    #[structopt(name = "FILES", required = true)]
+    pub files: Vec<PathBuf>,
+
+    #[structopt(
+        long,
+        help = "Replay at this many times the speed it arrived, rather than \
+                as fast as it can be ingested"
+    )]
+    pub speed: Option<f64>,
+    #[structopt(
+        long = "schema",
+        help = "Only replay messages of this schema, e.g. journal or commodity"
+    )]
+    pub schemas: Vec<String>,
+    #[structopt(
+        long,
+        help = "Only replay messages received at or after this, e.g. \
+                2026-10-18T11:00:00Z"
+    )]
+    pub since: Option<DateTime<Utc>>,
+    #[structopt(long, help = "Only replay messages received before this")]
+    pub until: Option<DateTime<Utc>>,
+    #[structopt(
+        long = "bad-software",
+        help = "Quarantine messages from this software, NAME or NAME@VERSION"
+    )]
+    pub bad_software: Vec<Software>,
+}
+
+impl Run for Cli {
+    /// Feed archived messages through the same processing EDDN's get, oldest
+    /// file first
+    ///
+    /// Each is written as its uploader, and checked as of when it was
+    /// received, which is otherwise only used for filtering and pacing, so a
+    /// replay writes or quarantines what the original would have and the
+    /// newer of two updates still wins.
+    fn run(&self, db: &Database) {
+        let mut files = self.files.clone();
+        files.sort();
+
+        let (mut replayed, mut skipped, mut unreadable) = (0, 0, 0);
+        let mut last: Option<DateTime<Utc>> = None;
+        'files: for path in files {
+            let records = match archive::read(&path) {
+                Ok(records) => records,
+                Err(err) => {
+                    eprintln!("{}: {}", path.display(), err);
+                    continue;
+                }
+            };
+            for record in records {
+                let record = match record {
+                    Ok(Ok(record)) => record,
+                    Ok(Err(_)) => {
+                        unreadable += 1;
+                        continue;
+                    }
+                    // Most likely the end of an hour cut short by a crash,
+                    // after which there is nothing to read.
+                    Err(err) => {
+                        eprintln!("{}: {}", path.display(), err);
+                        break;
+                    }
+                };
+                if !self.wanted(&record) {
+                    skipped += 1;
+                    continue;
+                }
+                let (envelope, message) = match read(&record) {
+                    Ok(read) => read,
+                    Err(_) => {
+                        unreadable += 1;
+                        continue;
+                    }
+                };
+
+                let speed = self.speed.filter(|speed| *speed > 0.);
+                if let (Some(speed), Some(last)) = (speed, last) {
+                    let gap = (record.received - last).to_std();
+                    if let Ok(gap) = gap {
+                        thread::sleep(Duration::from_secs_f64(
+                            gap.as_secs_f64() / speed,
+                        ));
+                    }
+                }
+                last = Some(record.received);
+
+                // Gone, the database is as good as gone for the rest too.
+                let bad = &self.bad_software;
+                let received = receive(db, bad, &record, &envelope, message);
+                if let Err(err) = received {
+                    eprintln!("{}: {}", path.display(), err);
+                    break 'files;
+                }
+                replayed += 1;
+            }
+        }
+        println!(
+            "Replayed {} messages, skipped {}, {} unreadable.",
+            replayed, skipped, unreadable
+        );
+    }
+}
+
+impl Cli {
+    fn wanted<T>(&self, record: &archive::Record<T>) -> bool {
+        (self.schemas.is_empty() || self.schemas.contains(&record.schema))
+            && self.since.is_none_or(|since| record.received >= since)
+            && self.until.is_none_or(|until| record.received < until)
+    }
+}
@@ -0,0 +1,152 @@
+use async_std::task;
+use chrono::Duration as Age;
+use galos::output::{Format, Output};
+use galos_db::{
+    markets::Criteria, outfitting::Offer, stations::PadSize, systems::System,
+    Database,
+};
+use prettytable::{format, Table};
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+pub enum Cli {
+    #[structopt(about = "The nearest stations selling a module")]
+    Module(ModuleCli),
+    #[structopt(about = "The nearest stations selling a ship")]
+    Ship(ShipCli),
+}
+
+#[derive(StructOpt, Debug)]
+pub struct ModuleCli {
+    /// The module, e.g. `hyperdrive`, `Int_Hyperdrive` or
+    /// `Int_Hyperdrive_Size5_Class5`
+    pub module: String,
+    #[structopt(short = "c", long, help = "Its size, 1 to 8")]
+    pub class: Option<i16>,
+    #[structopt(short = "r", long, help = "Its rating, A to E")]
+    pub rating: Option<char>,
+    #[structopt(flatten)]
+    pub near: Near,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct ShipCli {
+    /// The ship, e.g. `Krait Mk II` or `krait_mkii`
+    pub ship: String,
+    #[structopt(flatten)]
+    pub near: Near,
+}
+
+/// Where to look from, and which stations are worth being told about
+#[derive(StructOpt, Debug)]
+pub struct Near {
+    #[structopt(short = "n", long, help = "The system to look from")]
+    pub near: String,
+    #[structopt(
+        default_value = "100",
+        long,
+        help = "How far from it to look, in Ly"
+    )]
+    pub radius: f64,
+    #[structopt(short = "p", long, help = "S, M or L")]
+    pub pad: Option<PadSize>,
+    #[structopt(long, help = "Ignore lists older than this, in hours")]
+    pub max_age: Option<i64>,
+    #[structopt(default_value = "10", short = "l", long)]
+    pub limit: i64,
+}
+
+impl Near {
+    fn criteria(&self) -> Criteria {
+        Criteria {
+            pad: self.pad,
+            max_age: self.max_age.map(Age::hours),
+            ..Criteria::default()
+        }
+    }
+
+    /// Where the system looked from is, if it is known and has a position
+    fn center(&self, db: &Database) -> Result<[f64; 3], String> {
+        let system = task::block_on(System::fetch_by_name(db, &self.near))
+            .map_err(|err| format!("{}: {}", self.near, err))?;
+        let position = system
+            .position
+            .ok_or_else(|| format!("{} has no known position", self.near))?;
+        Ok([position.x, position.y, position.z])
+    }
+}
+
+impl Cli {
+    pub fn run(&self, db: &Database, format: Format) {
+        let near = match self {
+            Cli::Module(cli) => &cli.near,
+            Cli::Ship(cli) => &cli.near,
+        };
+        let center = match near.center(db) {
+            Ok(center) => center,
+            Err(err) => return eprintln!("{}", err),
+        };
+        let criteria = near.criteria();
+        let offers = match self {
+            Cli::Module(cli) => task::block_on(Offer::nearest_module(
+                db,
+                &cli.module,
+                cli.class,
+                cli.rating,
+                near.radius,
+                center,
+                &criteria,
+                near.limit,
+            )),
+            Cli::Ship(cli) => task::block_on(Offer::nearest_ship(
+                db,
+                &cli.ship,
+                near.radius,
+                center,
+                &criteria,
+                near.limit,
+            )),
+        };
+        let offers = match offers {
+            Ok(offers) => offers,
+            Err(err) => return eprintln!("{}", err),
+        };
+
+        if format != Format::Table {
+            let mut output = Output::stdout(format);
+            let written =
+                offers.iter().try_for_each(|offer| output.write(offer));
+            if let Err(err) = written.and_then(|_| output.finish()) {
+                eprintln!("{}", err);
+            }
+            return;
+        }
+
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+        table.set_titles(row![
+            "Station", "System", "Distance", "Arrival", "Sells", "Listed"
+        ]);
+        for offer in &offers {
+            let sells = match (offer.class, &offer.rating) {
+                (Some(class), Some(rating)) => {
+                    format!("{} ({}{})", offer.name, class, rating)
+                }
+                (Some(class), None) => format!("{} ({})", offer.name, class),
+                _ => offer.name.clone(),
+            };
+            table.add_row(row![
+                offer.station_name,
+                offer.system_name,
+                format!("{:.2} Ly", offer.distance),
+                offer
+                    .dist_from_star_ls
+                    .map_or("---".into(), |ls| format!("{:.0} Ls", ls)),
+                sells,
+                offer.listed_at.format("%Y-%m-%d %H:%M"),
+            ]);
+        }
+        table.printstd();
+        println!("{} stations found.", offers.len());
+    }
+}
@@ -1,8 +1,8 @@
 #[macro_use]
 extern crate prettytable;
 
-use galos::Run;
-use galos_db::{Database, Error};
+use galos::output::Format;
+use galos_db::{systems::System, Database, Error};
 use structopt::StructOpt;
 
 #[derive(StructOpt, Debug)]
@@ -13,8 +13,18 @@ struct Cli {
         help = "override default (.env) database URL"
     )]
     database_url: Option<String>,
+    #[structopt(
+        default_value = "table",
+        long,
+        global = true,
+        possible_values = &[
+            "table", "json", "ndjson", "csv", "navroute", "spansh"
+        ],
+        help = "How to print results; navroute and spansh are for routes alone"
+    )]
+    format: Format,
     #[structopt(subcommand)]
-    subcommand: Subcommand,
+    subcommand: Option<Subcommand>,
 }
 
 #[derive(StructOpt, Debug)]
@@ -23,13 +33,30 @@ enum Subcommand {
     Search(search::Cli),
     #[structopt(about = "Plot routes between to and from many systems")]
     Route(route::Cli),
+    #[structopt(about = "Find profitable trades between nearby markets")]
+    Trade(trade::Cli),
+    #[structopt(about = "Find the nearest stations selling a module or ship")]
+    Find(find::Cli),
+    #[structopt(about = "Visit a set of systems in the fewest jumps")]
+    Tour(tour::Cli),
+    #[structopt(about = "Where the commander is, read from the journal")]
+    Whereami(whereami::Cli),
 }
 
-impl Run for Subcommand {
-    fn run(&self, db: &Database) {
+impl Subcommand {
+    fn run(&self, db: &Database, format: Format) {
+        if matches!(format, Format::NavRoute | Format::Spansh)
+            && !matches!(self, Subcommand::Route(_))
+        {
+            return eprintln!("only a route can be written as {}", format);
+        }
         match self {
-            Subcommand::Search(cli) => cli.run(db),
-            Subcommand::Route(cli) => cli.run(db),
+            Subcommand::Search(cli) => cli.run(db, format),
+            Subcommand::Route(cli) => cli.run(db, format),
+            Subcommand::Trade(cli) => cli.run(db, format),
+            Subcommand::Find(cli) => cli.run(db, format),
+            Subcommand::Tour(cli) => cli.run(db, format),
+            Subcommand::Whereami(cli) => cli.run(db, format),
         }
     }
 }
@@ -43,9 +70,35 @@ async fn main() -> Result<(), Error> {
         Database::new().await?
     };
 
-    cli.subcommand.run(&db);
+    match cli.subcommand {
+        Some(subcommand) => subcommand.run(&db, cli.format),
+        None => interactive(&db),
+    }
     Ok(())
 }
 
+/// Run the interactive galos, from wherever the journal says the commander is
+#[cfg(unix)]
+fn interactive(db: &Database) {
+    let location = galos::journal::dir()
+        .and_then(|dir| galos::journal::whereabouts(dir).ok())
+        .and_then(|whereabouts| whereabouts.system_address)
+        .and_then(|address| {
+            async_std::task::block_on(System::fetch(db, address)).ok()
+        });
+    if let Err(err) = galos::app::run(db, location) {
+        eprintln!("{}", err);
+    }
+}
+
+#[cfg(not(unix))]
+fn interactive(_db: &Database) {
+    eprintln!("the interactive galos needs a unix terminal, give a subcommand");
+}
+
+mod find;
 mod route;
 mod search;
+mod tour;
+mod trade;
+mod whereami;
@@ -1,25 +1,90 @@
+use crate::whereami;
 use async_std::task;
-use galos::Run;
+use chrono::Utc;
+use elite_journal::prelude::{Allegiance, Security};
+use galos::{
+    output::{Format, Output},
+    route::{Plan, Route},
+};
 use galos_db::{
-    systems::{nav::ModuleClass, System},
+    export::{self, Waypoint},
+    factions::Faction,
+    ships::Ship,
+    stations::PadSize,
+    systems::nav::{
+        constraints::{parse_allegiance, parse_security},
+        metric::docking,
+        Fsd, Metric, ModuleClass, RouteConstraints,
+    },
+    systems::System,
     Database,
 };
 use indicatif::{ProgressBar, ProgressStyle};
-use itertools::Itertools;
 use prettytable::{format, Table};
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::process;
 use std::time::Duration;
 use structopt::StructOpt;
 
-#[allow(dead_code)]
 #[derive(StructOpt, Debug)]
 pub struct Cli {
-    // #[structopt(parse(lalrpop(Route)))]
-    pub start: String,
-    pub end: String,
+    #[structopt(
+        required = true,
+        help = "The stops, joined by `->` (in order), `+` (both) or `|` \
+                (either), e.g. 'Wolf 397 -> Sol + Meliae'. A route not saying \
+                where it starts, e.g. 'Sol + Meliae', is flown from where \
+                the journal says the commander is"
+    )]
+    pub route: Vec<String>,
+    #[structopt(
+        long,
+        parse(from_os_str),
+        help = "The game's journal directory, if not the usual one or \
+                GALOS_JOURNAL"
+    )]
+    pub journal: Option<PathBuf>,
 
     #[structopt(default_value = "7.5", short = "r", long)]
     pub range: f64,
-    #[structopt(default_value = "25", short = "m", long)]
+    #[structopt(
+        long,
+        help = "Fly a ship read from the journal, by name, ident or hull, \
+                rather than the range and drive given"
+    )]
+    pub ship: Option<String>,
+    #[structopt(
+        long,
+        help = "Whose --ship it is, if not the commander the journal names"
+    )]
+    pub commander: Option<String>,
+    #[structopt(long, help = "With the --ship's hold full")]
+    pub laden: bool,
+
+    #[structopt(
+        long,
+        possible_values = &["jumps", "distance", "time", "fuel"],
+        conflicts_with_all = &["fuel", "boost"],
+        help = "What to make the route cheapest in, jumps if not said; \
+                --fuel and --boost plot in jumps alone"
+    )]
+    pub metric: Option<String>,
+    #[structopt(
+        long,
+        help = "The station to time supercruise to at the last stop, rather \
+                than the nearest"
+    )]
+    pub station: Option<String>,
+    #[structopt(
+        default_value = "25",
+        short = "m",
+        long,
+        help = "Mass of the ship and its cargo, without fuel"
+    )]
     pub total_mass: f64,
     #[structopt(default_value = "48", short = "o", long)]
     pub optimized_mass: f64,
@@ -28,21 +93,119 @@ pub struct Cli {
     pub size: u8,
     #[structopt(default_value = "E", short = "c", long)]
     pub class: ModuleClass,
+    #[structopt(
+        default_value = "2",
+        short = "t",
+        long,
+        help = "Fuel tank, in tons"
+    )]
+    pub tank: f64,
+
+    #[structopt(
+        short = "f",
+        long,
+        help = "Plot on the fuel in the tank rather than a fixed --range"
+    )]
+    pub fuel: bool,
+    #[structopt(
+        short = "b",
+        long,
+        help = "Supercharge in neutron star and white dwarf jet cones"
+    )]
+    pub boost: bool,
+
+    #[structopt(flatten)]
+    pub constraints: Constraints,
 }
 
-impl Run for Cli {
-    fn run(&self, db: &Database) {
-        let spinner = ProgressBar::new_spinner();
-        spinner.enable_steady_tick(Duration::from_millis(100));
-        spinner.set_message("Finding systems...");
-        let (start, end) = task::block_on(async {
-            let start = System::fetch_by_name(db, &self.start).await.unwrap();
-            let end = System::fetch_by_name(db, &self.end).await.unwrap();
-            (start, end)
-        });
-        spinner.finish_with_message("Input systems found, finding route...");
+/// What a route keeps to, shared by everything that plots one
+#[derive(StructOpt, Debug)]
+pub struct Constraints {
+    #[structopt(
+        long,
+        number_of_values = 1,
+        help = "A system never to pass through, by name"
+    )]
+    pub avoid: Vec<String>,
+    #[structopt(long, help = "Keep out of systems that need a permit")]
+    pub avoid_permits: bool,
+    #[structopt(
+        long,
+        number_of_values = 1,
+        help = "A system whose permit is held, by name, not kept out of"
+    )]
+    pub permit: Vec<String>,
+    #[structopt(
+        long,
+        number_of_values = 1,
+        parse(try_from_str = parse_security),
+        help = "Keep out of systems of this security, e.g. Anarchy"
+    )]
+    pub avoid_security: Vec<Security>,
+    #[structopt(
+        long,
+        number_of_values = 1,
+        parse(try_from_str = parse_allegiance),
+        help = "Keep out of systems of this allegiance, e.g. Empire"
+    )]
+    pub avoid_allegiance: Vec<Allegiance>,
+    #[structopt(
+        long,
+        number_of_values = 1,
+        help = "Keep out of systems this faction is present in"
+    )]
+    pub avoid_faction: Vec<String>,
+    #[structopt(long, help = "Arrive only beside stars that can be scooped")]
+    pub scoopable: bool,
+    #[structopt(long, help = "Stop only where a station has a large pad")]
+    pub large_pad: bool,
+}
+
+impl Cli {
+    /// Plot the route, and print it as `format` says
+    ///
+    /// CSV, Spansh's JSON and a nav route are whole files, written by
+    /// [`galos_db::export`] as the server writes them. JSON and NDJSON are a
+    /// record a jump, as every other subcommand writes them.
+    pub fn run(&self, db: &Database, format: Format) {
+        let here = match whereami::here(self.journal.as_deref()) {
+            Ok(here) => here,
+            Err(err) => {
+                eprintln!("{}", err);
+                return;
+            }
+        };
+        // The words are joined back up and parsed whole, so a name split by
+        // the shell is one name again.
+        let route = match Route::from_here(
+            &self.route.join(" "),
+            here.system.as_deref(),
+        ) {
+            Ok(route) => route,
+            Err(err) => {
+                eprintln!("bad route: {}", err);
+                return;
+            }
+        };
+
+        let constraints = match self.constraints.resolve(db) {
+            Ok(constraints) => constraints,
+            Err(err) => {
+                eprintln!("{}", err);
+                return;
+            }
+        };
+
+        let commander = self.commander.as_deref().or(here.commander.as_deref());
+        let flown = match self.flown(db, commander) {
+            Ok(flown) => flown,
+            Err(err) => {
+                eprintln!("{}", err);
+                return;
+            }
+        };
 
-        spinner.reset();
+        let spinner = ProgressBar::new_spinner();
         spinner.set_style(
             ProgressStyle::default_spinner()
                 .tick_strings(&[
@@ -70,35 +233,475 @@ impl Run for Cli {
         );
         spinner.enable_steady_tick(Duration::from_millis(250));
 
+        if self.fuel {
+            self.run_with_fuel(
+                db,
+                &route,
+                &flown,
+                &constraints,
+                format,
+                spinner,
+            );
+        } else if self.boost {
+            self.run_boosted(db, &route, &flown, &constraints, format, spinner);
+        } else {
+            self.run_plain(db, &route, &flown, &constraints, format, spinner);
+        }
+    }
+}
+
+impl Constraints {
+    /// What the flags ask of the systems a route passes through
+    ///
+    /// Systems and factions are named, and looked up here for what the
+    /// routers know them by.
+    pub fn resolve(&self, db: &Database) -> Result<RouteConstraints, String> {
+        let mut avoid = Vec::new();
+        for name in &self.avoid {
+            let system = task::block_on(System::fetch_by_name(db, name))
+                .map_err(|_| format!("no system named {}", name))?;
+            avoid.push(system.address);
+        }
+        let mut avoid_factions = Vec::new();
+        for name in &self.avoid_faction {
+            let faction = task::block_on(Faction::fetch_by_name(db, name))
+                .map_err(|_| format!("no faction named {}", name))?;
+            avoid_factions.push(faction.id);
+        }
+        Ok(RouteConstraints {
+            avoid,
+            avoid_permits: self.avoid_permits,
+            permits: self.permit.clone(),
+            avoid_security: self.avoid_security.clone(),
+            avoid_allegiance: self.avoid_allegiance.clone(),
+            avoid_factions,
+            scoopable: self.scoopable,
+            pad: self.large_pad.then_some(PadSize::Large),
+        })
+    }
+}
+
+/// What a route is flown in
+struct Flown {
+    /// The range of a jump on a full tank
+    range: f64,
+    fsd: Fsd,
+    /// Everything but the fuel
+    mass: f64,
+    tank: f64,
+}
+
+impl Cli {
+    /// The ship named by `--ship`, or the one the other flags describe
+    ///
+    /// Named ships are `commander`'s, where the journal or `--commander` say
+    /// who that is.
+    fn flown(
+        &self,
+        db: &Database,
+        commander: Option<&str>,
+    ) -> Result<Flown, String> {
+        let Some(name) = &self.ship else {
+            let fsd = Fsd::new(self.size, self.class, self.optimized_mass)
+                .ok_or_else(|| {
+                    format!("no drive comes in size {}", self.size)
+                })?;
+            return Ok(Flown {
+                range: self.range,
+                fsd,
+                mass: self.total_mass,
+                tank: self.tank,
+            });
+        };
+        let ship = task::block_on(Ship::fetch_by_name(db, commander, name))
+            .map_err(|_| format!("no ship named {}", name))?;
+        let cargo = if self.laden { ship.cargo_capacity as f64 } else { 0. };
+        match (ship.fsd, ship.range(cargo)) {
+            (Some(fsd), Some(range)) => Ok(Flown {
+                range,
+                fsd,
+                mass: ship.unladen_mass + cargo,
+                tank: ship.fuel_capacity,
+            }),
+            _ => Err(format!("{} has no drive to plot with", ship.label())),
+        }
+    }
+
+    /// What `--metric` makes the route cheapest in, for the ship flown
+    fn metric(&self, flown: &Flown) -> Metric {
+        match self.metric.as_deref() {
+            Some("distance") => Metric::Distance,
+            Some("time") => Metric::Time {
+                fsd: Some(flown.fsd),
+                mass: flown.mass + flown.tank,
+            },
+            Some("fuel") => {
+                Metric::Fuel { fsd: flown.fsd, mass: flown.mass + flown.tank }
+            }
+            _ => Metric::Jumps,
+        }
+    }
+
+    /// Plot and print a route in jumps of a fixed range
+    ///
+    /// Cheapest in whatever `--metric` says, each jump costed in it. Timed,
+    /// each stop costs the supercruise to a station there too, which is
+    /// counted against the jump that arrives.
+    fn run_plain(
+        &self,
+        db: &Database,
+        route: &Route,
+        flown: &Flown,
+        constraints: &RouteConstraints,
+        format: Format,
+        spinner: ProgressBar,
+    ) {
+        let metric = self.metric(flown);
+        spinner.set_message(format!(
+            "Finding route, {:.2} Ly per jump...",
+            flown.range
+        ));
+        // The station named is at whichever stop the route ends on, and the
+        // rest are timed to their nearest.
+        let last: Vec<String> = route
+            .alternatives()
+            .iter()
+            .filter_map(|stops| stops.last().cloned())
+            .collect();
+        // Looked up as each leg is plotted, and read back as it is printed.
+        let docked: RefCell<HashMap<i64, f64>> = RefCell::default();
+        let dock = |b: &System| -> Result<f64, galos_db::Error> {
+            let Metric::Time { .. } = metric else { return Ok(0.) };
+            let station = self.station.as_deref().filter(|_| {
+                last.iter().any(|name| name.eq_ignore_ascii_case(&b.name))
+            });
+            let secs = task::block_on(docking(db, b, station))?;
+            docked.borrow_mut().insert(b.address, secs);
+            Ok(secs)
+        };
+        let plan = route.plan(db, |a, b| {
+            if !task::block_on(constraints.stops_at(db, b))? {
+                return Ok(None);
+            }
+            let route =
+                a.route_measured(db, b, flown.range, &metric, constraints)?;
+            match route {
+                Some((systems, cost)) => Ok(Some((systems, cost + dock(b)?))),
+                None => Ok(None),
+            }
+        });
+        let arrival = |b: &System| {
+            docked.borrow().get(&b.address).copied().unwrap_or_default()
+        };
+        spinner.finish_and_clear();
+        let plan = match plan {
+            Ok(plan) => plan,
+            Err(err) => failed(err),
+        };
+        if let Some(file) = file(format) {
+            let systems = through(&plan, |systems| systems.clone());
+            return export(file, task::block_on(Waypoint::along(db, &systems)));
+        }
+        if let Some(format) = listed(format) {
+            // Borrowed into each leg's jumps, which outlive the leg.
+            let (metric, arrival) = (&metric, &arrival);
+            return records(
+                format,
+                plan.legs.iter().flat_map(|(systems, _)| {
+                    systems.windows(2).enumerate().map(move |(i, pair)| {
+                        let (a, b) = (&pair[0], &pair[1]);
+                        let distance = a.distance(b);
+                        let mut cost = metric.jump(distance);
+                        if i + 2 == systems.len() {
+                            cost += arrival(b);
+                        }
+                        Jump { from: a, to: b, distance, cost }
+                    })
+                }),
+            );
+        }
+
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+        table.set_titles(row!["Origin", "Destination", "Distance", metric]);
+        let mut gross = 0.;
+        for (systems, _) in &plan.legs {
+            for (i, pair) in systems.windows(2).enumerate() {
+                let (a, b) = (&pair[0], &pair[1]);
+                let d = a.distance(b);
+                let mut cost = metric.jump(d);
+                if i + 2 == systems.len() {
+                    cost += arrival(b);
+                }
+                table.add_row(row![
+                    a.name,
+                    b.name,
+                    format!("{:.2} Ly", d),
+                    metric.show(cost)
+                ]);
+                gross += d;
+            }
+        }
+        table.printstd();
+        itinerary(&plan, &metric);
+        println!(
+            "{}: {}, path: {:.2} Ly, distance: {:.2} Ly",
+            metric.to_string().to_lowercase(),
+            metric.show(plan.cost.into_inner()),
+            gross,
+            straight(&plan)
+        );
+    }
+
+    /// Plot and print a route flown on the fuel in the tank
+    ///
+    /// Where the jump range comes from the drive and the mass rather than
+    /// being given, and a route is only as long as the tank lets it be. Each
+    /// leg sets out full, there being a station or a star at every stop.
+    fn run_with_fuel(
+        &self,
+        db: &Database,
+        route: &Route,
+        flown: &Flown,
+        constraints: &RouteConstraints,
+        format: Format,
+        spinner: ProgressBar,
+    ) {
+        let fsd = flown.fsd;
+        spinner.set_message(format!(
+            "Finding route, {:.2} Ly per jump at most...",
+            fsd.range(flown.mass, fsd.max_fuel_per_jump)
+        ));
+        let plan = route.plan(db, |a, b| {
+            if !task::block_on(constraints.stops_at(db, b))? {
+                return Ok(None);
+            }
+            a.route_with_fuel(db, b, &fsd, flown.mass, flown.tank, constraints)
+        });
+        spinner.finish_and_clear();
+        let plan = match plan {
+            Ok(plan) => plan,
+            Err(err) => failed(err),
+        };
+        if let Some(file) = file(format) {
+            let jumps: Vec<_> = plan
+                .legs
+                .iter()
+                .flat_map(|(jumps, _)| jumps)
+                .cloned()
+                .collect();
+            return export(file, task::block_on(Waypoint::fuelled(db, &jumps)));
+        }
+        if let Some(format) = listed(format) {
+            return records(
+                format,
+                plan.legs.iter().flat_map(|(jumps, _)| jumps),
+            );
+        }
+
         let mut table = Table::new();
         table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
-        table.set_titles(row!["Origin", "Destination", "Distance"]);
-        let (route, cost) = start.route_to(db, &end, self.range).unwrap();
+        table.set_titles(row![
+            "Origin",
+            "Destination",
+            "Distance",
+            "Fuel Used",
+            "Fuel Left",
+            "Refuel"
+        ]);
+        let mut gross = 0.;
+        let mut burned = 0.;
+        let mut refuels = 0;
+        for jump in plan.legs.iter().flat_map(|(jumps, _)| jumps) {
+            table.add_row(row![
+                jump.from.name,
+                jump.to.name,
+                format!("{:.2} Ly", jump.distance),
+                format!("{:.2} t", jump.fuel_used),
+                format!("{:.2} t", jump.fuel_left),
+                if jump.refuel { "scoop" } else { "" }
+            ]);
+            gross += jump.distance;
+            burned += jump.fuel_used;
+            refuels += jump.refuel as usize;
+        }
+        table.printstd();
+        itinerary(&plan, &Metric::Jumps);
+        println!(
+            "jumps: {:.2}, path: {:.2} Ly, fuel: {:.2} t, refuels: {}",
+            plan.cost, gross, burned, refuels
+        );
+    }
+
+    /// Plot and print a route that supercharges where it can
+    ///
+    /// Each boosted leg is marked with the star that boosted it, the rest
+    /// being jumps the drive makes on its own.
+    fn run_boosted(
+        &self,
+        db: &Database,
+        route: &Route,
+        flown: &Flown,
+        constraints: &RouteConstraints,
+        format: Format,
+        spinner: ProgressBar,
+    ) {
+        spinner.set_message("Finding route through jet cones...");
+        let plan = route.plan(db, |a, b| {
+            if !task::block_on(constraints.stops_at(db, b))? {
+                return Ok(None);
+            }
+            a.route_boosted_to(db, b, flown.range, constraints)
+        });
         spinner.finish_and_clear();
+        let plan = match plan {
+            Ok(plan) => plan,
+            Err(err) => failed(err),
+        };
+        if let Some(file) = file(format) {
+            let systems = through(&plan, |jumps| {
+                jumps
+                    .first()
+                    .map(|jump| jump.from.clone())
+                    .into_iter()
+                    .chain(jumps.iter().map(|jump| jump.to.clone()))
+                    .collect()
+            });
+            return export(file, task::block_on(Waypoint::along(db, &systems)));
+        }
+        if let Some(format) = listed(format) {
+            return records(
+                format,
+                plan.legs.iter().flat_map(|(jumps, _)| jumps),
+            );
+        }
+
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+        table.set_titles(row!["Origin", "Destination", "Distance", "Boost"]);
         let mut gross = 0.;
-        for (a, b) in route[..].into_iter().tuple_windows() {
-            let d = a.distance(&b);
-            table.add_row(row![a.name, b.name, format!("{:.2} Ly", d)]);
-            gross += d;
+        let mut boosted = 0;
+        for jump in plan.legs.iter().flat_map(|(jumps, _)| jumps) {
+            table.add_row(row![
+                jump.from.name,
+                jump.to.name,
+                format!("{:.2} Ly", jump.distance),
+                jump.boost.map(|b| b.to_string()).unwrap_or_default()
+            ]);
+            gross += jump.distance;
+            boosted += jump.boost.is_some() as usize;
         }
         table.printstd();
+        itinerary(&plan, &Metric::Jumps);
         println!(
-            "jumps: {:.2}, path: {:.2} Ly, distance: {:.2} Ly",
-            cost,
+            "jumps: {:.2}, boosted: {}, path: {:.2} Ly, distance: {:.2} Ly",
+            plan.cost,
+            boosted,
             gross,
-            route[0].distance(&route.last().expect("valid route"))
+            straight(&plan)
         );
     }
 }
 
-// enum Route {
-//     End,
-//     Stop(String),
-//     // `A -> B` specifies a direct path from A to B
-//     Path(Box<Route>, Box<Route>),
-//     // `A + B` specifies a path to both A and B, where the route could either visit
-//     // A or B first
-//     Both(Box<Route>, Box<Route>),
-//     // `A | B` specifies a path to either A or B
-//     Either(Box<Route>, Box<Route>),
-// }
+/// Print the stops in the order a plan visits them, and what each leg cost
+///
+/// Only for a route of more than one leg. With one, the jumps above are the
+/// whole of it.
+fn itinerary<L>(plan: &Plan<L>, metric: &Metric) {
+    if plan.legs.len() < 2 {
+        return;
+    }
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.set_titles(row!["Leg", "From", "To", metric]);
+    for (i, (pair, (_, cost))) in
+        plan.stops.windows(2).zip(&plan.legs).enumerate()
+    {
+        table.add_row(row![
+            i + 1,
+            pair[0].name,
+            pair[1].name,
+            metric.show(cost.into_inner())
+        ]);
+    }
+    table.printstd();
+}
+
+/// Every system a plan passes through, each stop once
+///
+/// A leg starts where the last one ended, so that stop is only counted the
+/// first time. `systems` says what systems a leg is.
+fn through<L>(
+    plan: &Plan<L>,
+    systems: impl Fn(&L) -> Vec<System>,
+) -> Vec<System> {
+    let mut all: Vec<System> = Vec::new();
+    for (leg, _) in &plan.legs {
+        let leg = systems(leg);
+        let skip = usize::from(!all.is_empty());
+        all.extend(leg.into_iter().skip(skip));
+    }
+    all
+}
+
+/// One jump of a route in jumps of a fixed range, and what it cost
+#[derive(Serialize)]
+struct Jump<'a> {
+    from: &'a System,
+    to: &'a System,
+    distance: f64,
+    cost: f64,
+}
+
+/// The file a route is written as in `format`, if it is one
+fn file(format: Format) -> Option<export::Format> {
+    match format {
+        Format::Csv => Some(export::Format::Csv),
+        Format::Spansh => Some(export::Format::Spansh),
+        Format::NavRoute => Some(export::Format::NavRoute),
+        Format::Table | Format::Json | Format::Ndjson => None,
+    }
+}
+
+/// Say why no route could be plotted, and exit as having failed
+fn failed(err: impl fmt::Display) -> ! {
+    eprintln!("{}", err);
+    process::exit(1)
+}
+
+/// The format a route is written in jump by jump, if it is one
+fn listed(format: Format) -> Option<Format> {
+    matches!(format, Format::Json | Format::Ndjson).then_some(format)
+}
+
+/// Write a route to stdout as a file in `format`
+fn export(
+    format: export::Format,
+    waypoints: Result<Vec<Waypoint>, galos_db::Error>,
+) {
+    let waypoints = match waypoints {
+        Ok(waypoints) => waypoints,
+        Err(err) => return eprintln!("{}", err),
+    };
+    let mut out = io::stdout().lock();
+    if let Err(err) = format.write(&mut out, &waypoints, Utc::now()) {
+        eprintln!("{}", err);
+    }
+}
+
+/// Write each jump to stdout as a record in `format`, as it is taken
+fn records<T: Serialize>(format: Format, jumps: impl IntoIterator<Item = T>) {
+    let mut output = Output::stdout(format);
+    let written = jumps.into_iter().try_for_each(|jump| output.write(&jump));
+    if let Err(err) = written.and_then(|_| output.finish()) {
+        eprintln!("{}", err);
+    }
+}
+
+/// How far the last stop is from the first, as the crow flies
+fn straight<L>(plan: &Plan<L>) -> f64 {
+    match (plan.stops.first(), plan.stops.last()) {
+        (Some(first), Some(last)) => first.distance(last),
+        _ => 0.,
+    }
+}
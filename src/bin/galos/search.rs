@@ -1,46 +1,116 @@
 use async_std::task;
-use galos::Run;
+use futures::StreamExt;
+use galos::output::{Format, Output};
+use galos_db::search::{Clause, Order, Query, Region, Term};
 use galos_db::{bodies::Body, factions::Faction, systems::System, Database};
 use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use serde_json::json;
+use std::error::Error;
 use std::time::Duration;
 use structopt::StructOpt;
 
-#[allow(dead_code)]
 #[derive(StructOpt, Debug)]
 pub struct Cli {
-    /// Systems:
-    ///     *Sol
-    ///     *LHS%
-    /// Factions:
-    ///     @newp
-    ///     @New LHS 3728 Alliance
-    /// Systems + Factions:
-    ///     *Sol@    Stars named Sol and their factions
-    ///     *@newp   Stars with newp factions (not null)
-
-    #[structopt(short = "s", long = "systems", name = "SYSTEM(s)")]
-    pub system_like: Option<String>,
-
+    /// Systems to find, e.g. `Meliae cube=40Ly factions={influence<7.5%}`
+    ///
+    /// Names are globs, `*` for anything. Clauses compare `population`,
+    /// `security`, `government`, `allegiance` and `economy`, search around
+    /// the systems named with `sphere=` or `cube=`, and ask of the factions
+    /// present with `factions={...}`. `-` in front of a clause negates it.
+    #[structopt(name = "QUERY", allow_hyphen_values = true)]
+    pub query: Vec<String>,
+
+    /// Factions named like this, instead of systems
     #[structopt(short = "f", long = "factions", name = "FACTION(s)")]
     pub faction_like: Option<String>,
 
-    #[structopt(short = "d", long = "diameter")]
-    pub diameter: Option<f64>,
+    /// What to sort by: name, population, distance or factions.influence,
+    /// `-` in front for the highest first
+    #[structopt(short = "o", long = "order")]
+    pub order: Option<Order>,
 
-    #[structopt(short = "r", long = "radius")]
-    pub radius: Option<f64>,
+    /// How many systems to print at most
+    #[structopt(short = "l", long = "limit")]
+    pub limit: Option<i64>,
 
     #[structopt(short = "c", long = "count")]
     pub count: bool,
-    // #[structopt(short = "f", long = "filter", parse(from_filter_string))]
-    // pub filters: Vec<String>,
 
-    // TODO: What is the best way to handle filters for systems, factions, etc.
-    // We don't want full SQL obviously.
+    /// Deprecated, a `LIKE` pattern for a name: put the name in QUERY
+    #[structopt(
+        short = "s",
+        long = "systems",
+        name = "SYSTEM(s)",
+        hidden = true
+    )]
+    pub system_like: Option<String>,
+
+    /// Deprecated, systems within this many Ly: add `sphere=` to QUERY
+    #[structopt(short = "r", long = "radius", hidden = true)]
+    pub radius: Option<f64>,
+
+    /// Deprecated, systems within a sphere this wide: add `sphere=` to QUERY
+    #[structopt(short = "d", long = "diameter", hidden = true)]
+    pub diameter: Option<f64>,
 }
 
-impl Run for Cli {
-    fn run(&self, db: &Database) {
+/// A system found, and the bodies known in it
+#[derive(Serialize)]
+struct Found {
+    #[serde(flatten)]
+    system: System,
+    bodies: Vec<Body>,
+}
+
+impl Cli {
+    /// The query typed, with what the old flags asked for added to it
+    ///
+    /// `-s`, `-r` and `-d` are what searches were before there was a query,
+    /// and still mean what they did, with a warning saying how to ask for the
+    /// same in one.
+    fn query(&self) -> Result<Option<Query>, String> {
+        let mut query = if self.query.is_empty() {
+            None
+        } else {
+            Some(self.query.join(" ").parse::<Query>()?)
+        };
+
+        if let Some(pattern) = &self.system_like {
+            eprintln!("warning: -s is deprecated, put the name in the query");
+            query.get_or_insert(Query { clauses: vec![] }).clauses.push(
+                Clause {
+                    negated: false,
+                    term: Term::Named(vec![pattern.clone()]),
+                },
+            );
+        }
+
+        let radius = match (self.radius, self.diameter) {
+            (Some(radius), _) => {
+                eprintln!("warning: -r is deprecated, use sphere={}Ly", radius);
+                Some(radius)
+            }
+            (None, Some(diameter)) => {
+                eprintln!(
+                    "warning: -d is deprecated, use sphere={}Ly",
+                    diameter / 2.
+                );
+                Some(diameter / 2.)
+            }
+            (None, None) => None,
+        };
+        if let Some(radius) = radius {
+            let query = query.as_mut().ok_or("a radius is around systems")?;
+            query.clauses.push(Clause {
+                negated: false,
+                term: Term::Within(Region::Sphere(radius)),
+            });
+        }
+        Ok(query)
+    }
+
+    pub fn run(&self, db: &Database, format: Format) {
         let spinner = ProgressBar::new_spinner();
         spinner.set_style(
             ProgressStyle::default_spinner()
@@ -53,59 +123,105 @@ impl Run for Cli {
         );
         spinner.enable_steady_tick(Duration::from_millis(125));
 
-        task::block_on(async {
-            match (self.system_like.as_ref(), self.faction_like.as_ref()) {
+        let query = match self.query() {
+            Ok(query) => query,
+            Err(err) => {
+                spinner.finish_and_clear();
+                eprintln!("{}", err);
+                return;
+            }
+        };
+
+        let mut output = Output::stdout(format);
+        let result = task::block_on(async {
+            match (query, self.faction_like.as_ref()) {
                 (Some(query), None) => {
-                    let systems = if let Some(radius) = self.radius {
-                        System::fetch_in_range_like_name(db, radius, &query)
-                            .await
-                            .unwrap()
-                    } else {
-                        System::fetch_like_name(db, &query).await.unwrap()
+                    if self.count {
+                        let count = match query.count() {
+                            Ok(statement) => statement.count(db).await?,
+                            Err(err) => {
+                                spinner.finish_and_clear();
+                                eprintln!("{}", err);
+                                return Ok(());
+                            }
+                        };
+                        spinner.finish_and_clear();
+                        if format == Format::Table {
+                            println!("{} systems found.", count);
+                        } else {
+                            output.write(&json!({ "systems": count }))?;
+                        }
+                        return Ok(());
+                    }
+
+                    let statement = match query.select(self.order, self.limit) {
+                        Ok(statement) => statement,
+                        Err(err) => {
+                            spinner.finish_and_clear();
+                            eprintln!("{}", err);
+                            return Ok(());
+                        }
                     };
 
+                    // Systems are printed as they come, rather than once
+                    // they all have, there being no telling how many match.
+                    let mut systems = statement.systems(db).boxed();
                     spinner.finish_and_clear();
 
-                    if self.count {
-                        println!("{} systems found.", systems.len());
-                    } else {
-                        for system in systems {
+                    while let Some(system) = systems.next().await {
+                        let system = system?;
+                        let bodies =
+                            Body::fetch_all(db, system.address).await?;
+                        if format == Format::Table {
                             print_system(&system);
-
-                            let bodies = Body::fetch_all(db, system.address)
-                                .await
-                                .unwrap();
                             if !bodies.is_empty() {
                                 println!("\tbodies:");
                                 for body in bodies {
                                     println!("\t\t- {}", body.name);
                                 }
                             }
+                        } else {
+                            output.write(&Found { system, bodies })?;
                         }
                     }
                 }
 
                 (None, Some(query)) => {
-                    let factions =
-                        Faction::fetch_like_name(db, &query).await.unwrap();
+                    let factions = Faction::fetch_like_name(db, query).await?;
 
                     spinner.finish_and_clear();
 
                     if self.count {
-                        println!("{} factions found.", factions.len());
+                        if format == Format::Table {
+                            println!("{} factions found.", factions.len());
+                        } else {
+                            output.write(
+                                &json!({ "factions": factions.len() }),
+                            )?;
+                        }
                     } else {
                         for faction in factions {
-                            println!("{:?}", faction)
+                            if format == Format::Table {
+                                println!("{:?}", faction)
+                            } else {
+                                output.write(&faction)?;
+                            }
                         }
                     }
                 }
 
                 (Some(_), Some(_)) | (None, None) => {
+                    spinner.finish_and_clear();
                     // XXX: Why is -r being printed after the next shell prompt?!
                     Cli::clap().print_help().expect("issue printing help")
                 }
             }
+            Ok::<_, Box<dyn Error>>(())
         });
+        spinner.finish_and_clear();
+        if let Err(err) = result.and_then(|_| Ok(output.finish()?)) {
+            eprintln!("{}", err);
+        }
     }
 }
 
@@ -114,7 +230,7 @@ fn print_system(system: &System) {
     if let Some(position) = system.position {
         print!("({}, {}, {})", position.x, position.y, position.z);
     }
-    println!("");
+    println!();
     if system.population > 0 {
         println!("\tpopulation: {}", system.population);
     }
@@ -132,6 +248,6 @@ fn print_system(system: &System) {
         if let Some(secondary) = economies.secondary {
             print!("/{:?}", secondary);
         }
-        println!("");
+        println!();
     }
 }
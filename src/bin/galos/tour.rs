@@ -0,0 +1,137 @@
+use crate::route::Constraints;
+use async_std::task;
+use galos::output::{Format, Output};
+use galos_db::{systems::System, Database};
+use indicatif::ProgressBar;
+use prettytable::{format, Table};
+use serde::Serialize;
+use std::time::Duration;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+pub struct Cli {
+    /// The systems to visit, in no particular order
+    pub systems: Vec<String>,
+
+    #[structopt(
+        short = "F",
+        long,
+        help = "Visit every system this faction is present in"
+    )]
+    pub faction: Option<String>,
+    #[structopt(long, help = "Start here rather than wherever is shortest")]
+    pub from: Option<String>,
+    #[structopt(default_value = "7.5", short = "r", long)]
+    pub range: f64,
+
+    #[structopt(flatten)]
+    pub constraints: Constraints,
+}
+
+/// A stop on a tour, and the jumps from the one before
+#[derive(Serialize)]
+struct Stop<'a> {
+    stop: usize,
+    system: &'a System,
+    jumps: Option<usize>,
+    distance: Option<f64>,
+}
+
+impl Cli {
+    pub fn run(&self, db: &Database, format: Format) {
+        let spinner = ProgressBar::new_spinner();
+        spinner.enable_steady_tick(Duration::from_millis(100));
+        spinner.set_message("Finding systems...");
+
+        let constraints = match self.constraints.resolve(db) {
+            Ok(constraints) => constraints,
+            Err(err) => {
+                spinner.finish_and_clear();
+                eprintln!("{}", err);
+                return;
+            }
+        };
+
+        let stops = task::block_on(async {
+            let mut stops = Vec::new();
+            if let Some(from) = &self.from {
+                stops.push(System::fetch_by_name(db, from).await?);
+            }
+            for name in &self.systems {
+                stops.push(System::fetch_by_name(db, name).await?);
+            }
+            if let Some(faction) = &self.faction {
+                stops.extend(System::fetch_faction(db, faction).await?);
+            }
+            Ok::<_, galos_db::Error>(stops)
+        });
+        let stops = match stops {
+            Ok(stops) => stops,
+            Err(err) => {
+                spinner.finish_and_clear();
+                eprintln!("{}", err);
+                return;
+            }
+        };
+
+        spinner.set_message(format!(
+            "Plotting between {} systems...",
+            stops.len()
+        ));
+        let tour = System::tour(
+            db,
+            &stops,
+            self.range,
+            self.from.is_some(),
+            &constraints,
+        );
+        spinner.finish_and_clear();
+        let tour = match tour {
+            Ok(Some(tour)) => tour,
+            Ok(None) => return println!("no tour reaches every system"),
+            Err(err) => return eprintln!("{}", err),
+        };
+
+        let jumps = tour.jumps();
+        if format != Format::Table {
+            let mut output = Output::stdout(format);
+            let written =
+                tour.stops.iter().enumerate().try_for_each(|(i, stop)| {
+                    output.write(&Stop {
+                        stop: i + 1,
+                        system: stop,
+                        jumps: i.checked_sub(1).map(|i| jumps[i]),
+                        distance: i
+                            .checked_sub(1)
+                            .map(|i| tour.stops[i].distance(stop)),
+                    })
+                });
+            if let Err(err) = written.and_then(|_| output.finish()) {
+                eprintln!("{}", err);
+            }
+            return;
+        }
+
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+        table.set_titles(row!["Stop", "System", "Jumps", "Distance"]);
+        for (i, stop) in tour.stops.iter().enumerate() {
+            let (jumps, distance) = if i == 0 {
+                (String::new(), String::new())
+            } else {
+                let before = &tour.stops[i - 1];
+                (
+                    jumps[i - 1].to_string(),
+                    format!("{:.2} Ly", before.distance(stop)),
+                )
+            };
+            table.add_row(row![i + 1, stop.name, jumps, distance]);
+        }
+        table.printstd();
+        println!(
+            "stops: {}, jumps: {}",
+            tour.stops.len(),
+            jumps.iter().sum::<usize>()
+        );
+    }
+}
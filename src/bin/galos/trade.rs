@@ -0,0 +1,202 @@
+use async_std::task;
+use chrono::Duration as Age;
+use galos::output::{Format, Output};
+use galos_db::{
+    markets::Criteria,
+    stations::PadSize,
+    systems::{
+        nav::trade::{Rank, TradeRoute, Trader},
+        System,
+    },
+    Database,
+};
+use indicatif::{ProgressBar, ProgressStyle};
+use prettytable::{format, Table};
+use serde::Serialize;
+use std::time::Duration;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+pub struct Cli {
+    /// The system to trade from
+    pub start: String,
+
+    #[structopt(
+        default_value = "40",
+        long,
+        help = "How far from the start to look for markets, in Ly"
+    )]
+    pub radius: f64,
+    #[structopt(default_value = "7.5", short = "r", long)]
+    pub range: f64,
+    #[structopt(default_value = "100", short = "t", long)]
+    pub capacity: i64,
+    #[structopt(default_value = "1000000", long)]
+    pub credits: i64,
+    #[structopt(short = "p", long, help = "S, M or L")]
+    pub pad: Option<PadSize>,
+    #[structopt(long, help = "Ignore prices older than this, in hours")]
+    pub max_age: Option<i64>,
+    #[structopt(default_value = "0", long)]
+    pub min_demand: i32,
+    #[structopt(default_value = "0", long)]
+    pub min_stock: i32,
+
+    #[structopt(long, help = "Carry a cargo back as well (A -> B -> A)")]
+    pub round_trip: bool,
+    #[structopt(
+        default_value = "ton",
+        short = "b",
+        long = "by",
+        help = "Rank by profit per ton, jump or hour"
+    )]
+    pub rank: Rank,
+    #[structopt(default_value = "10", short = "l", long)]
+    pub limit: usize,
+}
+
+/// A trade found, and what it makes
+#[derive(Serialize)]
+struct Found<'a> {
+    #[serde(flatten)]
+    route: &'a TradeRoute,
+    profit_per_ton: i64,
+    profit_per_jump: f64,
+    profit_per_hour: f64,
+    profit: i64,
+}
+
+impl Cli {
+    pub fn run(&self, db: &Database, format: Format) {
+        let spinner = ProgressBar::new_spinner();
+        spinner.set_style(
+            ProgressStyle::default_spinner()
+                .tick_strings(&["$  ", " $ ", "  $", " $ ", "$$$"])
+                .template("{spinner:.yellow} {msg}")
+                .unwrap(),
+        );
+        spinner.enable_steady_tick(Duration::from_millis(150));
+        spinner.set_message("Weighing markets...");
+
+        let start = match task::block_on(System::fetch_by_name(db, &self.start))
+        {
+            Ok(start) => start,
+            Err(err) => {
+                spinner.finish_and_clear();
+                if err.is_not_found() {
+                    return eprintln!("no system named {}", self.start);
+                }
+                return eprintln!("{}", err);
+            }
+        };
+        let trader = Trader {
+            capacity: self.capacity,
+            credits: self.credits,
+            range: self.range,
+            radius: self.radius,
+            criteria: Criteria {
+                pad: self.pad,
+                max_age: self.max_age.map(Age::hours),
+                min_demand: self.min_demand,
+                min_stock: self.min_stock,
+            },
+            round_trip: self.round_trip,
+            rank: self.rank,
+        };
+        let routes = start.trades(db, &trader, self.limit);
+        spinner.finish_and_clear();
+        let routes = match routes {
+            Ok(routes) => routes,
+            Err(err) => return eprintln!("{}", err),
+        };
+
+        if format != Format::Table {
+            let mut output = Output::stdout(format);
+            let written = routes.iter().try_for_each(|route| {
+                output.write(&Found {
+                    route,
+                    profit_per_ton: route.profit_per_ton(),
+                    profit_per_jump: route.profit_per_jump(),
+                    profit_per_hour: route.profit_per_hour(),
+                    profit: route.profit(),
+                })
+            });
+            if let Err(err) = written.and_then(|_| output.finish()) {
+                eprintln!("{}", err);
+            }
+            return;
+        }
+
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+        if self.round_trip {
+            table.set_titles(row![
+                "Buy At",
+                "Commodity",
+                "Units",
+                "Sell At",
+                "Commodity Back",
+                "Units Back",
+                "Jumps",
+                "Profit/t",
+                "Profit/Jump",
+                "Profit/h",
+                "Profit"
+            ]);
+        } else {
+            table.set_titles(row![
+                "Buy At",
+                "Commodity",
+                "Units",
+                "Sell At",
+                "Jumps",
+                "Profit/t",
+                "Profit/Jump",
+                "Profit/h",
+                "Profit"
+            ]);
+        }
+        for route in &routes {
+            let out = &route.outbound;
+            let from =
+                format!("{} ({})", out.from.station_name, out.from.system_name);
+            let to =
+                format!("{} ({})", out.to.station_name, out.to.system_name);
+            if self.round_trip {
+                let (back, units_back) = match &route.inbound {
+                    Some(t) => {
+                        (t.from.commodity.name.clone(), t.units.to_string())
+                    }
+                    None => ("---".into(), "---".into()),
+                };
+                table.add_row(row![
+                    from,
+                    out.from.commodity.name,
+                    out.units,
+                    to,
+                    back,
+                    units_back,
+                    route.jumps,
+                    route.profit_per_ton(),
+                    format!("{:.0}", route.profit_per_jump()),
+                    format!("{:.0}", route.profit_per_hour()),
+                    route.profit()
+                ]);
+            } else {
+                table.add_row(row![
+                    from,
+                    out.from.commodity.name,
+                    out.units,
+                    to,
+                    route.jumps,
+                    route.profit_per_ton(),
+                    format!("{:.0}", route.profit_per_jump()),
+                    format!("{:.0}", route.profit_per_hour()),
+                    route.profit()
+                ]);
+            }
+        }
+        table.printstd();
+        println!("{} trades found.", routes.len());
+    }
+}
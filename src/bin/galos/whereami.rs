@@ -0,0 +1,124 @@
+use galos::journal::{self, Watcher, Whereabouts};
+use galos::output::{Format, Output};
+use galos_db::Database;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+pub struct Cli {
+    #[structopt(
+        long,
+        parse(from_os_str),
+        help = "The game's journal directory, if not the usual one or \
+                GALOS_JOURNAL"
+    )]
+    pub journal: Option<PathBuf>,
+    #[structopt(
+        short = "f",
+        long,
+        help = "Keep watching, printing each time the commander moves"
+    )]
+    pub follow: bool,
+}
+
+impl Cli {
+    pub fn run(&self, _db: &Database, format: Format) {
+        let dir = match directory(self.journal.as_deref()) {
+            Ok(dir) => dir,
+            Err(err) => return eprintln!("{}", err),
+        };
+
+        let mut output = Output::stdout(format);
+        let mut watcher = Watcher::new(&dir);
+        loop {
+            match watcher.poll() {
+                Ok(changed) if changed || !self.follow => {
+                    print(&mut output, format, watcher.whereabouts())
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    eprintln!("{}: {}", dir.display(), err);
+                    break;
+                }
+            }
+            if !self.follow {
+                break;
+            }
+            thread::sleep(Duration::from_secs(1));
+        }
+        if let Err(err) = output.finish() {
+            eprintln!("{}", err);
+        }
+    }
+}
+
+/// The journal directory given, or else the game's
+fn directory(journal: Option<&Path>) -> Result<PathBuf, String> {
+    journal.map(Path::to_path_buf).or_else(journal::dir).ok_or_else(|| {
+        "no journal directory found, give one with --journal".to_string()
+    })
+}
+
+/// Who the commander is and where, as far as the journals say
+///
+/// Only an error when a directory was given and could not be read. Not
+/// finding the game's own is nothing to complain of, the route is plotted
+/// from where it says, and nothing is known.
+pub fn here(journal: Option<&Path>) -> Result<Whereabouts, String> {
+    let dir = match (journal, journal::dir()) {
+        (Some(dir), _) => dir.to_path_buf(),
+        (None, Some(dir)) => dir,
+        (None, None) => return Ok(Whereabouts::default()),
+    };
+    match journal::whereabouts(&dir) {
+        Ok(whereabouts) => Ok(whereabouts),
+        Err(err) if journal.is_some() => {
+            Err(format!("{}: {}", dir.display(), err))
+        }
+        Err(_) => Ok(Whereabouts::default()),
+    }
+}
+
+fn print(
+    output: &mut Output<std::io::Stdout>,
+    format: Format,
+    whereabouts: &Whereabouts,
+) {
+    if format != Format::Table {
+        if let Err(err) = output.write(whereabouts) {
+            eprintln!("{}", err);
+        }
+        return;
+    }
+
+    let unknown = || "unknown".to_string();
+    println!(
+        "Commander: {}",
+        whereabouts.commander.clone().unwrap_or_else(unknown)
+    );
+    print!("System: {}", whereabouts.system.clone().unwrap_or_else(unknown));
+    if let Some(p) = whereabouts.position {
+        print!(" ({}, {}, {})", p.x, p.y, p.z);
+    }
+    println!();
+    if let Some(station) = &whereabouts.station {
+        println!("Docked: {}", station);
+    }
+    if let Some(body) = &whereabouts.body {
+        let landed = if whereabouts.landed { ", landed" } else { "" };
+        println!("Body: {}{}", body, landed);
+    }
+    if let Some(ship) = &whereabouts.ship {
+        print!("Ship: ");
+        if let Some(name) = &ship.name {
+            print!("{} ", name);
+        }
+        print!("({})", ship.ty);
+        if let Some(ident) = &ship.ident {
+            print!(" {}", ident);
+        }
+        println!();
+    }
+}
@@ -0,0 +1,549 @@
+//! EDSM's records, written back into the journal events they came from
+//!
+//! Everything EDSM holds of a body, a station or a faction was sent to it as
+//! a `Scan`, a `Docked` or an `FSDJump`, and it is written here as one of
+//! those again, so that it is read by the journal's own parser and written
+//! by [`crate::ingest`] the same as anything EDDN relays. What EDSM renamed
+//! or measured in other units is put back as the game has it.
+use super::records::{Body, Faction, Populated, Station};
+use elite_journal::entry::{Entry, Event};
+use elite_journal::system::Coordinate;
+use serde_json::{json, Map, Value};
+
+const AU: f64 = 149_597_870_700.;
+const DAY: f64 = 86_400.;
+const G: f64 = 9.806_65;
+const ATMOSPHERE: f64 = 101_325.;
+const SOLAR_RADIUS: f64 = 695_700_000.;
+
+/// An event, as the journal's parser reads it
+pub fn entry(event: Value) -> Result<Entry<Event>, String> {
+    serde_json::from_value(event).map_err(|err| err.to_string())
+}
+
+/// When, as the journal writes it, from EDSM's `2017-02-25 13:09:06`
+fn timestamp(date: Option<&str>) -> Result<String, String> {
+    let date = date.ok_or("no date")?;
+    Ok(format!("{}Z", date.replacen(' ', "T", 1)))
+}
+
+/// A `Scan` of `body`, in the system at `address`
+///
+/// EDSM's bodies do not say where their system is, which a scan does, so
+/// that is asked of the caller.
+pub fn scan(
+    body: &Body,
+    address: i64,
+    system: &str,
+    position: Coordinate,
+) -> Result<Value, String> {
+    let mut scan = json!({
+        "timestamp": timestamp(body.update_time.as_deref())?,
+        "event": "Scan",
+        "ScanType": "Detailed",
+        "BodyName": body.name,
+        "BodyID": body.body_id.ok_or("no body id")?,
+        "StarSystem": system,
+        "SystemAddress": address,
+        "StarPos": [position.x, position.y, position.z],
+        "DistanceFromArrivalLS": body.distance_to_arrival.unwrap_or(0.),
+        // Discovered if EDSM knows who by, which a body found by someone not
+        // sharing with EDSM is not. Whether it has been mapped it does not
+        // say, and is taken not to have been.
+        "WasDiscovered": body.discovery.is_some(),
+        "WasMapped": false,
+    });
+    let fields = scan.as_object_mut().expect("an object");
+    put(fields, "Parents", body.parents.as_ref().map(|p| json!(p)));
+    put(fields, "SurfaceTemperature", body.surface_temperature);
+    put(fields, "SemiMajorAxis", body.semi_major_axis.map(|a| a * AU));
+    put(fields, "Eccentricity", body.orbital_eccentricity);
+    put(fields, "OrbitalInclination", body.orbital_inclination);
+    put(fields, "Periapsis", body.arg_of_periapsis);
+    put(fields, "OrbitalPeriod", body.orbital_period.map(|p| p * DAY));
+    put(fields, "RotationPeriod", body.rotational_period.map(|p| p * DAY));
+    put(fields, "AxialTilt", body.axial_tilt.map(f64::to_radians));
+
+    match body.ty.as_str() {
+        "Star" => {
+            let (class, subclass) = star_type(body)?;
+            fields.insert("StarType".into(), json!(class));
+            fields.insert("Subclass".into(), json!(subclass));
+            put(fields, "StellarMass", body.solar_masses);
+            put(fields, "Radius", body.solar_radius.map(|r| r * SOLAR_RADIUS));
+            put(fields, "AbsoluteMagnitude", body.absolute_magnitude);
+            put(fields, "Age_MY", body.age);
+            put(fields, "Luminosity", body.luminosity.clone());
+        }
+        "Planet" => {
+            let class = body.sub_type.as_deref().ok_or("no planet class")?;
+            fields.insert("PlanetClass".into(), json!(planet_class(class)));
+            put(fields, "MassEM", body.earth_masses);
+            put(fields, "Radius", body.radius.map(|r| r * 1000.));
+            put(fields, "SurfaceGravity", body.gravity.map(|g| g * G));
+            put(
+                fields,
+                "SurfacePressure",
+                body.surface_pressure.map(|p| p * ATMOSPHERE),
+            );
+            put(fields, "Landable", body.is_landable);
+            put(fields, "TidalLock", body.rotational_period_tidally_locked);
+            put(
+                fields,
+                "TerraformState",
+                body.terraforming_state.as_deref().map(terraform_state),
+            );
+            put(
+                fields,
+                "Volcanism",
+                body.volcanism_type.as_deref().map(volcanism),
+            );
+            if let Some(atmosphere) = body.atmosphere_type.as_deref() {
+                let (text, ty) = atmosphere_of(atmosphere);
+                fields.insert("Atmosphere".into(), json!(text));
+                fields.insert("AtmosphereType".into(), json!(ty));
+            }
+            if let Some(gases) = &body.atmosphere_composition {
+                fields.insert("AtmosphereComposition".into(), shares(gases));
+            }
+            if let Some(materials) = &body.materials {
+                let materials: Vec<Value> = materials
+                    .iter()
+                    .map(|(name, percent)| {
+                        json!({"Name": name.to_lowercase(), "Percent": percent})
+                    })
+                    .collect();
+                fields.insert("Materials".into(), json!(materials));
+            }
+            if let Some(solid) = &body.solid_composition {
+                let fraction =
+                    |part: &str| solid.get(part).map_or(0., |p| p / 100.);
+                fields.insert(
+                    "Composition".into(),
+                    json!({
+                        "Ice": fraction("Ice"),
+                        "Rock": fraction("Rock"),
+                        "Metal": fraction("Metal"),
+                    }),
+                );
+            }
+        }
+        ty => return Err(format!("unknown body type {}", ty)),
+    }
+    Ok(scan)
+}
+
+/// A `Docked` at `station`, in the system at `address`
+pub fn docked(
+    station: &Station,
+    address: i64,
+    system: &str,
+) -> Result<Value, String> {
+    let info =
+        station.update_time.as_ref().and_then(|t| t.information.as_deref());
+    let mut docked = json!({
+        "timestamp": timestamp(info)?,
+        "event": "Docked",
+        "StationName": station.name,
+        "StarSystem": system,
+        "SystemAddress": address,
+        "StationServices": services(station),
+    });
+    let fields = docked.as_object_mut().expect("an object");
+    put(fields, "MarketID", station.market_id);
+    put(fields, "StationType", station.ty.as_deref().and_then(station_type));
+    put(fields, "DistFromStarLS", station.distance_to_arrival);
+    put(fields, "StationAllegiance", station.allegiance.clone());
+    put(
+        fields,
+        "StationGovernment",
+        station.government.as_deref().map(government),
+    );
+    if let Some(faction) = &station.controlling_faction {
+        fields.insert("StationFaction".into(), json!({"Name": faction.name}));
+    }
+    if let Some(primary) = station.economy.as_deref() {
+        fields.insert("StationEconomy".into(), json!(economy(primary)));
+        // EDSM keeps which economies, not in what proportion, so the
+        // primary is taken to be the whole of it.
+        fields.insert(
+            "StationEconomies".into(),
+            json!([{"Name": economy(primary), "Proportion": 1.0}]),
+        );
+    }
+    Ok(docked)
+}
+
+/// A `Location` in a populated system, its factions and all
+pub fn location(system: &Populated) -> Result<Value, String> {
+    let position = system.coords.ok_or("no coordinates")?;
+    let mut location = json!({
+        "timestamp": timestamp(system.date.as_deref())?,
+        "event": "Location",
+        "Docked": false,
+        "StarSystem": system.name,
+        "SystemAddress": system.id64.ok_or("no address")?,
+        "StarPos": [position.x, position.y, position.z],
+        "Factions": system.factions.iter().map(faction).collect::<Vec<_>>(),
+    });
+    let fields = location.as_object_mut().expect("an object");
+    put(fields, "Population", system.population);
+    put(fields, "SystemAllegiance", system.allegiance.clone());
+    put(
+        fields,
+        "SystemGovernment",
+        system.government.as_deref().map(government),
+    );
+    put(fields, "SystemEconomy", system.economy.as_deref().map(economy));
+    put(fields, "SystemSecurity", system.security.as_deref().map(security));
+    if let Some(faction) = &system.controlling_faction {
+        fields.insert("SystemFaction".into(), json!({"Name": faction.name}));
+    }
+    Ok(location)
+}
+
+fn faction(faction: &Faction) -> Value {
+    let states = |states: &[super::records::FactionState]| -> Vec<Value> {
+        states
+            .iter()
+            .map(|s| json!({"State": state(&s.state), "Trend": 0}))
+            .collect()
+    };
+    let mut entry = json!({
+        "Name": faction.name,
+        "FactionState": state(faction.state.as_deref().unwrap_or("None")),
+        "Influence": faction.influence.unwrap_or(0.),
+        "ActiveStates": states(&faction.active_states),
+        "PendingStates": states(&faction.pending_states),
+        "RecoveringStates": states(&faction.recovering_states),
+    });
+    let fields = entry.as_object_mut().expect("an object");
+    put(fields, "Government", faction.government.clone());
+    put(fields, "Allegiance", faction.allegiance.clone());
+    put(fields, "Happiness", faction.happiness.clone());
+    entry
+}
+
+/// Add `value` as `name`, where there is one
+fn put<T: Into<Value>>(
+    fields: &mut Map<String, Value>,
+    name: &str,
+    value: Option<T>,
+) {
+    if let Some(value) = value {
+        fields.insert(name.to_string(), value.into());
+    }
+}
+
+/// Percentages by name, as the journal lists them
+fn shares(shares: &std::collections::BTreeMap<String, f64>) -> Value {
+    shares
+        .iter()
+        .map(|(name, percent)| json!({"Name": name, "Percent": percent}))
+        .collect()
+}
+
+/// Words run together, each capitalised, `Civil unrest` as `CivilUnrest`
+fn camel(words: &str) -> String {
+    words
+        .split([' ', '-'])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut letters = word.chars();
+            letters.next().map_or(String::new(), |first| {
+                first.to_uppercase().chain(letters).collect()
+            })
+        })
+        .collect()
+}
+
+/// The class and subclass of a star, `K1` being `K` and 1
+fn star_type(body: &Body) -> Result<(String, i64), String> {
+    if let Some(class) = &body.spectral_class {
+        let letters: String =
+            class.chars().take_while(|c| c.is_ascii_alphabetic()).collect();
+        let digits: String = class[letters.len()..]
+            .chars()
+            .take_while(char::is_ascii_digit)
+            .collect();
+        if !letters.is_empty() {
+            return Ok((letters, digits.parse().unwrap_or(0)));
+        }
+    }
+    // The white dwarfs as the journal writes them, `DA` for a
+    // `White Dwarf (DA) Star`.
+    let dwarf = body
+        .sub_type
+        .as_deref()
+        .and_then(|kind| kind.strip_prefix("White Dwarf ("))
+        .and_then(|kind| kind.strip_suffix(") Star"));
+    if let Some(class) = dwarf {
+        return Ok((class.to_string(), 0));
+    }
+    let class = match body.sub_type.as_deref() {
+        Some("Neutron Star") => "N",
+        Some("Black Hole") => "H",
+        Some("Supermassive Black Hole") => "SupermassiveBlackHole",
+        Some("Wolf-Rayet Star") => "W",
+        Some("Herbig Ae/Be Star") => "AeBe",
+        Some("T Tauri Star") => "TTS",
+        Some("C Star") => "C",
+        Some("S-type Star") => "S",
+        Some("MS-type Star") => "MS",
+        other => return Err(format!("unknown star {:?}", other)),
+    };
+    Ok((class.to_string(), 0))
+}
+
+/// The game's name for a kind of planet, where EDSM has another
+fn planet_class(class: &str) -> String {
+    match class {
+        "Earth-like world" => "Earthlike body",
+        "High metal content world" => "High metal content body",
+        "Metal-rich body" => "Metal rich body",
+        "Rocky Ice world" => "Rocky ice body",
+        "Gas giant with water-based life" => "Gas giant with water based life",
+        "Gas giant with ammonia-based life" => {
+            "Gas giant with ammonia based life"
+        }
+        "Helium-rich gas giant" => "Helium rich gas giant",
+        "Water-based life gas giant" => "Gas giant with water based life",
+        class => {
+            return match class.strip_prefix("Class ") {
+                Some(rest) => format!("Sudarsky class {}", rest),
+                None => class.to_string(),
+            }
+        }
+    }
+    .to_string()
+}
+
+fn terraform_state(state: &str) -> String {
+    match state {
+        "Not terraformable" => "",
+        "Candidate for terraforming" => "Terraformable",
+        state => state,
+    }
+    .to_string()
+}
+
+fn volcanism(volcanism: &str) -> String {
+    if volcanism == "No volcanism" {
+        String::new()
+    } else {
+        format!("{} volcanism", volcanism.to_lowercase())
+    }
+}
+
+/// What the journal says of an atmosphere, and its type
+fn atmosphere_of(atmosphere: &str) -> (String, String) {
+    let atmosphere = atmosphere.trim_end_matches(" atmosphere");
+    if atmosphere == "No" || atmosphere == "No atmosphere" {
+        return (String::new(), "None".to_string());
+    }
+    let mut gas = atmosphere.to_lowercase();
+    for density in ["hot ", "thin ", "thick "] {
+        if let Some(rest) = gas.strip_prefix(density) {
+            gas = rest.to_string();
+        }
+    }
+    let ty = match gas.as_str() {
+        "suitable for water-based life" => "EarthLike".to_string(),
+        gas => camel(gas),
+    };
+    (format!("{} atmosphere", atmosphere.to_lowercase()), ty)
+}
+
+fn station_type(ty: &str) -> Option<&'static str> {
+    Some(match ty {
+        "Coriolis Starport" => "Coriolis",
+        "Orbis Starport" => "Orbis",
+        "Ocellus Starport" => "Ocellus",
+        "Outpost" => "Outpost",
+        "Planetary Outpost" => "CraterOutpost",
+        "Planetary Port" => "CraterPort",
+        "Asteroid base" => "AsteroidBase",
+        "Mega ship" => "MegaShip",
+        "Fleet Carrier" => "FleetCarrier",
+        "Odyssey Settlement" => "OnFootSettlement",
+        _ => return None,
+    })
+}
+
+/// A station's services, as the journal names them, of those EDSM lists
+fn services(station: &Station) -> Vec<&'static str> {
+    let mut services = vec!["dock"];
+    if station.have_market {
+        services.push("commodities");
+    }
+    if station.have_shipyard {
+        services.push("shipyard");
+    }
+    if station.have_outfitting {
+        services.push("outfitting");
+    }
+    services.extend(station.other_services.iter().filter_map(|service| {
+        Some(match service.as_str() {
+            "Black Market" => "blackmarket",
+            "Restock" => "rearm",
+            "Refuel" => "refuel",
+            "Repair" => "repair",
+            "Contacts" => "contacts",
+            "Universal Cartographics" => "exploration",
+            "Missions" => "missions",
+            "Crew Lounge" => "crewlounge",
+            "Tuning" => "tuning",
+            "Search and Rescue" => "searchrescue",
+            "Material Trader" => "materialtrader",
+            "Technology Broker" => "techBroker",
+            "Interstellar Factors Contact" => "facilitator",
+            _ => return None,
+        })
+    }));
+    services
+}
+
+fn government(government: &str) -> String {
+    let government = match government {
+        "Private Ownership" => "Carrier",
+        government => government,
+    };
+    format!("$government_{};", camel(government))
+}
+
+fn economy(economy: &str) -> String {
+    let economy = match economy {
+        "Agriculture" => "Agri",
+        economy => economy,
+    };
+    format!("$economy_{};", camel(economy))
+}
+
+fn security(security: &str) -> String {
+    match security {
+        "Anarchy" => "$GAlAXY_MAP_INFO_state_anarchy;".to_string(),
+        security => format!("$SYSTEM_SECURITY_{};", security.to_lowercase()),
+    }
+}
+
+fn state(state: &str) -> String {
+    match state {
+        "Terrorist Attack" => "Terrorism".to_string(),
+        state => camel(state),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::records::{Body, Populated, Station};
+    use super::super::Records;
+    use super::*;
+
+    fn fixture<T: serde::de::DeserializeOwned>(name: &str) -> Vec<T> {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src/dumps/fixtures")
+            .join(name);
+        let text = std::fs::read_to_string(path).unwrap();
+        Records::new(text.as_bytes())
+            .map(|record| record.unwrap().unwrap())
+            .collect()
+    }
+
+    const SOL: Coordinate = Coordinate { x: 0., y: 0., z: 0. };
+
+    /// Converted to the game's units, and read by the journal's parser
+    #[test]
+    fn bodies_are_scans() {
+        let bodies: Vec<Body> = fixture("bodies.json");
+        let star = scan(&bodies[0], 10477373803, "Sol", SOL).unwrap();
+        assert_eq!(star["StarType"], "G");
+        assert_eq!(star["Subclass"], 2);
+        assert_eq!(star["timestamp"], "2019-02-18T02:09:26Z");
+
+        let earth = scan(&bodies[1], 10477373803, "Sol", SOL).unwrap();
+        assert_eq!(earth["PlanetClass"], "Earthlike body");
+        assert_eq!(earth["Radius"], 6_371_000.);
+        assert_eq!(earth["AtmosphereType"], "EarthLike");
+        assert_eq!(earth["SemiMajorAxis"], AU);
+        assert_eq!(earth["Composition"]["Metal"], 0.3);
+
+        for event in [star, earth] {
+            assert!(matches!(entry(event).unwrap().event, Event::Scan(_)));
+        }
+    }
+
+    /// Discovered is what EDSM says, rather than assumed of every body
+    #[test]
+    fn discovery_is_edsms() {
+        let mut bodies: Vec<Body> = fixture("bodies.json");
+        let unknown = scan(&bodies[1], 10477373803, "Sol", SOL).unwrap();
+        assert_eq!(unknown["WasDiscovered"], false);
+
+        bodies[1].discovery = serde_json::from_value(json!({
+            "commander": "Jameson",
+            "date": "3300-01-01 00:00:00",
+        }))
+        .unwrap();
+        let found = scan(&bodies[1], 10477373803, "Sol", SOL).unwrap();
+        assert_eq!(found["WasDiscovered"], true);
+    }
+
+    #[test]
+    fn stations_are_dockings() {
+        let stations: Vec<Station> = fixture("stations.json");
+        let docked = docked(&stations[0], 10477373803, "Sol").unwrap();
+        assert_eq!(docked["StationType"], "Orbis");
+        assert_eq!(docked["StationGovernment"], "$government_Democracy;");
+        assert_eq!(docked["StationEconomy"], "$economy_Refinery;");
+        assert!(docked["StationServices"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("shipyard")));
+        assert!(matches!(entry(docked).unwrap().event, Event::Docked(_)));
+    }
+
+    #[test]
+    fn populated_systems_are_locations_with_factions() {
+        let systems: Vec<Populated> = fixture("populated.json");
+        let location = location(&systems[0]).unwrap();
+        assert_eq!(location["SystemSecurity"], "$SYSTEM_SECURITY_high;");
+        assert_eq!(location["Factions"][0]["Name"], "Mother Gaia");
+        assert_eq!(location["Factions"][0]["FactionState"], "CivilLiberty");
+        let Event::Location(e) = entry(location).unwrap().event else {
+            panic!("not a location");
+        };
+        assert_eq!(e.system.factions.len(), 2);
+    }
+
+    /// Remnants are written as the journal writes them, which is what
+    /// routes look for
+    #[test]
+    fn remnants_are_the_journals() {
+        let star = |kind: &str| -> Body {
+            serde_json::from_value(json!({
+                "name": "Sirius B",
+                "type": "Star",
+                "subType": kind,
+            }))
+            .unwrap()
+        };
+        let dwarf = star_type(&star("White Dwarf (DA) Star")).unwrap();
+        assert_eq!(dwarf, ("DA".to_string(), 0));
+        let neutron = star_type(&star("Neutron Star")).unwrap();
+        assert_eq!(neutron, ("N".to_string(), 0));
+    }
+
+    #[test]
+    fn names_are_the_games() {
+        assert_eq!(
+            planet_class("Class III gas giant"),
+            "Sudarsky class III gas giant"
+        );
+        let (text, ty) = atmosphere_of("Thin Sulphur dioxide");
+        assert_eq!(text, "thin sulphur dioxide atmosphere");
+        assert_eq!(ty, "SulphurDioxide");
+        assert_eq!(atmosphere_of("No atmosphere").1, "None");
+        assert_eq!(economy("High Tech"), "$economy_HighTech;");
+        assert_eq!(state("Civil unrest"), "CivilUnrest");
+    }
+}
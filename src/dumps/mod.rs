@@ -0,0 +1,252 @@
+//! EDSM's nightly dumps, read a record at a time
+//!
+//! Each dump is one JSON array, written an element to a line:
+//!
+//! ```notrust
+//! [
+//! {"id":27,"id64":10477373803,"name":"Sol",...},
+//! {"id":28,"id64":...},
+//! ]
+//! ```
+//!
+//! which is read here a line at a time, never holding more than the one
+//! record, so that the gigabytes of bodies take as much memory as the few
+//! megabytes of stations. A file ending in `.gz` is read through
+//! decompression, as EDSM serves them.
+//!
+//! - [`Kind::Systems`], `systemsWithCoordinates.json`
+//! - [`Kind::Bodies`], `bodies.json` and `bodies7days.json`
+//! - [`Kind::Stations`], `stations.json`
+//! - [`Kind::Populated`], `systemsPopulated.json`, the only one with factions
+//!
+//! Bodies, stations and factions are written back into the journal events
+//! EDSM was sent them as, see [`events`], and written by [`crate::ingest`]
+//! as any other journal is.
+use chrono::{DateTime, NaiveDateTime, Utc};
+use flate2::read::MultiGzDecoder;
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read};
+use std::marker::PhantomData;
+use std::path::Path;
+use std::str::FromStr;
+
+pub mod events;
+pub mod records;
+
+/// Which of EDSM's dumps a file is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Systems,
+    Bodies,
+    Stations,
+    Populated,
+}
+
+impl Kind {
+    /// The kind of dump EDSM names a file as, `systems` failing all else
+    pub fn of<P: AsRef<Path>>(path: P) -> Kind {
+        let name = path
+            .as_ref()
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if name.starts_with("bodies") {
+            Kind::Bodies
+        } else if name.starts_with("stations") {
+            Kind::Stations
+        } else if name.starts_with("systemspopulated") {
+            Kind::Populated
+        } else {
+            Kind::Systems
+        }
+    }
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "systems" => Ok(Kind::Systems),
+            "bodies" => Ok(Kind::Bodies),
+            "stations" => Ok(Kind::Stations),
+            "populated" => Ok(Kind::Populated),
+            _ => Err("invalid dump".to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kind::Systems => write!(f, "systems"),
+            Kind::Bodies => write!(f, "bodies"),
+            Kind::Stations => write!(f, "stations"),
+            Kind::Populated => write!(f, "populated"),
+        }
+    }
+}
+
+/// `read`, decompressed when `path` says it is compressed
+pub fn open<R: Read + 'static>(path: &Path, read: R) -> Box<dyn Read> {
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        Box::new(MultiGzDecoder::new(read))
+    } else {
+        Box::new(read)
+    }
+}
+
+/// When a record was last updated, from EDSM's `2017-02-25 13:09:06`
+pub fn updated_at(date: Option<&str>) -> Result<DateTime<Utc>, String> {
+    let date = date.ok_or("no date")?;
+    NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S")
+        .map(|date| date.and_utc())
+        .map_err(|err| format!("bad date {}: {}", date, err))
+}
+
+/// A record that could not be read, and which one it was
+#[derive(Debug)]
+pub struct Rejected {
+    /// Counting from zero, as [`Records::skip_raw`] does
+    pub record: usize,
+    pub error: serde_json::Error,
+}
+
+/// The records of a dump, in order
+pub struct Records<R, T> {
+    lines: io::Lines<BufReader<R>>,
+    read: usize,
+    record: PhantomData<T>,
+}
+
+impl<R: Read, T: DeserializeOwned> Records<R, T> {
+    pub fn new(read: R) -> Self {
+        Records {
+            lines: BufReader::new(read).lines(),
+            read: 0,
+            record: PhantomData,
+        }
+    }
+
+    /// How many records have been read, rejected ones included
+    pub fn read(&self) -> usize {
+        self.read
+    }
+
+    /// Pass over `n` records without parsing them, to pick up where an
+    /// import stopped
+    pub fn skip_raw(&mut self, n: usize) -> io::Result<()> {
+        while self.read < n {
+            match self.next_line()? {
+                Some(_) => self.read += 1,
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// The next line holding a record, without the comma after it
+    fn next_line(&mut self) -> io::Result<Option<String>> {
+        for line in &mut self.lines {
+            let line = line?;
+            let line = line.trim().trim_end_matches(',');
+            if !matches!(line, "" | "[" | "]") {
+                return Ok(Some(line.to_string()));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<R: Read, T: DeserializeOwned> Iterator for Records<R, T> {
+    /// The record, or why it could not be read. Failing to read the file at
+    /// all ends the records.
+    type Item = io::Result<Result<T, Rejected>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = match self.next_line() {
+            Ok(line) => line?,
+            Err(err) => return Some(Err(err)),
+        };
+        let record = self.read;
+        self.read += 1;
+        Some(Ok(serde_json::from_str(&line)
+            .map_err(|error| Rejected { record, error })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::records::{Body, Station, System};
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn fixture(name: &str) -> String {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src/dumps/fixtures")
+            .join(name);
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    fn all<T: DeserializeOwned>(text: &str) -> Vec<T> {
+        Records::new(text.as_bytes())
+            .map(|record| record.unwrap().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn kinds_are_named_for_the_file() {
+        assert_eq!(Kind::of("dumps/bodies7days.json.gz"), Kind::Bodies);
+        assert_eq!(Kind::of("systemsPopulated.json"), Kind::Populated);
+        assert_eq!(Kind::of("stations.json"), Kind::Stations);
+        assert_eq!(Kind::of("systemsWithCoordinates.json"), Kind::Systems);
+    }
+
+    #[test]
+    fn records_are_read_a_line_at_a_time() {
+        let systems: Vec<System> = all(&fixture("systems.json"));
+        let names: Vec<&str> =
+            systems.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["Sol", "Alpha Centauri"]);
+        assert_eq!(systems[1].coords.map(|c| c.x), Some(3.03125));
+    }
+
+    /// A record that does not read is said to be, and the rest carry on
+    #[test]
+    fn bad_records_are_rejected_alone() {
+        let text = "[\n{\"id\":1,\"name\":\"Sol\"},\n{\"id\":\n{\"id\":3,\"name\":\"Lave\"}\n]\n";
+        let records: Vec<_> = Records::<_, System>::new(text.as_bytes())
+            .map(|record| record.unwrap())
+            .collect();
+        assert_eq!(records.len(), 3);
+        assert!(matches!(records[1], Err(Rejected { record: 1, .. })));
+        assert_eq!(records[2].as_ref().unwrap().name, "Lave");
+    }
+
+    #[test]
+    fn skipping_picks_up_where_it_stopped() {
+        let text = fixture("bodies.json");
+        let mut bodies = Records::<_, Body>::new(text.as_bytes());
+        bodies.skip_raw(1).unwrap();
+        let rest: Vec<Body> = bodies.map(|b| b.unwrap().unwrap()).collect();
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].name, "Earth");
+    }
+
+    #[test]
+    fn compressed_dumps_are_read_through() {
+        let text = fixture("stations.json");
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(text.as_bytes()).unwrap();
+        let gz = gz.finish().unwrap();
+
+        let read = open(Path::new("stations.json.gz"), io::Cursor::new(gz));
+        let stations: Vec<Station> =
+            Records::new(read).map(|record| record.unwrap().unwrap()).collect();
+        assert_eq!(stations, all::<Station>(&text));
+        assert_eq!(stations[0].name, "Abraham Lincoln");
+    }
+}
@@ -0,0 +1,187 @@
+//! The records of each dump, as much of them as is kept
+//!
+//! Named and measured as EDSM writes them, which is not how the journal does:
+//! lengths in kilometres and AU, periods in days, angles in degrees. The
+//! events they are written back into convert, see [`super::events`].
+use elite_journal::system::Coordinate;
+use serde::Deserialize;
+use std::collections::BTreeMap as Map;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Coords {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl From<Coords> for Coordinate {
+    fn from(c: Coords) -> Self {
+        Coordinate { x: c.x, y: c.y, z: c.z }
+    }
+}
+
+/// A system of `systemsWithCoordinates.json`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct System {
+    pub id64: Option<i64>,
+    pub name: String,
+    pub coords: Option<Coords>,
+    /// When EDSM last heard of it, `2017-02-25 13:09:06`
+    pub date: Option<String>,
+}
+
+/// A star or planet of `bodies.json`, or of a populated system
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Body {
+    pub body_id: Option<i16>,
+    pub name: String,
+    /// `Star` or `Planet`
+    #[serde(rename = "type")]
+    pub ty: String,
+    /// What kind, `K (Yellow-Orange) Star` or `High metal content world`
+    pub sub_type: Option<String>,
+    /// Nearest first, `None` for a star at the middle of its system
+    pub parents: Option<Vec<Map<String, i16>>>,
+    pub distance_to_arrival: Option<f64>,
+
+    // Stars
+    pub spectral_class: Option<String>,
+    pub luminosity: Option<String>,
+    pub absolute_magnitude: Option<f64>,
+    pub solar_masses: Option<f64>,
+    pub solar_radius: Option<f64>,
+    /// Millions of years
+    pub age: Option<i64>,
+
+    // Planets
+    pub is_landable: Option<bool>,
+    /// In g
+    pub gravity: Option<f64>,
+    pub earth_masses: Option<f64>,
+    /// In km
+    pub radius: Option<f64>,
+    /// In atmospheres
+    pub surface_pressure: Option<f64>,
+    pub volcanism_type: Option<String>,
+    pub atmosphere_type: Option<String>,
+    /// Percentages, by gas
+    pub atmosphere_composition: Option<Map<String, f64>>,
+    /// Percentages of `Ice`, `Rock` and `Metal`
+    pub solid_composition: Option<Map<String, f64>>,
+    pub terraforming_state: Option<String>,
+    /// Percentages, by material
+    pub materials: Option<Map<String, f64>>,
+
+    // Both
+    pub surface_temperature: Option<f64>,
+    /// In days
+    pub orbital_period: Option<f64>,
+    /// In AU
+    pub semi_major_axis: Option<f64>,
+    pub orbital_eccentricity: Option<f64>,
+    pub orbital_inclination: Option<f64>,
+    pub arg_of_periapsis: Option<f64>,
+    /// In days
+    pub rotational_period: Option<f64>,
+    pub rotational_period_tidally_locked: Option<bool>,
+    pub axial_tilt: Option<f64>,
+
+    /// Who EDSM has as having found it, if anyone
+    pub discovery: Option<Discovery>,
+    pub update_time: Option<String>,
+    /// Missing inside a populated system, which says it itself
+    pub system_id64: Option<i64>,
+    pub system_name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Discovery {
+    pub commander: Option<String>,
+    /// When, `2017-02-25 13:09:06`
+    pub date: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Named {
+    pub name: String,
+}
+
+/// A station of `stations.json`, or of a populated system
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Station {
+    pub market_id: Option<i64>,
+    /// `Coriolis Starport`, `Planetary Outpost` and the like
+    #[serde(rename = "type")]
+    pub ty: Option<String>,
+    pub name: String,
+    pub distance_to_arrival: Option<f64>,
+    pub allegiance: Option<String>,
+    pub government: Option<String>,
+    pub economy: Option<String>,
+    pub second_economy: Option<String>,
+    #[serde(default)]
+    pub have_market: bool,
+    #[serde(default)]
+    pub have_shipyard: bool,
+    #[serde(default)]
+    pub have_outfitting: bool,
+    #[serde(default)]
+    pub other_services: Vec<String>,
+    pub controlling_faction: Option<Named>,
+    pub update_time: Option<StationUpdated>,
+    pub system_id64: Option<i64>,
+    pub system_name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct StationUpdated {
+    pub information: Option<String>,
+}
+
+/// A system of `systemsPopulated.json`, with what is in it
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Populated {
+    pub id64: Option<i64>,
+    pub name: String,
+    pub coords: Option<Coords>,
+    pub allegiance: Option<String>,
+    pub government: Option<String>,
+    pub economy: Option<String>,
+    pub security: Option<String>,
+    pub population: Option<i64>,
+    pub controlling_faction: Option<Named>,
+    #[serde(default)]
+    pub factions: Vec<Faction>,
+    #[serde(default)]
+    pub stations: Vec<Station>,
+    #[serde(default)]
+    pub bodies: Vec<Body>,
+    pub date: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Faction {
+    pub name: String,
+    pub allegiance: Option<String>,
+    pub government: Option<String>,
+    /// A fraction, as the journal has it
+    pub influence: Option<f64>,
+    pub state: Option<String>,
+    /// As the journal writes it, `$Faction_HappinessBand2;`
+    pub happiness: Option<String>,
+    #[serde(default)]
+    pub active_states: Vec<FactionState>,
+    #[serde(default)]
+    pub pending_states: Vec<FactionState>,
+    #[serde(default)]
+    pub recovering_states: Vec<FactionState>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct FactionState {
+    pub state: String,
+}
@@ -0,0 +1,243 @@
+//! An EDDN envelope, its message left as it arrived
+//!
+//! Which schema a message is of is said by its envelope, so the message is
+//! kept as the JSON it came as until that has been read, and only then parsed
+//! as whatever the schema says it is, once.
+use chrono::{DateTime, Utc};
+use elite_journal::entry::{Entry, Event, Market};
+use galos_db::exploration::Exploration;
+use galos_db::outfitting::{Outfitting, Shipyard};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::value::RawValue;
+
+#[derive(Debug, Deserialize)]
+pub struct Envelope {
+    #[serde(rename = "$schemaRef")]
+    pub schema_ref: String,
+    pub header: Header,
+    pub message: Box<RawValue>,
+}
+
+/// Who uploaded a message, with what, and when the gateway had it
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Header {
+    pub gateway_timestamp: DateTime<Utc>,
+    pub software_name: String,
+    pub software_version: String,
+    #[serde(rename = "uploaderID")]
+    pub uploader_id: String,
+}
+
+impl Envelope {
+    /// `journal`, `commodity` and so on, from EDDN's
+    /// `https://eddn.edcd.io/schemas/journal/1`
+    pub fn schema(&self) -> &str {
+        let schema = self.schema_ref.trim_end_matches('/');
+        let schema = schema.rsplit_once("/schemas/").map_or(schema, |s| s.1);
+        schema.split('/').next().unwrap_or(schema)
+    }
+
+    /// Whether this is a schema's `/test` version, sent while trying out an
+    /// uploader and not meant to be taken as anything that happened
+    pub fn is_test(&self) -> bool {
+        self.schema_ref.trim_end_matches('/').ends_with("/test")
+    }
+
+    /// The message, read as a `T`
+    pub fn read<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_str(self.message.get())
+    }
+
+    /// The message, read as what its schema says it is
+    ///
+    /// A schema galos takes nothing from is [`Message::Other`], unread, and
+    /// so is any schema's test version, as EDDN asks of listeners.
+    pub fn message(&self) -> serde_json::Result<Message> {
+        if self.is_test() {
+            return Ok(Message::Other);
+        }
+        Ok(match self.schema() {
+            "journal" | "navroute" | "scanbarycentre" => {
+                // Mapped signals come as a journal event, and are kept with
+                // the rest of what is explored.
+                let tagged: Tagged = serde_json::from_str(self.message.get())?;
+                if tagged.event == "SAASignalsFound" {
+                    Message::Exploration(self.read()?)
+                } else {
+                    Message::Journal(self.read()?)
+                }
+            }
+            "commodity" => Message::Commodity(self.read()?),
+            "fssdiscoveryscan" | "fssallbodiesfound" | "fssbodysignals"
+            | "navbeaconscan" => Message::Exploration(self.read()?),
+            "outfitting" => Message::Outfitting(self.read()?),
+            "shipyard" => Message::Shipyard(self.read()?),
+            _ => Message::Other,
+        })
+    }
+}
+
+/// Which journal event a message is, and nothing else of it
+#[derive(Deserialize)]
+struct Tagged<'a> {
+    event: &'a str,
+}
+
+/// A message, as its schema has it
+///
+/// Read once and handed along whole, so a journal event being the largest
+/// by far costs nothing boxing it would save.
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum Message {
+    Journal(Entry<Event>),
+    Commodity(Entry<Market>),
+    Exploration(Exploration),
+    Outfitting(Outfitting),
+    Shipyard(Shipyard),
+    Other,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schemas_are_named_as_eddn_files_them() {
+        let envelope = |schema_ref: &str| Envelope {
+            schema_ref: schema_ref.into(),
+            header: Header {
+                gateway_timestamp: Utc::now(),
+                software_name: "E:D Market Connector".into(),
+                software_version: "5.12.1".into(),
+                uploader_id: "Someone".into(),
+            },
+            message: RawValue::from_string("{}".into()).unwrap(),
+        };
+        let schema = |schema_ref| envelope(schema_ref).schema().to_string();
+        assert_eq!(schema("https://eddn.edcd.io/schemas/journal/1"), "journal");
+        assert_eq!(schema("outfitting"), "outfitting");
+
+        let test =
+            envelope("https://eddn.edcd.io/schemas/fssdiscoveryscan/1/test");
+        assert!(test.is_test());
+        assert!(matches!(test.message(), Ok(Message::Other)));
+        assert!(!envelope("https://eddn.edcd.io/schemas/journal/1").is_test());
+    }
+
+    fn parse(json: &str) -> Message {
+        let envelope: Envelope = serde_json::from_str(json).unwrap();
+        envelope.message().unwrap()
+    }
+
+    #[test]
+    fn discovery_scans_are_exploration() {
+        let honk = parse(
+            r#"{"$schemaRef":"https://eddn.edcd.io/schemas/fssdiscoveryscan/1",
+                "header":{"gatewayTimestamp":"2026-10-18T11:00:03.729490Z",
+                          "softwareName":"EDDiscovery",
+                          "softwareVersion":"18.1.4.0",
+                          "uploaderID":"b3c4d5e6f7a8b9c0"},
+                "message":{"BodyCount":40,"NonBodyCount":3,
+                           "StarPos":[0.0,0.0,0.0],
+                           "SystemAddress":10477373803,"SystemName":"Sol",
+                           "event":"FSSDiscoveryScan","horizons":true,
+                           "odyssey":true,
+                           "timestamp":"2026-10-18T11:00:02Z"}}"#,
+        );
+        let Message::Exploration(honk) = honk else {
+            panic!("not exploration: {:?}", honk);
+        };
+        assert_eq!(honk.event.system_address(), 10477373803);
+    }
+
+    /// Mapped signals come under the journal schema, and are read as what
+    /// is explored all the same
+    #[test]
+    fn mapped_signals_are_exploration() {
+        let mapped = parse(
+            r#"{"$schemaRef":"https://eddn.edcd.io/schemas/journal/1",
+                "header":{"gatewayTimestamp":"2026-10-18T11:00:06.582210Z",
+                          "softwareName":"E:D Market Connector [Windows]",
+                          "softwareVersion":"5.12.1",
+                          "uploaderID":"a2a2b7e4b1b9e5d0"},
+                "message":{"BodyID":3,"BodyName":"Sol 3",
+                           "Signals":[{"Count":2,
+                                       "Type":"$SAA_SignalType_Biological;"}],
+                           "StarPos":[0.0,0.0,0.0],"StarSystem":"Sol",
+                           "SystemAddress":10477373803,
+                           "event":"SAASignalsFound","horizons":true,
+                           "odyssey":true,
+                           "timestamp":"2026-10-18T11:00:05Z"}}"#,
+        );
+        let Message::Exploration(mapped) = mapped else {
+            panic!("not exploration: {:?}", mapped);
+        };
+        assert_eq!(mapped.event.system_address(), 10477373803);
+    }
+
+    /// A station's lists, as EDDN's `outfitting/2` and `shipyard/2` carry
+    /// them
+    #[test]
+    fn station_lists_are_read_whole() {
+        let outfitting = parse(
+            r#"{"$schemaRef":"https://eddn.edcd.io/schemas/outfitting/2",
+                "header":{"gameversion":"4.0.0.1904",
+                          "gatewayTimestamp":"2026-10-18T11:00:02.047113Z",
+                          "softwareName":"E:D Market Connector [Windows]",
+                          "softwareVersion":"5.12.1",
+                          "uploaderID":"a2a2b7e4b1b9e5d0"},
+                "message":{"horizons":true,"marketId":128016640,
+                           "modules":["Hpt_BeamLaser_Fixed_Small",
+                                      "Int_Hyperdrive_Size5_Class5"],
+                           "odyssey":true,"stationName":"Abraham Lincoln",
+                           "systemName":"Sol",
+                           "timestamp":"2026-10-18T10:59:52Z"}}"#,
+        );
+        let Message::Outfitting(outfitting) = outfitting else {
+            panic!("not outfitting: {:?}", outfitting);
+        };
+        assert_eq!(outfitting.market_id, 128016640);
+        assert_eq!(outfitting.system_name, "Sol");
+        assert_eq!(
+            outfitting.modules,
+            ["Hpt_BeamLaser_Fixed_Small", "Int_Hyperdrive_Size5_Class5"]
+        );
+
+        let shipyard = parse(
+            r#"{"$schemaRef":"https://eddn.edcd.io/schemas/shipyard/2",
+                "header":{"gameversion":"4.0.0.1904",
+                          "gatewayTimestamp":"2026-10-18T11:00:02.048032Z",
+                          "softwareName":"E:D Market Connector [Windows]",
+                          "softwareVersion":"5.12.1",
+                          "uploaderID":"a2a2b7e4b1b9e5d0"},
+                "message":{"allowCobraMkIV":false,"horizons":true,
+                           "marketId":128016640,"odyssey":true,
+                           "ships":["krait_mkii","python"],
+                           "stationName":"Abraham Lincoln",
+                           "systemName":"Sol",
+                           "timestamp":"2026-10-18T10:59:52Z"}}"#,
+        );
+        let Message::Shipyard(shipyard) = shipyard else {
+            panic!("not a shipyard: {:?}", shipyard);
+        };
+        assert_eq!(shipyard.station_name, "Abraham Lincoln");
+        assert_eq!(shipyard.ships, ["krait_mkii", "python"]);
+    }
+
+    /// A schema nothing is taken from isn't read at all, however it reads
+    #[test]
+    fn other_schemas_are_left_unread() {
+        let codex = parse(
+            r#"{"$schemaRef":"https://eddn.edcd.io/schemas/codexentry/1",
+                "header":{"gatewayTimestamp":"2026-10-18T11:00:07.019283Z",
+                          "softwareName":"EDDiscovery",
+                          "softwareVersion":"18.1.4.0",
+                          "uploaderID":"b3c4d5e6f7a8b9c0"},
+                "message":{"event":"CodexEntry"}}"#,
+        );
+        assert!(matches!(codex, Message::Other));
+    }
+}
@@ -0,0 +1,194 @@
+//! EDDN's messages as they came off the wire
+//!
+//! [`eddn::subscribe`] hands back each message already parsed into its own
+//! types, and what those types leave out is gone. An [`crate::archive`] is
+//! only worth replaying if it holds what EDDN sent, so this subscribes the
+//! same way and stops at the JSON, leaving the parsing to
+//! [`crate::envelope`].
+//!
+//! The socket is set up as [`eddn::subscribe`] sets its own up, from the same
+//! constants, so a connection that closes, or dies without closing, is
+//! rebuilt the same way, and one that carries nothing for `stall` is
+//! replaced.
+use eddn::{
+    Error, HEARTBEAT_IVL_MS, HEARTBEAT_TIMEOUT_MS, POLL_INTERVAL_MS,
+    RECONNECT_MAX_MS, RECONNECT_MIN_MS,
+};
+use miniz_oxide::inflate;
+use serde_json::value::RawValue;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Names the endpoint each socket's monitor reports on
+static MONITORS: AtomicUsize = AtomicUsize::new(0);
+
+/// Subscribe to everything on `url`, replacing the connection after `stall`
+/// of silence, or never with `None`
+pub fn subscribe(url: &str, stall: Option<Duration>) -> Feed {
+    let ctx = zmq::Context::new();
+    let connection =
+        Connection::open(&ctx, url).expect("failed to open socket");
+    info!("Subscribed to {}", url);
+    Feed {
+        ctx,
+        url: url.to_string(),
+        connection,
+        stall,
+        quiet_since: Instant::now(),
+        lost_at: None,
+    }
+}
+
+/// Each message's envelope, decompressed and whole
+///
+/// Never ends. A message that can't be read comes back as an [`Error`] and
+/// the next is waited for.
+pub struct Feed {
+    ctx: zmq::Context,
+    url: String,
+    connection: Connection,
+    stall: Option<Duration>,
+    quiet_since: Instant,
+    /// When the connection was lost, while it is still gone
+    lost_at: Option<Instant>,
+}
+
+impl Feed {
+    fn reconnect(&mut self, reason: &str) {
+        warn!("{}, replacing the connection", reason);
+        loop {
+            match Connection::open(&self.ctx, &self.url) {
+                Ok(connection) => {
+                    self.connection = connection;
+                    self.quiet_since = Instant::now();
+                    self.lost_at = None;
+                    return;
+                }
+                Err(err) => {
+                    warn!("Could not open a socket: {}", err);
+                    thread::sleep(Duration::from_secs(5));
+                }
+            }
+        }
+    }
+
+    /// Say when the connection is lost, and when it is back
+    fn report(&mut self) {
+        for event in self.connection.events() {
+            match event {
+                zmq::SocketEvent::DISCONNECTED if self.lost_at.is_none() => {
+                    self.lost_at = Some(Instant::now());
+                    warn!(
+                        "Connection lost, retrying every {} to {} seconds",
+                        RECONNECT_MIN_MS / 1_000,
+                        RECONNECT_MAX_MS / 1_000,
+                    );
+                }
+                zmq::SocketEvent::CONNECTED => {
+                    if let Some(at) = self.lost_at.take() {
+                        info!(
+                            "Connected again, {}s without one",
+                            at.elapsed().as_secs()
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Iterator for Feed {
+    type Item = Result<Box<RawValue>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.report();
+            match self.connection.socket.recv_bytes(0) {
+                Ok(compressed) => {
+                    self.quiet_since = Instant::now();
+                    return Some(
+                        inflate::decompress_to_vec_zlib(&compressed)
+                            .map_err(Error::Decompress)
+                            .and_then(|json| {
+                                envelope(json).map_err(Error::Parse)
+                            }),
+                    );
+                }
+                Err(zmq::Error::EAGAIN) => {
+                    let quiet = self.quiet_since.elapsed();
+                    if self.stall.is_some_and(|stall| quiet >= stall) {
+                        let reason =
+                            format!("nothing for {}s", quiet.as_secs());
+                        self.reconnect(&reason);
+                    }
+                }
+                Err(zmq::Error::EINTR) => {}
+                Err(err) => {
+                    self.reconnect(&format!("socket error: {}", err));
+                    return Some(Err(Error::Socket(err)));
+                }
+            }
+        }
+    }
+}
+
+/// The envelope in `json`, checked to be JSON and kept to a line
+///
+/// A line break can only be whitespace between tokens, a string holding one
+/// escaped, so taking them out changes nothing but the layout, and leaves a
+/// message that fits on a line of an archive or a spool.
+fn envelope(mut json: Vec<u8>) -> serde_json::Result<Box<RawValue>> {
+    json.retain(|&byte| byte != b'\n' && byte != b'\r');
+    let json = String::from_utf8(json).map_err(|err| {
+        serde::de::Error::custom(format!("not UTF-8: {}", err))
+    })?;
+    RawValue::from_string(json)
+}
+
+/// A socket subscribed to EDDN, and the monitor libzmq reports on it through
+struct Connection {
+    socket: zmq::Socket,
+    monitor: zmq::Socket,
+}
+
+impl Connection {
+    fn open(ctx: &zmq::Context, url: &str) -> Result<Self, zmq::Error> {
+        let socket = ctx.socket(zmq::SUB)?;
+        socket.set_reconnect_ivl(RECONNECT_MIN_MS)?;
+        socket.set_reconnect_ivl_max(RECONNECT_MAX_MS)?;
+        socket.set_heartbeat_ivl(HEARTBEAT_IVL_MS)?;
+        socket.set_heartbeat_timeout(HEARTBEAT_TIMEOUT_MS)?;
+        // Receives return on their own so that silence can be timed.
+        socket.set_rcvtimeo(POLL_INTERVAL_MS)?;
+
+        let endpoint = format!(
+            "inproc://galos-feed-monitor-{}",
+            MONITORS.fetch_add(1, Ordering::Relaxed)
+        );
+        let events = zmq::SocketEvent::CONNECTED as i32
+            | zmq::SocketEvent::DISCONNECTED as i32;
+        socket.monitor(&endpoint, events)?;
+        let monitor = ctx.socket(zmq::PAIR)?;
+        monitor.connect(&endpoint)?;
+
+        socket.connect(url)?;
+        socket.set_subscribe(&[])?;
+        Ok(Connection { socket, monitor })
+    }
+
+    /// What libzmq has done to the connection since it was last asked
+    fn events(&self) -> Vec<zmq::SocketEvent> {
+        let mut events = vec![];
+        while let Ok(frames) = self.monitor.recv_multipart(zmq::DONTWAIT) {
+            if let Some([lo, hi, ..]) = frames.first().map(Vec::as_slice) {
+                events.push(zmq::SocketEvent::from_raw(u16::from_le_bytes([
+                    *lo, *hi,
+                ])));
+            }
+        }
+        events
+    }
+}
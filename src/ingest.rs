@@ -0,0 +1,392 @@
+//! Writing what the game says into the database
+//!
+//! EDDN relays journal events and markets from everyone playing, and a
+//! commander's own journal files hold the same events, so both are written
+//! by the same functions here, attributed to whoever `user` is: an uploader
+//! for EDDN, the commander for a journal.
+//!
+//! Nothing here fails but for the database being unreachable. Each thing an
+//! event describes is written on its own, and what could not be is logged and
+//! passed over, the rest of the event being worth keeping regardless. Where
+//! the database could not be reached at all, nothing more of the event will
+//! be, and the error is handed back so the caller can hold on to the event
+//! and write it again later. Everything here is an upsert, so writing again
+//! what was written before the database went away does no harm.
+use chrono::{DateTime, Utc};
+use elite_journal::entry::incremental::exploration::ScanTarget;
+use elite_journal::entry::market::Market as JournalMarket;
+use elite_journal::entry::route::NavRoute;
+use elite_journal::entry::{Entry, Event};
+use elite_journal::system::System as JournalSystem;
+use galos_db::exploration::{self, Exploration};
+use galos_db::outfitting::{Outfitting, Shipyard};
+use galos_db::{
+    barycenters::Barycenter, bodies::Body, markets::Market, stars::Star,
+    stations::Station, systems::System, Database, Error,
+};
+use tracing::{info, warn};
+
+/// Write whatever a journal event says of systems, bodies, stations and
+/// factions
+///
+/// Events saying nothing of them are passed over.
+pub async fn journal(
+    db: &Database,
+    entry: Entry<Event>,
+    user: &str,
+) -> Result<(), Error> {
+    match entry.event {
+        Event::Scan(scan) => {
+            let mut system =
+                JournalSystem::new(scan.system_address, &scan.star_system);
+            system.pos = Some(scan.star_pos);
+            match System::from_journal(db, entry.timestamp, user, &system).await
+            {
+                Ok(_) => info!(system = %system.name, "scan"),
+                Err(err) if err.is_unavailable() => return Err(err),
+                Err(err) => {
+                    warn!(system = %system.name, error = %err, "scan")
+                }
+            }
+
+            match scan.target {
+                ScanTarget::Star(star) => match Star::from_journal(
+                    db,
+                    entry.timestamp,
+                    user,
+                    &star,
+                    scan.system_address,
+                )
+                .await
+                {
+                    Ok(_) => {
+                        info!(star = %star.name, "scan")
+                    }
+                    Err(err) if err.is_unavailable() => return Err(err),
+                    Err(err) => {
+                        warn!(star = %star.name, error = %err, "scan")
+                    }
+                },
+                ScanTarget::Body(body) => match Body::from_journal(
+                    db,
+                    entry.timestamp,
+                    user,
+                    &body,
+                    scan.system_address,
+                )
+                .await
+                {
+                    Ok(_) => {
+                        info!(body = %body.name, "scan")
+                    }
+                    Err(err) if err.is_unavailable() => return Err(err),
+                    Err(err) => {
+                        warn!(body = %body.name, error = %err, "scan")
+                    }
+                },
+            }
+        }
+        // A barycenter is not a body and is not drawn. It is stored so
+        // that a body naming it as an ancestor can be placed where it
+        // belongs rather than at the middle of its system.
+        Event::ScanBaryCentre(scan) => {
+            let mut system =
+                JournalSystem::new(scan.system_address, &scan.star_system);
+            system.pos = Some(scan.star_pos);
+            match System::from_journal(db, entry.timestamp, user, &system).await
+            {
+                Ok(_) => {
+                    info!(system = %system.name, "scan barycenter")
+                }
+                Err(err) if err.is_unavailable() => return Err(err),
+                Err(err) => {
+                    warn!(system = %system.name, error = %err, "scan barycenter")
+                }
+            }
+
+            match Barycenter::from_journal(db, entry.timestamp, user, &scan)
+                .await
+            {
+                // A barycenter has no name of its own, so the id it is
+                // known by within its system is said along with the
+                // system, neither meaning much without the other.
+                Ok(_) => {
+                    info!(system = %system.name, barycenter = scan.body_id, "scan barycenter")
+                }
+                Err(err) if err.is_unavailable() => return Err(err),
+                Err(err) => {
+                    warn!(system = %system.name, barycenter = scan.body_id, error = %err, "scan barycenter")
+                }
+            }
+        }
+        Event::Location(e) => {
+            match System::from_journal(db, entry.timestamp, user, &e.system)
+                .await
+            {
+                Ok(_) => info!(system = %e.system.name, "location"),
+                Err(err) if err.is_unavailable() => return Err(err),
+                Err(err) => {
+                    warn!(system = %e.system.name, error = %err, "location")
+                }
+            }
+
+            if let Some(ref body) = e.body {
+                match Body::from_journal(
+                    db,
+                    entry.timestamp,
+                    user,
+                    body,
+                    e.system.address,
+                )
+                .await
+                {
+                    Ok(_) => info!(body = %body.name, "location"),
+                    Err(err) if err.is_unavailable() => return Err(err),
+                    Err(err) => {
+                        warn!(body = %body.name, error = %err, "location")
+                    }
+                }
+            }
+
+            if let Some(ref station) = e.station {
+                match Station::from_journal(
+                    db,
+                    entry.timestamp,
+                    user,
+                    station,
+                    e.system.address,
+                )
+                .await
+                {
+                    Ok(_) => {
+                        info!(station = %station.name, "location")
+                    }
+                    Err(err) if err.is_unavailable() => return Err(err),
+                    Err(err) => {
+                        warn!(station = %station.name, error = %err, "location")
+                    }
+                }
+            }
+        }
+        Event::Docked(e) => {
+            let system = JournalSystem::new(e.system_address, &e.system_name);
+            match System::from_journal(db, entry.timestamp, user, &system).await
+            {
+                Ok(_) => info!(system = %system.name, "docked"),
+                Err(err) if err.is_unavailable() => return Err(err),
+                Err(err) => {
+                    warn!(system = %system.name, error = %err, "docked")
+                }
+            }
+
+            match Station::from_journal(
+                db,
+                entry.timestamp,
+                user,
+                &e.station,
+                e.system_address,
+            )
+            .await
+            {
+                Ok(_) => {
+                    info!(station = %e.station.name, "docked")
+                }
+                Err(err) if err.is_unavailable() => return Err(err),
+                Err(err) => {
+                    warn!(station = %e.station.name, error = %err, "docked")
+                }
+            }
+        }
+        Event::FsdJump(e) => {
+            match System::from_journal(db, entry.timestamp, user, &e.system)
+                .await
+            {
+                Ok(_) => info!(system = %e.system.name, "fsd jump"),
+                Err(err) if err.is_unavailable() => return Err(err),
+                Err(err) => {
+                    warn!(system = %e.system.name, error = %err, "fsd jump")
+                }
+            }
+        }
+        Event::NavRoute(NavRoute::Route(destinations)) => {
+            for destination in destinations {
+                match System::create(
+                    db,
+                    destination.system_address as i64,
+                    &destination.star_system,
+                    Some(destination.star_pos),
+                    Some(destination.star_class),
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    entry.timestamp,
+                    user,
+                )
+                .await
+                {
+                    Ok(_) => {
+                        info!(system = %destination.star_system, "nav route")
+                    }
+                    Err(err) if err.is_unavailable() => return Err(err),
+                    Err(err) => {
+                        warn!(system = %destination.star_system, error = %err, "nav route")
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Write a market's prices, and the station it is at where the system is
+/// known
+pub async fn commodity(
+    db: &Database,
+    entry: &Entry<JournalMarket>,
+    user: &str,
+) -> Result<(), Error> {
+    let m = &entry.event;
+    // A market message cannot name its system by address, only by name, so
+    // the system may well be one we have never seen. Record the prices
+    // regardless. The station and the link to the system follow whenever the
+    // system itself turns up.
+    station(
+        db,
+        entry.timestamp,
+        user,
+        &m.system_name,
+        &m.station_name,
+        "commodity",
+    )
+    .await?;
+
+    match Market::from_journal(db, entry.timestamp, m).await {
+        // A market can arrive before anything that would create the system
+        // it names, and is recorded with no system to belong to until that
+        // turns up. The name it gave is all there is to go on in the
+        // meantime.
+        Ok(market) => info!(
+            market = %m.station_name,
+            system = %m.system_name,
+            orphan = market.system_address.is_none(),
+            "commodity",
+        ),
+        Err(err) if err.is_unavailable() => return Err(err),
+        Err(err) => {
+            warn!(market = %m.station_name, error = %err, "commodity")
+        }
+    }
+    Ok(())
+}
+
+/// Write the modules a station's outfitting sells, and the station it is at
+/// where the system is known
+///
+/// Apart from [`commodity`], and keyed by market the same way.
+pub async fn outfitting(
+    db: &Database,
+    outfitting: &Outfitting,
+    user: &str,
+) -> Result<(), Error> {
+    let o = outfitting;
+    station(
+        db,
+        o.timestamp,
+        user,
+        &o.system_name,
+        &o.station_name,
+        "outfitting",
+    )
+    .await?;
+    match Outfitting::from_eddn(db, o).await {
+        Ok(()) => info!(
+            market = %o.station_name,
+            system = %o.system_name,
+            modules = o.modules.len(),
+            "outfitting",
+        ),
+        Err(err) if err.is_unavailable() => return Err(err),
+        Err(err) => {
+            warn!(market = %o.station_name, error = %err, "outfitting")
+        }
+    }
+    Ok(())
+}
+
+/// Write the ships a station's shipyard sells, and the station it is at
+/// where the system is known
+pub async fn shipyard(
+    db: &Database,
+    shipyard: &Shipyard,
+    user: &str,
+) -> Result<(), Error> {
+    let y = shipyard;
+    station(db, y.timestamp, user, &y.system_name, &y.station_name, "shipyard")
+        .await?;
+    match Shipyard::from_eddn(db, y).await {
+        Ok(()) => info!(
+            market = %y.station_name,
+            system = %y.system_name,
+            ships = y.ships.len(),
+            "shipyard",
+        ),
+        Err(err) if err.is_unavailable() => return Err(err),
+        Err(err) => warn!(market = %y.station_name, error = %err, "shipyard"),
+    }
+    Ok(())
+}
+
+/// Write the station a market message names, where its system is known
+///
+/// The market's row points at the station, so it has to be there first.
+/// Failing to write it loses nothing the market can't wait for, and only
+/// the database being unreachable is handed back.
+async fn station(
+    db: &Database,
+    timestamp: DateTime<Utc>,
+    user: &str,
+    system_name: &str,
+    station_name: &str,
+    what: &str,
+) -> Result<(), Error> {
+    let Ok(system) = System::fetch_by_name(db, system_name).await else {
+        return Ok(());
+    };
+    match Station::create(db, timestamp, user, system.address, station_name)
+        .await
+    {
+        Ok(_) => info!(station = %station_name, "{}", what),
+        Err(err) if err.is_unavailable() => return Err(err),
+        Err(err) => warn!(station = %station_name, error = %err, "{}", what),
+    }
+    Ok(())
+}
+
+/// Write how many bodies an exploration event counts in a system, or what
+/// signals it finds on one
+///
+/// Apart from [`journal`], the journal parser having no events for these.
+/// See [`galos_db::exploration`].
+pub async fn exploration(
+    db: &Database,
+    entry: &Exploration,
+    user: &str,
+) -> Result<(), Error> {
+    let address = entry.event.system_address();
+    match exploration::Event::from_journal(
+        db,
+        entry.timestamp,
+        user,
+        &entry.event,
+    )
+    .await
+    {
+        Ok(_) => info!(system = address, "exploration"),
+        Err(err) if err.is_unavailable() => return Err(err),
+        Err(err) => warn!(system = address, error = %err, "exploration"),
+    }
+    Ok(())
+}
@@ -0,0 +1,541 @@
+//! Where the commander is, read from the game's journal as it is written
+//!
+//! The game keeps a `Journal.<date>.log` for each session, a JSON event to a
+//! line, starting a new one when the game is started again and once a file
+//! grows too long. Beside them it rewrites `Status.json` every few seconds
+//! with the ship's flags.
+//!
+//! A [`Watcher`] reads the newest journal from its start, which has the whole
+//! session in it, then only what is appended, moving on to the next file when
+//! one is started. Each [`Watcher::poll`] reads whatever is new and updates
+//! the [`Whereabouts`] it holds. Nothing here needs the database.
+use chrono::{DateTime, NaiveDateTime, Utc};
+use elite_journal::system::Coordinate;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Where the commander is, and in what
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Whereabouts {
+    pub commander: Option<String>,
+    pub system: Option<String>,
+    pub system_address: Option<i64>,
+    pub position: Option<Coordinate>,
+    /// The body being flown near, or landed on
+    pub body: Option<String>,
+    /// The station docked at
+    pub station: Option<String>,
+    pub market_id: Option<i64>,
+    pub ship: Option<Ship>,
+    /// Touched down on a surface, rather than docked
+    pub landed: bool,
+}
+
+/// The ship being flown
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Ship {
+    /// The hull, as the journal spells it, e.g. `asp`
+    pub ty: String,
+    pub id: Option<i64>,
+    pub name: Option<String>,
+    pub ident: Option<String>,
+}
+
+/// The events saying where the commander is, as the journal writes them
+///
+/// Only the fields wanted are read, and every other event is passed over.
+#[derive(Deserialize)]
+#[serde(tag = "event")]
+enum Line {
+    Commander {
+        #[serde(rename = "Name")]
+        name: String,
+    },
+    #[serde(rename_all = "PascalCase")]
+    LoadGame {
+        commander: String,
+        ship: Option<String>,
+        #[serde(rename = "ShipID")]
+        ship_id: Option<i64>,
+        ship_name: Option<String>,
+        ship_ident: Option<String>,
+    },
+    #[serde(rename_all = "PascalCase")]
+    Loadout {
+        ship: String,
+        #[serde(rename = "ShipID")]
+        ship_id: Option<i64>,
+        ship_name: Option<String>,
+        ship_ident: Option<String>,
+    },
+    #[serde(rename_all = "PascalCase")]
+    ShipyardSwap {
+        ship_type: String,
+        #[serde(rename = "ShipID")]
+        ship_id: Option<i64>,
+    },
+    Location(Arrival),
+    CarrierJump(Arrival),
+    #[serde(rename = "FSDJump")]
+    FsdJump(Arrival),
+    #[serde(rename_all = "PascalCase")]
+    Docked {
+        station_name: String,
+        #[serde(rename = "MarketID")]
+        market_id: Option<i64>,
+    },
+    Undocked,
+    #[serde(rename_all = "PascalCase")]
+    ApproachBody {
+        body: String,
+    },
+    LeaveBody,
+    Touchdown,
+    Liftoff,
+    #[serde(other)]
+    Other,
+}
+
+/// Being somewhere new, by jump or by loading the game
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Arrival {
+    star_system: String,
+    system_address: i64,
+    star_pos: [f64; 3],
+    body: Option<String>,
+    #[serde(default)]
+    docked: bool,
+    station_name: Option<String>,
+    #[serde(rename = "MarketID")]
+    market_id: Option<i64>,
+}
+
+/// What is wanted of `Status.json`
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Status {
+    #[serde(default)]
+    flags: u64,
+    body_name: Option<String>,
+}
+
+impl Status {
+    const DOCKED: u64 = 1 << 0;
+    const LANDED: u64 = 1 << 1;
+}
+
+impl Whereabouts {
+    /// Take one line of the journal, if it says anything of where
+    fn read(&mut self, line: &str) {
+        let Ok(line) = serde_json::from_str::<Line>(line) else {
+            return;
+        };
+        match line {
+            Line::Commander { name } => self.commander = Some(name),
+            Line::LoadGame {
+                commander,
+                ship,
+                ship_id,
+                ship_name,
+                ship_ident,
+            } => {
+                self.commander = Some(commander);
+                self.ship = ship.map(|ty| Ship {
+                    ty,
+                    id: ship_id,
+                    name: ship_name,
+                    ident: ship_ident,
+                });
+            }
+            Line::Loadout { ship, ship_id, ship_name, ship_ident } => {
+                self.ship = Some(Ship {
+                    ty: ship,
+                    id: ship_id,
+                    name: ship_name,
+                    ident: ship_ident,
+                });
+            }
+            Line::ShipyardSwap { ship_type, ship_id } => {
+                // The new ship's name follows in its loadout.
+                self.ship = Some(Ship {
+                    ty: ship_type,
+                    id: ship_id,
+                    ..Ship::default()
+                });
+            }
+            Line::Location(arrival)
+            | Line::CarrierJump(arrival)
+            | Line::FsdJump(arrival) => {
+                let [x, y, z] = arrival.star_pos;
+                self.system = Some(arrival.star_system);
+                self.system_address = Some(arrival.system_address);
+                self.position = Some(Coordinate { x, y, z });
+                self.body = arrival.body;
+                self.landed = false;
+                if arrival.docked {
+                    self.station = arrival.station_name;
+                    self.market_id = arrival.market_id;
+                } else {
+                    self.station = None;
+                    self.market_id = None;
+                }
+            }
+            Line::Docked { station_name, market_id } => {
+                self.station = Some(station_name);
+                self.market_id = market_id;
+            }
+            Line::Undocked => {
+                self.station = None;
+                self.market_id = None;
+            }
+            Line::ApproachBody { body } => self.body = Some(body),
+            Line::LeaveBody => self.body = None,
+            Line::Touchdown => self.landed = true,
+            Line::Liftoff => self.landed = false,
+            Line::Other => {}
+        }
+    }
+
+    /// Take the status, which is only ever as of now
+    fn status(&mut self, status: Status) {
+        self.landed = status.flags & Status::LANDED != 0;
+        if status.flags & Status::DOCKED == 0 {
+            self.station = None;
+            self.market_id = None;
+        }
+        if status.body_name.is_some() {
+            self.body = status.body_name;
+        }
+    }
+}
+
+/// Follows the journals in a directory, see the [module docs](self)
+#[derive(Debug)]
+pub struct Watcher {
+    dir: PathBuf,
+    /// The journal being read, and how far
+    file: Option<(PathBuf, u64)>,
+    /// The end of the last read, which the game had not finished writing
+    partial: String,
+    /// `Status.json` as last read, to tell when it has changed
+    status: String,
+    whereabouts: Whereabouts,
+}
+
+impl Watcher {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Watcher {
+            dir: dir.as_ref().to_path_buf(),
+            file: None,
+            partial: String::new(),
+            status: String::new(),
+            whereabouts: Whereabouts::default(),
+        }
+    }
+
+    /// Where the commander is, as of the last poll
+    pub fn whereabouts(&self) -> &Whereabouts {
+        &self.whereabouts
+    }
+
+    /// Read whatever has been written since the last poll
+    ///
+    /// Returns whether the whereabouts changed. A journal the game has moved
+    /// on from is read to its end before the next is started, so nothing
+    /// written between two polls is missed.
+    pub fn poll(&mut self) -> io::Result<bool> {
+        let before = self.whereabouts.clone();
+        let newest = journals(&self.dir)?.pop();
+        if let Some(newest) = newest {
+            if self.file.as_ref().map(|(path, _)| path) != Some(&newest) {
+                if self.file.is_some() {
+                    self.tail()?;
+                }
+                self.file = Some((newest, 0));
+                self.partial.clear();
+            }
+            self.tail()?;
+        }
+        self.read_status();
+        Ok(self.whereabouts != before)
+    }
+
+    /// Read the rest of the current journal
+    fn tail(&mut self) -> io::Result<()> {
+        let Some((path, offset)) = self.file.as_mut() else {
+            return Ok(());
+        };
+        let mut file = File::open(&*path)?;
+        let len = file.metadata()?.len();
+        if len < *offset {
+            // Written over from the start, which the game does not do, but
+            // reading it again beats reading nothing until it is as long.
+            *offset = 0;
+            self.partial.clear();
+        }
+        file.seek(SeekFrom::Start(*offset))?;
+        let mut read = Vec::new();
+        file.read_to_end(&mut read)?;
+        *offset += read.len() as u64;
+
+        self.partial.push_str(&String::from_utf8_lossy(&read));
+        // A line without its newline is still being written, and is kept
+        // back for the next read.
+        let complete = match self.partial.rfind('\n') {
+            Some(end) => end + 1,
+            None => return Ok(()),
+        };
+        let lines: String = self.partial.drain(..complete).collect();
+        for line in lines.lines() {
+            self.whereabouts.read(line);
+        }
+        Ok(())
+    }
+
+    fn read_status(&mut self) {
+        // The game writes the file in place, so it is sometimes read empty,
+        // or half written, neither of which parse.
+        let Ok(text) = fs::read_to_string(self.dir.join("Status.json")) else {
+            return;
+        };
+        if text == self.status {
+            return;
+        }
+        if let Ok(status) = serde_json::from_str(&text) {
+            self.whereabouts.status(status);
+            self.status = text;
+        }
+    }
+}
+
+/// Where the commander is now, from the journals in `dir`
+pub fn whereabouts<P: AsRef<Path>>(dir: P) -> io::Result<Whereabouts> {
+    let mut watcher = Watcher::new(dir);
+    watcher.poll()?;
+    Ok(watcher.whereabouts)
+}
+
+/// The `Journal.*.log` files in `dir`, oldest first
+///
+/// Ordered by when they were started, which their names say, and then by
+/// name, for the parts of one session.
+pub fn journals(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut found: Vec<(Option<NaiveDateTime>, PathBuf)> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name().and_then(|name| name.to_str()).is_some_and(
+                |name| name.starts_with("Journal.") && name.ends_with(".log"),
+            )
+        })
+        .map(|path| (started(&path), path))
+        .collect();
+    found.sort();
+    Ok(found.into_iter().map(|(_, path)| path).collect())
+}
+
+/// When the journal at `path` was started
+///
+/// Named `Journal.2026-10-18T120000.01.log` now, and once
+/// `Journal.261018120000.01.log`, which sorts after every name of the newer
+/// kind. A name that says neither is taken to be as old as it was last
+/// written.
+fn started(path: &Path) -> Option<NaiveDateTime> {
+    let named = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.strip_prefix("Journal."))
+        .and_then(|name| name.split('.').next())
+        .and_then(|stamp| {
+            NaiveDateTime::parse_from_str(stamp, "%Y-%m-%dT%H%M%S")
+                .or_else(|_| {
+                    NaiveDateTime::parse_from_str(stamp, "%y%m%d%H%M%S")
+                })
+                .ok()
+        });
+    named.or_else(|| {
+        let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+        Some(DateTime::<Utc>::from(modified).naive_utc())
+    })
+}
+
+/// Where the game keeps its journals
+///
+/// `GALOS_JOURNAL` if it is set, otherwise where the game writes them on
+/// Windows, or under Proton in Steam's default library on Linux.
+pub fn dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("GALOS_JOURNAL") {
+        return Some(PathBuf::from(dir));
+    }
+    let saved = Path::new("Saved Games/Frontier Developments/Elite Dangerous");
+    let candidates = [
+        env::var_os("USERPROFILE").map(|home| PathBuf::from(home).join(saved)),
+        env::var_os("HOME").map(|home| {
+            PathBuf::from(home)
+                .join(".steam/steam/steamapps/compatdata/359320/pfx")
+                .join("drive_c/users/steamuser")
+                .join(saved)
+        }),
+    ];
+    IntoIterator::into_iter(candidates).flatten().find(|dir| dir.is_dir())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    /// A fresh directory for one test to write journals in
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!(
+            "galos-journal-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn append(path: &Path, text: &str) {
+        let mut file =
+            OpenOptions::new().create(true).append(true).open(path).unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+    }
+
+    const LOAD: &str = concat!(
+        r#"{"timestamp":"2026-10-18T12:00:00Z","event":"LoadGame","#,
+        r#""Commander":"Jameson","Ship":"asp","ShipID":4,"#,
+        r#""ShipName":"Nomad","ShipIdent":"NO-01"}"#,
+        "\n",
+        r#"{"timestamp":"2026-10-18T12:00:01Z","event":"Location","#,
+        r#""Docked":true,"StationName":"Abraham Lincoln","MarketID":128016640,"#,
+        r#""StarSystem":"Sol","SystemAddress":10477373803,"#,
+        r#""StarPos":[0.0,0.0,0.0]}"#,
+        "\n",
+    );
+
+    const JUMP: &str = concat!(
+        r#"{"timestamp":"2026-10-18T12:05:00Z","event":"Undocked","#,
+        r#""StationName":"Abraham Lincoln"}"#,
+        "\n",
+        r#"{"timestamp":"2026-10-18T12:07:00Z","event":"FSDJump","#,
+        r#""StarSystem":"Alpha Centauri","SystemAddress":1458376315610,"#,
+        r#""StarPos":[3.03125,-0.09375,3.15625]}"#,
+        "\n",
+    );
+
+    #[test]
+    fn reads_the_session_so_far() {
+        let dir = temp_dir("session");
+        append(&dir.join("Journal.2026-10-18T120000.01.log"), LOAD);
+
+        let here = whereabouts(&dir).unwrap();
+        assert_eq!(here.commander.as_deref(), Some("Jameson"));
+        assert_eq!(here.system.as_deref(), Some("Sol"));
+        assert_eq!(here.station.as_deref(), Some("Abraham Lincoln"));
+        assert_eq!(here.market_id, Some(128016640));
+        let ship = here.ship.unwrap();
+        assert_eq!((ship.ty.as_str(), ship.id), ("asp", Some(4)));
+        assert_eq!(ship.name.as_deref(), Some("Nomad"));
+    }
+
+    /// Lines appended are read on the next poll, a line half written only
+    /// once it is whole
+    #[test]
+    fn follows_what_is_appended() {
+        let dir = temp_dir("appended");
+        let journal = dir.join("Journal.2026-10-18T120000.01.log");
+        append(&journal, LOAD);
+        let mut watcher = Watcher::new(&dir);
+        assert!(watcher.poll().unwrap());
+        assert!(!watcher.poll().unwrap());
+
+        let (first, rest) = JUMP.split_at(JUMP.len() - 40);
+        append(&journal, first);
+        assert!(watcher.poll().unwrap());
+        assert_eq!(watcher.whereabouts().station, None);
+        assert_eq!(watcher.whereabouts().system.as_deref(), Some("Sol"));
+
+        append(&journal, rest);
+        assert!(watcher.poll().unwrap());
+        let here = watcher.whereabouts();
+        assert_eq!(here.system.as_deref(), Some("Alpha Centauri"));
+        assert_eq!(here.system_address, Some(1458376315610));
+        assert_eq!(here.position.map(|p| p.x), Some(3.03125));
+    }
+
+    /// What was written to the old journal before the new one started is
+    /// still read, then the new one from its start
+    #[test]
+    fn follows_into_new_journals() {
+        let dir = temp_dir("rotated");
+        let old = dir.join("Journal.2026-10-18T120000.01.log");
+        append(&old, LOAD);
+        let mut watcher = Watcher::new(&dir);
+        watcher.poll().unwrap();
+
+        append(&old, JUMP);
+        let new = dir.join("Journal.2026-10-18T130000.01.log");
+        append(
+            &new,
+            concat!(
+                r#"{"timestamp":"2026-10-18T13:00:00Z","event":"Loadout","#,
+                r#""Ship":"krait_mkii","ShipID":7,"ShipName":"Hauler"}"#,
+                "\n",
+            ),
+        );
+        watcher.poll().unwrap();
+        let here = watcher.whereabouts();
+        assert_eq!(here.system.as_deref(), Some("Alpha Centauri"));
+        assert_eq!(here.ship.as_ref().map(|s| s.id), Some(Some(7)));
+    }
+
+    /// Journals named the old way are older than any named the new way,
+    /// whichever way their names sort
+    #[test]
+    fn journals_are_ordered_by_when_they_were_started() {
+        let dir = temp_dir("ordered");
+        let names = [
+            "Journal.211018120000.01.log",
+            "Journal.220301090000.01.log",
+            "Journal.220301090000.02.log",
+            "Journal.2026-10-18T120000.01.log",
+            "Journal.2026-10-18T120000.02.log",
+            "Journal.2026-10-18T130000.01.log",
+        ];
+        for name in names.iter().rev() {
+            append(&dir.join(name), LOAD);
+        }
+
+        let found = journals(&dir).unwrap();
+        let found: Vec<_> = found
+            .iter()
+            .map(|path| path.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(found, names);
+    }
+
+    #[test]
+    fn status_says_whether_docked_or_landed() {
+        let dir = temp_dir("status");
+        append(&dir.join("Journal.2026-10-18T120000.01.log"), LOAD);
+        let mut watcher = Watcher::new(&dir);
+        watcher.poll().unwrap();
+
+        fs::write(
+            dir.join("Status.json"),
+            r#"{"event":"Status","Flags":2,"BodyName":"Moon"}"#,
+        )
+        .unwrap();
+        assert!(watcher.poll().unwrap());
+        let here = watcher.whereabouts();
+        assert!(here.landed);
+        assert_eq!(here.station, None);
+        assert_eq!(here.body.as_deref(), Some("Moon"));
+    }
+}
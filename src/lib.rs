@@ -32,12 +32,16 @@
 //! -------------------------------------------
 //!```
 //!
+//! The systems listed are a search by name as the filter is typed, the one
+//! highlighted described alongside, and those checked are the route. See
+//! [`app`] for the keys.
+//!
 //! ### `galos search [OPTIONS] <query>`
 //!
 //! Search for systems, bodies, and stations in the database. This command shows a
 //! selection of details for each object found.
 //!
-//! Examples (TODO):
+//! Examples, see [`galos_db::search`] for the whole of the query language:
 //! ```notrust
 //! $ galos search --count HD* sphere=500Ly
 //! $ galos search Meliae cube=40Ly factions={influence<7.5%}
@@ -57,23 +61,138 @@
 //!
 //! Examples:
 //! ```notrust
-//! $ galos route Sol -> Alpha Centauri
+//! $ galos route 'Sol -> Alpha Centauri'
+//!
+//! $ galos route 'Wolf 397 -> Sol + Meliae -> Nagalinn + Sol'
+//! yields:        Wolf 397 -> Meliae -> Sol -> Nagalinn
+//! ```
 //!
-//! $ galos route Wolf 397 -> Sol + Meliae -> Nagalinn + Sol
-//! yields:       Wolf 397 -> Meliae -> Sol -> Nagalinn
+//! A route not saying where it starts, a lone stop or stops joined only by `+`
+//! and `|`, starts from where the journal says the commander is. So does one
+//! starting with `->`, given after `--` to keep it from reading as an option.
+//! ```notrust
+//! $ galos route Colonia
+//! $ galos route -- '-> Sol -> Colonia'
 //! ```
 //!
+//! Operators are set apart by spaces, since system names have `+` in them,
+//! and need quoting from the shell. `(` and `)` group, also set apart. See
+//! [`route`] for the grammar.
+//!
 //! TODO: Incorperate queries for both `+` and `|` nodes in the route.
 //!
+//! ### `galos whereami`
+//!
+//! Where the commander is, docked or landed, and in what ship, from the
+//! newest journal in the game's journal directory, or `GALOS_JOURNAL`.
+//! `--follow` keeps watching and prints each move. See [`journal`].
+//!
+//! ### `galos find module|ship`
+//!
+//! The nearest stations selling a module or a ship, within `--radius` of the
+//! system given as `--near`. A module is named as the game names it, whole or
+//! by its item alone, and narrowed by `--class` and `--rating`. A ship is
+//! named as the shipyard shows it or as the game does. See
+//! [`galos_db::outfitting`].
+//! ```notrust
+//! $ galos find module hyperdrive --class 5 --rating A --near Sol
+//! $ galos find ship 'Krait Mk II' --near 'Shinrarta Dezhra' --pad L
+//! ```
+//!
+//! ### `--format table|json|ndjson|csv`
+//!
+//! Every subcommand prints tables unless asked for something a script can
+//! read instead, written a record at a time. See [`output`].
+//! ```notrust
+//! $ galos --format ndjson search 'HIP *' | jq .name
+//! $ galos route --format navroute 'Sol -> Colonia' > NavRoute.json
+//! ```
+//!
 //! ### `galos-sync <provider>`
 //!
 //! Syncs the DB with EDDN, EDSM and/or EDDB.
 //!
 //! Syncing from the `eddn` provider will subscribe to its ZMQ service and
-//! continue to process events until the process is killed.
+//! continue to process events until the process is killed. With `--archive`
+//! it keeps every message as it goes, which `replay` feeds through the same
+//! ingest again, as fast as it can or at `--speed`, picking out messages by
+//! `--schema` and `--since`/`--until`. See [`archive`].
+//! ```notrust
+//! $ galos-sync eddn --archive ~/eddn
+//! $ galos-sync replay --schema journal --since 2026-10-18T11:00:00Z ~/eddn/*
+//! ```
+//!
+//! While the database can't be reached, messages are spooled to disk, in
+//! `eddn-spool` or `--spool`, and written out in the order they arrived once
+//! it can again, so a restarted database misses nothing. Past `--spool-limit`
+//! MiB they are dropped, and the log says so. See [`spool`].
+//!
+//! Messages are written by `--workers` threads at once, four by default,
+//! those of the same system always by the same worker and in the order they
+//! arrived. A worker with a full queue holds up the reading of the rest. See
+//! [`pool`].
+//!
+//! Each message is checked before it is written, for systems outside the
+//! galaxy or away from where they are known to be, timestamps well away from
+//! when it arrived, and software given as `--bad-software`. One that fails is
+//! quarantined with the reason instead, and `quarantine` lists, releases or
+//! purges them. See [`validate`].
+//! ```notrust
+//! $ galos-sync eddn --bad-software 'Bad Uploader@1.2'
+//! $ galos-sync quarantine list
+//! $ galos-sync quarantine release 17 18
+//! $ galos-sync quarantine purge --before 2026-10-01T00:00:00Z
+//! ```
+//!
+//! Exploration is taken in too, from EDDN and from journals alike: a
+//! system's honk says how many bodies it has, `FSSAllBodiesFound` that all of
+//! them are scanned, a nav beacon that its bodies are all known, and
+//! `FSSBodySignals` and `SAASignalsFound` what signals each body has. Held
+//! against the bodies on record, that says how complete what is known of a
+//! system is, and the map says how many of its bodies are yet to be scanned.
+//! See [`galos_db::exploration`].
+//!
+//! Stations' outfitting and shipyards, EDDN's `outfitting/2` and `shipyard/2`,
+//! are kept beside their markets, each list replacing the last, for `galos
+//! find`.
+//!
+//! `edsm file` reads any of EDSM's nightly dumps a record at a time, gzipped
+//! or not, so systems, bodies, stations and populated systems with their
+//! factions all import in the same little memory. Which dump it is goes by
+//! the file's name, or `--kind`. See [`dumps`].
+//! ```notrust
+//! $ galos-sync edsm file bodies7days.json.gz
+//! $ galos-sync edsm file --kind populated latest.json
+//! ```
+//!
+//! Systems and bodies, from EDSM or EDDB, are copied in ten thousand at a
+//! time and merged, the newer of two updates winning, rather than upserted
+//! one by one. Each import says how many rows were inserted, updated, left
+//! as stale or rejected. `--row-by-row` upserts them one at a time instead.
+//! See [`galos_db::bulk`].
+//!
+//! Files, journals and dumps alike, are checkpointed as they are imported. A
+//! file unchanged since is skipped, and one stopped partway, or added to,
+//! carries on from where it got. `--force` imports it all again. See
+//! [`galos_db::imports`].
 
 use galos_db::Database;
 
+#[cfg(unix)]
+pub mod app;
+pub mod archive;
+pub mod dumps;
+pub mod envelope;
+#[cfg(unix)]
+pub mod feed;
+pub mod ingest;
+pub mod journal;
+pub mod output;
+pub mod pool;
+pub mod route;
+pub mod spool;
+pub mod validate;
+
 pub trait Run {
     // TODO: Reture Error
     fn run(&self, db: &Database);
@@ -0,0 +1,249 @@
+//! What `galos` prints, for people and for scripts
+//!
+//! Every subcommand prints tables by default, laid out for reading. The other
+//! formats are for whatever is built on top: each record is written as it
+//! comes, through its serde serialization, so that a search matching half the
+//! galaxy starts printing straight away and never holds more than one record.
+//!
+//! - [`Format::Json`], one array of every record
+//! - [`Format::Ndjson`], a record to a line
+//! - [`Format::Csv`], a record to a row, nested fields flattened into
+//!   dotted columns, e.g. `economies.primary`
+//!
+//! A route is the exception in CSV, written as a whole file by
+//! [`galos_db::export`] as it is in the game's own [`Format::NavRoute`] and
+//! Spansh's [`Format::Spansh`], which nothing else is written as. In JSON and
+//! NDJSON it is a record a jump like anything else.
+use galos_db::export::field;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// How results are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    #[default]
+    Table,
+    Json,
+    Ndjson,
+    Csv,
+    /// The game's `NavRoute.json`, which only a route can be written as
+    NavRoute,
+    /// The JSON Spansh's plotters answer with, likewise a route's alone
+    Spansh,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(Format::Table),
+            "json" => Ok(Format::Json),
+            "ndjson" => Ok(Format::Ndjson),
+            "csv" => Ok(Format::Csv),
+            "navroute" => Ok(Format::NavRoute),
+            "spansh" => Ok(Format::Spansh),
+            _ => Err("invalid format".to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Table => write!(f, "table"),
+            Format::Json => write!(f, "json"),
+            Format::Ndjson => write!(f, "ndjson"),
+            Format::Csv => write!(f, "csv"),
+            Format::NavRoute => write!(f, "navroute"),
+            Format::Spansh => write!(f, "spansh"),
+        }
+    }
+}
+
+/// Records written one at a time, in a format other than a table
+///
+/// Tables are laid out by each subcommand for what it prints, and a nav route
+/// and Spansh's JSON are a route's alone, so none of them is written here.
+/// Asked to, this writes NDJSON.
+pub struct Output<W: Write> {
+    format: Format,
+    out: W,
+    written: usize,
+    /// The columns of a CSV, taken from the first record
+    columns: Vec<String>,
+}
+
+impl Output<io::Stdout> {
+    pub fn stdout(format: Format) -> Self {
+        Output::new(format, io::stdout())
+    }
+}
+
+impl<W: Write> Output<W> {
+    pub fn new(format: Format, out: W) -> Self {
+        Output { format, out, written: 0, columns: Vec::new() }
+    }
+
+    /// Write the next record
+    pub fn write<T: Serialize>(&mut self, record: &T) -> io::Result<()> {
+        match self.format {
+            Format::Json => {
+                let sep = if self.written == 0 { "[\n" } else { ",\n" };
+                self.out.write_all(sep.as_bytes())?;
+                serde_json::to_writer(&mut self.out, record)?;
+            }
+            Format::Table
+            | Format::Ndjson
+            | Format::NavRoute
+            | Format::Spansh => {
+                serde_json::to_writer(&mut self.out, record)?;
+                writeln!(self.out)?;
+            }
+            Format::Csv => {
+                let record = serde_json::to_value(record)?;
+                if self.written == 0 {
+                    let mut row = Map::new();
+                    flatten(&mut row, "", record.clone());
+                    self.columns = row.keys().cloned().collect();
+                    let header: Vec<String> =
+                        self.columns.iter().map(|c| field(c)).collect();
+                    writeln!(self.out, "{}", header.join(","))?;
+                }
+                // Later records are written in the first one's columns, each
+                // cell looked up by its path. A field the first record had
+                // nothing in is one column, and whatever a later record has
+                // there is written whole in it rather than left out.
+                let cells: Vec<String> = self
+                    .columns
+                    .iter()
+                    .map(|column| field(&cell(at(&record, column))))
+                    .collect();
+                writeln!(self.out, "{}", cells.join(","))?;
+            }
+        }
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Close off what has been written, even if that was nothing
+    pub fn finish(mut self) -> io::Result<()> {
+        if self.format == Format::Json {
+            let end = if self.written == 0 { "[]\n" } else { "\n]\n" };
+            self.out.write_all(end.as_bytes())?;
+        }
+        self.out.flush()
+    }
+}
+
+/// Each leaf of `value` into `row`, named by its path from the top
+///
+/// Objects are opened up, since a column is a field. Arrays are left whole,
+/// there being no telling how many columns one would take.
+fn flatten(row: &mut Map<String, Value>, prefix: &str, value: Value) {
+    match value {
+        Value::Object(fields) => {
+            for (name, value) in fields {
+                let path = if prefix.is_empty() {
+                    name
+                } else {
+                    format!("{}.{}", prefix, name)
+                };
+                flatten(row, &path, value);
+            }
+        }
+        value => {
+            row.insert(prefix.to_string(), value);
+        }
+    }
+}
+
+/// The value at a dotted `path` into `value`, as [`flatten`] names it
+fn at<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    if path.is_empty() {
+        return Some(value);
+    }
+    path.split('.').try_fold(value, |value, name| value.get(name))
+}
+
+/// A value as it reads in a cell, nothing for null
+fn cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(value) => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn written(format: Format, records: &[Value]) -> String {
+        let mut out = Vec::new();
+        let mut output = Output::new(format, &mut out);
+        for record in records {
+            output.write(record).unwrap();
+        }
+        output.finish().unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn records() -> Vec<Value> {
+        vec![
+            json!({"name": "Sol", "economies": {"primary": "Refinery"}}),
+            json!({"name": "Alpha, Centauri", "economies": null}),
+        ]
+    }
+
+    #[test]
+    fn json_is_one_array() {
+        let out = written(Format::Json, &records());
+        let read: Vec<Value> = serde_json::from_str(&out).unwrap();
+        assert_eq!(read, records());
+        assert_eq!(written(Format::Json, &[]), "[]\n");
+    }
+
+    #[test]
+    fn ndjson_is_a_record_a_line() {
+        let out = written(Format::Ndjson, &records());
+        let read: Vec<Value> = out
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(read, records());
+    }
+
+    /// Nested fields become dotted columns, and a record without one has an
+    /// empty cell there
+    #[test]
+    fn csv_flattens_into_the_first_records_columns() {
+        let out = written(Format::Csv, &records());
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(
+            lines,
+            ["economies.primary,name", "Refinery,Sol", ",\"Alpha, Centauri\"",]
+        );
+    }
+
+    /// What a later record has where the first had nothing is kept, whole
+    #[test]
+    fn csv_keeps_what_the_first_record_lacked() {
+        let mut records = records();
+        records.reverse();
+        let out = written(Format::Csv, &records);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "economies,name",
+                ",\"Alpha, Centauri\"",
+                "\"{\"\"primary\"\":\"\"Refinery\"\"}\",Sol",
+            ]
+        );
+    }
+}
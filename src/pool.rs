@@ -0,0 +1,108 @@
+//! Work shared between threads, in order wherever it has to be
+//!
+//! `galos-sync eddn` hands each message to one of a few workers, picked by
+//! the system it is about, so two messages of the same system are always
+//! written by the same worker in the order they arrived, and a newer update
+//! never races an older one. Messages of different systems are written
+//! alongside each other.
+//!
+//! Each worker holds only so many messages waiting. Past that, handing it
+//! another blocks, so a database falling behind slows the reading of the
+//! socket rather than piling messages up in memory.
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::Arc;
+use std::thread::Scope;
+
+pub struct Pool<T> {
+    workers: Vec<SyncSender<T>>,
+}
+
+impl<T: Send> Pool<T> {
+    /// Start `workers` threads within `scope`, each doing `work` with what
+    /// it is given, and each holding up to `queue` jobs waiting
+    ///
+    /// The threads end once the pool is dropped and they have finished what
+    /// they were given, and are joined with the scope.
+    pub fn new<'scope, 'env, F>(
+        scope: &'scope Scope<'scope, 'env>,
+        workers: usize,
+        queue: usize,
+        work: F,
+    ) -> Self
+    where
+        F: Fn(T) + Send + Sync + 'scope,
+        T: 'scope,
+    {
+        let work = Arc::new(work);
+        let workers = (0..workers.max(1))
+            .map(|_| {
+                let (send, receive) = mpsc::sync_channel(queue);
+                let work = Arc::clone(&work);
+                scope.spawn(move || {
+                    for job in receive {
+                        work(job)
+                    }
+                });
+                send
+            })
+            .collect();
+        Pool { workers }
+    }
+
+    /// Hand `job` to the worker for `key`, after everything it was handed
+    /// before, waiting while that worker has a full queue
+    ///
+    /// The job is given back if the worker has died.
+    pub fn send<K: Hash + ?Sized>(&self, key: &K, job: T) -> Result<(), T> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let worker = hasher.finish() as usize % self.workers.len();
+        self.workers[worker].send(job).map_err(|err| err.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::thread;
+
+    #[test]
+    fn a_key_is_worked_in_order() {
+        let done = Mutex::new(vec![]);
+        thread::scope(|scope| {
+            let pool = Pool::new(scope, 4, 2, |(key, n): (u64, u32)| {
+                done.lock().unwrap().push((key, n))
+            });
+            for n in 0..100 {
+                for key in 0..8 {
+                    pool.send(&key, (key, n)).unwrap();
+                }
+            }
+        });
+
+        let done = done.into_inner().unwrap();
+        assert_eq!(done.len(), 800);
+        for key in 0..8 {
+            let order: Vec<u32> = done
+                .iter()
+                .filter(|(k, _)| *k == key)
+                .map(|(_, n)| *n)
+                .collect();
+            assert_eq!(order, (0..100).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn no_workers_is_one() {
+        let done = Mutex::new(0);
+        thread::scope(|scope| {
+            let pool =
+                Pool::new(scope, 0, 0, |_: ()| *done.lock().unwrap() += 1);
+            pool.send("Sol", ()).unwrap();
+        });
+        assert_eq!(done.into_inner().unwrap(), 1);
+    }
+}
@@ -0,0 +1,396 @@
+//! Route expressions, and the plans that answer them
+//!
+//! The grammar `galos route` takes, as the crate docs describe it. Looser to
+//! tighter:
+//!
+//! ```notrust
+//! path   := either ('->' either)*
+//! either := both ('|' both)*
+//! both   := stop ('+' stop)*
+//! stop   := <name> | '(' path ')'
+//! ```
+//!
+//! Operators stand apart from the names around them, with whitespace either
+//! side. System names have `+` in them, `BD+49 2586` among others, and a name
+//! is whatever runs between two operators.
+use async_std::task;
+use galos_db::{systems::System, Database, Error};
+use ordered_float::OrderedFloat;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Route {
+    Stop(String),
+    /// `A -> B` specifies a direct path from A to B
+    Path(Box<Route>, Box<Route>),
+    /// `A + B` specifies a path to both A and B, where the route could either
+    /// visit A or B first
+    Both(Box<Route>, Box<Route>),
+    /// `A | B` specifies a path to either A or B
+    Either(Box<Route>, Box<Route>),
+}
+
+impl Route {
+    /// Parse a route flown from `here`, where it does not say where it starts
+    ///
+    /// A route starts where its first `->` leaves from. One written with
+    /// nothing before its first `->`, or with none at all, `Sol + Meliae`
+    /// being two stops and no start, is flown from `here`. Without knowing
+    /// where that is, the first is an error and the second is read as ever.
+    pub fn from_here(s: &str, here: Option<&str>) -> Result<Route, String> {
+        let (unstarted, rest) = match s.trim_start().strip_prefix("->") {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        let route: Route = rest.parse()?;
+        match here {
+            Some(here) if unstarted || !matches!(route, Route::Path(..)) => {
+                Ok(Route::Path(
+                    Box::new(Route::Stop(here.to_string())),
+                    Box::new(route),
+                ))
+            }
+            None if unstarted => {
+                Err("no start given, and nowhere known to start from"
+                    .to_string())
+            }
+            _ => Ok(route),
+        }
+    }
+
+    /// Every order of stops that would answer this route
+    ///
+    /// Each is a list of names, travelled first to last. A route of `n`
+    /// stops joined by `+` has `n!` of them, which is fine for the handful a
+    /// route is typed with and no way to plan a tour.
+    pub fn alternatives(&self) -> Vec<Vec<String>> {
+        match self {
+            Route::Stop(name) => vec![vec![name.clone()]],
+            Route::Path(a, b) => followed(&a.alternatives(), &b.alternatives()),
+            Route::Both(a, b) => {
+                let (a, b) = (a.alternatives(), b.alternatives());
+                let mut both = followed(&a, &b);
+                both.extend(followed(&b, &a));
+                both
+            }
+            Route::Either(a, b) => {
+                let mut either = a.alternatives();
+                either.extend(b.alternatives());
+                either
+            }
+        }
+    }
+
+    /// The cheapest way to answer this route, a leg at a time
+    ///
+    /// `leg` plots from one stop to the next, however the caller is flying,
+    /// and says what it cost, or that there is no such leg. A leg is plotted
+    /// once however many of the alternatives share it, and an alternative is
+    /// given up on as soon as it costs more than the best found so far. A
+    /// leg that fails to plot at all fails the plan.
+    ///
+    /// A stop arrived at twice in a row is one stop, so `A -> B + A` is free
+    /// to visit `A` then `B` without a leg from `A` to itself.
+    pub fn plan<L, F>(
+        &self,
+        db: &Database,
+        mut leg: F,
+    ) -> Result<Plan<L>, String>
+    where
+        L: Clone,
+        F: FnMut(
+            &System,
+            &System,
+        ) -> Result<Option<(L, OrderedFloat<f64>)>, Error>,
+    {
+        let alternatives = self.alternatives();
+
+        let mut systems: HashMap<&str, System> = HashMap::new();
+        for name in alternatives.iter().flatten() {
+            if systems.contains_key(name.as_str()) {
+                continue;
+            }
+            let system = task::block_on(System::fetch_by_name(db, name))
+                .map_err(|_| format!("no system named {}", name))?;
+            systems.insert(name, system);
+        }
+
+        let mut plotted: HashMap<_, Option<(L, OrderedFloat<f64>)>> =
+            HashMap::new();
+        let mut best: Option<Plan<L>> = None;
+        'alternatives: for names in &alternatives {
+            let mut stops: Vec<System> = Vec::new();
+            for name in names {
+                let system = &systems[name.as_str()];
+                if stops.last() != Some(system) {
+                    stops.push(system.clone());
+                }
+            }
+
+            let mut legs = Vec::new();
+            let mut cost = OrderedFloat(0.);
+            for pair in stops.windows(2) {
+                let key = (pair[0].address, pair[1].address);
+                let plot = match plotted.entry(key) {
+                    Entry::Occupied(plot) => plot.get().clone(),
+                    Entry::Vacant(slot) => {
+                        let plot = leg(&pair[0], &pair[1])
+                            .map_err(|err| err.to_string())?;
+                        slot.insert(plot).clone()
+                    }
+                };
+                let Some((route, jumps)) = plot else {
+                    continue 'alternatives;
+                };
+                cost += jumps;
+                if best.as_ref().is_some_and(|best| cost >= best.cost) {
+                    continue 'alternatives;
+                }
+                legs.push((route, jumps));
+            }
+
+            if best.as_ref().is_none_or(|best| cost < best.cost) {
+                best = Some(Plan { stops, legs, cost });
+            }
+        }
+
+        best.ok_or_else(|| "no route found".to_string())
+    }
+}
+
+/// Each of `a` followed by each of `b`
+fn followed(a: &[Vec<String>], b: &[Vec<String>]) -> Vec<Vec<String>> {
+    a.iter()
+        .flat_map(|a| b.iter().map(move |b| [&a[..], &b[..]].concat()))
+        .collect()
+}
+
+/// The stops a route was answered with, and the legs between them
+#[derive(Debug, Clone)]
+pub struct Plan<L> {
+    /// Each stop, in the order visited
+    pub stops: Vec<System>,
+    /// Each leg as plotted, and what it cost, one fewer than the stops
+    pub legs: Vec<(L, OrderedFloat<f64>)>,
+    pub cost: OrderedFloat<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Name(String),
+    Then,
+    And,
+    Or,
+    Open,
+    Close,
+}
+
+fn tokenize(s: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut name: Vec<&str> = Vec::new();
+    for word in s.split_whitespace() {
+        let op = match word {
+            "->" => Token::Then,
+            "+" => Token::And,
+            "|" => Token::Or,
+            "(" => Token::Open,
+            ")" => Token::Close,
+            _ => {
+                name.push(word);
+                continue;
+            }
+        };
+        if !name.is_empty() {
+            tokens.push(Token::Name(name.join(" ")));
+            name.clear();
+        }
+        tokens.push(op);
+    }
+    if !name.is_empty() {
+        tokens.push(Token::Name(name.join(" ")));
+    }
+    tokens
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    at: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.at)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.at).cloned();
+        self.at += 1;
+        token
+    }
+
+    /// One level of the grammar, `below` joined by `op` into `join`
+    fn joined(
+        &mut self,
+        op: Token,
+        join: fn(Box<Route>, Box<Route>) -> Route,
+        below: fn(&mut Parser) -> Result<Route, String>,
+    ) -> Result<Route, String> {
+        let mut route = below(self)?;
+        while self.peek() == Some(&op) {
+            self.next();
+            route = join(Box::new(route), Box::new(below(self)?));
+        }
+        Ok(route)
+    }
+
+    fn path(&mut self) -> Result<Route, String> {
+        self.joined(Token::Then, Route::Path, Parser::either)
+    }
+
+    fn either(&mut self) -> Result<Route, String> {
+        self.joined(Token::Or, Route::Either, Parser::both)
+    }
+
+    fn both(&mut self) -> Result<Route, String> {
+        self.joined(Token::And, Route::Both, Parser::stop)
+    }
+
+    fn stop(&mut self) -> Result<Route, String> {
+        match self.next() {
+            Some(Token::Name(name)) => Ok(Route::Stop(name)),
+            Some(Token::Open) => {
+                let route = self.path()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(route),
+                    _ => Err("unclosed '('".to_string()),
+                }
+            }
+            Some(_) => Err("expected a system name".to_string()),
+            None => Err("expected a system name, found the end".to_string()),
+        }
+    }
+}
+
+impl FromStr for Route {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { tokens: tokenize(s), at: 0 };
+        let route = parser.path()?;
+        match parser.peek() {
+            None => Ok(route),
+            Some(Token::Close) => Err("unopened ')'".to_string()),
+            Some(_) => Err("expected an operator".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stop(name: &str) -> Box<Route> {
+        Box::new(Route::Stop(name.to_string()))
+    }
+
+    #[test]
+    fn routes_without_a_start_start_here() {
+        let from_sol = |s| Route::from_here(s, Some("Sol")).unwrap();
+        assert_eq!(
+            from_sol("-> Meliae"),
+            Route::Path(stop("Sol"), stop("Meliae"))
+        );
+        assert_eq!(
+            from_sol("Meliae + Nagalinn"),
+            Route::Path(
+                stop("Sol"),
+                Box::new(Route::Both(stop("Meliae"), stop("Nagalinn")))
+            )
+        );
+        assert_eq!(
+            from_sol("Wolf 397 -> Meliae"),
+            Route::Path(stop("Wolf 397"), stop("Meliae"))
+        );
+        assert!(Route::from_here("-> Meliae", None).is_err());
+        assert_eq!(
+            Route::from_here("Meliae", None).unwrap(),
+            Route::Stop("Meliae".to_string())
+        );
+    }
+
+    /// Names run on across spaces until an operator
+    #[test]
+    fn names_have_spaces_in_them() {
+        assert_eq!(
+            "Sol -> Alpha Centauri".parse(),
+            Ok(Route::Path(stop("Sol"), stop("Alpha Centauri")))
+        );
+    }
+
+    /// A `+` inside a name is part of it
+    #[test]
+    fn operators_stand_apart() {
+        assert_eq!(
+            "BD+49 2586 + Sol".parse(),
+            Ok(Route::Both(stop("BD+49 2586"), stop("Sol")))
+        );
+    }
+
+    /// `->` binds loosest, then `|`, then `+`
+    #[test]
+    fn precedence() {
+        assert_eq!(
+            "A -> B | C + D".parse(),
+            Ok(Route::Path(
+                stop("A"),
+                Box::new(Route::Either(
+                    stop("B"),
+                    Box::new(Route::Both(stop("C"), stop("D")))
+                ))
+            ))
+        );
+        assert_eq!(
+            "( A -> B ) + C".parse(),
+            Ok(Route::Both(
+                Box::new(Route::Path(stop("A"), stop("B"))),
+                stop("C")
+            ))
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        assert!("A ->".parse::<Route>().is_err());
+        assert!("-> A".parse::<Route>().is_err());
+        assert!("( A -> B".parse::<Route>().is_err());
+        assert!("A -> B )".parse::<Route>().is_err());
+        assert!("".parse::<Route>().is_err());
+    }
+
+    /// `+` goes both ways round, and `|` goes one way or the other
+    #[test]
+    fn alternatives() {
+        let names = |route: &str| {
+            route
+                .parse::<Route>()
+                .unwrap()
+                .alternatives()
+                .into_iter()
+                .map(|names| names.join(" "))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("A -> B + C"), vec!["A B C", "A C B"]);
+        assert_eq!(names("A -> B | C"), vec!["A B", "A C"]);
+        assert_eq!(
+            names("Wolf 397 -> Sol + Meliae -> Nagalinn + Sol"),
+            vec![
+                "Wolf 397 Sol Meliae Nagalinn Sol",
+                "Wolf 397 Sol Meliae Sol Nagalinn",
+                "Wolf 397 Meliae Sol Nagalinn Sol",
+                "Wolf 397 Meliae Sol Sol Nagalinn",
+            ]
+        );
+    }
+}
@@ -0,0 +1,331 @@
+//! Messages held on disk while the database can't take them
+//!
+//! When Postgres restarts, or a migration holds a table, `galos-sync eddn`
+//! keeps receiving regardless. What it cannot write goes here, a line to a
+//! message in the same form [`crate::archive`] keeps them, and everything
+//! after it follows until the spool is empty again, so messages are still
+//! written in the order they arrived.
+//!
+//! The spool is a directory of numbered files, appended to at the newest and
+//! read from the oldest, each deleted once it has all been read. How far into
+//! the oldest has been read is kept beside them, so a spool outlives the
+//! process, and a restart carries on draining it. It is bounded by how many
+//! bytes it may hold, past which a message is refused rather than kept.
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// How large a file grows before the next is started, so that what has been
+/// drained is given back to the disk as it goes
+const SEGMENT: u64 = 16 << 20;
+
+/// Where the oldest file has been read to
+const CURSOR: &str = "cursor";
+
+pub struct Spool {
+    dir: PathBuf,
+    limit: u64,
+    /// Oldest first, and never empty, the last being appended to
+    segments: VecDeque<u64>,
+    /// How far into the oldest has been read
+    offset: u64,
+    reader: Option<BufReader<File>>,
+    writer: File,
+    written: u64,
+    len: usize,
+    size: u64,
+}
+
+impl Spool {
+    /// Open the spool in `dir`, with whatever it held when last closed,
+    /// holding no more than `limit` bytes
+    pub fn open<P: AsRef<Path>>(dir: P, limit: u64) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let (first, mut offset) = match fs::read_to_string(dir.join(CURSOR)) {
+            Ok(cursor) => parse(&cursor).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "bad spool cursor")
+            })?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (0, 0),
+            Err(err) => return Err(err),
+        };
+        let mut segments: Vec<u64> = fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name();
+                name.to_str()?.strip_suffix(".ndjson")?.parse().ok()
+            })
+            .collect();
+        segments.sort_unstable();
+        // Anything before the cursor was read before the cursor moved past
+        // it, and only outlived that by a crash in between.
+        for &seq in segments.iter().filter(|&&seq| seq < first) {
+            fs::remove_file(segment(&dir, seq))?;
+        }
+        segments.retain(|&seq| seq >= first);
+        if segments.first() != Some(&first) {
+            offset = 0;
+        }
+        if segments.is_empty() {
+            segments.push(first);
+        }
+
+        let (mut len, mut size) = (0, 0);
+        // Where the last whole line of the last file ends
+        let mut whole = 0;
+        for (i, &seq) in segments.iter().enumerate() {
+            let mut read = BufReader::new(open_or_create(&dir, seq)?);
+            whole = 0;
+            if i == 0 {
+                whole = read.seek(SeekFrom::Start(offset))?;
+            }
+            // A line cut off by a crash is never drained, so isn't waiting.
+            let mut line = vec![];
+            while read.read_until(b'\n', &mut line)? > 0 {
+                if line.ends_with(b"\n") {
+                    len += 1;
+                    size += line.len() as u64;
+                    whole += line.len() as u64;
+                }
+                line.clear();
+            }
+        }
+
+        // And is cut off for good, or the next line pushed would be read as
+        // the rest of it.
+        let last = *segments.last().expect("never empty");
+        let writer = append(&dir, last)?;
+        writer.set_len(whole)?;
+        let written = whole;
+        Ok(Spool {
+            dir,
+            limit,
+            segments: segments.into(),
+            offset,
+            reader: None,
+            writer,
+            written,
+            len,
+            size,
+        })
+    }
+
+    /// How many messages are waiting
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// How many bytes are waiting
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Keep `line` after everything already waiting, saying whether there
+    /// was room for it
+    ///
+    /// Synced before it returns, so a message said to be kept is kept.
+    pub fn push(&mut self, line: &str) -> io::Result<bool> {
+        let bytes = line.len() as u64 + 1;
+        if self.size + bytes > self.limit {
+            return Ok(false);
+        }
+        if self.written >= SEGMENT {
+            let next = self.segments.back().expect("never empty") + 1;
+            self.writer = append(&self.dir, next)?;
+            self.segments.push_back(next);
+            self.written = 0;
+        }
+        let mut record = line.as_bytes().to_vec();
+        record.push(b'\n');
+        self.writer.write_all(&record)?;
+        self.writer.sync_data()?;
+        self.written += bytes;
+        self.len += 1;
+        self.size += bytes;
+        Ok(true)
+    }
+
+    /// The oldest message waiting, left waiting until it is [`Spool::pop`]ped
+    pub fn peek(&mut self) -> io::Result<Option<String>> {
+        loop {
+            if self.is_empty() {
+                return Ok(None);
+            }
+            let reader = match &mut self.reader {
+                Some(reader) => reader,
+                None => {
+                    let first = *self.segments.front().expect("never empty");
+                    let mut file = File::open(segment(&self.dir, first))?;
+                    file.seek(SeekFrom::Start(self.offset))?;
+                    self.reader.insert(BufReader::new(file))
+                }
+            };
+            let mut line = String::new();
+            reader.read_line(&mut line)?;
+            // Read again from the same place next time, this only having
+            // looked.
+            reader.seek(SeekFrom::Start(self.offset))?;
+            if line.ends_with('\n') {
+                line.pop();
+                return Ok(Some(line));
+            }
+            // Nothing whole is left in this file, so the next is where the
+            // rest of what is waiting is.
+            if self.segments.len() == 1 {
+                return Ok(None);
+            }
+            self.next_segment()?;
+        }
+    }
+
+    /// Be done with the oldest message waiting
+    pub fn pop(&mut self) -> io::Result<()> {
+        let Some(line) = self.peek()? else {
+            return Ok(());
+        };
+        let bytes = line.len() as u64 + 1;
+        self.offset += bytes;
+        if let Some(reader) = &mut self.reader {
+            reader.seek(SeekFrom::Start(self.offset))?;
+        }
+        self.len -= 1;
+        self.size -= bytes;
+        self.save()
+    }
+
+    /// Move on past the oldest file, deleting it
+    fn next_segment(&mut self) -> io::Result<()> {
+        let done = self.segments.pop_front().expect("never empty");
+        self.offset = 0;
+        self.reader = None;
+        self.save()?;
+        fs::remove_file(segment(&self.dir, done))
+    }
+
+    /// Keep the cursor, replacing the old one whole so a crash leaves one or
+    /// the other
+    fn save(&self) -> io::Result<()> {
+        let first = self.segments.front().expect("never empty");
+        let cursor = self.dir.join(CURSOR);
+        let partial = self.dir.join(format!("{}.partial", CURSOR));
+        fs::write(&partial, format!("{} {}", first, self.offset))?;
+        fs::rename(partial, cursor)
+    }
+}
+
+fn segment(dir: &Path, seq: u64) -> PathBuf {
+    dir.join(format!("{:020}.ndjson", seq))
+}
+
+fn append(dir: &Path, seq: u64) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(segment(dir, seq))
+}
+
+fn parse(cursor: &str) -> Option<(u64, u64)> {
+    let (seq, offset) = cursor.trim().split_once(' ')?;
+    Some((seq.parse().ok()?, offset.parse().ok()?))
+}
+
+fn open_or_create(dir: &Path, seq: u64) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .read(true)
+        .open(segment(dir, seq))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "galos-spool-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn drain(spool: &mut Spool) -> Vec<String> {
+        let mut lines = vec![];
+        while let Some(line) = spool.peek().unwrap() {
+            spool.pop().unwrap();
+            lines.push(line);
+        }
+        lines
+    }
+
+    #[test]
+    fn messages_drain_in_order() {
+        let dir = dir("order");
+        let mut spool = Spool::open(&dir, 1 << 20).unwrap();
+        for line in ["a", "b", "c"] {
+            assert!(spool.push(line).unwrap());
+        }
+        assert_eq!(spool.len(), 3);
+        assert_eq!(spool.peek().unwrap().as_deref(), Some("a"));
+        assert_eq!(spool.peek().unwrap().as_deref(), Some("a"));
+        assert_eq!(drain(&mut spool), ["a", "b", "c"]);
+        assert!(spool.is_empty());
+        assert_eq!(spool.size(), 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// What was waiting, and how far it had been drained, is still so when
+    /// the spool is opened again
+    #[test]
+    fn a_spool_outlives_the_process() {
+        let dir = dir("restart");
+        let mut spool = Spool::open(&dir, 1 << 20).unwrap();
+        for line in ["a", "b", "c"] {
+            spool.push(line).unwrap();
+        }
+        spool.pop().unwrap();
+        drop(spool);
+
+        let mut spool = Spool::open(&dir, 1 << 20).unwrap();
+        assert_eq!(spool.len(), 2);
+        spool.push("d").unwrap();
+        assert_eq!(drain(&mut spool), ["b", "c", "d"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// A line a crash cut off partway is dropped, rather than taken for the
+    /// start of the next one pushed
+    #[test]
+    fn a_torn_line_is_dropped() {
+        let dir = dir("torn");
+        let mut spool = Spool::open(&dir, 1 << 20).unwrap();
+        spool.push("a").unwrap();
+        spool.writer.write_all(b"b").unwrap();
+        drop(spool);
+
+        let mut spool = Spool::open(&dir, 1 << 20).unwrap();
+        assert_eq!(spool.len(), 1);
+        spool.push("c").unwrap();
+        assert_eq!(spool.size(), 4);
+        assert_eq!(drain(&mut spool), ["a", "c"]);
+        assert_eq!(spool.size(), 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_full_spool_refuses() {
+        let dir = dir("full");
+        let mut spool = Spool::open(&dir, 4).unwrap();
+        assert!(spool.push("abc").unwrap());
+        assert!(!spool.push("d").unwrap());
+        assert_eq!(spool.len(), 1);
+        spool.pop().unwrap();
+        assert!(spool.push("d").unwrap());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
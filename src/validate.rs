@@ -0,0 +1,259 @@
+//! Telling messages not to be trusted from the rest
+//!
+//! Anyone can upload anything to EDDN, and what they upload is written as
+//! fact. Before it is, each message is checked for what can't be so: a
+//! system outside the galaxy, or somewhere other than where it is already
+//! known to be, a timestamp well away from when the message arrived, or
+//! software known to upload bad data. One that fails is quarantined with the
+//! reason rather than written. See [`galos_db::quarantine`].
+use chrono::{DateTime, Duration, Utc};
+use elite_journal::entry::route::NavRoute;
+use elite_journal::entry::{Entry, Event};
+use elite_journal::prelude::Coordinate;
+use galos_db::{systems::System, Database, Error};
+use std::fmt;
+use std::str::FromStr;
+use tracing::warn;
+
+/// Generous bounds of the galaxy, in light years from Sol along each axis
+///
+/// The disc is about a hundred thousand light years across with Sol some
+/// twenty-six thousand from its middle, and only a few thousand thick.
+const BOUNDS: [(f64, f64); 3] =
+    [(-45_000., 45_000.), (-10_000., 10_000.), (-25_000., 70_000.)];
+
+/// How far a system may be said to be from where it is known to be
+///
+/// Positions are a 32nd of a light year apart, and dumps round them to a
+/// thousandth, so anything past this is somewhere else.
+const TOLERANCE: f64 = 0.1;
+
+/// How far after arriving a message may say it happened, for clocks that
+/// are a little off
+const FUTURE: Duration = Duration::minutes(15);
+
+/// How long before arriving a message may say it happened
+const PAST: Duration = Duration::days(7);
+
+/// Uploading software known to send bad data, a name and perhaps a version
+///
+/// Given as `NAME` for every version, or `NAME@VERSION` for that version and
+/// those under it, `1.2` being `1.2.3` too.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Software {
+    pub name: String,
+    pub version: Option<String>,
+}
+
+impl Software {
+    pub fn matches(&self, name: &str, version: &str) -> bool {
+        self.name == name
+            && self.version.as_deref().is_none_or(|v| {
+                version == v
+                    || version
+                        .strip_prefix(v)
+                        .is_some_and(|rest| rest.starts_with('.'))
+            })
+    }
+}
+
+impl FromStr for Software {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, version) = match s.rsplit_once('@') {
+            Some((name, version)) => (name, Some(version.to_string())),
+            None => (s, None),
+        };
+        if name.is_empty() || version.as_deref() == Some("") {
+            return Err(format!("bad software: {}", s));
+        }
+        Ok(Software { name: name.to_string(), version })
+    }
+}
+
+impl fmt::Display for Software {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.version {
+            Some(version) => write!(f, "{}@{}", self.name, version),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// Why software at a version can't be trusted, if it's one of `bad`
+pub fn software(bad: &[Software], name: &str, version: &str) -> Option<String> {
+    bad.iter().find(|bad| bad.matches(name, version)).map(|bad| {
+        format!("software {} {} is known bad ({})", name, version, bad)
+    })
+}
+
+/// Why a message saying it happened `at` can't have, having arrived at
+/// `received`
+pub fn timestamp(at: DateTime<Utc>, received: DateTime<Utc>) -> Option<String> {
+    if at > received + FUTURE {
+        Some(format!("timestamp {} is after it arrived at {}", at, received))
+    } else if at < received - PAST {
+        Some(format!(
+            "timestamp {} is long before it arrived at {}",
+            at, received
+        ))
+    } else {
+        None
+    }
+}
+
+/// Why `name` can't be at `position`, if it's outside the galaxy
+pub fn bounds(name: &str, position: &Coordinate) -> Option<String> {
+    let axes = [position.x, position.y, position.z];
+    let inside = axes
+        .iter()
+        .zip(BOUNDS.iter())
+        .all(|(n, (min, max))| (*min..=*max).contains(n));
+    (!inside).then(|| {
+        format!(
+            "{} at ({}, {}, {}) is outside the galaxy",
+            name, position.x, position.y, position.z
+        )
+    })
+}
+
+/// The systems an event says where they are
+pub fn positions(event: &Event) -> Vec<(i64, &str, Coordinate)> {
+    match event {
+        Event::Scan(scan) => {
+            vec![(
+                scan.system_address,
+                scan.star_system.as_str(),
+                scan.star_pos,
+            )]
+        }
+        Event::ScanBaryCentre(scan) => {
+            vec![(
+                scan.system_address,
+                scan.star_system.as_str(),
+                scan.star_pos,
+            )]
+        }
+        Event::Location(e) => e
+            .system
+            .pos
+            .map(|pos| (e.system.address, e.system.name.as_str(), pos))
+            .into_iter()
+            .collect(),
+        Event::FsdJump(e) => e
+            .system
+            .pos
+            .map(|pos| (e.system.address, e.system.name.as_str(), pos))
+            .into_iter()
+            .collect(),
+        Event::NavRoute(NavRoute::Route(destinations)) => destinations
+            .iter()
+            .map(|d| {
+                (d.system_address as i64, d.star_system.as_str(), d.star_pos)
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Why a journal event received at `received` can't be trusted, if it can't
+///
+/// Each system it places is checked against where it is already known to
+/// be, which is the only part needing the database, and fails only where
+/// the database can't be reached. A system on record that can't be read back
+/// is said so, and its position taken as it comes.
+pub async fn journal(
+    db: &Database,
+    entry: &Entry<Event>,
+    received: DateTime<Utc>,
+) -> Result<Option<String>, Error> {
+    if let Some(reason) = timestamp(entry.timestamp, received) {
+        return Ok(Some(reason));
+    }
+    for (address, name, position) in positions(&entry.event) {
+        if let Some(reason) = bounds(name, &position) {
+            return Ok(Some(reason));
+        }
+        match System::fetch(db, address).await {
+            Ok(System { position: Some(known), .. })
+                if distance(&known, &position) > TOLERANCE =>
+            {
+                return Ok(Some(format!(
+                    "{} at ({}, {}, {}) is known to be at ({}, {}, {})",
+                    name,
+                    position.x,
+                    position.y,
+                    position.z,
+                    known.x,
+                    known.y,
+                    known.z
+                )));
+            }
+            Ok(_) => {}
+            Err(err) if err.is_unavailable() => return Err(err),
+            // Not known yet, so anywhere in the galaxy will do.
+            Err(err) if err.is_not_found() => {}
+            // Known, but not read back, which is no reason to refuse it.
+            Err(err) => {
+                warn!(system = %name, error = %err, "position unchecked")
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn distance(a: &Coordinate, b: &Coordinate) -> f64 {
+    ((a.x - b.x).powi(2) + (a.y - b.y).powi(2) + (a.z - b.z).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn software_is_matched_by_name_and_version() {
+        let any: Software = "Bad Uploader".parse().unwrap();
+        assert!(any.matches("Bad Uploader", "3.0"));
+        assert!(!any.matches("EDMarketConnector", "3.0"));
+
+        let some: Software = "Bad Uploader@1.2".parse().unwrap();
+        assert!(some.matches("Bad Uploader", "1.2"));
+        assert!(some.matches("Bad Uploader", "1.2.3"));
+        assert!(!some.matches("Bad Uploader", "1.20"));
+        assert!(!some.matches("Bad Uploader", "1.3"));
+
+        assert!("@1.2".parse::<Software>().is_err());
+        assert!("Bad Uploader@".parse::<Software>().is_err());
+        assert_eq!(some.to_string(), "Bad Uploader@1.2");
+    }
+
+    #[test]
+    fn only_known_bad_software_is_refused() {
+        let bad = vec!["Bad Uploader@1.2".parse().unwrap()];
+        assert!(software(&bad, "Bad Uploader", "1.2.3").is_some());
+        assert!(software(&bad, "Bad Uploader", "1.3").is_none());
+        assert!(software(&[], "Bad Uploader", "1.2").is_none());
+    }
+
+    #[test]
+    fn timestamps_are_near_when_they_arrived() {
+        let received = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+        assert!(timestamp(received, received).is_none());
+        assert!(timestamp(received + Duration::minutes(5), received).is_none());
+        assert!(timestamp(received + Duration::hours(1), received).is_some());
+        assert!(timestamp(received - Duration::days(1), received).is_none());
+        assert!(timestamp(received - Duration::days(30), received).is_some());
+    }
+
+    #[test]
+    fn systems_are_within_the_galaxy() {
+        let sol = Coordinate { x: 0., y: 0., z: 0. };
+        let sagittarius = Coordinate { x: 25.2, y: -20.9, z: 25899.97 };
+        let nowhere = Coordinate { x: 0., y: 0., z: 1e9 };
+        assert!(bounds("Sol", &sol).is_none());
+        assert!(bounds("Sagittarius A*", &sagittarius).is_none());
+        assert!(bounds("Nowhere", &nowhere).is_some());
+    }
+}